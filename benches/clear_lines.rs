@@ -0,0 +1,25 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tetrox::{
+    field::DefaultField,
+    pieces::{tetromino::TetrominoSrs, PieceKind},
+    spins::NoSpinDetector,
+    SingleBag,
+};
+
+// hard drops on fields of increasing height, each of which clears a line
+fn clear_lines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("clear_lines");
+    for height in [20, 40, 100] {
+        group.bench_with_input(BenchmarkId::from_parameter(height), &height, |b, &height| {
+            // every i piece dropped on a 4 wide field fills and clears the bottom line
+            let kinds = [PieceKind::TetrominoSrs(TetrominoSrs::I)];
+            let mut bag = SingleBag::seeded(kinds.to_vec(), 0);
+            let mut field = DefaultField::new(4, height, height / 2, &kinds, &mut bag);
+            b.iter(|| field.hard_drop(&mut bag, &NoSpinDetector));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, clear_lines);
+criterion_main!(benches);
@@ -1,13 +1,24 @@
 use crate::{
-    canvas::{self, Field, HoldPiece, NextQueue},
-    config::{Config, GoalTypes, Input, SpinTypes, UiEnabled},
-    goal,
+    canvas::{self, ClearAnimation, Field, HoldPiece, NextQueue, StackHidden},
+    challenge::SeedState,
+    config::{self, Config, GoalTypes, Input, UiEnabled},
+    effects::Effects,
+    goal::{self, PcCounts},
+    graphs::GameGraphs,
+    input_display::InputDisplay,
+    net::{OpponentBoard, VersusLink},
+    replay::{self, GhostBoard, RecordedGame},
+    scores::{self, GameResult, GameTotals},
+    sound,
     stats::Stats,
-    timer::{self, Timer},
+    timer::{self, StepClock, Timer},
     util,
 };
 
-use std::{cell::RefCell, collections::HashMap};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+};
 
 use js_sys::Date;
 use strum::IntoEnumIterator;
@@ -15,67 +26,139 @@ use sycamore::{
     component,
     generic_node::Html,
     prelude::{
-        create_effect, create_selector, create_signal, provide_context, provide_context_ref, use_context, ReadSignal,
-        Scope, Signal,
+        create_effect, create_memo, create_selector, create_signal, provide_context_ref, try_use_context, use_context,
+        Indexed, ReadSignal, Scope, Signal,
     },
     view,
     view::View,
 };
 use tetrox::{
-    field::{DefaultField, LineClear},
-    pieces::{tetromino::TetrominoSrs, PieceKindTrait},
-    Randomizer, SingleBag,
+    field::{DefaultField, LineClear, MoveError},
+    fumen,
+    garbage::{RandomGarbage, ScriptedGarbage},
+    pieces::PieceKind,
+    replay::Replay,
+    sim,
+    ticks::TickRate,
+    CustomQueue, QueuePolicy, Randomizer, SingleBag,
 };
-use wasm_bindgen::JsCast;
-use web_sys::{Event, HtmlImageElement, KeyboardEvent};
+use web_sys::{Event, KeyboardEvent};
 
 #[component]
 pub fn Board<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let last_replay = use_context::<Signal<Option<RecordedGame>>>(cx);
 
     let c = config.get();
     let c = (*c.borrow()).clone();
 
     let piece_type = util::create_config_selector(cx, config, |c| c.piece_type);
     let piece_kinds = piece_type.get().kinds();
-    let spin_types = util::create_config_selector(cx, config, |c| c.spin_types);
 
-    let mut bag = SingleBag::new(piece_kinds.clone());
-    let field = DefaultField::new(c.field_width, c.field_height, c.field_hidden, &piece_kinds, &mut bag);
+    // bags are seeded so games can be replayed, or played again with the same pieces
+    let seed_state = use_context::<Signal<SeedState>>(cx);
+    let new_seed = move || {
+        let seed = seed_state.get_untracked().fixed.unwrap_or_else(replay::new_seed);
+        seed_state.modify().current = seed;
+        seed
+    };
+    let seed = new_seed();
+
+    // the match being played, if this board is in a versus room
+    let versus = try_use_context::<VersusLink>(cx);
+
+    // a valid practice queue overrides the randomizer and a practice board (e.g. from a setup) is filled in at the
+    // bottom of the field, but those games aren't kept as results or replays since the seed no longer gives them
+    // versus matches are played from the match seed alone, so the opponent's copy of the game gets the same pieces
+    let practice_queue = util::create_config_selector(cx, config, move |c| match versus {
+        Some(_) => (String::new(), false),
+        None => (c.practice_queue.clone(), c.practice_queue_loop),
+    });
+    let practice_board = util::create_config_selector(cx, config, move |c| match versus {
+        Some(_) => String::new(),
+        None => c.practice_board.clone(),
+    });
+    let custom_start = create_signal(cx, false);
+    let make_bag = move |kinds: &[PieceKind], seed| {
+        let (text, looping) = &*practice_queue.get_untracked();
+        let sequence = tetrox::parse_queue(text, kinds).unwrap_or_default();
+        custom_start.set(!sequence.is_empty() || !practice_board.get_untracked().trim().is_empty());
+        CustomQueue::new(sequence, *looping, SingleBag::seeded(kinds.to_vec(), seed))
+    };
+    let new_field = move |c: &Config, kinds: &[PieceKind], bag: &mut CustomQueue<SingleBag>| {
+        let mut field = DefaultField::new(c.field_width, c.field_height, c.field_hidden, kinds, bag);
+        sim::set_board(&mut field, &practice_board.get_untracked());
+        field
+    };
+    let mut bag = make_bag(&piece_kinds, seed);
+
+    // results aren't kept in free play or with a practice queue or board, so practice tools (e.g. undo) can be used
+    // outside of versus matches, where the opponent's copy of the field would no longer match
+    let free_play = util::create_config_selector(cx, config, |c| c.goal_type == GoalTypes::None);
+    let practicing = move || (*free_play.get_untracked() || *custom_start.get_untracked()) && versus.is_none();
+
+    // game timers wait for the step key instead of running in real time while frame stepping
+    let frame_step = util::create_config_selector(cx, config, |c| c.practice_frame_step);
+    let stepping = create_selector(cx, move || {
+        *frame_step.get() && (*free_play.get() || *custom_start.get()) && versus.is_none()
+    });
+    let step_clock = StepClock::new(cx, stepping);
+
+    let field = new_field(&c, &piece_kinds, &mut bag);
     let field_signal = create_signal(cx, RefCell::new(field));
     provide_context_ref(cx, field_signal);
 
+    let new_replay = move |c: &Config, kinds: &[PieceKind], seed| {
+        let replay = Replay::new(c.field_width, c.field_height, c.field_hidden, kinds, seed, TickRate::default());
+        RefCell::new(replay)
+    };
+    let replay = create_signal(cx, new_replay(&c, &piece_kinds, seed));
+
     let piece_kinds = piece_type.map(cx, |t| t.kinds());
     let bag = create_signal(cx, RefCell::new(bag));
 
+    // switch piece sets without resetting the board
     create_effect(cx, || {
-        bag.set(RefCell::new(SingleBag::new((*piece_kinds.get()).clone())))
+        let kinds = (*piece_kinds.get()).clone();
+        util::with_signal_mut_untracked(bag, |bag| {
+            bag.set_kinds(kinds.clone(), QueuePolicy::Flush);
+            util::with_signal_mut_untracked(field_signal, |field| field.set_kinds(&kinds, bag));
+        });
     });
 
-    // update field on field dimension config option updates
+    // resize field on field dimension config option updates, keeping the stack
     let field_dims = util::create_config_selector(cx, config, |c| (c.field_width, c.field_height, c.field_hidden));
     create_effect(cx, || {
         let (width, height, hidden) = *field_dims.get();
-        let new_field = util::with_signal_mut_untracked(bag, |bag| {
-            DefaultField::new(width, height, hidden, &*piece_kinds.get_untracked(), bag)
-        });
-        field_signal.set(RefCell::new(new_field));
+        util::with_signal_mut_untracked(field_signal, |field| field.resize(width, height, hidden));
     });
 
-    // used in canvas drawing
-    provide_context(cx, make_asset_cache());
-
     let time_elapsed = create_signal(cx, 0.0);
     provide_context_ref(cx, time_elapsed);
 
     // measuring time elapsed since last board reset
-    let start_time = create_signal(cx, Date::now());
+    let clock = timer::use_clock(cx);
+    let start_time = create_signal(cx, clock.now());
     let elapsed_timer = create_signal(cx, Timer::new(cx, 33));
     timer::create_timer_finish_effect(cx, elapsed_timer, move || {
-        time_elapsed.set(Date::now() - *start_time.get());
+        time_elapsed.set(clock.now() - *start_time.get());
         true
     });
 
+    // record an input that moved the current piece `n_times` times, also sending it to the opponent in versus
+    let now_tick = move || TickRate::default().ticks(clock.now() - *start_time.get_untracked());
+    let record = move |input: Input, n_times: usize| {
+        let tick = now_tick();
+        if let Some(input) = replay::replay_input(input) {
+            let replay = replay.get_untracked();
+            let mut replay = replay.borrow_mut();
+            (0..n_times).for_each(|_| replay.push(tick, input));
+            if let Some(versus) = versus {
+                (0..n_times).for_each(|_| versus.send_input(tick, input));
+            }
+        }
+    };
+
     let timer_interval = util::create_config_selector(cx, config, |c| c.timer_interval);
     create_effect(cx, || elapsed_timer.get().set_duration(*timer_interval.get()));
 
@@ -85,16 +168,42 @@ pub fn Board<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     let sdr = util::create_config_selector(cx, config, |c| c.soft_drop_rate);
 
     let inputs = create_signal(cx, RefCell::new(InputStates::new()));
+    let press_times = create_signal(cx, RefCell::new(VecDeque::new()));
+
+    // seconds left before the game starts, while counting down
+    let countdown = create_signal(cx, None::<u32>);
+    // between a piece locking and the next one entering, for speed curves with entry delay (are)
+    let entering = create_signal(cx, false);
+    // inputs only charge das while waiting for the game to start or for the next piece
+    let waiting = move || countdown.get_untracked().is_some() || *entering.get_untracked();
+
+    let totals = create_signal(cx, RefCell::new(GameTotals::new(&field_signal.get_untracked().borrow())));
+
+    // run a loop timer action and record it, with soft drops also scoring a point per row
+    let run_action = move |input: Input, action: &ReadSignal<fn(&mut DefaultField) -> usize>| {
+        let n_moved = util::with_signal_mut_untracked(field_signal, |field| action.get()(field));
+        if input == Input::SoftDrop {
+            totals.get_untracked().borrow_mut().score.add_drop(n_moved as u32, false);
+        }
+        record(input, n_moved);
+    };
 
     // creates an action that moves the piece to be executed on every tick of a loop timer
     // special action is given for a delay of zero
     macro_rules! loop_timer_shift_action {
         ($rows:expr, $cols:expr, $delay:expr) => {
             $delay.map(cx, |delay| {
+                // actions give the number of times the piece moved, for recording
                 if *delay == 0 {
-                    |field: &mut DefaultField| while field.try_shift($rows, $cols) {}
+                    |field: &mut DefaultField| {
+                        let mut n_shifts = 0;
+                        while field.try_shift($rows, $cols).is_ok() {
+                            n_shifts += 1;
+                        }
+                        n_shifts
+                    }
                 } else {
-                    |field: &mut DefaultField| drop(field.try_shift($rows, $cols))
+                    |field: &mut DefaultField| field.try_shift($rows, $cols).is_ok() as usize
                 }
             })
         };
@@ -104,14 +213,14 @@ pub fn Board<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     let soft_drop_action = loop_timer_shift_action!(1, 0, sdr);
 
     // timer loop executing an action on an interval
-    let loop_timer = |delay: &'a ReadSignal<u32>, input, action: &'a ReadSignal<fn(&mut DefaultField)>| {
+    let loop_timer = |delay: &'a ReadSignal<u32>, input, action: &'a ReadSignal<fn(&mut DefaultField) -> usize>| {
         // derive timer from looping interval
-        let timer = delay.map(cx, move |d| Timer::new(cx, *d.max(&16)));
+        let timer = delay.map(cx, move |d| Timer::stepped(cx, *d.max(&16), step_clock));
 
         timer::create_timer_finish_effect(cx, timer, move || {
             let state = inputs.get_untracked().borrow().get_state(&input);
-            if state.is_pressed() {
-                util::with_signal_mut_untracked(field_signal, |field| action.get()(field));
+            if state.is_pressed() && !waiting() {
+                run_action(input, action);
             }
             state.is_held() // continue the timer loop if the input is held (pressed or suppressed)
         });
@@ -120,59 +229,311 @@ pub fn Board<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     };
 
     // timer loop executing an action on an interval after an initial buffer timeout
-    let buffered_loop_timer = |delays: &'a ReadSignal<_>, input, action: &'a ReadSignal<fn(&mut DefaultField)>| {
+    // also gives the loop timer and whether the buffer timeout has passed (i.e. das is charged)
+    let buffered_loop_timer = |delays: &'a ReadSignal<_>, input, action: &'a ReadSignal<fn(&mut DefaultField) -> usize>| {
         // derive timers from buffer and loop durations
-        let buffer_timer = delays.map(cx, move |(b, _)| Timer::new(cx, *b));
+        let buffer_timer = delays.map(cx, move |(b, _)| Timer::stepped(cx, *b, step_clock));
         let loop_timer = loop_timer(delays.map(cx, |d| d.1), input, action);
+        let charged = create_signal(cx, false);
 
         timer::create_timer_finish_effect(cx, buffer_timer, move || {
             // apply the action if the input is still held down
-            if inputs.get_untracked().borrow().get_state(&input).is_pressed() {
-                util::with_signal_mut_untracked(field_signal, |field| action.get()(field));
+            // das still charges while waiting, but the piece doesn't move until the wait ends
+            let pressed = inputs.get_untracked().borrow().get_state(&input).is_pressed();
+            if pressed && !waiting() {
+                run_action(input, action);
             }
+            charged.set(true);
             loop_timer.get().start(); // activate the loop timer
             false
         });
 
-        buffer_timer
+        (buffer_timer, loop_timer, charged)
     };
 
     // looping input timers
-    let left_timer = buffered_loop_timer(das_arr, Input::Left, left_action);
-    let right_timer = buffered_loop_timer(das_arr, Input::Right, right_action);
+    let left_timers = buffered_loop_timer(das_arr, Input::Left, left_action);
+    let right_timers = buffered_loop_timer(das_arr, Input::Right, right_action);
     let buffered_sdr = sdr.map(cx, |s| (0, *s));
-    let soft_drop_timer = buffered_loop_timer(buffered_sdr, Input::SoftDrop, soft_drop_action);
+    let (soft_drop_timer, _, _) = buffered_loop_timer(buffered_sdr, Input::SoftDrop, soft_drop_action);
+
+    // start charging das in a direction, or start repeating right away if turning from a charged direction
+    let start_das = move |input| {
+        let ((timer, loop_timer, charged), (_, _, other_charged)) = match input {
+            Input::Left => (left_timers, right_timers),
+            _ => (right_timers, left_timers),
+        };
+        if config.get_untracked().borrow().das_kept_on_turn && *other_charged.get_untracked() {
+            charged.set(true);
+            loop_timer.get().start();
+        } else {
+            timer.get().start();
+        }
+    };
+
+    // das cut delay, which pauses auto repeat in charged directions for a moment after rotating or holding
+    let dcd = util::create_config_selector(cx, config, |c| c.das_cut_delay);
+    let cut_timer = |(_, loop_timer, charged): (_, &'a ReadSignal<Timer<'a>>, &'a Signal<bool>)| {
+        let timer = dcd.map(cx, move |d| Timer::stepped(cx, *d, step_clock));
+        timer::create_timer_finish_effect(cx, timer, move || {
+            // resume unless the direction was released or recharged in the meantime
+            if *charged.get_untracked() {
+                loop_timer.get().start();
+            }
+            false
+        });
+        timer
+    };
+    let (left_cut_timer, right_cut_timer) = (cut_timer(left_timers), cut_timer(right_timers));
+    let cut_das = move || {
+        if *dcd.get_untracked() == 0 {
+            return;
+        }
+        for ((_, loop_timer, charged), timer) in [(left_timers, left_cut_timer), (right_timers, right_cut_timer)] {
+            if *charged.get_untracked() {
+                loop_timer.get().stop();
+                timer.get().start();
+            }
+        }
+    };
+
+    // held directions charge das again for each new piece, unless the charge is kept
+    let recharge_das = move || {
+        if config.get_untracked().borrow().das_kept_on_spawn {
+            return;
+        }
+        for (input, (timer, loop_timer, charged)) in [(Input::Left, left_timers), (Input::Right, right_timers)] {
+            loop_timer.get().stop();
+            charged.set(false);
+            if inputs.get_untracked().borrow().get_state(&input).is_held() {
+                timer.get().start();
+            }
+        }
+    };
 
     let last_line_clear = create_signal(cx, None::<LineClear>);
     let topped_out = create_selector(cx, || field_signal.get().borrow().topped_out());
+    let n_lines = create_memo(cx, || {
+        last_line_clear.track();
+        totals.get().borrow().stats.n_lines()
+    });
+
+    // level and timings from the speed curve as lines are cleared, if there is one
+    let speed_curve = util::create_config_selector(cx, config, |c| c.active_speed_curve());
+    let speed = create_selector(cx, || {
+        let n_lines = *n_lines.get() as usize;
+        speed_curve.get().curve().map(|curve| (curve.level(n_lines), curve.speed_after_lines(n_lines)))
+    });
+
+    // entry delay timer, started whenever a piece locks
+    let are = create_selector(cx, || speed.get().map_or(0, |(_, s)| s.are as u32));
+    let are_timer = create_signal(cx, Timer::stepped(cx, *are.get(), step_clock));
+    create_effect(cx, || are_timer.get().set_duration(*are.get()));
+    timer::create_timer_finish_effect(cx, are_timer, || {
+        entering.set(false);
+        false
+    });
+
+    // placements can be taken back while practicing, going back to the totals and bag from before the placement
+    // and the field as the piece spawned (kept in the totals)
+    let undo_history = create_signal(cx, RefCell::new(vec![]));
+    // replays can't be played back from their inputs once the game has been undone, rewound, or stepped through
+    let history_edited = create_signal(cx, false);
+
+    // pieces left to place with the stack hidden, for memorizing the queue, with a piece placed in view between each
+    // stretch of blind placements
+    let blind_pieces = util::create_config_selector(cx, config, |c| c.practice_blind_pieces);
+    let blind_left = create_signal(cx, *blind_pieces.get());
+    create_effect(cx, || blind_left.set(*blind_pieces.get()));
+    let stack_hidden = create_memo(cx, || StackHidden(*blind_left.get() > 0 && !*topped_out.get()));
+    provide_context_ref(cx, stack_hidden);
+
+    // the last line clear, animated on the field
+    let clear_animation = create_signal(cx, None::<ClearAnimation>);
+    provide_context_ref(cx, clear_animation);
+
+    // blind placements making new holes are misdrops, and each stretch placed without topping out is a round survived
+    let place_blind = move |n_holes_before: usize| {
+        let n_blind = *blind_pieces.get_untracked();
+        match *blind_left.get_untracked() {
+            _ if n_blind == 0 => {}
+            0 => blind_left.set(n_blind),
+            left => {
+                let field = field_signal.get_untracked();
+                let field = field.borrow();
+                let totals = totals.get_untracked();
+                let mut totals = totals.borrow_mut();
+                totals.misdrops += (field.n_holes() > n_holes_before) as u32;
+                totals.blind_rounds += (left == 1 && !field.topped_out()) as u32;
+                drop((field, totals));
+                blind_left.set(left - 1);
+            }
+        }
+    };
+
+    // in perfect clear practice, an attempt ends in a perfect clear or once the stack is too tall for one, and each
+    // attempt is dealt a fresh field and bag
+    let pc_counts = create_signal(cx, PcCounts::default());
+    let end_pc_attempt = move || {
+        if config.get_untracked().borrow().goal_type != GoalTypes::PerfectClear {
+            return;
+        }
+        let cleared = (*last_line_clear.get_untracked()).as_ref().is_some_and(|l| l.is_perfect_clear());
+        if !cleared && field_signal.get_untracked().borrow().stack_height() <= PC_HEIGHT {
+            return;
+        }
+        pc_counts.modify().add_attempt(cleared);
 
-    // gravity timer
-    let gravity_delay = util::create_config_selector(cx, config, |c| c.gravity_delay);
+        let config = config.get_untracked();
+        let c = config.borrow();
+        let kinds = piece_kinds.get_untracked();
+        let mut new_bag = make_bag(&kinds, new_seed());
+        let field = new_field(&c, &kinds, &mut new_bag);
+        totals.get_untracked().borrow_mut().spawn_piece(&field);
+        undo_history.get_untracked().borrow_mut().clear();
+        history_edited.set(true);
+        field_signal.set(RefCell::new(field));
+        bag.set(RefCell::new(new_bag));
+    };
+
+    // garbage rows added so far, from survival, practice, or versus, which flash the field
+    let n_garbage_rows = create_signal(cx, 0);
+    // replays only hold inputs, so games which took in garbage can't be played back from them
+    let garbage_received = create_signal(cx, false);
+
+    // in versus, attacks are sent once the piece locks, then any garbage left pending enters the field
+    let exchange_garbage = move |versus: &VersusLink, attack: u32| {
+        versus.send_attack(attack);
+        let n_lines = (*last_line_clear.get_untracked()).as_ref().map_or(0, |l| l.n_lines());
+        let width = field_signal.get_untracked().borrow().width();
+        let holes = versus.take_garbage(n_lines, now_tick(), width);
+        if !holes.is_empty() {
+            let n_rows = holes.len();
+            let mut holes = ScriptedGarbage::new(holes);
+            util::with_signal_mut_untracked(field_signal, |field| field.add_garbage(n_rows, &mut holes));
+            n_garbage_rows.set(*n_garbage_rows.get_untracked() + n_rows);
+            garbage_received.set(true);
+        }
+    };
+
+    // flashed and counted in the stats, with a sound if enabled
+    let n_finesse_faults = create_signal(cx, 0);
+    let drop_piece = move || {
+        record(Input::HardDrop, 1);
+        let attack_before = totals.get_untracked().borrow().stats.attack();
+        if practicing() {
+            let before = (totals.get_untracked().borrow().clone(), bag.get_untracked().borrow().clone());
+            undo_history.get_untracked().borrow_mut().push(before);
+        }
+        let n_holes = field_signal.get_untracked().borrow().n_holes();
+        let mut taken_back = false;
+        if hard_drop(field_signal, bag, config, last_line_clear, totals) {
+            n_finesse_faults.set(totals.get_untracked().borrow().finesse_faults);
+            let c = config.get_untracked();
+            let c = c.borrow();
+            if c.finesse_sound_enabled {
+                sound::play_fault_sound();
+            }
+            // the finesse trainer already took the placement back
+            taken_back = c.finesse_trainer_enabled;
+            if taken_back {
+                undo_history.get_untracked().borrow_mut().pop();
+            }
+        }
+        if !taken_back {
+            place_blind(n_holes);
+            end_pc_attempt();
+            if let Some(versus) = versus {
+                exchange_garbage(versus, totals.get_untracked().borrow().stats.attack() - attack_before);
+            }
+        }
+
+        // clears animate over the line clear delay if there is one, which holds the next piece back on top of any
+        // entry delay
+        let cleared_rows = match &*last_line_clear.get_untracked() {
+            Some(line_clear) if !taken_back => line_clear.cleared_rows().to_vec(),
+            _ => vec![],
+        };
+        let (animation_duration, line_clear_delay) = {
+            let c = config.get_untracked();
+            let c = c.borrow();
+            (c.clear_animation_duration, if cleared_rows.is_empty() { 0 } else { c.line_clear_delay })
+        };
+        if animation_duration > 0 && !cleared_rows.is_empty() {
+            let duration = if line_clear_delay > 0 { line_clear_delay } else { animation_duration };
+            clear_animation.set(Some(ClearAnimation {
+                cleared_rows,
+                start: clock.now(),
+                duration: duration as f64,
+            }));
+        }
+        let entry_delay = *are.get_untracked() + line_clear_delay;
+        if entry_delay > 0 {
+            entering.set(true);
+            are_timer.get_untracked().set_duration(entry_delay);
+            are_timer.get_untracked().start();
+        }
+        recharge_das();
+    };
+
+    // frame stepping keeps the state from before each of the last few seconds of steps, to rewind through
+    let rewind_buffer = create_signal(cx, RefCell::new(VecDeque::new()));
+    let step = move || {
+        let field = field_signal.get_untracked().borrow().clone();
+        let state = (field, bag.get_untracked().borrow().clone(), totals.get_untracked().borrow().clone());
+        let rewind_buffer = rewind_buffer.get_untracked();
+        let mut rewind_buffer = rewind_buffer.borrow_mut();
+        rewind_buffer.push_back((state, step_clock.now()));
+        if rewind_buffer.len() > MAX_REWIND_STEPS {
+            rewind_buffer.pop_front();
+        }
+        drop(rewind_buffer);
+
+        history_edited.set(true);
+        step_clock.step(TickRate::default().ms(1));
+    };
+    let rewind = move || {
+        let last = rewind_buffer.get_untracked().borrow_mut().pop_back();
+        if let Some(((field, old_bag, old_totals), now)) = last {
+            n_finesse_faults.set(old_totals.finesse_faults);
+            *totals.get_untracked().borrow_mut() = old_totals;
+            field_signal.set(RefCell::new(field));
+            bag.set(RefCell::new(old_bag));
+            step_clock.rewind_to(now);
+        }
+    };
+
+    // gravity timer, with the delay from the speed curve if there is one
+    let static_gravity_delay = util::create_config_selector(cx, config, |c| c.gravity_delay);
+    let gravity_delay =
+        create_selector(cx, || speed.get().map_or(*static_gravity_delay.get(), |(_, s)| s.gravity_delay as u32));
     let gravity_action = loop_timer_shift_action!(1, 0, gravity_delay);
     let gravity_timer = gravity_delay.map(cx, move |d| {
-        let timer = Timer::new(cx, *d);
+        let timer = Timer::stepped(cx, *d, step_clock);
         timer.start();
         timer
     });
-    timer::create_timer_finish_effect(cx, gravity_timer, || {
-        if config.get_untracked().borrow().gravity_enabled {
-            util::with_signal_mut_untracked(field_signal, |field| gravity_action.get()(field));
+    timer::create_timer_finish_effect(cx, gravity_timer, move || {
+        if config.get_untracked().borrow().gravity_enabled && !waiting() {
+            // gravity moves the piece the same way as soft dropping
+            record(Input::SoftDrop, util::with_signal_mut_untracked(field_signal, |field| gravity_action.get()(field)));
         }
         true
     });
 
     // lock delay timer
-    let lock_delay = util::create_config_selector(cx, config, |c| c.lock_delay);
-    let lock_delay_timer = lock_delay.map(cx, move |d| Timer::new(cx, *d));
-    let cur_piece = create_selector(cx, || field_signal.get().borrow().cur_piece().coords().clone());
+    let static_lock_delay = util::create_config_selector(cx, config, |c| c.lock_delay);
+    let lock_delay = create_selector(cx, || speed.get().map_or(*static_lock_delay.get(), |(_, s)| s.lock_delay as u32));
+    let lock_delay_timer = lock_delay.map(cx, move |d| Timer::stepped(cx, *d, step_clock));
+    let cur_piece = create_selector(cx, || field_signal.get().borrow().cur_piece().coords().to_vec());
     let lock_delay_piece = create_signal(cx, (*cur_piece.get()).clone());
 
     // auto lock
-    timer::create_timer_finish_effect(cx, lock_delay_timer, || {
+    timer::create_timer_finish_effect(cx, lock_delay_timer, move || {
         // lock the piece if it is the same as when the timer started
         let still_same_piece = cur_piece.get_untracked() == lock_delay_piece.get_untracked();
         if config.get_untracked().borrow().auto_lock_enabled && still_same_piece {
-            hard_drop(field_signal, bag, spin_types, last_line_clear);
+            drop_piece();
         }
         false
     });
@@ -187,139 +548,571 @@ pub fn Board<'a, G: Html>(cx: Scope<'a>) -> View<G> {
         }
     });
 
+    // survival garbage rises a row at a time, a bit sooner after each row
+    let garbage = create_signal(cx, RefCell::new(RandomGarbage::new(seed)));
+    let garbage_interval = create_signal(cx, c.survival_interval as f64);
+    let garbage_timer = create_signal(cx, Timer::stepped(cx, c.survival_interval, step_clock));
+    timer::create_timer_finish_effect(cx, garbage_timer, || {
+        let (goal_type, acceleration) = {
+            let c = config.get_untracked();
+            let c = c.borrow();
+            (c.goal_type, c.survival_acceleration)
+        };
+        if goal_type != GoalTypes::Survival {
+            return false;
+        }
+
+        // stop rising once the stack is pushed out of the field
+        let topped_out = util::with_signal_mut_untracked(field_signal, |field| {
+            field.add_garbage(1, &mut *garbage.get_untracked().borrow_mut());
+            field.topped_out()
+        });
+        n_garbage_rows.set(*n_garbage_rows.get_untracked() + 1);
+        garbage_received.set(true);
+        if topped_out {
+            return false;
+        }
+
+        let interval = (*garbage_interval.get_untracked() * (1.0 - acceleration)).max(MIN_SURVIVAL_INTERVAL);
+        garbage_interval.set(interval);
+        garbage_timer.get_untracked().set_duration(interval as u32);
+        true
+    });
+
     // toggle running state of timers
-    let run_timers = create_signal(cx, true);
+    let run_timers = create_signal(cx, !c.countdown_enabled);
     create_effect(cx, || {
         elapsed_timer.get().stop();
         gravity_timer.get().stop();
         lock_delay_timer.get().stop();
+        garbage_timer.get().stop();
 
         // set elapsed time accurately
-        time_elapsed.set(Date::now() - *start_time.get_untracked());
+        time_elapsed.set(clock.now() - *start_time.get_untracked());
 
         if *run_timers.get() {
             time_elapsed.set(0.0);
-            start_time.set(Date::now());
+            start_time.set(clock.now());
 
             // don't start lock delay timer
             elapsed_timer.get().start();
             gravity_timer.get().start();
+
+            let c = config.get_untracked();
+            let c = c.borrow();
+            if c.goal_type == GoalTypes::Survival {
+                garbage_interval.set(c.survival_interval as f64);
+                garbage_timer.get().set_duration(c.survival_interval);
+                garbage_timer.get().start();
+            }
         }
     });
 
     // current game goal
     let goal_type = util::create_config_selector(cx, config, |c| c.goal_type);
+
+    // lines cleared in zen are kept across games
+    let lifetime_lines = create_signal(cx, scores::load_lifetime_lines());
+    create_effect(cx, || {
+        let n_lines = (*last_line_clear.get()).as_ref().map_or(0, |l| l.n_lines()) as u64;
+        if n_lines > 0 && *goal_type.get_untracked() == GoalTypes::Zen {
+            lifetime_lines.set(*lifetime_lines.get_untracked() + n_lines);
+            scores::save_lifetime_lines(*lifetime_lines.get_untracked());
+        }
+    });
+
     let make_goal = move || match *goal_type.get() {
         GoalTypes::None => goal::none(cx),
-        GoalTypes::LinesCleared => goal::lines_cleared(cx, config, last_line_clear),
+        GoalTypes::LinesCleared => goal::lines_cleared(cx, config, last_line_clear, time_elapsed),
         GoalTypes::TimeLimit => goal::time_limit(cx, config, time_elapsed),
+        GoalTypes::ScoreAttack => goal::score_attack(cx, config, time_elapsed, totals),
+        GoalTypes::Survival => goal::survival(cx, time_elapsed),
+        GoalTypes::Zen => goal::zen(cx, last_line_clear, totals, lifetime_lines),
+        GoalTypes::ScoreTarget => goal::score_target(cx, config, time_elapsed, totals),
+        GoalTypes::Combined => goal::combined(cx, config, last_line_clear, time_elapsed, totals),
+        GoalTypes::PerfectClear => goal::perfect_clear(cx, pc_counts),
     };
 
     // not mapped signal as it must be mutable (for resetting)
     let goal = create_signal(cx, make_goal());
     create_effect(cx, move || goal.set(make_goal()));
 
-    // top out to end the game when the goal is reached or the player topped out naturally
+    let replayable = move || {
+        !*custom_start.get_untracked() && !*history_edited.get_untracked() && !*garbage_received.get_untracked()
+    };
+    let recorded_game = move || {
+        let c = config.get_untracked();
+        let c = c.borrow();
+        RecordedGame {
+            replay: replay.get_untracked().borrow().clone(),
+            piece_type: c.piece_type,
+            kick_table: c.kick_table,
+            kick_table_180: c.kick_table_180,
+            spin_types: c.spin_types,
+            date: Date::now(),
+        }
+    };
+
+    // top out to end the game when the goal is reached or failed, or the player topped out naturally
     create_effect(cx, move || {
-        if goal.get().is_completed() {
+        if goal.get().is_completed() || goal.get().is_failed() {
             util::with_signal_mut(field_signal, |field| field.top_out());
         }
         if *topped_out.get() {
             run_timers.set(false);
+            if let Some(versus) = versus {
+                versus.finish(goal.get_untracked().is_completed());
+            }
+
+            // keep the finished game's replay for the viewer, if it can be played back from its seed and inputs
+            last_replay.set(replayable().then(recorded_game));
+        }
+    });
+
+    // store the result of a game once its goal is reached, or once topping out for survival
+    let result_saved = create_signal(cx, false);
+    create_effect(cx, move || {
+        let survived = *topped_out.get() && *goal_type.get_untracked() == GoalTypes::Survival;
+        if !(goal.get().is_completed() || survived) || *result_saved.get_untracked() || *custom_start.get_untracked() {
+            return;
+        }
+        result_saved.set(true);
+
+        let c = config.get_untracked();
+        let c = c.borrow();
+        let (goal, time) = match c.goal_type {
+            // combined goals can be anything, so they aren't compared
+            GoalTypes::None | GoalTypes::Zen | GoalTypes::Combined | GoalTypes::PerfectClear => return,
+            GoalTypes::LinesCleared => (c.goal_n_lines as u64, clock.now() - *start_time.get_untracked()),
+            // the timer may have run slightly past the limit
+            GoalTypes::TimeLimit | GoalTypes::ScoreAttack => {
+                (c.goal_time_limit_secs, c.goal_time_limit_secs as f64 * 1_000.0)
+            }
+            GoalTypes::Survival => (c.survival_interval as u64, clock.now() - *start_time.get_untracked()),
+            GoalTypes::ScoreTarget => (c.goal_score, clock.now() - *start_time.get_untracked()),
+        };
+
+        let totals = totals.get_untracked();
+        let totals = totals.borrow();
+        let score = match c.goal_type {
+            GoalTypes::ScoreAttack | GoalTypes::ScoreTarget => totals.score.score(),
+            _ => totals.stats.attack() as u64,
+        };
+        let result = GameResult {
+            mode: c.goal_type,
+            goal,
+            time,
+            score,
+            lines: totals.stats.n_lines(),
+            pps: totals.stats.pieces_per_second(time),
+            date: Date::now(),
+        };
+
+        // race against the new best from the next game on
+        if result.mode == GoalTypes::LinesCleared && scores::is_personal_best(&result) && replayable() {
+            replay::save_pb_replay(goal, &recorded_game());
+        }
+        scores::save_result(result);
+    });
+
+    // the personal best replay for the current sprint goal, played as a ghost
+    let sprint_goal = util::create_config_selector(cx, config, |c| {
+        (c.goal_type == GoalTypes::LinesCleared).then_some(c.goal_n_lines as u64)
+    });
+    let load_ghost = move || sprint_goal.get().and_then(replay::load_pb_replay);
+    let ghost = create_signal(cx, load_ghost());
+    create_effect(cx, move || ghost.set(load_ghost()));
+    // offered once the game is over
+    let replay_saved = create_signal(cx, false);
+    let save_replay = move |_| {
+        if let Some(recorded) = &*last_replay.get_untracked() {
+            replay::save_replay(cx, recorded);
+            replay_saved.set(true);
+        }
+    };
+    let save_replay_label = replay_saved.map(cx, |s| if *s { "Replay saved" } else { "Save replay" });
+
+    // results shown over the field once the game is over, until retrying or going back to the menu
+    let results_dismissed = create_signal(cx, false);
+    let topping_out = util::create_config_selector(cx, config, |c| c.topping_out());
+    let game_over = create_memo(cx, move || {
+        let goal_ended = goal.get().is_completed() || goal.get().is_failed();
+        *topped_out.get() && (*topping_out.get() || goal_ended) && !*results_dismissed.get()
+    });
+    let results = create_memo(cx, move || {
+        if !*game_over.get() {
+            return vec![];
         }
+        let time = *time_elapsed.get();
+        let totals = totals.get_untracked();
+        let totals = totals.borrow();
+        let stats = &totals.stats;
+        let mut results = vec![
+            ("time", util::format_duration(time)),
+            ("attack", stats.attack().to_string()),
+            ("lines", stats.n_lines().to_string()),
+            ("pieces", stats.n_pieces().to_string()),
+            ("pps", format!("{:.2}", stats.pieces_per_second(time))),
+            ("kpp", format!("{:.2}", stats.keys_per_piece())),
+            ("finesse faults", totals.finesse_faults.to_string()),
+            ("max combo", stats.max_combo().to_string()),
+            ("max b2b", stats.max_back_to_back().to_string()),
+        ];
+        // score attack, zen, and score target are played for score, so it comes first
+        let goal_type = *goal_type.get_untracked();
+        if matches!(goal_type, GoalTypes::ScoreAttack | GoalTypes::Zen | GoalTypes::ScoreTarget) {
+            results.insert(0, ("score", totals.score.score().to_string()));
+        }
+        results
+    });
+    let results_heading = create_memo(cx, || match &*goal.get() {
+        goal if goal.is_completed() => "finished",
+        goal if goal.is_failed() => "failed",
+        _ => "topped out",
+    });
+
+    // count down once a second, then start the game with any held hold or rotation applied to the first piece (ihs
+    // and irs)
+    let countdown_timer = create_signal(cx, Timer::new(cx, 1_000));
+    timer::create_timer_finish_effect(cx, countdown_timer, move || {
+        if let Some(n) = countdown.get_untracked().filter(|&n| n > 1) {
+            countdown.set(Some(n - 1));
+            return true;
+        }
+        countdown.set(None);
+        run_timers.set(true);
+
+        let held = |input| inputs.get_untracked().borrow().get_state(&input).is_pressed();
+        util::with_signal_mut_untracked(field_signal, |field| {
+            let c = config.get_untracked();
+            let c = c.borrow();
+
+            if held(Input::SwapHold) {
+                let result = util::with_signal_mut_silent_untracked(bag, |bag| field.swap_hold_piece(bag));
+                if !matches!(result, Err(MoveError::HoldUsed | MoveError::Disallowed)) {
+                    record(Input::SwapHold, 1);
+                    totals.get_untracked().borrow_mut().spawn_piece(field);
+                }
+            }
+
+            let rotation = [Input::RotateCw, Input::RotateCcw, Input::Rotate180].into_iter().find(|&i| held(i));
+            let rotated = rotation.filter(|rotation| match rotation {
+                Input::RotateCw => field.try_rotate_cw(c.kick_table.table()).is_ok(),
+                Input::RotateCcw => field.try_rotate_ccw(c.kick_table.table()).is_ok(),
+                _ => field.try_rotate_180(c.kick_table_180.table()).is_ok(),
+            });
+            if let Some(rotation) = rotated {
+                record(rotation, 1);
+            }
+        });
+        util::notify_subscribers(bag);
+        false
     });
 
+    let start_countdown = move || {
+        countdown.set(Some(COUNTDOWN_SECS));
+        countdown_timer.get().start();
+    };
+    if c.countdown_enabled {
+        start_countdown();
+    }
+
     let reset_board = move || {
         last_line_clear.set(None);
+        replay_saved.set(false);
+        results_dismissed.set(false);
+        n_finesse_faults.set(0);
+        pc_counts.set(PcCounts::default());
+        result_saved.set(false);
+        undo_history.get_untracked().borrow_mut().clear();
+        rewind_buffer.get_untracked().borrow_mut().clear();
+        blind_left.set(*blind_pieces.get_untracked());
+        history_edited.set(false);
+        garbage_received.set(false);
+        are_timer.get().stop();
+        entering.set(false);
+        clear_animation.set(None);
+        ghost.set(load_ghost());
         goal.set(make_goal());
 
         let config = config.get();
         let c = config.borrow();
 
         let kinds = piece_kinds.get();
-        let mut new_bag = SingleBag::new((*kinds).clone());
-        let field = DefaultField::new(c.field_width, c.field_height, c.field_hidden, &*kinds, &mut new_bag);
-
-        run_timers.set(true);
+        let seed = new_seed();
+        let mut new_bag = make_bag(&kinds, seed);
+        let field = new_field(&c, &kinds, &mut new_bag);
+        replay.set(new_replay(&c, &kinds, seed));
+        garbage.set(RefCell::new(RandomGarbage::new(seed)));
+        totals.set(RefCell::new(GameTotals::new(&field)));
+
+        // the clock starts from zero once the countdown ends
+        if c.countdown_enabled {
+            start_time.set(clock.now());
+            run_timers.set(false);
+            start_countdown();
+        } else {
+            countdown_timer.get().stop();
+            countdown.set(None);
+            run_timers.set(true);
+        }
 
         field_signal.set(RefCell::new(field));
         bag.set(RefCell::new(new_bag));
     };
 
+    // with a reset hold delay, the board only resets once reset has been held for that long
+    let reset_hold_delay = util::create_config_selector(cx, config, |c| c.reset_hold_delay);
+    let reset_timer = create_signal(cx, Timer::new(cx, *reset_hold_delay.get()));
+    create_effect(cx, || reset_timer.get().set_duration(*reset_hold_delay.get()));
+    let resetting = create_signal(cx, false);
+    timer::create_timer_finish_effect(cx, reset_timer, move || {
+        reset_timer.get().stop();
+        resetting.set(false);
+        reset_board();
+        false
+    });
+
+    // start over once a seed is entered or cleared, so the next game uses it
+    let last_fixed_seed = create_signal(cx, seed_state.get_untracked().fixed);
+    create_effect(cx, move || {
+        let fixed = seed_state.get().fixed;
+        if fixed != *last_fixed_seed.get_untracked() {
+            last_fixed_seed.set(fixed);
+            reset_board();
+        }
+    });
+
+    // start over with the practice queue and board once either is edited
+    let last_practice_start = create_signal(cx, (practice_queue.get_untracked(), practice_board.get_untracked()));
+    create_effect(cx, move || {
+        let start = (practice_queue.get(), practice_board.get());
+        if start != *last_practice_start.get_untracked() {
+            last_practice_start.set(start);
+            reset_board();
+        }
+    });
+
+    // start over once frame stepping is turned on or off, so running timers switch between steps and real time
+    let last_stepping = create_signal(cx, *stepping.get_untracked());
+    create_effect(cx, move || {
+        if *stepping.get() != *last_stepping.get_untracked() {
+            last_stepping.set(*stepping.get_untracked());
+            reset_board();
+        }
+    });
+
     let ui_enabled = use_context::<Signal<UiEnabled>>(cx);
 
-    let keydown_handler = move |e: Event| {
-        let e = e.dyn_into::<KeyboardEvent>().unwrap();
+    let retry = move |_| reset_board();
+    let back_to_menu = move |_| {
+        results_dismissed.set(true);
+        ui_enabled.set(true.into());
+    };
+
+    // keys are taken from the whole window, so clicking around the page doesn't stop the game from getting them
+    // keys typed into text boxes and ones already handled (e.g. by keybind capture buttons) are left alone
+    let keydown_handler = move |e: &KeyboardEvent| {
+        if e.default_prevented() || util::is_text_entry(e) {
+            return;
+        }
         let c = config.get();
         let c = c.borrow();
 
-        c.keybinds.get_by_right(&e.key()).map(|input| {
+        if let Some(input) = c.keybinds.input(&config::key_combo(e)) {
+            // keep bound keys from also scrolling the page or pressing focused buttons
+            e.prevent_default();
+
             // don't do anything if the input was already pressed
             // these presses come from the operating system repeating inputs automatically
             if util::with_signal_mut(inputs, |inputs| inputs.set_pressed(input)).is_pressed() {
                 return;
             }
+            let practice_input =
+                matches!(input, Input::AddGarbage | Input::Undo | Input::Step | Input::Rewind | Input::SwapPiece);
+            let export_input = matches!(input, Input::ExportFumen | Input::ExportImage);
+            if !matches!(input, Input::Reset | Input::ShowHideUi) && !practice_input && !export_input {
+                press_times.get_untracked().borrow_mut().push_back(clock.now());
+            }
 
             // actions possible after topping out
+            // versus matches can't be restarted, since the opponent would still be playing the old game
             match input {
-                Input::Reset => reset_board(),
+                Input::Reset if versus.is_some() => {}
+                Input::Reset if *reset_hold_delay.get() == 0 => reset_board(),
+                Input::Reset => {
+                    resetting.set(true);
+                    reset_timer.get().start();
+                }
                 Input::ShowHideUi => ui_enabled.set((!**ui_enabled.get()).into()),
+                Input::ExportFumen => {
+                    let field = field_signal.get_untracked();
+                    let field = field.borrow();
+                    let next = bag.get_untracked().borrow_mut().peek_n(c.queue_len).collect::<Vec<_>>();
+                    let quiz = fumen::quiz(field.hold_piece(), field.cur_piece().kind(), next);
+                    util::copy_to_clipboard(&fumen::encode(&field, &quiz));
+                }
+                Input::ExportImage => canvas::save_field_image(),
+                _ => {}
+            }
+
+            if *topped_out.get() && c.topping_out() {
+                return;
+            }
+            totals.get_untracked().borrow_mut().press_key(*input);
+
+            // steps move time instead of the piece, so they also work while waiting
+            match input {
+                Input::Step if *stepping.get() => return step(),
+                Input::Rewind if *stepping.get() => return rewind(),
                 _ => {}
             }
 
-            if *topped_out.get() && c.topping_out_enabled {
+            // only charge das while waiting, with hold and rotations applied once the countdown ends
+            if waiting() {
+                match input {
+                    Input::Left | Input::Right => start_das(*input),
+                    Input::SoftDrop => soft_drop_timer.get().start(),
+                    _ => {}
+                }
                 return;
             }
 
             util::with_signal_mut(field_signal, |field| {
                 // shift the current piece and activate a loop timer to handle a held input
                 let mut shift_and_start_timer = |rows, cols, timer: &ReadSignal<Timer>| {
-                    field.try_shift(rows, cols);
+                    let shifted = field.try_shift(rows, cols).is_ok();
                     timer.get().start();
+                    shifted
                 };
 
-                match input {
-                    Input::Left => shift_and_start_timer(0, -1, left_timer),
-                    Input::Right => shift_and_start_timer(0, 1, right_timer),
+                let mut n_dropped_rows = 0;
+                let moved = match input {
+                    Input::Left => {
+                        start_das(*input);
+                        field.try_shift(0, -1).is_ok()
+                    }
+                    Input::Right => {
+                        start_das(*input);
+                        field.try_shift(0, 1).is_ok()
+                    }
                     Input::SoftDrop => shift_and_start_timer(1, 0, soft_drop_timer),
-                    Input::RotateCw => drop(field.try_rotate_cw(c.kick_table.table())),
-                    Input::RotateCcw => drop(field.try_rotate_ccw(c.kick_table.table())),
-                    Input::Rotate180 => drop(field.try_rotate_180(c.kick_table_180.table())),
-                    Input::SwapHold => util::with_signal_mut_silent(bag, |bag| field.swap_hold_piece(bag)),
-                    _ => {}
+                    // like a hard drop but leaving the piece to lock on its own
+                    Input::SonicDrop => {
+                        let row = field.cur_piece().coords()[0].0;
+                        field.project_down();
+                        n_dropped_rows = (field.cur_piece().coords()[0].0 - row) as u32;
+                        n_dropped_rows > 0
+                    }
+                    Input::RotateCw => field.try_rotate_cw(c.kick_table.table()).is_ok(),
+                    Input::RotateCcw => field.try_rotate_ccw(c.kick_table.table()).is_ok(),
+                    Input::Rotate180 => field.try_rotate_180(c.kick_table_180.table()).is_ok(),
+                    Input::SwapHold => {
+                        let result = util::with_signal_mut_silent(bag, |bag| field.swap_hold_piece(bag));
+                        // hold is used up even if the piece swapped in is blocked
+                        let held = !matches!(result, Err(MoveError::HoldUsed | MoveError::Disallowed));
+                        if held {
+                            totals.get_untracked().borrow_mut().spawn_piece(field);
+                            recharge_das();
+                        }
+                        held
+                    }
+                    Input::Undo if practicing() => {
+                        let last = undo_history.get_untracked().borrow_mut().pop();
+                        if let Some((mut old_totals, old_bag)) = last {
+                            *field = old_totals.spawn_field().unwrap().clone();
+                            old_totals.spawn_piece(field);
+                            n_finesse_faults.set(old_totals.finesse_faults);
+                            *totals.get_untracked().borrow_mut() = old_totals;
+                            util::with_signal_mut_silent(bag, |bag| *bag = old_bag.clone());
+                            history_edited.set(true);
+                        }
+                        false
+                    }
+                    Input::SwapPiece if practicing() => {
+                        let kinds = piece_kinds.get_untracked();
+                        let cur = kinds.iter().position(|&k| k == field.cur_piece().kind());
+                        let next = cur.map_or(0, |i| (i + 1) % kinds.len());
+                        if field.try_replace_cur_piece(kinds[next]).is_ok() {
+                            totals.get_untracked().borrow_mut().spawn_piece(field);
+                            history_edited.set(true);
+                        }
+                        false
+                    }
+                    // random holes come from the same generator as survival garbage
+                    Input::AddGarbage => {
+                        let n_rows = c.practice_garbage_rows;
+                        if c.practice_garbage_fixed_hole {
+                            field.add_garbage(n_rows, &mut ScriptedGarbage::new(vec![c.practice_garbage_hole - 1]));
+                        } else {
+                            field.add_garbage(n_rows, &mut *garbage.get_untracked().borrow_mut());
+                        }
+                        n_garbage_rows.set(*n_garbage_rows.get_untracked() + n_rows);
+                        garbage_received.set(true);
+                        false
+                    }
+                    _ => false,
+                };
+                if moved {
+                    if matches!(input, Input::RotateCw | Input::RotateCcw | Input::Rotate180 | Input::SwapHold) {
+                        cut_das();
+                    }
+                    if *input == Input::SoftDrop {
+                        n_dropped_rows = 1;
+                    }
+                    if n_dropped_rows > 0 {
+                        totals.get_untracked().borrow_mut().score.add_drop(n_dropped_rows, false);
+                    }
+                    record(*input, 1);
                 }
             });
 
             // see comment below
             if *input == Input::HardDrop {
-                hard_drop(field_signal, bag, spin_types, last_line_clear);
+                drop_piece();
             }
 
             // only notify bag subscribers after the field is updated
             // certain field updates (e.g. hard drop) also update the bag, which updates the next queue, which
             // requires a reference to the field (but `with_signal_mut` already has an exclusive reference)
             util::notify_subscribers(bag);
-        });
+        }
     };
 
-    let keyup_handler = |e: Event| {
-        let e = e.dyn_into::<KeyboardEvent>().unwrap();
-        let c = config.get();
-        let c = c.borrow();
-
-        c.keybinds.get_by_right(&e.key()).map(|input| {
-            util::with_signal_mut(inputs, |inputs| inputs.set_released(input));
+    let release_input = move |input: &Input| {
+        util::with_signal_mut(inputs, |inputs| inputs.set_released(input));
 
-            // cancel timers on release
-            // this means pressing the input again before the buffer timer completes will not cause the action to run
-            match input {
-                Input::Left => left_timer.get().stop(),
-                Input::Right => right_timer.get().stop(),
-                Input::SoftDrop => soft_drop_timer.get().stop(),
-                _ => {}
+        // cancel timers on release
+        // this means pressing the input again before the buffer timer completes will not cause the action to run
+        match input {
+            Input::Left | Input::Right => {
+                let (timer, _, charged) = if input == &Input::Left { left_timers } else { right_timers };
+                timer.get().stop();
+                charged.set(false);
             }
-        });
+            Input::SoftDrop => soft_drop_timer.get().stop(),
+            Input::Reset => {
+                reset_timer.get().stop();
+                resetting.set(false);
+            }
+            _ => {}
+        }
+    };
+    // modifiers can change while the key is held, so it releases everything bound to it
+    let keyup_handler = move |e: &KeyboardEvent| {
+        let c = config.get();
+        c.borrow().keybinds.inputs_with_code(&e.code()).for_each(release_input);
     };
+    util::on_window_event(cx, "keydown", keydown_handler);
+    util::on_window_event(cx, "keyup", keyup_handler);
+
+    // keys released while the window is out of focus are never seen, so everything is released when it loses focus
+    let focused = create_signal(cx, web_sys::window().unwrap().document().unwrap().has_focus().unwrap_or(true));
+    util::on_window_event(cx, "focus", |_: &Event| focused.set(true));
+    util::on_window_event(cx, "blur", move |_: &Event| {
+        focused.set(false);
+        Input::iter().for_each(|input| release_input(&input));
+    });
 
     let move_limit = util::create_config_selector(cx, config, |c| c.move_limit);
     let actions_since_lock_delay = create_selector(cx, || {
@@ -327,28 +1120,34 @@ pub fn Board<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     });
 
     // action limit (after piece touches stack)
-    create_effect(cx, || {
+    create_effect(cx, move || {
         let limit_reached = actions_since_lock_delay.get() == move_limit.get_untracked();
         if config.get_untracked().borrow().move_limit_enabled && limit_reached {
-            hard_drop(field_signal, bag, spin_types, last_line_clear);
+            drop_piece();
         }
     });
 
     let style_values = util::create_config_selector(cx, config, |c| (c.field_zoom * 100.0, c.vertical_offset));
     let game_style = style_values.map(cx, |d| format!("transform: scale({}%); margin-top: {}px;", d.0, d.1));
 
-    // opacity of unfocused warning overlay text
-    let focus_warning_opacity = create_signal(cx, 0.0);
-    let focus_warning_style = focus_warning_opacity.map(cx, |o| format!("opacity: {};", o));
+    // unfocused warning overlay text shows while the window is out of focus
+    let focus_warning_style = focused.map(cx, |f| format!("opacity: {};", if *f { 0 } else { 1 }));
     let focus_warning_enabled = util::create_config_selector(cx, config, |c| c.focus_warning_enabled);
+    let input_display_enabled = util::create_config_selector(cx, config, |c| c.input_display_enabled);
+
+    // the field moves around while shaking from heavy clears
+    let shake_offset = create_signal(cx, (0.0, 0.0));
+    let field_style = shake_offset.map(cx, |(x, y)| format!("transform: translate({}px, {}px);", x, y));
+
+    // the hold and next panels can be swapped to the other sides or hidden
+    let panels =
+        util::create_config_selector(cx, config, |c| (c.panels_swapped, c.hold_panel_enabled, c.next_panel_enabled));
+    let game_class = panels.map(cx, |p| if p.0 { "game game-swapped" } else { "game" });
 
     view! { cx,
         div(
-            class="game",
-            tabindex="0",
+            class=*game_class.get(),
             style=game_style.get(),
-            on:keydown=keydown_handler, on:keyup=keyup_handler,
-            on:focusin=|_| focus_warning_opacity.set(0.0), on:focusout=|_| focus_warning_opacity.set(1.0),
         ) {
             // board unfocused warning overlay
             (if *focus_warning_enabled.get() {
@@ -358,47 +1157,148 @@ pub fn Board<'a, G: Html>(cx: Scope<'a>) -> View<G> {
             })
 
             div(class="field-panel") {
-                div(class="hold-piece") { HoldPiece {} }
-                div(class="game-stats") { Stats { last_line_clear, goal } }
+                (if panels.get().1 {
+                    view! { cx, div(class="hold-piece") { HoldPiece {} } }
+                } else {
+                    view! { cx, }
+                })
+                div(class="game-stats") { Stats { last_line_clear, goal, totals, n_finesse_faults, speed } }
+                (if *input_display_enabled.get() {
+                    view! { cx, InputDisplay { inputs, press_times } }
+                } else {
+                    view! { cx, }
+                })
+                // fills up while reset is held
+                (if *resetting.get() {
+                    let style = format!("animation-duration: {}ms;", reset_hold_delay.get());
+                    view! { cx, div(class="reset-progress", style=style) }
+                } else {
+                    view! { cx, }
+                })
+            }
+            div(class="field", style=field_style.get()) {
+                Field {}
+                Effects { last_line_clear, n_garbage_rows, shake_offset }
+                (match *countdown.get() {
+                    Some(n) => view! { cx, div(class="countdown") { (n) } },
+                    None => view! { cx, },
+                })
+                (if *game_over.get() {
+                    view! { cx,
+                        div(class="results") {
+                            p(class="results-heading") { (results_heading.get()) }
+                            table(class="results-table") {
+                                Indexed {
+                                    iterable: results,
+                                    view: |cx, (label, value)| view! { cx, tr { td { (label) } td { (value) } } },
+                                }
+                            }
+                            (match (*last_replay.get()).clone() {
+                                Some(recorded) => view! { cx, GameGraphs { recorded } },
+                                None => view! { cx, },
+                            })
+                            div(class="menu-button-box") {
+                                (if versus.is_none() {
+                                    view! { cx,
+                                        div(class="menu-option") { input(type="button", value="Retry", on:click=retry) }
+                                    }
+                                } else {
+                                    view! { cx, }
+                                })
+                                (if last_replay.get().is_some() {
+                                    view! { cx,
+                                        div(class="menu-option") {
+                                            input(type="button", value=save_replay_label.get(), on:click=save_replay)
+                                        }
+                                    }
+                                } else {
+                                    view! { cx, }
+                                })
+                                div(class="menu-option") { input(type="button", value="Menu", on:click=back_to_menu) }
+                            }
+                        }
+                    }
+                } else {
+                    view! { cx, }
+                })
             }
-            div(class="field") { Field {} }
-            div(class="next-queue") { NextQueue { bag } }
+            (if panels.get().2 {
+                view! { cx, div(class="next-queue") { NextQueue { bag } } }
+            } else {
+                view! { cx, }
+            })
+            (match (*ghost.get()).clone() {
+                Some(recorded) => view! { cx, GhostBoard { recorded, time_elapsed, n_lines } },
+                None => view! { cx, },
+            })
+            (if versus.is_some() {
+                view! { cx, OpponentBoard {} }
+            } else {
+                view! { cx, }
+            })
         }
     }
 }
 
-pub type AssetCache = HashMap<String, HtmlImageElement>;
-
-fn make_asset_cache() -> AssetCache {
-    <TetrominoSrs as PieceKindTrait>::iter()
-        .map(|k| k.asset_name().to_string())
-        .chain(["grey".to_string()])
-        .flat_map(|asset_name| {
-            let field_square_mul = canvas::SQUARE_WIDTH as u32;
-            crate::SKIN_NAMES.iter().map(move |skin| {
-                let image = HtmlImageElement::new_with_width_and_height(field_square_mul, field_square_mul).unwrap();
-                let asset_src = format!("assets/skins/{}/{}.png", skin, asset_name);
-                image.set_src(&asset_src);
-                (asset_src.to_string(), image)
-            })
-        })
-        .collect()
-}
+const COUNTDOWN_SECS: u32 = 3;
+
+// survival garbage stops speeding up once it rises this often, in milliseconds
+const MIN_SURVIVAL_INTERVAL: f64 = 250.0;
 
+// frame stepping can go back this many steps, three seconds at the default tick rate
+const MAX_REWIND_STEPS: usize = 180;
+
+// perfect clear attempts fail once the stack is taller than this many rows
+const PC_HEIGHT: usize = 4;
+
+// gives whether the piece was placed with a finesse fault
 fn hard_drop(
     field: &Signal<RefCell<DefaultField>>,
-    bag: &Signal<RefCell<impl Randomizer>>,
-    spin_types: &ReadSignal<SpinTypes>,
+    bag: &Signal<RefCell<impl Randomizer + Clone>>,
+    config: &Signal<RefCell<Config>>,
     last_line_clear: &Signal<Option<LineClear>>,
-) {
-    util::with_signal_mut_untracked(field, |field| {
+    totals: &Signal<RefCell<GameTotals>>,
+) -> bool {
+    let (rules, finesse_trainer_enabled, speed_curve) = {
+        let c = config.get_untracked();
+        let c = c.borrow();
+        (c.rules(), c.finesse_trainer_enabled, c.active_speed_curve().curve())
+    };
+
+    let fault = util::with_signal_mut_untracked(field, |field| {
         util::with_signal_mut_silent_untracked(bag, |bag| {
+            let dropped = field.shadow_piece();
+            let n_rows_dropped = dropped.coords()[0].0 - field.cur_piece().coords()[0].0;
+            let bag_before = bag.clone();
+            let line_clear = field.hard_drop(bag, rules.spin_detector);
+
+            let totals = totals.get_untracked();
+            let mut totals = totals.borrow_mut();
+            let (stats_before, score_before) = (totals.stats, totals.score);
+            totals.score.add_drop(n_rows_dropped as u32, true);
+            // clears score more at higher levels, with levels below 1 (e.g. in nes) scoring like level 1
+            let level = speed_curve.map_or(1, |curve| curve.level(totals.stats.n_lines() as usize).max(1));
+            let fault = totals.add(&line_clear, &dropped, &rules, level as u32);
+
+            // the finesse trainer takes back placements with faults, so the piece has to be placed again
+            if let Some(spawn_field) = totals.spawn_field().filter(|_| fault && finesse_trainer_enabled) {
+                *field = spawn_field.clone();
+                *bag = bag_before;
+                totals.stats = stats_before;
+                totals.score = score_before;
+                totals.spawn_piece(field);
+                return true;
+            }
+            totals.spawn_piece(field);
+
             // silent so effects depending on this don't try to double borrow the field
-            last_line_clear.set_silent(Some(field.hard_drop(bag, spin_types.get().detector())))
+            last_line_clear.set_silent(Some(line_clear));
+            fault
         })
     });
     util::notify_subscribers(last_line_clear);
     util::notify_subscribers(bag);
+    fault
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -1,40 +1,76 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use sycamore::{
     component,
     generic_node::{DomNode, Html},
-    prelude::{create_effect, create_node_ref, create_selector, use_context, NodeRef, ReadSignal, Scope, Signal},
+    motion::create_raf,
+    prelude::{
+        create_effect, create_memo, create_node_ref, create_ref, create_selector, create_signal, try_use_context,
+        use_context, NodeRef, ReadSignal, Scope, Signal,
+    },
     view,
     view::View,
     Prop,
 };
 use tetrox::{
-    field::{DefaultField, Square},
-    pieces::PieceKind,
-    Coords, Randomizer,
+    field::{Connections, DefaultField, Square},
+    game::{Game, GameRules},
+    parse_queue,
+    pieces::{tetromino::TetrominoSrs, PieceKind, PieceKindTrait},
+    sim, Coords, FixedQueue, Randomizer,
 };
 use wasm_bindgen::JsCast;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
 
 use crate::{
-    board::AssetCache,
-    config::{Config, FieldValues, PieceTypes},
-    util,
+    config::{Config, FieldValues, GridStyles, Palettes, PieceTypes},
+    skins::{self, AssetCache, CustomSkin},
+    timer, util,
 };
 
 pub const SQUARE_WIDTH: usize = 36; // the size of each square on the field
 pub const LABEL_HEIGHT: usize = 30; // height of "hold" and "next" labels
 pub const SIDE_BAR_PADDING: usize = SQUARE_WIDTH / 6; // bottom padding of hold/queue panels
+const HIDDEN_ROW_PEEK: usize = SQUARE_WIDTH / 4; // how much of the lowest hidden row shows when they're clipped
+const GRID_DOT_SIZE: f64 = 3.0; // width of the dots where grid lines would cross, for the dotted grid style
+
+// pieces in a queue too long to fit beside the field are shrunk down to this much of their usual size, and the queue
+// scrolls if it still doesn't fit
+const MIN_QUEUE_PIECE_SCALE: f64 = 0.5;
+
+// whether only the current piece is drawn on the field, with the stack and shadow hidden (e.g. for placing blind)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StackHidden(pub bool);
+
+// a line clear being animated on the field, starting at a time from the clock and lasting for a duration in
+// milliseconds
+#[derive(Clone, Debug)]
+pub struct ClearAnimation {
+    pub cleared_rows: Vec<usize>,
+    pub start: f64,
+    pub duration: f64,
+}
+
+impl ClearAnimation {
+    // how far through the animation it is at a time, from zero to one
+    fn progress(&self, now: f64) -> f64 { ((now - self.start) / self.duration).clamp(0.0, 1.0) }
+}
 
 #[component]
 pub fn HoldPiece<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     let hold_piece_ref = create_node_ref(cx);
+    let size = create_memo(cx, move || {
+        let height = LABEL_HEIGHT + padded_piece_height(cx) + SIDE_BAR_PADDING;
+        (scaled_panel_px(cx, padded_piece_width(cx)), scaled_panel_px(cx, height))
+    });
+
     let view = view! { cx,
         canvas(
             ref=hold_piece_ref,
             class="hold-piece-canvas",
-            width=padded_piece_width(cx),
-            height=(LABEL_HEIGHT + padded_piece_height(cx) + SIDE_BAR_PADDING),
+            width=canvas_px(cx, size.get().0),
+            height=canvas_px(cx, size.get().1),
+            style=css_size(*size.get()),
         )
     };
 
@@ -43,13 +79,15 @@ pub fn HoldPiece<'a, G: Html>(cx: Scope<'a>) -> View<G> {
 
     let config = use_context::<Signal<RefCell<Config>>>(cx);
     let piece_type = util::create_config_selector(cx, config, |c| c.piece_type);
-    let skin_name = util::create_config_selector(cx, config, |c| c.skin_name.clone());
+    let style = util::create_config_selector(cx, config, CanvasStyle::new);
+    let piece_cache = create_piece_cache(cx);
 
     create_effect(cx, move || {
         // make sure the canvas updates every time the piece type does (which causes the canvas size to update)
         piece_type.track();
-        get_canvas_drawer(hold_piece_ref, &field.get().borrow(), asset_cache, skin_name)
-            .map(|c| c.draw_hold_piece(cx));
+        if let Some(c) = get_canvas_drawer(hold_piece_ref, &field.get().borrow(), asset_cache, style) {
+            c.with_piece_cache(piece_cache).draw_hold_piece(cx);
+        }
     });
 
     view
@@ -68,9 +106,13 @@ pub fn Field<'a, G: Html>(cx: Scope<'a>) -> View<G> {
         canvas(
             ref=field_ref,
             class="field-canvas",
-            width=(SQUARE_WIDTH * field_dims.get().0),
-            height=(SQUARE_WIDTH * field_dims.get().1),
-            style=format!("margin-top: -{}px;", SQUARE_WIDTH * field_dims.get().2),
+            width=canvas_px(cx, SQUARE_WIDTH * field_dims.get().0),
+            height=canvas_px(cx, SQUARE_WIDTH * field_dims.get().1),
+            style=format!(
+                "{} margin-top: -{}px;",
+                css_size((SQUARE_WIDTH * field_dims.get().0, SQUARE_WIDTH * field_dims.get().1)),
+                SQUARE_WIDTH * field_dims.get().2,
+            ),
         )
     };
 
@@ -78,12 +120,169 @@ pub fn Field<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     let asset_cache = use_context::<AssetCache>(cx);
 
     let config = use_context::<Signal<RefCell<Config>>>(cx);
-    let field_drawer_values = util::create_config_selector(cx, config, |c| (c.shadow_opacity, c.topping_out_enabled));
-    let skin_name = util::create_config_selector(cx, config, |c| c.skin_name.clone());
+    let field_drawer_values = util::create_config_selector(cx, config, |c| (c.shadow_opacity, c.topping_out()));
+    let style = util::create_config_selector(cx, config, CanvasStyle::new);
+
+    // provided by boards for placing blind, so the replay viewer always draws the stack
+    let stack_hidden = try_use_context::<ReadSignal<StackHidden>>(cx);
+
+    // also provided by boards, with the field redrawn every frame until the clear is done animating
+    let clear_animation = try_use_context::<Signal<Option<ClearAnimation>>>(cx);
+    let clock = timer::use_clock(cx);
+    let now = create_signal(cx, clock.now());
+    let (_, start_animating, stop_animating) = create_raf(cx, move || now.set(clock.now()));
+    let clear_progress = create_memo(cx, move || {
+        let animation = clear_animation?.get();
+        let animation = animation.as_ref().as_ref()?;
+        let progress = animation.progress(*now.get());
+        (progress < 1.0).then(|| (animation.cleared_rows.clone(), progress))
+    });
+    create_effect(cx, || match clear_progress.get().is_some() {
+        true => start_animating(),
+        false => stop_animating(),
+    });
+
+    // what the field was last drawn with, so only the rows that changed are drawn again if nothing else did
+    let last_drawn = create_ref(cx, RefCell::new(None::<DrawnField>));
 
-    create_effect(cx, || {
-        get_canvas_drawer(field_ref, &field.get().borrow(), asset_cache, skin_name)
-            .map(|c| c.draw_field(*field_dims.get(), *field_drawer_values.get()));
+    create_effect(cx, move || {
+        let stack_hidden = stack_hidden.is_some_and(|h| h.get().0);
+        let clear_progress = clear_progress.get();
+        let clear_animation = clear_progress.as_ref().as_ref().map(|(rows, progress)| (rows.as_slice(), *progress));
+
+        let field = field.get();
+        let dirty_rows = field.borrow_mut().take_dirty_rows();
+        let field = field.borrow();
+        let Some(drawer) = get_canvas_drawer(field_ref, &field, asset_cache, style) else {
+            return;
+        };
+
+        let drawn = DrawnField::new(&field, *field_dims.get(), *field_drawer_values.get(), stack_hidden, style.get());
+        let mut last_drawn = last_drawn.borrow_mut();
+        let redraw_rows = match &*last_drawn {
+            Some(last) if last.settings == drawn.settings && clear_animation.is_none() => {
+                Some(drawn.redraw_rows(last, &dirty_rows))
+            }
+            _ => None,
+        };
+        let (dims, drawer_values) = (drawn.settings.dims, drawn.settings.drawer_values);
+        drawer.draw_field(dims, drawer_values, stack_hidden, clear_animation, redraw_rows);
+
+        // the whole field is drawn every frame while a clear animates, and once after
+        *last_drawn = clear_animation.is_none().then_some(drawn);
+    });
+
+    view
+}
+
+// everything the field canvas was drawn with besides the stack, which has to be the same for only the rows of the stack
+// that changed to be drawn again
+#[derive(PartialEq)]
+struct FieldSettings {
+    dims: (usize, usize, usize),
+    drawer_values: (f64, bool),
+    stack_hidden: bool,
+    topped_out: bool,
+    style: Rc<CanvasStyle>,
+    // rows added above the field when pieces are moved above it, which aren't drawn
+    extra_rows: usize,
+}
+
+struct DrawnField {
+    settings: FieldSettings,
+    // rows with the shadow or a falling piece in them, which change without the stack changing
+    piece_rows: Vec<usize>,
+}
+
+impl DrawnField {
+    fn new(
+        field: &DefaultField,
+        dims: (usize, usize, usize),
+        drawer_values: (f64, bool),
+        stack_hidden: bool,
+        style: Rc<CanvasStyle>,
+    ) -> Self {
+        let settings = FieldSettings {
+            dims,
+            drawer_values,
+            stack_hidden,
+            topped_out: field.topped_out(),
+            style,
+            extra_rows: field.hidden().saturating_sub(dims.2),
+        };
+        let shadow_piece = field.shadow_piece();
+        let pieces = field.live_pieces().chain([&shadow_piece]);
+        let piece_rows = pieces.flat_map(|p| p.coords().iter().map(|Coords(row, _)| *row as usize)).collect();
+        DrawnField { settings, piece_rows }
+    }
+
+    // which rows of the canvas to draw again, given the rows of the stack that changed since the last field was drawn
+    // the squares next to a changed one can have their connections change too
+    fn redraw_rows(&self, last: &DrawnField, dirty_rows: &[usize]) -> Vec<bool> {
+        let (extra_rows, height) = (self.settings.extra_rows, self.settings.dims.1);
+        let mut redraw_rows = vec![false; height];
+        let neighbors = dirty_rows.iter().flat_map(|row| [row.saturating_sub(1), *row, row + 1]);
+        for row in neighbors.chain(last.piece_rows.iter().copied()).chain(self.piece_rows.iter().copied()) {
+            if let Some(redraw) = row.checked_sub(extra_rows).and_then(|row| redraw_rows.get_mut(row)) {
+                *redraw = true;
+            }
+        }
+        redraw_rows
+    }
+}
+
+// a small field with a few pieces placed and one falling, as (width, height, hidden rows)
+const PREVIEW_DIMS: (usize, usize, usize) = (6, 10, 3);
+const PREVIEW_QUEUE: &str = "IOLJT";
+const PREVIEW_INPUTS: &str = "left hard left left hard right right right hard right hard soft soft soft";
+
+fn preview_field() -> DefaultField {
+    let kinds = <TetrominoSrs as PieceKindTrait>::iter().collect::<Vec<_>>();
+    let queue = parse_queue(PREVIEW_QUEUE, &kinds).unwrap().into_iter().flatten().collect();
+    let (width, height, hidden) = PREVIEW_DIMS;
+    let mut game = Game::new(width, height, hidden, &kinds, FixedQueue::new(queue), GameRules::default());
+    sim::run(&mut game, &sim::Input::parse(PREVIEW_INPUTS).unwrap());
+    game.field().clone()
+}
+
+// the preview field drawn with the current skin, shadow opacity, and zoom, for seeing them change from the config panel
+#[component]
+pub fn FieldPreview<'a, G: Html>(cx: Scope<'a>) -> View<G> {
+    let (width, height, hidden) = PREVIEW_DIMS;
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let field_zoom = util::create_config_selector(cx, config, |c| c.field_zoom);
+    let hidden_rows_clipped = util::create_config_selector(cx, config, |c| c.hidden_rows_clipped);
+    let preview_ref = create_node_ref(cx);
+
+    // scaled like the board is, with the hidden rows cut off by the preview box (except for what shows when they're
+    // clipped on the board)
+    let style = create_memo(cx, move || {
+        let square_width = SQUARE_WIDTH as f64 * *field_zoom.get();
+        let peek = if *hidden_rows_clipped.get() { HIDDEN_ROW_PEEK as f64 * *field_zoom.get() } else { 0.0 };
+        let margin = square_width * hidden as f64 - peek;
+        format!("width: {}px; margin-top: -{}px;", square_width * width as f64, margin)
+    });
+
+    let view = view! { cx,
+        div(class="field-preview") {
+            canvas(
+                ref=preview_ref,
+                width=canvas_px(cx, SQUARE_WIDTH * width),
+                height=canvas_px(cx, SQUARE_WIDTH * height),
+                style=style.get(),
+            )
+        }
+    };
+
+    let field = create_ref(cx, preview_field());
+    let asset_cache = use_context::<AssetCache>(cx);
+    let shadow_opacity = util::create_config_selector(cx, config, |c| c.shadow_opacity);
+    let style = util::create_config_selector(cx, config, CanvasStyle::new);
+
+    create_effect(cx, move || {
+        if let Some(c) = get_canvas_drawer(preview_ref, field, asset_cache, style) {
+            c.draw_field(PREVIEW_DIMS, (*shadow_opacity.get(), false), false, None, None);
+        }
     });
 
     view
@@ -100,13 +299,30 @@ pub fn NextQueue<'a, R: Randomizer, G: Html>(cx: Scope<'a>, props: NextQueueProp
     let queue_len = create_selector(cx, || field_vals.get().queue_len);
     let next_queue_ref = create_node_ref(cx);
 
+    let queue_height =
+        create_memo(cx, move || LABEL_HEIGHT + queue_piece_height(cx) * *queue_len.get() + SIDE_BAR_PADDING);
+    let size = create_memo(cx, move || {
+        (scaled_panel_px(cx, padded_piece_width(cx)), scaled_panel_px(cx, *queue_height.get()))
+    });
+    let scroll_style = create_memo(cx, move || {
+        let max_height = queue_max_height(cx);
+        if *queue_height.get() > max_height {
+            format!("max-height: {}px; overflow-y: auto;", scaled_panel_px(cx, max_height))
+        } else {
+            String::new()
+        }
+    });
+
     let view = view! { cx,
-        canvas(
-            ref=next_queue_ref,
-            class="next-queue-canvas",
-            width=padded_piece_width(cx),
-            height=(LABEL_HEIGHT + padded_piece_height(cx) * *queue_len.get() + SIDE_BAR_PADDING),
-        )
+        div(style=scroll_style.get()) {
+            canvas(
+                ref=next_queue_ref,
+                class="next-queue-canvas",
+                width=canvas_px(cx, size.get().0),
+                height=canvas_px(cx, size.get().1),
+                style=css_size(*size.get()),
+            )
+        }
     };
 
     let field = use_context::<Signal<RefCell<DefaultField>>>(cx);
@@ -114,18 +330,55 @@ pub fn NextQueue<'a, R: Randomizer, G: Html>(cx: Scope<'a>, props: NextQueueProp
 
     let config = use_context::<Signal<RefCell<Config>>>(cx);
     let queue_len = util::create_config_selector(cx, config, |c| c.queue_len);
-    let skin_name = util::create_config_selector(cx, config, |c| c.skin_name.clone());
+    let style = util::create_config_selector(cx, config, CanvasStyle::new);
+    let piece_cache = create_piece_cache(cx);
 
     create_effect(cx, move || {
-        get_canvas_drawer(next_queue_ref, &field.get_untracked().borrow(), asset_cache, skin_name)
-            .map(|c| c.draw_next_queue(cx, props.bag, *queue_len.get()));
+        if let Some(c) = get_canvas_drawer(next_queue_ref, &field.get_untracked().borrow(), asset_cache, style) {
+            c.with_piece_cache(piece_cache).draw_next_queue(cx, props.bag, *queue_len.get());
+        }
     });
 
     view
 }
 
+// canvases are drawn with this many of their pixels per css pixel, so they're as sharp as the screen and field zoom
+// allow instead of being scaled up blurrily
+pub fn canvas_resolution(config: &Config) -> f64 { web_sys::window().unwrap().device_pixel_ratio() * config.field_zoom }
+
+// size in the canvas's own pixels for a size in css pixels
+pub fn canvas_px(cx: Scope<'_>, css_px: usize) -> usize {
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let resolution = util::create_config_selector(cx, config, canvas_resolution);
+    (css_px as f64 * *resolution.get()).round() as usize
+}
+
+// style for showing a canvas at a size in css pixels, as (width, height)
+pub fn css_size((width, height): (usize, usize)) -> String { format!("width: {}px; height: {}px;", width, height) }
+
+// the hold and next panels are scaled apart from the field, with their canvases drawn at the scaled size
+fn scaled_panel_px(cx: Scope<'_>, px: usize) -> usize {
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let panel_scale = util::create_config_selector(cx, config, |c| c.panel_scale);
+    (px as f64 * *panel_scale.get()).round() as usize
+}
+
 fn padded_piece_width(cx: Scope<'_>) -> usize { padded_piece_dims(cx).1 }
 
+// the next queue is shrunk to fit beside the visible part of the field
+fn queue_max_height(cx: Scope<'_>) -> usize {
+    let field_vals = use_context::<ReadSignal<FieldValues>>(cx).get();
+    field_vals.height.saturating_sub(field_vals.hidden) * SQUARE_WIDTH
+}
+
+// height of each piece in the next queue, which is less than usual if the queue is shrunk
+fn queue_piece_height(cx: Scope<'_>) -> usize {
+    let queue_len = use_context::<ReadSignal<FieldValues>>(cx).get().queue_len;
+    let piece_height = padded_piece_height(cx);
+    let fitted = queue_max_height(cx).saturating_sub(LABEL_HEIGHT + SIDE_BAR_PADDING) / queue_len.max(1);
+    fitted.clamp((piece_height as f64 * MIN_QUEUE_PIECE_SCALE) as usize, piece_height)
+}
+
 fn padded_piece_height(cx: Scope<'_>) -> usize { padded_piece_dims(cx).0 }
 
 fn padded_piece_dims(cx: Scope<'_>) -> (usize, usize) {
@@ -136,35 +389,108 @@ fn padded_piece_dims(cx: Scope<'_>) -> (usize, usize) {
     ((dims.0 + 1) * SQUARE_WIDTH, (dims.1 + 1) * SQUARE_WIDTH)
 }
 
+// the rows and columns of squares a piece in a side panel fits in, which bigger pieces are shrunk to fit
+fn piece_slot(cx: Scope<'_>) -> (f64, f64) {
+    let (rows, cols) = max_piece_kind_dims(use_context::<ReadSignal<FieldValues>>(cx).get_untracked().piece_type);
+    (rows as f64, cols as f64)
+}
+
 // bounding dimensions (rows and columns, in order) for the given piece kind
 fn max_piece_kind_dims(kind: PieceTypes) -> (usize, usize) {
     match kind {
         PieceTypes::TetrominoSrs | PieceTypes::TetrominoAsc | PieceTypes::Mino1234 => (2, 4),
         PieceTypes::Mino123 => (2, 3),
-        PieceTypes::Pentomino => (3, 5),
+        PieceTypes::Pentomino | PieceTypes::TetrominoPentomino | PieceTypes::Mino12345 => (3, 5),
     }
 }
 
+// download what's drawn on the field canvas as a png
+pub fn save_field_image() {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let Some(canvas) = document.query_selector(".field-canvas").ok().flatten() else {
+        return;
+    };
+    let url = canvas.unchecked_into::<HtmlCanvasElement>().to_data_url().unwrap();
+    util::download_url("tetrox.png", &url);
+}
+
 fn get_canvas_drawer<'a, 'b, G: Html>(
     canvas_ref: &NodeRef<G>,
     field: &'b DefaultField,
     asset_cache: &'a AssetCache,
-    skin_name: &'a ReadSignal<String>,
+    style: &'a ReadSignal<CanvasStyle>,
 ) -> Option<CanvasDrawer<'a, 'b>> {
     // get a `CanvasDrawer` for the given `canvas_ref`
     canvas_ref.try_get::<DomNode>().map(|node| {
         let canvas = node.unchecked_into::<HtmlCanvasElement>();
         let context = canvas.get_context("2d").unwrap().unwrap();
         let context = context.dyn_into::<CanvasRenderingContext2d>().unwrap();
-        CanvasDrawer::new(asset_cache, field, context, skin_name.get())
+        CanvasDrawer::new(asset_cache, field, context, style.get())
     })
 }
 
+// pieces drawn on their own canvases, which the hold and next panels copy instead of drawing every square again
+// they're drawn again when the style they were drawn with changes
+#[derive(Default)]
+pub struct PieceCache {
+    style: Option<Rc<CanvasStyle>>,
+    pieces: HashMap<PieceKind, HtmlCanvasElement>,
+}
+
+fn create_piece_cache<'a>(cx: Scope<'a>) -> &'a RefCell<PieceCache> {
+    let piece_cache = create_ref(cx, RefCell::new(PieceCache::default()));
+
+    // uploading a skin with the same name as another one replaces its squares
+    let custom_skins = use_context::<Signal<Vec<CustomSkin>>>(cx);
+    create_effect(cx, move || {
+        custom_skins.track();
+        piece_cache.borrow_mut().pieces.clear();
+    });
+    piece_cache
+}
+
+// the skin and theme settings the canvases are drawn with
+#[derive(Clone, PartialEq)]
+pub struct CanvasStyle {
+    skin_name: String,
+    palette: Palettes,
+    grid_style: GridStyles,
+    grid_color: String,
+    grid_opacity: f64,
+    skyline: bool,
+    panel_tint: String,
+    text_color: String,
+    panel_scale: f64,
+    resolution: f64,
+    bag_separators: bool,
+    hidden_rows_clipped: bool,
+}
+
+impl CanvasStyle {
+    pub fn new(config: &Config) -> Self {
+        CanvasStyle {
+            skin_name: config.skin_name.clone(),
+            palette: config.palette,
+            grid_style: config.grid_style,
+            grid_color: config.grid_color.clone(),
+            grid_opacity: config.grid_opacity,
+            skyline: config.skyline_enabled,
+            panel_tint: config.panel_tint.clone(),
+            text_color: config.text_color.clone(),
+            panel_scale: config.panel_scale,
+            resolution: canvas_resolution(config),
+            bag_separators: config.bag_separators_enabled,
+            hidden_rows_clipped: config.hidden_rows_clipped,
+        }
+    }
+}
+
 pub struct CanvasDrawer<'a, 'b> {
     asset_cache: &'a AssetCache,
     field: &'b DefaultField,
     context: CanvasRenderingContext2d,
-    skin_name: Rc<String>,
+    style: Rc<CanvasStyle>,
+    piece_cache: Option<&'a RefCell<PieceCache>>,
 }
 
 impl<'a, 'b> CanvasDrawer<'a, 'b> {
@@ -172,13 +498,25 @@ impl<'a, 'b> CanvasDrawer<'a, 'b> {
         asset_cache: &'a AssetCache,
         field: &'b DefaultField,
         context: CanvasRenderingContext2d,
-        skin_name: Rc<String>,
+        style: Rc<CanvasStyle>,
     ) -> Self {
+        // drawing is done in css pixels
+        let resolution = style.resolution;
+        context.set_transform(resolution, 0.0, 0.0, resolution, 0.0, 0.0).unwrap();
         CanvasDrawer {
             asset_cache,
             field,
             context,
-            skin_name,
+            style,
+            piece_cache: None,
+        }
+    }
+
+    // draw pieces in the hold and next panels by copying them from a cache
+    fn with_piece_cache(self, piece_cache: &'a RefCell<PieceCache>) -> Self {
+        CanvasDrawer {
+            piece_cache: Some(piece_cache),
+            ..self
         }
     }
 
@@ -187,16 +525,16 @@ impl<'a, 'b> CanvasDrawer<'a, 'b> {
         let hp_h_px = (LABEL_HEIGHT + piece_height + SIDE_BAR_PADDING) as f64;
 
         let ctx = &self.context;
-        ctx.set_fill_style(&"black".into());
+        self.scale_to_panel();
         ctx.clear_rect(0.0, 0.0, piece_width as f64, hp_h_px);
 
         // fill background
-        ctx.set_stroke_style(&"black".into());
+        ctx.set_fill_style(&self.style.panel_tint.as_str().into());
         ctx.set_global_alpha(0.6);
         ctx.fill_rect(0.0, 0.0, piece_width as f64, hp_h_px);
 
         // draw label
-        ctx.set_fill_style(&"#ccc".into());
+        ctx.set_fill_style(&self.style.text_color.as_str().into());
         ctx.set_global_alpha(1.0);
         ctx.set_font("18px 'IBM Plex Sans'");
         ctx.fill_text("hold", 8.0, 24.0).unwrap();
@@ -204,11 +542,20 @@ impl<'a, 'b> CanvasDrawer<'a, 'b> {
         // dim the held piece if it cannot be swapped out again
         ctx.set_global_alpha(if self.field.hold_swapped() { 0.3 } else { 1.0 });
         if let Some(kind) = self.field.hold_piece() {
-            self.draw_piece(kind, piece_width / 2, LABEL_HEIGHT + piece_height / 2)
+            self.draw_piece(kind, (piece_width / 2, LABEL_HEIGHT + piece_height / 2), piece_slot(cx))
         }
     }
 
-    fn draw_field(&self, (width, height, hidden): (usize, usize, usize), (shadow_opacity, topping_out): (f64, bool)) {
+    fn draw_field(
+        &self,
+        (width, height, hidden): (usize, usize, usize),
+        (shadow_opacity, topping_out): (f64, bool),
+        stack_hidden: bool,
+        // rows cleared by the last clear and how far through animating it is, if it's still animating
+        clear_animation: Option<(&[usize], f64)>,
+        // whether each row of the canvas is drawn again, if only some changed since it was last drawn
+        redraw_rows: Option<Vec<bool>>,
+    ) {
         let field = self.field;
 
         // field width and height in squares
@@ -221,107 +568,310 @@ impl<'a, 'b> CanvasDrawer<'a, 'b> {
         let fhidden_end_px = (hidden * SQUARE_WIDTH) as f64; // end of board hidden area
 
         let ctx = &self.context;
-        ctx.set_fill_style(&"black".into());
-        ctx.clear_rect(0.0, 0.0, fw_px, fh_px);
+        ctx.save();
+
+        // drawing is clipped to the rows drawn again, which are cleared first
+        let redrawn = |row: usize| redraw_rows.as_ref().is_none_or(|rows| rows.get(row).copied().unwrap_or(false));
+        match &redraw_rows {
+            Some(_) => {
+                ctx.begin_path();
+                for row in (0..height).filter(|row| redrawn(*row)) {
+                    let row_px = (row * SQUARE_WIDTH) as f64;
+                    ctx.clear_rect(0.0, row_px, fw_px, SQUARE_WIDTH as f64);
+                    ctx.rect(0.0, row_px, fw_px, SQUARE_WIDTH as f64);
+                }
+                ctx.clip();
+            }
+            None => ctx.clear_rect(0.0, 0.0, fw_px, fh_px),
+        }
+
+        // like in guideline games, only the bottom of the lowest hidden row shows if the hidden rows are clipped, so
+        // pieces above the visible rows aren't completely invisible
+        if self.style.hidden_rows_clipped {
+            let clip_top_px = fhidden_end_px - HIDDEN_ROW_PEEK as f64;
+            ctx.begin_path();
+            ctx.rect(0.0, clip_top_px, fw_px, fh_px - clip_top_px);
+            ctx.clip();
+        }
 
         // fill background
+        ctx.set_fill_style(&self.style.panel_tint.as_str().into());
         ctx.set_global_alpha(0.6);
         ctx.fill_rect(0.0, fhidden_end_px, fw_px, fh_px);
 
-        ctx.set_stroke_style(&"#555".into());
-        ctx.set_global_alpha(0.3);
+        ctx.set_stroke_style(&self.style.grid_color.as_str().into());
+        ctx.set_fill_style(&self.style.grid_color.as_str().into());
+        ctx.set_global_alpha(self.style.grid_opacity);
+
+        match self.style.grid_style {
+            GridStyles::Lines => {
+                // vertical grid lines
+                for col in 1..width {
+                    ctx.begin_path();
+                    ctx.move_to((col * SQUARE_WIDTH) as f64, fhidden_end_px);
+                    ctx.line_to((col * SQUARE_WIDTH) as f64, fh_px);
+                    ctx.stroke();
+                }
 
-        // vertical grid lines
-        for col in 1..width {
-            ctx.begin_path();
-            ctx.move_to((col * SQUARE_WIDTH) as f64, fhidden_end_px);
-            ctx.line_to((col * SQUARE_WIDTH) as f64, fh_px);
-            ctx.stroke();
+                // horizontal grid lines (only for non-hidden board area)
+                for row in (hidden + 1..height).filter(|row| redrawn(*row) || redrawn(row - 1)) {
+                    ctx.begin_path();
+                    ctx.move_to(0.0, (row * SQUARE_WIDTH) as f64);
+                    ctx.line_to(fw_px, (row * SQUARE_WIDTH) as f64);
+                    ctx.stroke();
+                }
+            }
+            GridStyles::Dots => {
+                let size = GRID_DOT_SIZE;
+                let rows = (hidden + 1..height).filter(|row| redrawn(*row) || redrawn(row - 1));
+                for (row, col) in rows.flat_map(|row| (1..width).map(move |col| (row, col))) {
+                    let (y, x) = ((row * SQUARE_WIDTH) as f64, (col * SQUARE_WIDTH) as f64);
+                    ctx.fill_rect(x - size / 2.0, y - size / 2.0, size, size);
+                }
+            }
+            GridStyles::Off => {}
         }
 
-        // horizontal grid lines (only for non-hidden board area)
-        for row in hidden + 1..height {
+        // line along the top of the visible rows
+        if self.style.skyline {
+            ctx.set_stroke_style(&self.style.text_color.as_str().into());
+            ctx.set_global_alpha(0.6);
+            ctx.set_line_width(2.0);
             ctx.begin_path();
-            ctx.move_to(0.0, (row * SQUARE_WIDTH) as f64);
-            ctx.line_to(fw_px, (row * SQUARE_WIDTH) as f64);
+            ctx.move_to(0.0, fhidden_end_px);
+            ctx.line_to(fw_px, fhidden_end_px);
             ctx.stroke();
+            ctx.set_line_width(1.0);
         }
 
+        // the field grows upward when pieces are moved above it, so skip any rows added that way
+        let extra_rows = field.hidden().saturating_sub(hidden);
+
         ctx.set_global_alpha(shadow_opacity);
         let shadow_piece = field.shadow_piece();
         let topped_out = field.topped_out() && topping_out;
 
-        if !topped_out {
-            for Coords(row, col) in shadow_piece.coords() {
-                let kind = shadow_piece.kind();
-                let asset = kind.asset_name();
-                self.draw_square(asset, *row as usize * SQUARE_WIDTH, *col as usize * SQUARE_WIDTH);
+        if !topped_out && !stack_hidden {
+            let kind = shadow_piece.kind();
+            let asset = kind.asset_name();
+            for coords @ Coords(row, col) in shadow_piece.coords() {
+                if let Some(row) = (*row as usize).checked_sub(extra_rows) {
+                    let connections = Connections::within(shadow_piece.coords(), *coords);
+                    self.draw_square(asset, connections, row * SQUARE_WIDTH, *col as usize * SQUARE_WIDTH);
+                    self.draw_letter(kind, row * SQUARE_WIDTH, *col as usize * SQUARE_WIDTH);
+                }
             }
         }
 
+        // while a clear animates, the cleared rows flash and then the stack above them falls into place
+        let cleared_rows = clear_animation.map_or(&[][..], |(rows, _)| rows);
+        let (flash, fall) = match clear_animation {
+            Some((_, progress)) => ((1.0 - progress * 2.0).max(0.0), (progress * 2.0 - 1.0).max(0.0).powi(2)),
+            None => (0.0, 1.0),
+        };
+
         ctx.set_global_alpha(1.0);
-        for (row, line) in field.lines().iter().enumerate() {
-            for (col, square) in line.squares().iter().enumerate() {
-                if let Square::Filled(kind) = square {
-                    let asset = if topped_out { "grey" } else { kind.asset_name() };
-                    self.draw_square(asset, row * SQUARE_WIDTH, col * SQUARE_WIDTH);
+        let cur_piece = field.cur_piece().coords();
+        for (coords @ Coords(row, col), square) in field.occupied_cells() {
+            let is_cur_piece = cur_piece.contains(&coords);
+            if stack_hidden && !is_cur_piece {
+                continue;
+            }
+            let row = match is_cur_piece {
+                true => row as f64,
+                false => Self::falling_row(row as usize, cleared_rows, fall),
+            };
+            if row >= extra_rows as f64 && redrawn((row - extra_rows as f64) as usize) {
+                let (asset, kind) = match &square {
+                    Square::Filled(kind) if !topped_out => (kind.asset_name(), Some(*kind)),
+                    Square::Filled(_) | Square::Garbage => ("grey", None),
+                    Square::Empty => continue,
+                };
+                let row_px = ((row - extra_rows as f64) * SQUARE_WIDTH as f64).round() as usize;
+                let connections = field.connections_at(&coords);
+                self.draw_square(asset, connections, row_px, col as usize * SQUARE_WIDTH);
+                if let Some(kind) = kind {
+                    self.draw_letter(kind, row_px, col as usize * SQUARE_WIDTH);
                 }
             }
         }
+
+        // the cleared rows are already gone from the field, so they flash as bars
+        if flash > 0.0 {
+            ctx.set_fill_style(&"white".into());
+            ctx.set_global_alpha(flash * 0.8);
+            for row in cleared_rows.iter().filter_map(|row| row.checked_sub(extra_rows)) {
+                ctx.fill_rect(0.0, (row * SQUARE_WIDTH) as f64, fw_px, SQUARE_WIDTH as f64);
+            }
+            ctx.set_global_alpha(1.0);
+        }
+        ctx.restore();
+    }
+
+    // where a row of the stack is drawn while falling into place after a clear, going from the row it was at before
+    // the clear to the row it's at now as `fall` goes from zero to one
+    fn falling_row(row: usize, cleared_rows: &[usize], fall: f64) -> f64 {
+        let mut before = row;
+        for &cleared in cleared_rows.iter().rev() {
+            if cleared >= before {
+                before = before.saturating_sub(1);
+            }
+        }
+        before as f64 + (row - before) as f64 * fall
     }
 
     fn draw_next_queue(&self, cx: Scope<'a>, bag: &Signal<RefCell<impl Randomizer>>, queue_len: usize) {
         // total height of queue in pixels
-        let (piece_height, piece_width) = padded_piece_dims(cx);
+        let piece_width = padded_piece_width(cx);
+        let piece_height = queue_piece_height(cx);
         let nq_h_px = (LABEL_HEIGHT + piece_height * queue_len + SIDE_BAR_PADDING) as f64;
 
         let ctx = &self.context;
-        ctx.set_fill_style(&"black".into());
+        self.scale_to_panel();
         ctx.clear_rect(0.0, 0.0, piece_width as f64, nq_h_px);
 
         // fill background
-        ctx.set_stroke_style(&"black".into());
+        ctx.set_fill_style(&self.style.panel_tint.as_str().into());
         ctx.set_global_alpha(0.6);
         ctx.fill_rect(0.0, 0.0, piece_width as f64, nq_h_px);
 
         // draw label
-        ctx.set_fill_style(&"#ccc".into());
+        ctx.set_fill_style(&self.style.text_color.as_str().into());
         ctx.set_global_alpha(1.0);
         ctx.set_font("18px 'IBM Plex Sans'");
         ctx.fill_text("next", 8.0, 24.0).unwrap();
 
+        // shrunk pieces have smaller slots too
+        let shrink = piece_height as f64 / padded_piece_height(cx) as f64;
+        let slot = (piece_slot(cx).0 * shrink, piece_slot(cx).1 * shrink);
         util::with_signal_mut_silent(bag, |bag| {
-            for (nth, kind) in bag.peek().take(queue_len).enumerate() {
-                self.draw_piece(
-                    kind,
-                    piece_width / 2,
-                    LABEL_HEIGHT + piece_height * (nth + 1) - piece_height / 2,
-                )
+            for (nth, kind) in bag.peek_n(queue_len).enumerate() {
+                let y_center = LABEL_HEIGHT + piece_height * (nth + 1) - piece_height / 2;
+                self.draw_piece(kind, (piece_width / 2, y_center), slot)
+            }
+
+            // faint lines between the pieces from different bags
+            if self.style.bag_separators {
+                ctx.set_stroke_style(&self.style.text_color.as_str().into());
+                ctx.set_global_alpha(0.3);
+                for start in bag.bag_starts(queue_len) {
+                    let y = (LABEL_HEIGHT + piece_height * start) as f64;
+                    ctx.begin_path();
+                    ctx.move_to(8.0, y);
+                    ctx.line_to(piece_width as f64 - 8.0, y);
+                    ctx.stroke();
+                }
+                ctx.set_global_alpha(1.0);
             }
         });
     }
 
-    fn draw_piece(&self, kind: PieceKind, x_offset: usize, y_offset: usize) {
-        let base_coords = kind
-            .spawn_offsets()
-            .into_iter()
-            .map(|Coords(row, col)| Coords(row * SQUARE_WIDTH as i32, col * SQUARE_WIDTH as i32))
-            .collect();
+    // side panels are drawn at their usual size, scaled to fit their canvas
+    fn scale_to_panel(&self) {
+        let scale = self.style.panel_scale * self.style.resolution;
+        self.context.set_transform(scale, 0.0, 0.0, scale, 0.0, 0.0).unwrap();
+    }
 
-        let offset = Coords(y_offset as i32, x_offset as i32);
-        let final_coords = Self::center_coords_around_origin(base_coords)
-            .into_iter()
-            .map(|c| c + offset);
+    // draw a piece centered on a point, shrunk to fit in a slot of `max_dims` squares (rows and columns) if it's bigger
+    fn draw_piece(&self, kind: PieceKind, (x_center, y_center): (usize, usize), (max_rows, max_cols): (f64, f64)) {
+        let (_, (n_rows, n_cols)) = Self::piece_bounds(kind);
+        let (n_rows, n_cols) = (n_rows as f64, n_cols as f64);
+        let scale = (max_rows / n_rows).min(max_cols / n_cols).min(1.0);
 
-        for Coords(row, col) in final_coords {
-            self.draw_square(kind.asset_name(), row as usize, col as usize);
+        // the piece's bounding box is moved so its center is on the point, then scaled
+        let ctx = &self.context;
+        let half_square = SQUARE_WIDTH as f64 * scale / 2.0;
+        let left = (x_center as f64 - n_cols * half_square).round();
+        let top = (y_center as f64 - n_rows * half_square).round();
+        ctx.save();
+        ctx.translate(left, top).unwrap();
+        ctx.scale(scale, scale).unwrap();
+
+        match self.piece_cache {
+            Some(piece_cache) => {
+                let piece = self.cached_piece(piece_cache, kind);
+                let (width, height) = (n_cols * SQUARE_WIDTH as f64, n_rows * SQUARE_WIDTH as f64);
+                ctx.draw_image_with_html_canvas_element_and_dw_and_dh(&piece, 0.0, 0.0, width, height).unwrap();
+            }
+            None => {
+                self.draw_piece_squares(kind);
+            }
         }
+        ctx.restore();
     }
 
-    // draw a square at the given coords on a canvas
-    fn draw_square(&self, asset_name: &str, row: usize, col: usize) {
-        let asset_name = format!("assets/skins/{}/{}.png", self.skin_name, asset_name);
-        let asset = &self.asset_cache.get(&asset_name).unwrap();
+    // the top left of a piece's bounding box relative to its spawn offsets, and its size (rows and columns)
+    fn piece_bounds(kind: PieceKind) -> (Coords, (usize, usize)) {
+        let offsets = kind.spawn_offsets();
+        let min_row = offsets.iter().map(|Coords(row, _)| *row).min().unwrap();
+        let max_row = offsets.iter().map(|Coords(row, _)| *row).max().unwrap();
+        let min_col = offsets.iter().map(|Coords(_, col)| *col).min().unwrap();
+        let max_col = offsets.iter().map(|Coords(_, col)| *col).max().unwrap();
+        let dims = ((max_row - min_row + 1) as usize, (max_col - min_col + 1) as usize);
+        (Coords(min_row, min_col), dims)
+    }
+
+    // draw a piece with the top left of its bounding box at the origin, returning whether every square's image had
+    // loaded already
+    fn draw_piece_squares(&self, kind: PieceKind) -> bool {
+        let offsets = kind.spawn_offsets();
+        let (Coords(min_row, min_col), _) = Self::piece_bounds(kind);
+
+        let mut loaded = true;
+        for offset @ Coords(row, col) in offsets {
+            let connections = Connections::within(offsets, *offset);
+            let (row, col) = ((row - min_row) as usize * SQUARE_WIDTH, (col - min_col) as usize * SQUARE_WIDTH);
+            loaded &= self.draw_square(kind.asset_name(), connections, row, col);
+            self.draw_letter(kind, row, col);
+        }
+        loaded
+    }
+
+    // the piece drawn on a canvas the size of its bounding box, drawing it if it isn't cached
+    // pieces with squares that haven't loaded yet aren't cached, so they're drawn again once they have
+    fn cached_piece(&self, piece_cache: &RefCell<PieceCache>, kind: PieceKind) -> HtmlCanvasElement {
+        let mut piece_cache = piece_cache.borrow_mut();
+        if !piece_cache.style.as_ref().is_some_and(|style| Rc::ptr_eq(style, &self.style)) {
+            piece_cache.style = Some(self.style.clone());
+            piece_cache.pieces.clear();
+        }
+        if let Some(piece) = piece_cache.pieces.get(&kind) {
+            return piece.clone();
+        }
+
+        // drawn at the resolution the side panels are shown at
+        let (_, (n_rows, n_cols)) = Self::piece_bounds(kind);
+        let scale = self.style.panel_scale * self.style.resolution;
+        let document = web_sys::window().unwrap().document().unwrap();
+        let piece = document.create_element("canvas").unwrap().unchecked_into::<HtmlCanvasElement>();
+        piece.set_width(((n_cols * SQUARE_WIDTH) as f64 * scale).round() as u32);
+        piece.set_height(((n_rows * SQUARE_WIDTH) as f64 * scale).round() as u32);
+
+        let context = piece.get_context("2d").unwrap().unwrap().unchecked_into::<CanvasRenderingContext2d>();
+        let drawer = CanvasDrawer::new(self.asset_cache, self.field, context, self.style.clone());
+        drawer.scale_to_panel();
+        if drawer.draw_piece_squares(kind) {
+            piece_cache.pieces.insert(kind, piece.clone());
+        }
+        piece
+    }
+
+    // draw a square at the given coords on a canvas, as the square for its connections if the skin is connected,
+    // returning whether its image had loaded
+    fn draw_square(&self, asset_name: &str, connections: Connections, row: usize, col: usize) -> bool {
+        // the skin can be a custom one that isn't loaded here (e.g. one uploaded in another tab), which is drawn with
+        // the default skin instead
+        let asset_cache = self.asset_cache.borrow();
+        let connected = skins::connected_asset(asset_name, skins::connection_number(connections));
+        let assets = [
+            (self.style.skin_name.as_str(), connected.as_str()),
+            (self.style.skin_name.as_str(), asset_name),
+            (skins::DEFAULT_SKIN, asset_name),
+        ];
+        let asset = assets
+            .iter()
+            .find_map(|(skin_name, asset_name)| asset_cache.get(&skins::asset_src(skin_name, asset_name)))
+            .unwrap();
 
         self.context
             .draw_image_with_html_image_element_and_dw_and_dh(
@@ -332,20 +882,33 @@ impl<'a, 'b> CanvasDrawer<'a, 'b> {
                 SQUARE_WIDTH as f64,
             )
             .unwrap();
+
+        // palettes tint the square's colors, keeping the skin's shading
+        if let Some(color) = self.style.palette.piece_color(asset_name) {
+            let ctx = &self.context;
+            ctx.save();
+            ctx.set_global_composite_operation("color").unwrap();
+            ctx.set_fill_style(&color.into());
+            ctx.fill_rect(col as f64, row as f64, SQUARE_WIDTH as f64, SQUARE_WIDTH as f64);
+            ctx.restore();
+        }
+        asset.complete() && asset.natural_width() > 0
     }
 
-    fn center_coords_around_origin(coords: Vec<Coords>) -> Vec<Coords> {
-        let min_col = coords.iter().min_by_key(|Coords(_, col)| col).unwrap().1;
-        let max_col = coords.iter().max_by_key(|Coords(_, col)| col).unwrap().1;
-        let min_row = coords.iter().min_by_key(|Coords(row, _)| row).unwrap().0;
-        let max_row = coords.iter().max_by_key(|Coords(row, _)| row).unwrap().0;
-
-        let offset = Coords((max_row + min_row) / 2, (max_col + min_col) / 2);
-        coords
-            .into_iter()
-            // (0, 0) is not the center since images are drawn from the top-left corner
-            // the actual center is half a `SQUARE_WIDTH` away in both directions
-            .map(|c| c - offset - Coords(SQUARE_WIDTH as i32 / 2, SQUARE_WIDTH as i32 / 2))
-            .collect()
+    // draw the piece's letter over one of its squares, if the palette has letters
+    fn draw_letter(&self, kind: PieceKind, row: usize, col: usize) {
+        if !self.style.palette.shows_letters() {
+            return;
+        }
+
+        let ctx = &self.context;
+        let center = (SQUARE_WIDTH / 2) as f64;
+        ctx.save();
+        ctx.set_font("bold 18px 'Roboto Condensed'");
+        ctx.set_text_align("center");
+        ctx.set_text_baseline("middle");
+        ctx.set_fill_style(&"black".into());
+        ctx.fill_text(&kind.display_name().to_uppercase(), col as f64 + center, row as f64 + center).unwrap();
+        ctx.restore();
     }
 }
@@ -0,0 +1,186 @@
+use std::cell::RefCell;
+
+use strum::IntoEnumIterator;
+use sycamore::{
+    component,
+    generic_node::Html,
+    prelude::{create_memo, create_signal, use_context, Scope, Signal},
+    view,
+    view::View,
+    Prop,
+};
+
+use crate::{
+    board::Board,
+    config::{Config, GoalTypes, KickTable180s, KickTables, PieceTypes, SpinTypes},
+    util::{self, Padding},
+};
+
+// the seed used for new games, shared between the board and the seed panel
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SeedState {
+    // every game uses this seed if it is set, so the piece sequence is the same each time
+    pub fixed: Option<u64>,
+    // seed of the game being played
+    pub current: u64,
+}
+
+// the parts of the config that change how a game plays out, so two players given the same seed and rules see the
+// same pieces and are racing to the same goal
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChallengeRules {
+    piece_type: PieceTypes,
+    kick_table: KickTables,
+    kick_table_180: KickTable180s,
+    spin_types: SpinTypes,
+    goal_type: GoalTypes,
+    goal_n_lines: u32,
+    goal_time_limit_secs: u64,
+    field_width: usize,
+    field_hidden: usize,
+}
+
+impl ChallengeRules {
+    pub fn from_config(c: &Config) -> Self {
+        ChallengeRules {
+            piece_type: c.piece_type,
+            kick_table: c.kick_table,
+            kick_table_180: c.kick_table_180,
+            spin_types: c.spin_types,
+            goal_type: c.goal_type,
+            goal_n_lines: c.goal_n_lines,
+            goal_time_limit_secs: c.goal_time_limit_secs,
+            field_width: c.field_width,
+            field_hidden: c.field_hidden,
+        }
+    }
+
+    pub fn apply(&self, c: &mut Config) {
+        c.piece_type = self.piece_type;
+        c.kick_table = self.kick_table;
+        c.kick_table_180 = self.kick_table_180;
+        c.spin_types = self.spin_types;
+        c.goal_type = self.goal_type;
+        c.goal_n_lines = self.goal_n_lines;
+        c.goal_time_limit_secs = self.goal_time_limit_secs;
+        c.field_width = self.field_width;
+        c.field_height = self.field_hidden * 2;
+        c.field_hidden = self.field_hidden;
+    }
+
+    // dot separated, with options given by their position in the config panel's lists (e.g. `0.0.0.0.1.40.120.10.20`)
+    pub fn to_param(&self) -> String {
+        [
+            index_of(self.piece_type),
+            index_of(self.kick_table),
+            index_of(self.kick_table_180),
+            index_of(self.spin_types),
+            index_of(self.goal_type),
+            self.goal_n_lines as usize,
+            self.goal_time_limit_secs as usize,
+            self.field_width,
+            self.field_hidden,
+        ]
+        .map(|n| n.to_string())
+        .join(".")
+    }
+
+    pub fn from_param(param: &str) -> Option<Self> {
+        let numbers = param.split('.').map(|n| n.parse().ok()).collect::<Option<Vec<usize>>>()?;
+        match numbers[..] {
+            [piece_type, kick_table, kick_table_180, spin_types, goal_type, n_lines, secs, width, hidden] => {
+                Some(ChallengeRules {
+                    piece_type: PieceTypes::iter().nth(piece_type)?,
+                    kick_table: KickTables::iter().nth(kick_table)?,
+                    kick_table_180: KickTable180s::iter().nth(kick_table_180)?,
+                    spin_types: SpinTypes::iter().nth(spin_types)?,
+                    goal_type: GoalTypes::iter().nth(goal_type)?,
+                    goal_n_lines: n_lines as u32,
+                    goal_time_limit_secs: secs as u64,
+                    field_width: width.max(1),
+                    field_hidden: hidden.max(1),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn index_of<E: IntoEnumIterator + PartialEq>(value: E) -> usize { E::iter().position(|v| v == value).unwrap() }
+
+// link to a challenge with `seed` and the rules from `c`
+pub fn challenge_link(seed: u64, c: &Config) -> String {
+    let origin = web_sys::window().unwrap().location().origin().unwrap();
+    format!("{}/challenge/{}/{}", origin, seed, ChallengeRules::from_config(c).to_param())
+}
+
+// seed and rules from a pasted challenge link
+fn parse_challenge_link(link: &str) -> Option<(u64, ChallengeRules)> {
+    let (seed, rules) = link.split_once("/challenge/")?.1.split_once('/')?;
+    Some((seed.parse().ok()?, ChallengeRules::from_param(rules.trim_end_matches('/'))?))
+}
+
+#[derive(Prop)]
+pub struct ChallengeProps {
+    seed: u64,
+    rules: String,
+}
+
+// a board with the seed and rules from a challenge link
+#[component]
+pub fn Challenge<'a, G: Html>(cx: Scope<'a>, props: ChallengeProps) -> View<G> {
+    let Some(rules) = ChallengeRules::from_param(&props.rules) else {
+        return view! { cx, p(class="loading-text") { "invalid challenge link" } };
+    };
+
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let seed_state = use_context::<Signal<SeedState>>(cx);
+    util::with_signal_mut(config, |c| rules.apply(c));
+    seed_state.modify().fixed = Some(props.seed);
+
+    view! { cx, Board {} }
+}
+
+// enter a seed to play, and share a link to the current game's seed and rules
+#[component]
+pub fn SeedPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let seed_state = use_context::<Signal<SeedState>>(cx);
+
+    let seed_text = create_signal(cx, seed_state.get_untracked().fixed.map_or(String::new(), |s| s.to_string()));
+    let invalid_seed = create_signal(cx, false);
+
+    // an empty seed goes back to random seeds, and a challenge link also sets its rules
+    let use_seed = move |_| {
+        let text = seed_text.get().trim().to_string();
+        let fixed = if text.is_empty() {
+            None
+        } else if let Ok(seed) = text.parse::<u64>() {
+            Some(seed)
+        } else if let Some((seed, rules)) = parse_challenge_link(&text) {
+            util::with_signal_mut(config, |c| rules.apply(c));
+            Some(seed)
+        } else {
+            invalid_seed.set(true);
+            return;
+        };
+        seed_state.modify().fixed = fixed;
+        invalid_seed.set(false);
+    };
+    let seed_label = create_memo(cx, || if *invalid_seed.get() { "Invalid seed" } else { "Use seed" });
+
+    let link = create_memo(cx, move || challenge_link(seed_state.get().current, &config.get().borrow()));
+
+    view! { cx,
+        div(class="menu-option seed-input") {
+            input(type="text", placeholder="random seed, or a challenge link", bind:value=seed_text)
+        }
+        div(class="menu-button-box menu-button-box-l") {
+            div(class="menu-option menu-option-l") { input(type="button", value=seed_label.get(), on:click=use_seed) }
+        }
+        Padding(1)
+        div(class="menu-option seed-input") {
+            input(type="text", readonly=true, value=link.get())
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     cell::RefCell,
+    collections::HashMap,
     fmt::{self, Display},
     ops::Deref,
     str::FromStr,
@@ -7,27 +8,36 @@ use std::{
 };
 
 use crate::{
+    canvas::FieldPreview,
     menu::Menu,
+    profiles::ProfileSwitcher,
+    skins::{self, SkinSelect, SkinUpload},
     util::{self, Padding, SectionHeading},
 };
 
-use bimap::BiMap;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use sycamore::{
     component, easing,
     generic_node::Html,
     motion::create_tweened_signal,
-    prelude::{create_effect, create_memo, create_signal, provide_context_ref, Keyed, ReadSignal, Scope, Signal},
+    prelude::{
+        create_effect, create_memo, create_rc_signal, create_signal, on_cleanup, provide_context_ref, Indexed, Keyed,
+        ReadSignal, Scope, Signal,
+    },
     view,
     view::View,
     Prop,
 };
 
 use tetrox::{
+    curves::{GuidelineMarathonCurve, NesCurve, SpeedCurve, TgmMasterCurve},
+    game::GameRules,
     kicks::{AscKickTable, BasicKickTable, KickTable, KickTable180, SrsKickTable, TetrIo180KickTable},
     pieces::{
+        composite::CompositeSet,
         mino123::Mino123,
         mino1234::Mino1234,
         pentomino::Pentomino,
@@ -36,11 +46,18 @@ use tetrox::{
     },
     spins::{ImmobileSpinDetector, NoSpinDetector, SpinDetector, TSpinDetector},
 };
-use wasm_bindgen::JsCast;
-use web_sys::{Event, HtmlInputElement, HtmlSelectElement, KeyboardEvent, Storage};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{Event, HtmlInputElement, HtmlSelectElement, KeyboardEvent, Storage, StorageEvent};
 
 const CONFIG_LOCAL_STORAGE_KEY: &str = "config";
 
+// the queue can be longer than a bag, since randomizers generate more bags to fill it, and long queues are shrunk or
+// scrolled to fit beside the field
+const MAX_QUEUE_LEN: usize = 30;
+
+// the built in background, used for the image background when no image url is given
+const DEFAULT_BACKGROUND: &str = "url(\"assets/bg.png\")";
+
 #[component]
 pub fn ConfigPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     let c = Config::from_local_storage(get_local_storage()).unwrap_or_else(|| Config::default());
@@ -55,12 +72,51 @@ pub fn ConfigPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     let config = create_signal(cx, RefCell::new(c));
     provide_context_ref(cx, config);
 
+    // the config can also be changed without going through `update` (e.g. by profiles or challenge links), so keep
+    // `field_values` in sync with it
+    create_effect(cx, move || {
+        let c = config.get();
+        let c = c.borrow();
+        let values = FieldValues::new(c.field_width, c.field_height, c.field_hidden, c.queue_len, c.piece_type);
+        if *field_values.get_untracked() != values {
+            field_values.set(values);
+        }
+    });
+
     // store the config on changes
     create_effect(cx, move || {
         let json = serde_json::to_string(&*config.get()).unwrap();
         get_local_storage().set_item(CONFIG_LOCAL_STORAGE_KEY, &json).unwrap();
     });
 
+    // take on configs stored by other tabs (e.g. one open just for changing options), so this one doesn't put back
+    // the config it had the next time it stores it
+    let stored_elsewhere = create_rc_signal(None);
+    let on_storage = Closure::<dyn Fn(StorageEvent)>::new({
+        let stored_elsewhere = stored_elsewhere.clone();
+        move |e: StorageEvent| {
+            if e.key().as_deref() == Some(CONFIG_LOCAL_STORAGE_KEY) {
+                stored_elsewhere.set(e.new_value());
+            }
+        }
+    });
+    let window = web_sys::window().unwrap();
+    window.add_event_listener_with_callback("storage", on_storage.as_ref().unchecked_ref()).unwrap();
+    on_cleanup(cx, move || {
+        let on_storage = on_storage.as_ref().unchecked_ref();
+        window.remove_event_listener_with_callback("storage", on_storage).unwrap();
+    });
+    create_effect(cx, move || {
+        let Some(stored) = stored_elsewhere.get().as_deref().and_then(|json| serde_json::from_str(json).ok()) else {
+            return;
+        };
+        // keybinds can be stored in any order, so compare values to not send the same config back and forth
+        let new_config = Config::from_stored(stored);
+        if serde_json::to_value(&new_config).ok() != serde_json::to_value(&*config.get_untracked().borrow()).ok() {
+            config.set(RefCell::new(new_config));
+        }
+    });
+
     let update = move |msg| {
         // see comment on `field_values` above
         match msg {
@@ -94,13 +150,50 @@ pub fn ConfigPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
             gen_config_setter_match! {
                 gravity_delay; GravityDelay, lock_delay; LockDelay, move_limit; MoveLimit,
                 topping_out_enabled; ToppingOutEnabled, auto_lock_enabled; AutoLockEnabled,
-                gravity_enabled; GravityEnabled, move_limit_enabled; MoveLimitEnabled, field_width; FieldWidth,
+                gravity_enabled; GravityEnabled, move_limit_enabled; MoveLimitEnabled,
+                countdown_enabled; CountdownEnabled, field_width; FieldWidth,
                 queue_len; QueueLen, piece_type; PieceType, spin_types; SpinType, kick_table; KickTable,
                 kick_table_180; KickTable180, goal_type; GoalType, goal_n_lines; GoalNLines,
                 goal_time_limit_secs; GoalTimeLimitSecs, skin_name; SkinName, field_zoom; FieldZoom,
                 vertical_offset; VerticalOffset, shadow_opacity; ShadowOpacity, keybinds; Keybinds,
                 delayed_auto_shift; DelayedAutoShift, auto_repeat_rate; AutoRepeatRate, soft_drop_rate; SoftDropRate,
-                timer_interval; TimerInterval, focus_warning_enabled; FocusWarningEnabled
+                reset_hold_delay; ResetHoldDelay, timer_interval; TimerInterval,
+                focus_warning_enabled; FocusWarningEnabled,
+                pieces_stat_enabled; PiecesStatEnabled, pps_stat_enabled; PpsStatEnabled,
+                apm_stat_enabled; ApmStatEnabled, kpp_stat_enabled; KppStatEnabled,
+                finesse_trainer_enabled; FinesseTrainerEnabled, finesse_sound_enabled; FinesseSoundEnabled,
+                faults_stat_enabled; FaultsStatEnabled,
+                input_display_enabled; InputDisplayEnabled,
+                survival_interval; SurvivalInterval, survival_acceleration; SurvivalAcceleration,
+                speed_curve; SpeedCurve,
+                goal_score; GoalScore,
+                goal_expression; GoalExpression,
+                practice_garbage_rows; PracticeGarbageRows, practice_garbage_fixed_hole; PracticeGarbageFixedHole,
+                practice_garbage_hole; PracticeGarbageHole,
+                practice_queue; PracticeQueue, practice_queue_loop; PracticeQueueLoop,
+                practice_frame_step; PracticeFrameStep,
+                practice_blind_pieces; PracticeBlindPieces,
+                practice_board; PracticeBoard,
+                background_type; BackgroundType, background_color; BackgroundColor,
+                background_gradient_color; BackgroundGradientColor, background_image; BackgroundImage,
+                grid_color; GridColor, grid_opacity; GridOpacity, panel_tint; PanelTint, text_color; TextColor,
+                palette; Palette,
+                clear_particles_enabled; ClearParticlesEnabled, pc_flash_enabled; PcFlashEnabled,
+                shake_enabled; ShakeEnabled, damage_flash_enabled; DamageFlashEnabled,
+                line_clear_delay; LineClearDelay,
+                clear_animation_duration; ClearAnimationDuration,
+                drop_trail_duration; DropTrailDuration, lock_flash_duration; LockFlashDuration,
+                panels_swapped; PanelsSwapped, hold_panel_enabled; HoldPanelEnabled,
+                next_panel_enabled; NextPanelEnabled, panel_scale; PanelScale,
+                bag_separators_enabled; BagSeparatorsEnabled,
+                hidden_rows_clipped; HiddenRowsClipped,
+                grid_style; GridStyle,
+                skyline_enabled; SkylineEnabled, field_border_enabled; FieldBorderEnabled,
+                das_kept_on_spawn; DasKeptOnSpawn, das_kept_on_turn; DasKeptOnTurn,
+                das_cut_delay; DasCutDelay,
+                clear_names; ClearNames, spin_minis_shown; SpinMinisShown, action_text_duration; ActionTextDuration,
+                action_text_scale; ActionTextScale,
+                versus_server; VersusServer
             }
         });
     };
@@ -121,12 +214,47 @@ pub fn ConfigPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     gen_config_signals! {
         gravity_delay; GravityDelay, lock_delay; LockDelay, move_limit; MoveLimit,
         topping_out_enabled; ToppingOutEnabled, auto_lock_enabled; AutoLockEnabled, gravity_enabled; GravityEnabled,
-        move_limit_enabled; MoveLimitEnabled, field_width; FieldWidth, field_hidden; FieldHidden, queue_len; QueueLen,
-        piece_type; PieceType, spin_types; SpinType, kick_table; KickTable, kick_table_180; KickTable180,
+        move_limit_enabled; MoveLimitEnabled, countdown_enabled; CountdownEnabled, field_width; FieldWidth,
+        field_hidden; FieldHidden, queue_len; QueueLen, piece_type; PieceType, spin_types; SpinType,
+        kick_table; KickTable, kick_table_180; KickTable180,
         goal_type; GoalType, goal_n_lines; GoalNLines, goal_time_limit_secs; GoalTimeLimitSecs, skin_name; SkinName,
         field_zoom; FieldZoom, vertical_offset; VerticalOffset, shadow_opacity; ShadowOpacity, keybinds; Keybinds,
         delayed_auto_shift; DelayedAutoShift, auto_repeat_rate; AutoRepeatRate, soft_drop_rate; SoftDropRate,
-        timer_interval; TimerInterval, focus_warning_enabled; FocusWarningEnabled
+        reset_hold_delay; ResetHoldDelay, timer_interval; TimerInterval, focus_warning_enabled; FocusWarningEnabled,
+        pieces_stat_enabled; PiecesStatEnabled, pps_stat_enabled; PpsStatEnabled, apm_stat_enabled; ApmStatEnabled,
+        kpp_stat_enabled; KppStatEnabled, finesse_trainer_enabled; FinesseTrainerEnabled,
+        finesse_sound_enabled; FinesseSoundEnabled, faults_stat_enabled; FaultsStatEnabled,
+        input_display_enabled; InputDisplayEnabled,
+        survival_interval; SurvivalInterval, survival_acceleration; SurvivalAcceleration,
+        speed_curve; SpeedCurve,
+        goal_score; GoalScore,
+        goal_expression; GoalExpression,
+        practice_garbage_rows; PracticeGarbageRows, practice_garbage_fixed_hole; PracticeGarbageFixedHole,
+        practice_garbage_hole; PracticeGarbageHole,
+        practice_queue; PracticeQueue, practice_queue_loop; PracticeQueueLoop,
+        practice_frame_step; PracticeFrameStep,
+        practice_blind_pieces; PracticeBlindPieces,
+        practice_board; PracticeBoard,
+        background_type; BackgroundType, background_color; BackgroundColor,
+        background_gradient_color; BackgroundGradientColor, background_image; BackgroundImage, grid_color; GridColor,
+        grid_opacity; GridOpacity, panel_tint; PanelTint, text_color; TextColor,
+        palette; Palette,
+        clear_particles_enabled; ClearParticlesEnabled, pc_flash_enabled; PcFlashEnabled, shake_enabled; ShakeEnabled,
+        damage_flash_enabled; DamageFlashEnabled,
+        line_clear_delay; LineClearDelay,
+        clear_animation_duration; ClearAnimationDuration,
+        drop_trail_duration; DropTrailDuration, lock_flash_duration; LockFlashDuration,
+        panels_swapped; PanelsSwapped, hold_panel_enabled; HoldPanelEnabled, next_panel_enabled; NextPanelEnabled,
+        panel_scale; PanelScale,
+        bag_separators_enabled; BagSeparatorsEnabled,
+        hidden_rows_clipped; HiddenRowsClipped,
+        grid_style; GridStyle,
+        skyline_enabled; SkylineEnabled, field_border_enabled; FieldBorderEnabled,
+        das_kept_on_spawn; DasKeptOnSpawn, das_kept_on_turn; DasKeptOnTurn,
+        das_cut_delay; DasCutDelay,
+        clear_names; ClearNames, spin_minis_shown; SpinMinisShown, action_text_duration; ActionTextDuration,
+        action_text_scale; ActionTextScale,
+        versus_server; VersusServer
     };
 
     // make label and item pair list for the select inputs
@@ -141,16 +269,30 @@ pub fn ConfigPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
         "Tetromino ASC",
         "123Mino",
         "1234Mino",
-        "Pentomino"
+        "Pentomino",
+        "Tetra/Penta mix",
+        "12345Mino"
     );
     let kick_table_items = gen_selector_items!(KickTables, "SRS", "ASC", "Basic");
     let kick_table_180_items = gen_selector_items!(KickTable180s, "TETR.IO", "Basic");
     let spin_type_items = gen_selector_items!(SpinTypes, "T-Spins", "Immobile", "None");
-    let goal_type_items = gen_selector_items!(GoalTypes, "None", "Lines cleared", "Time limit");
-    let skin_name_items = ["Tetrox", "Gradient", "Inset", "Cirxel", "TETR.IO", "Solid"]
-        .into_iter()
-        .zip(crate::SKIN_NAMES.iter().map(|s| s.to_string()))
-        .collect();
+    let speed_curve_items = gen_selector_items!(SpeedCurves, "None", "Marathon", "NES", "Master");
+    let palette_items = gen_selector_items!(Palettes, "Default", "Deuteranopia", "Protanopia", "High contrast");
+    let background_type_items = gen_selector_items!(BackgroundTypes, "Image", "Color", "Gradient");
+    let grid_style_items = gen_selector_items!(GridStyles, "Lines", "Dots", "Off");
+    let clear_name_items = gen_selector_items!(ClearNames, "Quad", "Tetris");
+    let goal_type_items = gen_selector_items!(
+        GoalTypes,
+        "None",
+        "Lines cleared",
+        "Time limit",
+        "Score attack",
+        "Survival",
+        "Zen",
+        "Score target",
+        "Combined",
+        "PC practice"
+    );
 
     // generate buttons for showing/getting keybinds
     macro_rules! keybind_capture_buttons {
@@ -163,16 +305,6 @@ pub fn ConfigPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
         }
     }
 
-    // make maximum queue length dynamic on piece kind and clamp it when it changes
-    let max_queue_len = piece_type.map(cx, |p| p.kinds().len());
-    create_effect(cx, move || {
-        let clamped = (*queue_len.get_untracked()).clamp(0, *max_queue_len.get());
-        update(ConfigMsg::QueueLen(clamped));
-    });
-    let queue_len_input = max_queue_len.map(cx, move |l| {
-        view! { cx, RangeInput { label: "Queue length", min: 0, max: *l, step: 1, value: queue_len } }
-    });
-
     // make minimum field width and height dynamic on piece kind (as above)
     let min_field_dims = piece_type.map(cx, |p| min_field_dims(p.kinds()));
     create_effect(cx, move || {
@@ -190,6 +322,14 @@ pub fn ConfigPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
         view! { cx, RangeInput { label: "Field height", min: height, max: 100, step: 1, value: field_hidden } }
     });
 
+    // the custom queue is read with the names of the current piece set
+    let practice_queue_help = create_memo(cx, || {
+        match tetrox::parse_queue(&practice_queue.get(), &piece_type.get().kinds()) {
+            Ok(_) => "pieces to play in order, with * for a random piece".to_string(),
+            Err(rest) => format!("unknown piece at \"{}\", so the queue is ignored", rest),
+        }
+    });
+
     // ui style
     let ui_offset = create_tweened_signal(cx, 0.0, Duration::from_millis(200), easing::quart_inout);
     let config_style = create_memo(cx, || format!("margin-right: -{}rem;", ui_offset.get()));
@@ -199,27 +339,85 @@ pub fn ConfigPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     provide_context_ref(cx, ui_enabled);
     create_effect(cx, || ui_offset.set(if **ui_enabled.get() { 0.0 } else { 20.0 }));
 
+    // theme settings are applied through css variables on the root element, which the stylesheet uses
+    let theme_style = util::create_config_selector(cx, config, Config::theme_style);
+    create_effect(cx, || {
+        let document = web_sys::window().unwrap().document().unwrap();
+        document.document_element().unwrap().set_attribute("style", &theme_style.get()).unwrap();
+    });
+
     view! { cx,
         div(class="content") {
             Menu { ui_offset }
 
             div(class="config-panel", style=config_style.get()) {
+                SectionHeading("Profile")
+                ProfileSwitcher {}
+                Padding(2)
+
                 SectionHeading("Gameplay")
-                RangeInput { label: "Gravity delay", min: 0, max: 5_000, step: 5, value: gravity_delay }
-                RangeInput { label: "Lock delay", min: 10, max: 3_000, step: 5, value: lock_delay }
+                SelectInput { label: "Speed curve", items: speed_curve_items, value: speed_curve }
+                // the curve sets gravity and lock delay as lines are cleared
+                (if *speed_curve.get() == SpeedCurves::None {
+                    view! { cx,
+                        RangeInput { label: "Gravity delay", min: 0, max: 5_000, step: 5, value: gravity_delay }
+                        RangeInput { label: "Lock delay", min: 10, max: 3_000, step: 5, value: lock_delay }
+                    }
+                } else {
+                    view! { cx, }
+                })
                 RangeInput { label: "Move limit", min: 1, max: 100, step: 1, value: move_limit }
+                RangeInput { label: "Line clear delay", min: 0, max: 1_000, step: 10, value: line_clear_delay }
                 div(class="menu-button-box") {
                     ToggleButton { label: "Topping out", value: topping_out_enabled }
                     ToggleButton { label: "Lock delay", value: auto_lock_enabled }
                     ToggleButton { label: "Gravity", value: gravity_enabled }
                     ToggleButton { label: "Move limit", value: move_limit_enabled }
+                    ToggleButton { label: "Countdown", value: countdown_enabled }
+                }
+                div(class="menu-button-box") {
+                    ToggleButton { label: "Finesse trainer", value: finesse_trainer_enabled }
+                    ToggleButton { label: "Fault sound", value: finesse_sound_enabled }
+                }
+                Padding(2)
+
+                SectionHeading("Practice")
+                RangeInput { label: "Garbage rows", min: 1, max: 20, step: 1, value: practice_garbage_rows }
+                RangeInput { label: "Garbage hole", min: 1, max: 20, step: 1, value: practice_garbage_hole }
+                RangeInput { label: "Blind pieces", min: 0, max: 20, step: 1, value: practice_blind_pieces }
+                div(class="menu-button-box") {
+                    ToggleButton { label: "Fixed hole", value: practice_garbage_fixed_hole }
+                }
+                div(class="menu-option practice-queue") {
+                    input(type="text", placeholder="custom queue, e.g. TIOSZLJ*", bind:value=practice_queue)
+                }
+                p(class="practice-queue-help") { (practice_queue_help.get()) }
+                div(class="menu-button-box") {
+                    ToggleButton { label: "Loop queue", value: practice_queue_loop }
+                    ToggleButton { label: "Frame step", value: practice_frame_step }
                 }
+                // the practice board comes from a setup or fumen, and is cleared to go back to an empty field
+                (if practice_board.get().is_empty() {
+                    view! { cx, }
+                } else {
+                    view! { cx,
+                        div(class="menu-button-box") {
+                            div(class="menu-option") {
+                                input(
+                                    type="button",
+                                    value="Clear board",
+                                    on:click=move |_| practice_board.set(String::new()),
+                                )
+                            }
+                        }
+                    }
+                })
                 Padding(2)
 
                 SectionHeading("Playfield")
                 (*field_width_input.get())
                 (*field_height_input.get())
-                (*queue_len_input.get())
+                RangeInput { label: "Queue length", min: 0, max: MAX_QUEUE_LEN, step: 1, value: queue_len }
                 SelectInput { label: "Piece kind", items: piece_kind_items, value: piece_type }
                 SelectInput { label: "Spin detection", items: spin_type_items, value: spin_types }
                 SelectInput { label: "Kick table", items: kick_table_items, value: kick_table }
@@ -234,25 +432,106 @@ pub fn ConfigPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
                         Padding(2)
                         RangeInput { label: "Lines cleared", min: 1, max: 1_000, step: 1, value: goal_n_lines }
                     },
-                    GoalTypes::TimeLimit => view! { cx,
+                    GoalTypes::TimeLimit | GoalTypes::ScoreAttack => view! { cx,
                         Padding(2)
                         RangeInput { label: "Time limit", min: 5, max: 3_600, step: 1, value: goal_time_limit_secs }
                     },
+                    GoalTypes::Survival => view! { cx,
+                        Padding(2)
+                        RangeInput { label: "Interval", min: 200, max: 10_000, step: 100, value: survival_interval }
+                        RangeInput { label: "Speed up", min: 0.0, max: 0.2, step: 0.005, value: survival_acceleration }
+                    },
+                    GoalTypes::ScoreTarget => view! { cx,
+                        Padding(2)
+                        RangeInput { label: "Score", min: 1_000, max: 1_000_000, step: 1_000, value: goal_score }
+                    },
+                    GoalTypes::Combined => view! { cx,
+                        Padding(2)
+                        div(class="menu-option goal-expression") {
+                            input(type="text", placeholder="lines 40 within 180", bind:value=goal_expression)
+                        }
+                        p(class="goal-expression-help") {
+                            "lines N, time N, score N, or topout, joined by and/or, then optionally within N"
+                        }
+                    },
                     _ => view! { cx, }
                 })
 
                 SectionHeading("Visual")
                 RangeInput { label: "Field zoom", min: 0.1, max: 4.0, step: 0.05, value: field_zoom }
                 RangeInput { label: "Vertical offset", min: -2_000, max: 2_000, step: 10, value: vertical_offset }
+                RangeInput { label: "Panel scale", min: 0.25, max: 2.0, step: 0.05, value: panel_scale }
+                div(class="menu-button-box") {
+                    ToggleButton { label: "Left-handed", value: panels_swapped }
+                    ToggleButton { label: "Hold panel", value: hold_panel_enabled }
+                    ToggleButton { label: "Next panel", value: next_panel_enabled }
+                    ToggleButton { label: "Bag separators", value: bag_separators_enabled }
+                    ToggleButton { label: "Clip hidden rows", value: hidden_rows_clipped }
+                }
                 RangeInput { label: "Shadow opacity", min: 0.0, max: 1.0, step: 0.05, value: shadow_opacity }
-                SelectInput { label: "Block skin", items: skin_name_items, value: skin_name }
+                SkinSelect { value: skin_name }
+                SelectInput { label: "Palette", items: palette_items, value: palette }
+                SkinUpload {}
+                SelectInput { label: "Background", items: background_type_items, value: background_type }
+                (match *background_type.get() {
+                    BackgroundTypes::Image => view! { cx,
+                        div(class="menu-option background-image") {
+                            input(
+                                type="text",
+                                placeholder="image url (blank for the default)",
+                                bind:value=background_image,
+                            )
+                        }
+                    },
+                    BackgroundTypes::Color => view! { cx,
+                        ColorInput { label: "Background color", value: background_color }
+                    },
+                    BackgroundTypes::Gradient => view! { cx,
+                        ColorInput { label: "Gradient top", value: background_color }
+                        ColorInput { label: "Gradient bottom", value: background_gradient_color }
+                    },
+                })
+                SelectInput { label: "Grid", items: grid_style_items, value: grid_style }
+                ColorInput { label: "Grid color", value: grid_color }
+                RangeInput { label: "Grid opacity", min: 0.0, max: 1.0, step: 0.05, value: grid_opacity }
+                div(class="menu-button-box") {
+                    ToggleButton { label: "Field border", value: field_border_enabled }
+                    ToggleButton { label: "Skyline", value: skyline_enabled }
+                }
+                ColorInput { label: "Panel tint", value: panel_tint }
+                ColorInput { label: "Text color", value: text_color }
+                FieldPreview {}
+                div(class="menu-button-box") {
+                    ToggleButton { label: "Pieces", value: pieces_stat_enabled }
+                    ToggleButton { label: "PPS", value: pps_stat_enabled }
+                    ToggleButton { label: "APM", value: apm_stat_enabled }
+                    ToggleButton { label: "KPP", value: kpp_stat_enabled }
+                    ToggleButton { label: "Faults", value: faults_stat_enabled }
+                }
+                div(class="menu-button-box") {
+                    ToggleButton { label: "Particles", value: clear_particles_enabled }
+                    ToggleButton { label: "PC flash", value: pc_flash_enabled }
+                    ToggleButton { label: "Shake", value: shake_enabled }
+                    ToggleButton { label: "Damage flash", value: damage_flash_enabled }
+                }
+                RangeInput { label: "Clear animation", min: 0, max: 1_000, step: 10, value: clear_animation_duration }
+                RangeInput { label: "Drop trail", min: 0, max: 1_000, step: 10, value: drop_trail_duration }
+                RangeInput { label: "Lock flash", min: 0, max: 1_000, step: 10, value: lock_flash_duration }
+                SelectInput { label: "Clear names", items: clear_name_items, value: clear_names }
+                div(class="menu-button-box") {
+                    ToggleButton { label: "Spin minis", value: spin_minis_shown }
+                }
+                RangeInput { label: "Action text time", min: 0, max: 10_000, step: 100, value: action_text_duration }
+                RangeInput { label: "Action text size", min: 0.25, max: 3.0, step: 0.05, value: action_text_scale }
                 Padding(4)
 
                 SectionHeading("Keybinds")
                 (keybind_capture_buttons! {
                     "Left"; Left, "Right"; Right, "Soft drop"; SoftDrop, "Hard drop"; HardDrop,
-                    "Rotate CW"; RotateCw, "Rotate CCW"; RotateCcw, "Rotate 180"; Rotate180, "Swap hold"; SwapHold,
-                    "Reset"; Reset, "Show/hide UI"; ShowHideUi
+                    "Sonic drop"; SonicDrop, "Rotate CW"; RotateCw, "Rotate CCW"; RotateCcw, "Rotate 180"; Rotate180,
+                    "Swap hold"; SwapHold, "Reset"; Reset, "Show/hide UI"; ShowHideUi, "Add garbage"; AddGarbage,
+                    "Undo"; Undo, "Step"; Step, "Rewind"; Rewind, "Swap piece"; SwapPiece, "Copy fumen"; ExportFumen,
+                    "Save image"; ExportImage
                 })
                 Padding(2)
 
@@ -260,11 +539,21 @@ pub fn ConfigPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
                 RangeInput { label: "DAS", min: 0, max: 500, step: 1, value: delayed_auto_shift }
                 RangeInput { label: "ARR", min: 0, max: 500, step: 1, value: auto_repeat_rate }
                 RangeInput { label: "SDR", min: 0, max: 500, step: 1, value: soft_drop_rate }
+                RangeInput { label: "DCD", min: 0, max: 500, step: 1, value: das_cut_delay }
+                div(class="menu-button-box") {
+                    ToggleButton { label: "Keep DAS on spawn", value: das_kept_on_spawn }
+                    ToggleButton { label: "Keep DAS on turn", value: das_kept_on_turn }
+                }
+                RangeInput { label: "Reset hold", min: 0, max: 2_000, step: 10, value: reset_hold_delay }
 
                 SectionHeading("Misc")
                 RangeInput { label: "Timer accuracy", min: 16, max: 1_000, step: 1, value: timer_interval }
                 div(class="menu-button-box") {
                     ToggleButton { label: "Show focus warning", value: focus_warning_enabled }
+                    ToggleButton { label: "Input display", value: input_display_enabled }
+                }
+                div(class="menu-option versus-server") {
+                    input(type="text", placeholder="versus server, e.g. wss://host/versus", bind:value=versus_server)
                 }
             }
         }
@@ -310,6 +599,25 @@ where
     }
 }
 
+#[derive(Prop)]
+struct ColorInputProps<'a> {
+    label: &'static str,
+    value: &'a Signal<String>,
+}
+
+// color picker for a hex color like `#cccccc`
+#[component]
+fn ColorInput<'a, G: Html>(cx: Scope<'a>, props: ColorInputProps<'a>) -> View<G> {
+    let ColorInputProps { label, value } = props;
+
+    view! { cx,
+        div(class="menu-option color-input") {
+            label(class="menu-option-label") { (label) ":" }
+            input(type="color", bind:value=value)
+        }
+    }
+}
+
 #[derive(Prop)]
 struct SelectInputProps<'a, T: Clone + PartialEq + Eq + 'static> {
     label: &'static str,
@@ -382,27 +690,19 @@ struct InputCaptureButtonProps<'a> {
     keybinds: &'a Signal<Keybinds>,
 }
 
-// button that captures keyboard input when pressed (used for assigning keybinds)
+// button that captures keyboard input when pressed (used for adding keybinds), followed by the keys already bound
+// which are unbound when clicked
 #[component]
 fn InputCaptureButton<'a, G: Html>(cx: Scope<'a>, props: InputCaptureButtonProps<'a>) -> View<G> {
     let InputCaptureButtonProps { label, input, keybinds } = props;
 
     let is_capturing_input = create_signal(cx, false); // currently capturing input?
-    let label = is_capturing_input.map(cx, move |i| {
-        let keybind = i.then(|| "<press a key>".to_string()).unwrap_or_else(|| {
-            keybinds
-                .get()
-                .get_by_left(&input)
-                .map(|keybind| match keybind.as_str() {
-                    " " => "Space",
-                    _ if keybind.starts_with("Arrow") => &keybind[5..],
-                    _ => keybind.as_str(),
-                })
-                .unwrap_or("<unset>")
-                .to_string()
-        });
-        format!("{} ({})", label, keybind)
+    let label = is_capturing_input.map(cx, move |i| match *i {
+        true => format!("{} (<press a key>)", label),
+        false if keybinds.get().keys(input).is_empty() => format!("{} (<unset>)", label),
+        false => format!("{} (+)", label),
     });
+    let keys = keybinds.map(cx, move |k| k.keys(input).to_vec());
 
     view! { cx,
         div(class="menu-option") {
@@ -411,20 +711,48 @@ fn InputCaptureButton<'a, G: Html>(cx: Scope<'a>, props: InputCaptureButtonProps
                 value=label.get(),
                 on:click=|_| is_capturing_input.set(!*is_capturing_input.get()),
                 on:keydown=move |e: Event| {
+                    // only change binds if currently capturing and let escape cancel the action
+                    // captured keys are marked as handled so the game doesn't take them as inputs
+                    if !*is_capturing_input.get() {
+                        return;
+                    }
                     e.prevent_default();
                     let e = e.dyn_into::<KeyboardEvent>().unwrap();
-
-                    // only change binds if currently capturing and let escape cancel the action
-                    if *is_capturing_input.get() && !e.key().starts_with("Esc") {
-                        keybinds.modify().insert(input, e.key());
+                    if e.code() != "Escape" {
+                        keybinds.modify().bind(input, key_combo(&e));
                     }
                     is_capturing_input.set(false);
                 },
             )
+            Indexed {
+                iterable: keys,
+                view: move |cx, key| {
+                    let name = format!("{} \u{d7}", key_name(&key));
+                    view! { cx,
+                        input(type="button", class="keybind-key", value=name, on:click=move |_| {
+                            keybinds.modify().unbind(&key)
+                        })
+                    }
+                },
+            }
         }
     }
 }
 
+// short name for a combo, with each key named like it's labelled on a us layout (e.g. `Ctrl+Z`)
+fn key_name(combo: &str) -> String {
+    let code_name = |code: &str| {
+        let punctuation = PUNCTUATION_CODES.iter().find(|(_, c)| *c == code).map(|(key, _)| key.to_string());
+        let modifier = MODIFIERS.iter().find_map(|(name, prefix)| {
+            let side = code.strip_prefix(prefix)?;
+            (side == "Left" || side == "Right").then(|| format!("{}{}", &side[..1], name))
+        });
+        let prefixed = ["Key", "Digit", "Arrow"].iter().find_map(|prefix| code.strip_prefix(prefix));
+        punctuation.or(modifier).unwrap_or_else(|| prefixed.unwrap_or(code).to_string())
+    };
+    combo.split('+').map(code_name).collect::<Vec<_>>().join("+")
+}
+
 #[derive(Prop)]
 struct InputLabelProps<'a, T: Display + 'static> {
     label: &'static str,
@@ -436,7 +764,7 @@ fn InputLabel<'a, T: Display + 'static, G: Html>(cx: Scope<'a>, props: InputLabe
     view! { cx, p(class="menu-option-label") { (props.label) " (" (props.value.get()) "):" } }
 }
 
-fn get_local_storage() -> Storage { web_sys::window().unwrap().local_storage().unwrap().unwrap() }
+pub fn get_local_storage() -> Storage { web_sys::window().unwrap().local_storage().unwrap().unwrap() }
 
 fn min_field_dims(pieces: Vec<PieceKind>) -> (usize, usize) {
     // get the column and row offsets (from the center and top of the board, respectively) for each piece kind
@@ -444,7 +772,7 @@ fn min_field_dims(pieces: Vec<PieceKind>) -> (usize, usize) {
         .into_iter()
         .map(|kind| {
             let (row_offsets, col_offsets): (Vec<_>, Vec<_>) =
-                kind.spawn_offsets().into_iter().map(|c| (c.0, c.1)).unzip();
+                kind.spawn_offsets().iter().map(|c| (c.0, c.1)).unzip();
 
             // maximum column offset from the center, and maximum row offset from the top of the visible board
             let max_cols_from_origin = col_offsets.into_iter().map(|c| if c > 0 { c } else { -c + 1 }).max();
@@ -463,7 +791,7 @@ fn min_field_dims(pieces: Vec<PieceKind>) -> (usize, usize) {
     (max_cols_from_origin * 2, max_rows_from_hidden * 2)
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct FieldValues {
     pub width: usize,
     pub height: usize,
@@ -492,6 +820,8 @@ pub enum PieceTypes {
     Mino123,
     Mino1234,
     Pentomino,
+    TetrominoPentomino,
+    Mino12345,
 }
 
 impl PieceTypes {
@@ -503,6 +833,8 @@ impl PieceTypes {
             PieceTypes::Mino123 => <Mino123 as PieceKindTrait>::iter(),
             PieceTypes::Mino1234 => <Mino1234 as PieceKindTrait>::iter(),
             PieceTypes::Pentomino => <Pentomino as PieceKindTrait>::iter(),
+            PieceTypes::TetrominoPentomino => <CompositeSet<TetrominoSrs, Pentomino> as PieceKindTrait>::iter(),
+            PieceTypes::Mino12345 => <CompositeSet<Mino1234, Pentomino> as PieceKindTrait>::iter(),
         }
         .collect()
     }
@@ -516,7 +848,7 @@ pub enum KickTables {
 }
 
 impl KickTables {
-    pub fn table(&self) -> &dyn KickTable {
+    pub fn table(&self) -> &'static dyn KickTable {
         match self {
             KickTables::Srs => &SrsKickTable,
             KickTables::Asc => &AscKickTable,
@@ -532,7 +864,7 @@ pub enum KickTable180s {
 }
 
 impl KickTable180s {
-    pub fn table(&self) -> &dyn KickTable180 {
+    pub fn table(&self) -> &'static dyn KickTable180 {
         match self {
             KickTable180s::TetrIo => &TetrIo180KickTable,
             KickTable180s::Basic => &BasicKickTable,
@@ -548,7 +880,7 @@ pub enum SpinTypes {
 }
 
 impl SpinTypes {
-    pub fn detector(&self) -> &dyn SpinDetector {
+    pub fn detector(&self) -> &'static dyn SpinDetector {
         match self {
             SpinTypes::TSpins => &TSpinDetector,
             SpinTypes::AllImmobile => &ImmobileSpinDetector,
@@ -557,11 +889,105 @@ impl SpinTypes {
     }
 }
 
+// speed curves driving gravity, lock delay, and entry delay from lines cleared instead of the static settings
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum SpeedCurves {
+    None,
+    Marathon,
+    Nes,
+    Master,
+}
+
+impl SpeedCurves {
+    pub fn curve(&self) -> Option<&'static dyn SpeedCurve> {
+        match self {
+            SpeedCurves::None => None,
+            SpeedCurves::Marathon => Some(&GuidelineMarathonCurve),
+            SpeedCurves::Nes => Some(&NesCurve),
+            SpeedCurves::Master => Some(&TgmMasterCurve),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
 pub enum GoalTypes {
     None,
     LinesCleared,
     TimeLimit,
+    // time limit played for guideline score, like ultra
+    ScoreAttack,
+    // garbage rises faster and faster until topping out, played for time
+    Survival,
+    // endless marathon, going on until topping out (if enabled)
+    Zen,
+    // race to a guideline score
+    ScoreTarget,
+    // conditions from the other goals joined together (e.g. 40 lines within 3 minutes)
+    Combined,
+    // perfect clears from an empty field over and over, with a fresh field after each attempt
+    PerfectClear,
+}
+
+// alternative piece and action text colors, for colorblind players or anyone wanting more contrast
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum Palettes {
+    Default,
+    Deuteranopia,
+    Protanopia,
+    // saturated colors, with each piece's letter drawn on its squares
+    HighContrast,
+}
+
+impl Palettes {
+    // color the skin's square for the asset is tinted to, with the default palette leaving skins as they are
+    pub fn piece_color(&self, asset_name: &str) -> Option<&'static str> {
+        let colors = match self {
+            Palettes::Default => return None,
+            Palettes::Deuteranopia => ["#009e73", "#d55e00", "#e69f00", "#0072b2", "#cc79a7", "#f0e442", "#56b4e9"],
+            Palettes::Protanopia => ["#2f6df6", "#ffa200", "#ececec", "#1a3a8c", "#b07cff", "#ffe14d", "#3fd0ff"],
+            Palettes::HighContrast => ["#00ff00", "#ff0000", "#ff8000", "#0040ff", "#c000ff", "#ffff00", "#00ffff"],
+        };
+        let index = ["s", "z", "l", "j", "t", "o", "i"].iter().position(|a| *a == asset_name)?;
+        Some(colors[index])
+    }
+
+    pub fn shows_letters(&self) -> bool { *self == Palettes::HighContrast }
+
+    // colors of the line clear, b2b, combo, and finesse fault text, with the default palette using the theme's
+    fn action_text_colors(&self) -> Option<[&'static str; 4]> {
+        match self {
+            Palettes::Default => None,
+            Palettes::Deuteranopia => Some(["#56b4e9", "#e69f00", "#cc79a7", "#d55e00"]),
+            Palettes::Protanopia => Some(["#3fd0ff", "#ffe14d", "#b07cff", "#ffa200"]),
+            Palettes::HighContrast => Some(["#ffffff", "#ffff00", "#00ffff", "#ff00ff"]),
+        }
+    }
+}
+
+// what's drawn behind everything, using the matching background colors or image from the config
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum BackgroundTypes {
+    Image,
+    Color,
+    Gradient,
+}
+
+// what clears of four or more lines are called in the line clear text
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum ClearNames {
+    // quad, penta
+    Quad,
+    // tetris, pentris
+    Tetris,
+}
+
+// how the grid lines on the field are drawn
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum GridStyles {
+    Lines,
+    // only where the lines would cross
+    Dots,
+    Off,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, EnumIter)]
@@ -570,26 +996,172 @@ pub enum Input {
     Right,
     SoftDrop,
     HardDrop,
+    // all the way down without locking
+    SonicDrop,
     RotateCw,
     RotateCcw,
     Rotate180,
     SwapHold,
     Reset,
     ShowHideUi,
+    // push garbage into the field, for practicing downstacking
+    AddGarbage,
+    // take back the last placed piece, in free play or with a practice queue
+    Undo,
+    // move a tick forward or back with frame stepping on
+    Step,
+    Rewind,
+    // change the current piece to the next one in the piece set, for setting up spins without waiting for a bag
+    SwapPiece,
+    // copy the stack as a fumen with the queue as a quiz, or save the field as a png, for sharing positions
+    ExportFumen,
+    ExportImage,
+}
+
+// keys bound to each input, where a key can only be bound to one input
+// keys are combos of held modifiers and a physical key code (e.g. `Ctrl+KeyZ`), so they don't change with the layout
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keybinds(HashMap<Input, Vec<String>>);
+
+impl Keybinds {
+    // the input bound to `combo`, or to its key on its own so modifiers held for other inputs (e.g. shift for rotating)
+    // don't get in the way
+    pub fn input(&self, combo: &str) -> Option<&Input> {
+        self.bound_input(combo).or_else(|| self.bound_input(combo_code(combo)))
+    }
+
+    fn bound_input(&self, key: &str) -> Option<&Input> {
+        self.0.iter().find(|(_, keys)| keys.iter().any(|k| k == key)).map(|(input, _)| input)
+    }
+
+    // inputs bound to `code` with any modifiers, which are all released with the key
+    pub fn inputs_with_code<'a>(&'a self, code: &'a str) -> impl Iterator<Item = &'a Input> {
+        self.0.iter().filter(move |(_, keys)| keys.iter().any(|k| combo_code(k) == code)).map(|(input, _)| input)
+    }
+
+    // keys bound to `input`, in the order they were bound
+    pub fn keys(&self, input: Input) -> &[String] { self.0.get(&input).map(Vec::as_slice).unwrap_or_default() }
+
+    // add `key` as an alternate for `input`, moving it from any input it was bound to before
+    pub fn bind(&mut self, input: Input, key: String) {
+        self.unbind(&key);
+        self.0.entry(input).or_default().push(key);
+    }
+
+    pub fn unbind(&mut self, key: &str) { self.0.values_mut().for_each(|keys| keys.retain(|k| k != key)); }
 }
 
-pub type Keybinds = BiMap<Input, String>;
+impl FromIterator<(Input, &'static str)> for Keybinds {
+    fn from_iter<T: IntoIterator<Item = (Input, &'static str)>>(binds: T) -> Self {
+        let mut keybinds = Keybinds::default();
+        binds.into_iter().for_each(|(input, key)| keybinds.bind(input, key.to_string()));
+        keybinds
+    }
+}
 
+// modifiers in the order they're written in combos, with the start of their key codes
+const MODIFIERS: [(&str, &str); 4] = [("Ctrl", "Control"), ("Alt", "Alt"), ("Shift", "Shift"), ("Meta", "Meta")];
+
+// the combo for a key event, with the modifiers held and the physical key pressed
+// modifier keys are bound on their own, since their own modifier is only held while they're down
+pub fn key_combo(e: &KeyboardEvent) -> String {
+    let code = e.code();
+    if MODIFIERS.iter().any(|(_, prefix)| code.starts_with(prefix)) {
+        return code;
+    }
+    let held = [e.ctrl_key(), e.alt_key(), e.shift_key(), e.meta_key()];
+    let modifiers = MODIFIERS.iter().zip(held).filter(|(_, held)| *held).map(|((name, _), _)| format!("{}+", name));
+    modifiers.chain([code]).collect()
+}
+
+// the key code in a combo, without its modifiers
+fn combo_code(combo: &str) -> &str { combo.rsplit('+').next().unwrap_or(combo) }
+
+// punctuation keys by the characters they give on a us layout
+const PUNCTUATION_CODES: [(&str, &str); 11] = [
+    ("`", "Backquote"),
+    ("-", "Minus"),
+    ("=", "Equal"),
+    ("[", "BracketLeft"),
+    ("]", "BracketRight"),
+    ("\\", "Backslash"),
+    (";", "Semicolon"),
+    ("'", "Quote"),
+    (",", "Comma"),
+    (".", "Period"),
+    ("/", "Slash"),
+];
+
+// bumped whenever stored configs need upgrading to be read (e.g. after renaming an option or changing its type), with
+// a migration added for the new version
+const CONFIG_VERSION: u64 = 2;
+
+// each migration upgrades a stored config from the version it's at to the next one
+const MIGRATIONS: [fn(&mut Map<String, Value>); CONFIG_VERSION as usize] = [migrate_single_keys, migrate_key_codes];
+
+// configs saved before inputs could have more than one key have a single key for each
+fn migrate_single_keys(stored: &mut Map<String, Value>) {
+    if let Some(Value::Object(keybinds)) = stored.get_mut("keybinds") {
+        for keys in keybinds.values_mut() {
+            if let Value::String(key) = keys {
+                *keys = Value::Array(vec![Value::String(key.clone())]);
+            }
+        }
+    }
+}
+
+// configs saved before binding physical keys have keys as given by `KeyboardEvent::key`, which become the codes of the
+// keys giving them on a us layout
+fn migrate_key_codes(stored: &mut Map<String, Value>) {
+    if let Some(Value::Object(keybinds)) = stored.get_mut("keybinds") {
+        for keys in keybinds.values_mut() {
+            if let Value::Array(keys) = keys {
+                *keys = keys.iter().filter_map(Value::as_str).flat_map(key_codes).map(Value::String).collect();
+            }
+        }
+    }
+}
+
+// codes for a key from `KeyboardEvent::key`, with modifier keys on both sides of the keyboard
+fn key_codes(key: &str) -> Vec<String> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphabetic() => vec![format!("Key{}", c.to_ascii_uppercase())],
+        (Some(c), None) if c.is_ascii_digit() => vec![format!("Digit{}", c)],
+        _ if key == " " => vec!["Space".to_string()],
+        _ if MODIFIERS.iter().any(|(_, prefix)| key == *prefix) => {
+            vec![format!("{}Left", key), format!("{}Right", key)]
+        }
+        _ => {
+            let code = PUNCTUATION_CODES.iter().find(|(k, _)| *k == key).map_or(key, |&(_, code)| code);
+            vec![code.to_string()]
+        }
+    }
+}
+
+// options missing from stored configs (e.g. ones saved before the option was added) take their default values
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    // the `CONFIG_VERSION` this config was stored at, with configs from before versioning being at zero
+    pub version: u64,
+
     // gameplay
     pub gravity_delay: u32,
     pub lock_delay: u32,
+    pub speed_curve: SpeedCurves,
     pub move_limit: usize,
+    // the next piece waits this long after a line clear, on top of any entry delay
+    pub line_clear_delay: u32,
     pub topping_out_enabled: bool,
     pub auto_lock_enabled: bool,
     pub gravity_enabled: bool,
     pub move_limit_enabled: bool,
+    // the finesse trainer takes back placements with finesse faults, which are otherwise just flashed
+    pub finesse_trainer_enabled: bool,
+    pub finesse_sound_enabled: bool,
+    // 3-2-1 countdown before the game starts, during which inputs are buffered
+    pub countdown_enabled: bool,
 
     // field property settings
     pub field_width: usize,
@@ -605,12 +1177,61 @@ pub struct Config {
     pub goal_type: GoalTypes,
     pub goal_n_lines: u32,
     pub goal_time_limit_secs: u64,
+    pub goal_score: u64,
+    // a combined goal, parsed by `GoalExpr::parse`
+    pub goal_expression: String,
+    // garbage rises a row every this many milliseconds in survival, with the interval shrinking by the acceleration
+    // (a fraction of it) after each row
+    pub survival_interval: u32,
+    pub survival_acceleration: f64,
 
     // visual settings
     pub skin_name: String,
+    pub palette: Palettes,
     pub field_zoom: f64,
     pub vertical_offset: i32,
+    // hold and next panels, which can be swapped to the other sides (for a left-handed layout), hidden, or scaled apart
+    // from the field
+    pub panels_swapped: bool,
+    pub hold_panel_enabled: bool,
+    pub next_panel_enabled: bool,
+    pub bag_separators_enabled: bool,
+    pub hidden_rows_clipped: bool,
+    pub panel_scale: f64,
     pub shadow_opacity: f64,
+    // theme colors, applied to the page as css variables and to the canvases when drawing
+    pub background_type: BackgroundTypes,
+    pub background_color: String,
+    pub background_gradient_color: String,
+    pub background_image: String,
+    pub grid_color: String,
+    pub grid_style: GridStyles,
+    pub grid_opacity: f64,
+    pub skyline_enabled: bool,
+    pub field_border_enabled: bool,
+    pub panel_tint: String,
+    pub text_color: String,
+    // live stats shown next to the field
+    pub pieces_stat_enabled: bool,
+    pub pps_stat_enabled: bool,
+    pub apm_stat_enabled: bool,
+    pub kpp_stat_enabled: bool,
+    pub faults_stat_enabled: bool,
+    // effects drawn over the field, which can be turned off for a minimal view
+    pub clear_particles_enabled: bool,
+    pub pc_flash_enabled: bool,
+    pub shake_enabled: bool,
+    pub damage_flash_enabled: bool,
+    // cleared rows flash and the stack falls into place over this many milliseconds (or the line clear delay if set)
+    pub clear_animation_duration: u32,
+    // hard dropped pieces leave a trail and flash where they lock, fading over these many milliseconds (zero for none)
+    pub drop_trail_duration: u32,
+    pub lock_flash_duration: u32,
+    pub clear_names: ClearNames,
+    pub spin_minis_shown: bool,
+    // line clear texts show for this many milliseconds, with combo and b2b texts showing for half again as long
+    pub action_text_duration: u32,
+    pub action_text_scale: f64,
 
     // controls
     pub keybinds: Keybinds,
@@ -619,43 +1240,184 @@ pub struct Config {
     pub delayed_auto_shift: u32,
     pub auto_repeat_rate: u32,
     pub soft_drop_rate: u32,
+    // auto repeat pauses for this long after a rotation or hold
+    pub das_cut_delay: u32,
+    // held directions keep their das charge for the next piece, and the other direction starts charged when turning
+    pub das_kept_on_spawn: bool,
+    pub das_kept_on_turn: bool,
+    // how long reset has to be held for before the board resets, with zero resetting on press
+    pub reset_hold_delay: u32,
 
     // misc
     pub timer_interval: u32,
     pub focus_warning_enabled: bool,
+    // pressed keys and keys per second, e.g. for streaming
+    pub input_display_enabled: bool,
+    // server relaying versus matches, or the one hosting the page if empty
+    pub versus_server: String,
+    // rows added by the add garbage key, with holes in random columns unless the hole is fixed (to a 1-based column)
+    pub practice_garbage_rows: usize,
+    pub practice_garbage_fixed_hole: bool,
+    pub practice_garbage_hole: usize,
+    // pieces given out before the randomizer takes over (e.g. `TIOSZLJ*`, with `*` for a random piece), starting over
+    // once they run out if looping
+    pub practice_queue: String,
+    pub practice_queue_loop: bool,
+    // bottom rows of the starting field in the ascii format from `tetrox::sim` (`#` for filled squares), filled in
+    // by picking a setup or loading a fumen
+    pub practice_board: String,
+    // game timers only run when stepped with the step key, a tick at a time, with the last few seconds of steps kept
+    // to rewind through
+    pub practice_frame_step: bool,
+    // pieces placed with the stack hidden before it's shown again for a piece, or 0 to always show it
+    pub practice_blind_pieces: usize,
 }
 
 impl Config {
     fn from_local_storage(storage: Storage) -> Option<Self> {
         let json = storage.get_item(CONFIG_LOCAL_STORAGE_KEY).ok()??;
-        serde_json::from_str(&json).ok()
+        Some(Config::from_stored(serde_json::from_str(&json).ok()?))
+    }
+
+    // a config stored by any version, upgraded to this one
+    // options that still can't be read take their default values, instead of the whole config being lost
+    pub fn from_stored(stored: Value) -> Self {
+        let Value::Object(mut stored) = stored else {
+            return Config::default();
+        };
+        let version = stored.get("version").and_then(Value::as_u64).unwrap_or(0);
+        for migrate in MIGRATIONS.iter().skip(version as usize) {
+            migrate(&mut stored);
+        }
+        stored.insert("version".to_string(), CONFIG_VERSION.into());
+
+        // keybinds for inputs that no longer exist are dropped so the rest can be read, and inputs added since the
+        // config was stored get their default keys, unless those are already bound
+        if let Some(Value::Object(keybinds)) = stored.get_mut("keybinds") {
+            keybinds.retain(|input, _| serde_json::from_value::<Input>(Value::String(input.clone())).is_ok());
+            let bound = keybinds.values().filter_map(Value::as_array).flatten().cloned().collect::<Vec<_>>();
+            let defaults = Config::default().keybinds;
+            for input in Input::iter() {
+                let Value::String(name) = serde_json::to_value(input).unwrap() else {
+                    unreachable!()
+                };
+                if !keybinds.contains_key(&name) {
+                    let keys = defaults.keys(input).iter().map(|k| Value::String(k.clone()));
+                    keybinds.insert(name, keys.filter(|k| !bound.contains(k)).collect());
+                }
+            }
+        }
+
+        let Ok(Value::Object(defaults)) = serde_json::to_value(Config::default()) else {
+            unreachable!()
+        };
+        let mut fields = defaults.clone();
+        fields.extend(stored.clone());
+        if let Ok(config) = serde_json::from_value(Value::Object(fields)) {
+            return config;
+        }
+
+        // some option can't be read, so options are put in one at a time, keeping each only if the config still reads
+        let mut fields = defaults;
+        for (name, value) in stored {
+            let default = fields.insert(name.clone(), value);
+            if serde_json::from_value::<Config>(Value::Object(fields.clone())).is_err() {
+                match default {
+                    Some(default) => fields.insert(name, default),
+                    None => fields.remove(&name),
+                };
+            }
+        }
+        serde_json::from_value(Value::Object(fields)).unwrap()
+    }
+
+    // whether topping out ends the game, which it always does in survival
+    pub fn topping_out(&self) -> bool { self.topping_out_enabled || self.goal_type == GoalTypes::Survival }
+
+    // zen always levels up like marathon, whatever the speed curve setting
+    pub fn active_speed_curve(&self) -> SpeedCurves {
+        match self.goal_type {
+            GoalTypes::Zen => SpeedCurves::Marathon,
+            _ => self.speed_curve,
+        }
+    }
+
+    // rules for the parts of play the game's rules cover, like kicks and spin detection
+    pub fn rules(&self) -> GameRules {
+        GameRules {
+            kick_table: self.kick_table.table(),
+            kick_table_180: self.kick_table_180.table(),
+            spin_detector: self.spin_types.detector(),
+            ..GameRules::default()
+        }
+    }
+
+    // css variables for the theme settings, set on the root element so the stylesheet can use them
+    pub fn theme_style(&self) -> String {
+        let background = match self.background_type {
+            BackgroundTypes::Image if self.background_image.is_empty() => DEFAULT_BACKGROUND.to_string(),
+            BackgroundTypes::Image => format!("url(\"{}\")", self.background_image.replace('"', "%22")),
+            BackgroundTypes::Color => self.background_color.clone(),
+            BackgroundTypes::Gradient => {
+                format!("linear-gradient({}, {})", self.background_color, self.background_gradient_color)
+            }
+        };
+        let field_border = if self.field_border_enabled { "4px solid #bbbbbb88" } else { "none" };
+        let mut style = format!(
+            "--background: {}; --panel-tint: {}; --text-color: {}; --field-border: {};",
+            background, self.panel_tint, self.text_color, field_border
+        );
+        if let Some([clear, b2b, combo, finesse]) = self.palette.action_text_colors() {
+            style += &format!(
+                " --clear-text-color: {}; --b2b-text-color: {}; --combo-text-color: {}; --finesse-text-color: {};",
+                clear, b2b, combo, finesse
+            );
+        }
+        style
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
-        // guideline controls (minus double binds)
+        // guideline controls
         let inputs = [
             (Input::Left, "ArrowLeft"),
             (Input::Right, "ArrowRight"),
             (Input::SoftDrop, "ArrowDown"),
-            (Input::HardDrop, " "),
-            (Input::RotateCw, "x"),
-            (Input::RotateCcw, "z"),
-            (Input::Rotate180, "Shift"),
-            (Input::SwapHold, "c"),
-            (Input::Reset, "`"),
+            (Input::HardDrop, "Space"),
+            (Input::RotateCw, "KeyX"),
+            (Input::RotateCw, "ArrowUp"),
+            (Input::RotateCcw, "KeyZ"),
+            (Input::RotateCcw, "ControlLeft"),
+            (Input::RotateCcw, "ControlRight"),
+            (Input::Rotate180, "ShiftLeft"),
+            (Input::Rotate180, "ShiftRight"),
+            (Input::SwapHold, "KeyC"),
+            (Input::Reset, "Backquote"),
             (Input::ShowHideUi, "F9"),
+            (Input::AddGarbage, "KeyG"),
+            (Input::Undo, "KeyU"),
+            (Input::Step, "Period"),
+            (Input::Rewind, "Comma"),
+            (Input::SwapPiece, "KeyP"),
+            (Input::ExportFumen, "KeyF"),
+            (Input::ExportImage, "KeyI"),
         ];
 
         Config {
+            version: CONFIG_VERSION,
             gravity_delay: 1_000,
             lock_delay: 500,
+            speed_curve: SpeedCurves::None,
             move_limit: 30,
+            line_clear_delay: 0,
             topping_out_enabled: true,
             auto_lock_enabled: true,
             gravity_enabled: true,
             move_limit_enabled: true,
+            finesse_trainer_enabled: false,
+            finesse_sound_enabled: true,
+            countdown_enabled: false,
 
             field_width: 10,
             field_height: 40,
@@ -669,20 +1431,72 @@ impl Default for Config {
             goal_type: GoalTypes::None,
             goal_n_lines: 40,
             goal_time_limit_secs: 120,
+            goal_score: 10_000,
+            goal_expression: "lines 40 within 180".to_string(),
+            survival_interval: 3_000,
+            survival_acceleration: 0.02,
 
-            skin_name: "tetrox".to_string(),
+            skin_name: skins::DEFAULT_SKIN.to_string(),
+            palette: Palettes::Default,
             field_zoom: 1.0,
             vertical_offset: 170,
+            panels_swapped: false,
+            hold_panel_enabled: true,
+            next_panel_enabled: true,
+            bag_separators_enabled: true,
+            hidden_rows_clipped: false,
+            panel_scale: 1.0,
             shadow_opacity: 0.3,
-
-            keybinds: inputs.into_iter().map(|(i, k)| (i, k.to_string())).collect(),
+            background_type: BackgroundTypes::Image,
+            background_color: "#1b1b26".to_string(),
+            background_gradient_color: "#3d2f52".to_string(),
+            background_image: String::new(),
+            grid_color: "#555555".to_string(),
+            grid_style: GridStyles::Lines,
+            grid_opacity: 0.3,
+            skyline_enabled: false,
+            field_border_enabled: true,
+            panel_tint: "#000000".to_string(),
+            text_color: "#cccccc".to_string(),
+            pieces_stat_enabled: true,
+            pps_stat_enabled: true,
+            apm_stat_enabled: true,
+            kpp_stat_enabled: true,
+            faults_stat_enabled: true,
+            clear_particles_enabled: true,
+            pc_flash_enabled: true,
+            shake_enabled: true,
+            damage_flash_enabled: true,
+            clear_animation_duration: 200,
+            drop_trail_duration: 150,
+            lock_flash_duration: 150,
+            clear_names: ClearNames::Quad,
+            spin_minis_shown: true,
+            action_text_duration: 2_000,
+            action_text_scale: 1.0,
+
+            keybinds: inputs.into_iter().collect(),
 
             delayed_auto_shift: 280,
             auto_repeat_rate: 50,
             soft_drop_rate: 30,
+            das_cut_delay: 0,
+            das_kept_on_spawn: true,
+            das_kept_on_turn: false,
+            reset_hold_delay: 0,
 
             timer_interval: 33,
             focus_warning_enabled: true,
+            input_display_enabled: false,
+            versus_server: String::new(),
+            practice_garbage_rows: 1,
+            practice_garbage_fixed_hole: false,
+            practice_garbage_hole: 1,
+            practice_queue: String::new(),
+            practice_queue_loop: false,
+            practice_board: String::new(),
+            practice_frame_step: false,
+            practice_blind_pieces: 0,
         }
     }
 }
@@ -696,6 +1510,7 @@ enum ConfigMsg {
     AutoLockEnabled(bool),
     GravityEnabled(bool),
     MoveLimitEnabled(bool),
+    CountdownEnabled(bool),
 
     FieldWidth(usize),
     FieldHidden(usize),
@@ -720,8 +1535,64 @@ enum ConfigMsg {
     AutoRepeatRate(u32),
     SoftDropRate(u32),
 
+    ResetHoldDelay(u32),
     TimerInterval(u32),
     FocusWarningEnabled(bool),
+    PiecesStatEnabled(bool),
+    PpsStatEnabled(bool),
+    ApmStatEnabled(bool),
+    KppStatEnabled(bool),
+    FinesseTrainerEnabled(bool),
+    FinesseSoundEnabled(bool),
+    FaultsStatEnabled(bool),
+    InputDisplayEnabled(bool),
+    SurvivalInterval(u32),
+    SurvivalAcceleration(f64),
+    SpeedCurve(SpeedCurves),
+    GoalScore(u64),
+    GoalExpression(String),
+    PracticeGarbageRows(usize),
+    PracticeGarbageFixedHole(bool),
+    PracticeGarbageHole(usize),
+    PracticeQueue(String),
+    PracticeQueueLoop(bool),
+    PracticeFrameStep(bool),
+    PracticeBlindPieces(usize),
+    PracticeBoard(String),
+    BackgroundType(BackgroundTypes),
+    BackgroundColor(String),
+    BackgroundGradientColor(String),
+    BackgroundImage(String),
+    GridColor(String),
+    GridOpacity(f64),
+    PanelTint(String),
+    TextColor(String),
+    Palette(Palettes),
+    ClearParticlesEnabled(bool),
+    PcFlashEnabled(bool),
+    ShakeEnabled(bool),
+    DamageFlashEnabled(bool),
+    LineClearDelay(u32),
+    ClearAnimationDuration(u32),
+    DropTrailDuration(u32),
+    LockFlashDuration(u32),
+    PanelsSwapped(bool),
+    HoldPanelEnabled(bool),
+    NextPanelEnabled(bool),
+    PanelScale(f64),
+    BagSeparatorsEnabled(bool),
+    HiddenRowsClipped(bool),
+    GridStyle(GridStyles),
+    SkylineEnabled(bool),
+    FieldBorderEnabled(bool),
+    DasKeptOnSpawn(bool),
+    DasKeptOnTurn(bool),
+    DasCutDelay(u32),
+    ClearNames(ClearNames),
+    SpinMinisShown(bool),
+    ActionTextDuration(u32),
+    ActionTextScale(f64),
+    VersusServer(String),
 }
 
 pub struct UiEnabled(bool);
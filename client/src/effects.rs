@@ -0,0 +1,314 @@
+use std::cell::RefCell;
+
+use js_sys::Math;
+use sycamore::{
+    component,
+    generic_node::{DomNode, Html},
+    motion::create_raf,
+    prelude::{
+        create_effect, create_node_ref, create_ref, create_selector, create_signal, use_context, ReadSignal, Scope,
+        Signal,
+    },
+    view,
+    view::View,
+    Prop,
+};
+use tetrox::{
+    field::{DefaultField, LineClear},
+    Coords,
+};
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+use crate::{
+    canvas::{self, SQUARE_WIDTH},
+    config::{Config, FieldValues},
+    timer, util,
+};
+
+// particles thrown from each square of a cleared row, which fall and fade out over their lifetime (in milliseconds)
+const PARTICLES_PER_SQUARE: usize = 2;
+const PARTICLE_LIFETIME: f64 = 700.0;
+const PARTICLE_SIZE: f64 = 5.0;
+// in pixels per millisecond, and pixels per millisecond squared for gravity
+const PARTICLE_SPEED: f64 = 0.25;
+const PARTICLE_GRAVITY: f64 = 0.001;
+
+const PC_FLASH_DURATION: f64 = 600.0;
+const DAMAGE_FLASH_DURATION: f64 = 300.0;
+
+// starting opacities of the white drop trails and lock flashes, which fade out
+const TRAIL_OPACITY: f64 = 0.25;
+const LOCK_FLASH_OPACITY: f64 = 0.5;
+
+// heavy clears shake the field, further the more lines were cleared
+const SHAKE_DURATION: f64 = 250.0;
+const SHAKE_PX_PER_LINE: f64 = 2.0;
+
+struct Particle {
+    x: f64,
+    y: f64,
+    vx: f64,
+    vy: f64,
+    start: f64,
+}
+
+impl Particle {
+    // a particle from the center of a square, thrown up and out in a random direction
+    fn new(x: f64, y: f64, start: f64) -> Self {
+        let angle = Math::random() * std::f64::consts::PI;
+        let speed = PARTICLE_SPEED * (0.5 + Math::random());
+        Particle {
+            x,
+            y,
+            vx: angle.cos() * speed,
+            vy: -angle.sin() * speed,
+            start,
+        }
+    }
+}
+
+// the squares a hard dropped piece fell through in a column, in pixels
+struct Trail {
+    x: f64,
+    top: f64,
+    bottom: f64,
+    start: f64,
+}
+
+// a square a piece locked in, in pixels
+struct LockFlash {
+    x: f64,
+    y: f64,
+    start: f64,
+}
+
+// effects currently showing, with the times they started at (from the clock)
+#[derive(Default)]
+struct EffectState {
+    particles: Vec<Particle>,
+    trails: Vec<Trail>,
+    lock_flashes: Vec<LockFlash>,
+    pc_flash: Option<f64>,
+    damage_flash: Option<f64>,
+    // start time and distance
+    shake: Option<(f64, f64)>,
+    // garbage rows counted so far, so only new rows flash
+    n_garbage_rows: usize,
+}
+
+impl EffectState {
+    fn is_active(&self) -> bool {
+        let flashing = self.pc_flash.is_some() || self.damage_flash.is_some();
+        let dropping = !self.trails.is_empty() || !self.lock_flashes.is_empty();
+        !self.particles.is_empty() || flashing || dropping || self.shake.is_some()
+    }
+}
+
+#[derive(Prop)]
+pub struct EffectsProps<'a> {
+    last_line_clear: &'a Signal<Option<LineClear>>,
+    // total garbage rows received, which flash the field when they go up
+    n_garbage_rows: &'a ReadSignal<usize>,
+    // offset of the field in pixels while it's shaking, for the board to move it by
+    shake_offset: &'a Signal<(f64, f64)>,
+}
+
+// particles and flashes drawn on a canvas over the field, which is only redrawn while an effect is showing
+#[component]
+pub fn Effects<'a, G: Html>(cx: Scope<'a>, props: EffectsProps<'a>) -> View<G> {
+    let EffectsProps {
+        last_line_clear,
+        n_garbage_rows,
+        shake_offset,
+    } = props;
+
+    let field_vals = use_context::<ReadSignal<FieldValues>>(cx);
+    let field_dims = create_selector(cx, || {
+        let field_vals = field_vals.get();
+        (field_vals.width, field_vals.height, field_vals.hidden)
+    });
+    let effects_ref = create_node_ref(cx);
+
+    let view = view! { cx,
+        canvas(
+            ref=effects_ref,
+            class="effects-canvas",
+            width=canvas::canvas_px(cx, SQUARE_WIDTH * field_dims.get().0),
+            height=canvas::canvas_px(cx, SQUARE_WIDTH * field_dims.get().1),
+            style=format!(
+                "{} margin-top: -{}px;",
+                canvas::css_size((SQUARE_WIDTH * field_dims.get().0, SQUARE_WIDTH * field_dims.get().1)),
+                SQUARE_WIDTH * field_dims.get().2,
+            ),
+        )
+    };
+
+    let field = use_context::<Signal<RefCell<DefaultField>>>(cx);
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let enabled = util::create_config_selector(cx, config, |c| {
+        (c.clear_particles_enabled, c.pc_flash_enabled, c.shake_enabled, c.damage_flash_enabled)
+    });
+    let resolution = util::create_config_selector(cx, config, canvas::canvas_resolution);
+    let drop_durations =
+        util::create_config_selector(cx, config, |c| (c.drop_trail_duration as f64, c.lock_flash_duration as f64));
+    let state = create_ref(cx, RefCell::new(EffectState::default()));
+    let clock = timer::use_clock(cx);
+
+    // draw a frame of every effect showing, stopping once none are
+    let active = create_signal(cx, false);
+    let (_, start, stop) = create_raf(cx, move || {
+        let Some(canvas) = effects_ref.try_get::<DomNode>() else {
+            return;
+        };
+        let canvas = canvas.unchecked_into::<HtmlCanvasElement>();
+        let ctx = canvas.get_context("2d").unwrap().unwrap();
+        let ctx = ctx.dyn_into::<CanvasRenderingContext2d>().unwrap();
+
+        // drawing is done in css pixels, like on the field canvas
+        let resolution = *resolution.get_untracked();
+        ctx.set_transform(resolution, 0.0, 0.0, resolution, 0.0, 0.0).unwrap();
+        let (width, height) = (canvas.width() as f64 / resolution, canvas.height() as f64 / resolution);
+        let hidden_end_px = (field_dims.get_untracked().2 * SQUARE_WIDTH) as f64;
+        ctx.clear_rect(0.0, 0.0, width, height);
+
+        let now = clock.now();
+        let mut state = state.borrow_mut();
+        let state = &mut *state;
+
+        let (trail_duration, lock_flash_duration) = *drop_durations.get_untracked();
+        ctx.set_fill_style(&"white".into());
+
+        state.trails.retain(|t| now - t.start < trail_duration);
+        for trail in &state.trails {
+            ctx.set_global_alpha(TRAIL_OPACITY * (1.0 - (now - trail.start) / trail_duration));
+            ctx.fill_rect(trail.x, trail.top, SQUARE_WIDTH as f64, trail.bottom - trail.top);
+        }
+        state.lock_flashes.retain(|f| now - f.start < lock_flash_duration);
+        for flash in &state.lock_flashes {
+            ctx.set_global_alpha(LOCK_FLASH_OPACITY * (1.0 - (now - flash.start) / lock_flash_duration));
+            ctx.fill_rect(flash.x, flash.y, SQUARE_WIDTH as f64, SQUARE_WIDTH as f64);
+        }
+
+        state.particles.retain(|p| now - p.start < PARTICLE_LIFETIME);
+        for particle in &state.particles {
+            let t = now - particle.start;
+            let x = particle.x + particle.vx * t;
+            let y = particle.y + particle.vy * t + PARTICLE_GRAVITY * t * t / 2.0;
+            ctx.set_global_alpha(1.0 - t / PARTICLE_LIFETIME);
+            ctx.fill_rect(x - PARTICLE_SIZE / 2.0, y - PARTICLE_SIZE / 2.0, PARTICLE_SIZE, PARTICLE_SIZE);
+        }
+
+        // flashes cover the visible part of the field, fading out
+        let flashes = [
+            (&mut state.pc_flash, PC_FLASH_DURATION, "white", 0.6),
+            (&mut state.damage_flash, DAMAGE_FLASH_DURATION, "#f53131", 0.4),
+        ];
+        for (flash, duration, color, opacity) in flashes {
+            *flash = flash.filter(|start| now - start < duration);
+            if let Some(start) = flash {
+                ctx.set_fill_style(&color.into());
+                ctx.set_global_alpha(opacity * (1.0 - (now - *start) / duration));
+                ctx.fill_rect(0.0, hidden_end_px, width, height - hidden_end_px);
+            }
+        }
+        ctx.set_global_alpha(1.0);
+
+        state.shake = state.shake.filter(|(start, _)| now - start < SHAKE_DURATION);
+        shake_offset.set(match state.shake {
+            Some((start, distance)) => {
+                let t = now - start;
+                let distance = distance * (1.0 - t / SHAKE_DURATION);
+                ((t / 15.0).sin() * distance, (t / 20.0).cos() * distance / 2.0)
+            }
+            None => (0.0, 0.0),
+        });
+
+        active.set(state.is_active());
+    });
+    create_effect(cx, || {
+        if !*active.get() {
+            stop();
+        }
+    });
+
+    let start_effects = move || {
+        active.set(true);
+        start();
+    };
+
+    create_effect(cx, move || {
+        let line_clear = last_line_clear.get();
+        let Some(line_clear) = &*line_clear else {
+            return;
+        };
+        let (particles_enabled, pc_flash_enabled, shake_enabled, _) = *enabled.get_untracked();
+        let now = clock.now();
+        let mut state = state.borrow_mut();
+
+        // the field grows upward when pieces are moved above it, so rows are offset like when drawing it
+        let field = field.get_untracked();
+        let field = field.borrow();
+        let extra_rows = field.hidden().saturating_sub(field_dims.get_untracked().2);
+        let row_px = |row: i32| (row as f64 - extra_rows as f64).max(0.0) * SQUARE_WIDTH as f64;
+
+        // every hard drop updates the line clear, even without clearing lines
+        let (trail_duration, lock_flash_duration) = *drop_durations.get_untracked();
+        if let Some(piece_drop) = field.last_drop() {
+            for &Coords(row, col) in &piece_drop.to {
+                let x = (col as usize * SQUARE_WIDTH) as f64;
+                // trails go from the top of the piece before it dropped to the top of it after, in each column
+                let top_before = piece_drop.from.iter().filter(|c| c.1 == col).map(|c| c.0).min().unwrap_or(row);
+                let top_after = piece_drop.to.iter().filter(|c| c.1 == col).map(|c| c.0).min().unwrap_or(row);
+                if trail_duration > 0.0 && row == top_after && top_before < top_after {
+                    let (top, bottom) = (row_px(top_before), row_px(top_after));
+                    state.trails.push(Trail { x, top, bottom, start: now });
+                }
+                if lock_flash_duration > 0.0 {
+                    state.lock_flashes.push(LockFlash { x, y: row_px(row), start: now });
+                }
+            }
+        }
+
+        if particles_enabled {
+            for &row in line_clear.cleared_rows() {
+                let Some(row) = row.checked_sub(extra_rows) else {
+                    continue;
+                };
+                let y = (row * SQUARE_WIDTH + SQUARE_WIDTH / 2) as f64;
+                for col in 0..field_dims.get_untracked().0 {
+                    let x = (col * SQUARE_WIDTH + SQUARE_WIDTH / 2) as f64;
+                    state.particles.extend((0..PARTICLES_PER_SQUARE).map(|_| Particle::new(x, y, now)));
+                }
+            }
+        }
+        if pc_flash_enabled && line_clear.is_perfect_clear() {
+            state.pc_flash = Some(now);
+        }
+        // quads and spin doubles or better
+        let n_lines = line_clear.n_lines();
+        if shake_enabled && (n_lines >= 4 || n_lines >= 2 && line_clear.spin().is_some()) {
+            state.shake = Some((now, n_lines as f64 * SHAKE_PX_PER_LINE));
+        }
+
+        if state.is_active() {
+            drop(state);
+            start_effects();
+        }
+    });
+
+    create_effect(cx, move || {
+        let n_rows = *n_garbage_rows.get();
+        let mut state = state.borrow_mut();
+        let received = n_rows > state.n_garbage_rows;
+        state.n_garbage_rows = n_rows;
+
+        if received && enabled.get_untracked().3 {
+            state.damage_flash = Some(clock.now());
+            drop(state);
+            start_effects();
+        }
+    });
+
+    view
+}
@@ -1,21 +1,26 @@
-use std::{cell::RefCell, ops::AddAssign};
+use std::{cell::RefCell, ops::AddAssign, str::FromStr};
 
 use sycamore::{
     generic_node::Html,
-    prelude::{create_effect, create_selector, create_signal, ReadSignal, Scope, Signal},
+    prelude::{
+        create_effect, create_memo, create_selector, create_signal, use_context, Indexed, ReadSignal, Scope, Signal,
+    },
     view,
     view::View,
 };
-use tetrox::field::LineClear;
+use tetrox::field::{DefaultField, LineClear};
 
-use crate::{config::Config, util};
+use crate::{config::Config, scores::GameTotals, util};
 
-// a goal for completion of a game (e.g. clear 40 lines)
-pub struct Goal<'a, G: Html>(&'a ReadSignal<bool>, View<G>, bool);
+// a goal for completion of a game (e.g. clear 40 lines), which some goals can also fail (e.g. by running out of time)
+pub struct Goal<'a, G: Html>(&'a ReadSignal<bool>, View<G>, bool, Option<&'a ReadSignal<bool>>);
 
 impl<'a, G: Html> Goal<'a, G> {
     pub fn is_completed(&self) -> bool { *self.0.get() }
 
+    // the game ends without the goal being completed once this is true
+    pub fn is_failed(&self) -> bool { self.3.is_some_and(|failed| *failed.get()) }
+
     pub fn view(&self) -> &View<G> { &self.1 }
 
     // whether to show the default timer (counting up) when this goal is used
@@ -23,13 +28,14 @@ impl<'a, G: Html> Goal<'a, G> {
     pub fn show_elapsed_time(&self) -> bool { self.2 }
 }
 
-pub fn none<'a, G: Html>(cx: Scope<'a>) -> Goal<'a, G> { Goal(create_signal(cx, false), view! { cx, }, true) }
+pub fn none<'a, G: Html>(cx: Scope<'a>) -> Goal<'a, G> { Goal(create_signal(cx, false), view! { cx, }, true, None) }
 
 // goal which completes upon reaching a certain number of lines cleared
 pub fn lines_cleared<'a, G: Html>(
     cx: Scope<'a>,
     config: &'a Signal<RefCell<Config>>,
     clear_type: &'a Signal<Option<LineClear>>,
+    time_elapsed: &'a Signal<f64>,
 ) -> Goal<'a, G> {
     // simple line clear counter
     let new_lines_cleared = clear_type.map(cx, |c| c.as_ref().map(|c| c.n_lines()).unwrap_or(0) as u32);
@@ -39,12 +45,23 @@ pub fn lines_cleared<'a, G: Html>(
     let n_lines = util::create_config_selector(cx, config, |c| c.goal_n_lines);
     let completed = n_lines.map(cx, |n| n <= &n_cleared.get());
 
+    // finish time if the pace so far keeps up, only updated on clears so it doesn't drift between them
+    let projected = create_selector(cx, || match *n_cleared.get() {
+        0 => "-".to_string(),
+        n_cleared => {
+            let n_lines = *n_lines.get() as f64;
+            util::format_duration(*time_elapsed.get_untracked() * n_lines / n_cleared as f64)
+        }
+    });
+
     let view = view! { cx,
         p(class="game-stats-label") { "LINES" }
         p(class="game-stats-display", style="direction: ltr;") { (format!("{}/{}", n_cleared.get(), n_lines.get())) }
+        p(class="game-stats-label") { "PROJECTED" }
+        p(class="game-stats-display", style="direction: ltr;") { (projected.get()) }
     };
 
-    Goal(completed, view, true)
+    Goal(completed, view, true, None)
 }
 
 // goal which completes upon reaching the expiration of a time limit
@@ -63,5 +80,277 @@ pub fn time_limit<'a, G: Html>(
         p(class="game-stats-display", style="direction: ltr;") { (util::format_duration(*time_remaining.get())) }
     };
 
-    Goal(completed, view, false)
+    Goal(completed, view, false, None)
+}
+
+// goal which completes upon reaching a guideline score
+pub fn score_target<'a, G: Html>(
+    cx: Scope<'a>,
+    config: &'a Signal<RefCell<Config>>,
+    time_elapsed: &'a Signal<f64>,
+    totals: &'a Signal<RefCell<GameTotals>>,
+) -> Goal<'a, G> {
+    // soft drops score points without notifying, so also check whenever the timer ticks
+    let score = create_selector(cx, || {
+        time_elapsed.track();
+        totals.get().borrow().score.score()
+    });
+
+    let target = util::create_config_selector(cx, config, |c| c.goal_score);
+    let completed = create_selector(cx, || *score.get() >= *target.get());
+
+    let view = view! { cx,
+        p(class="game-stats-label") { "SCORE" }
+        p(class="game-stats-display", style="direction: ltr;") { (format!("{}/{}", score.get(), target.get())) }
+    };
+
+    Goal(completed, view, true, None)
+}
+
+// time limit goal which also shows guideline score, updated on every piece and drop
+pub fn score_attack<'a, G: Html>(
+    cx: Scope<'a>,
+    config: &'a Signal<RefCell<Config>>,
+    time_elapsed: &'a Signal<f64>,
+    totals: &'a Signal<RefCell<GameTotals>>,
+) -> Goal<'a, G> {
+    let Goal(completed, time_view, show_elapsed_time, _) = time_limit(cx, config, time_elapsed);
+
+    // soft drops score points without notifying, so also check whenever the timer ticks
+    let score = create_selector(cx, || {
+        time_elapsed.track();
+        totals.get().borrow().score.score()
+    });
+
+    let view = view! { cx,
+        p(class="game-stats-label") { "SCORE" }
+        p(class="game-stats-display", style="direction: ltr;") { (score.get()) }
+        (time_view)
+    };
+
+    Goal(completed, view, show_elapsed_time, None)
+}
+
+// goal which never completes, with the game ending on topping out and the time survived being the result
+pub fn survival<'a, G: Html>(cx: Scope<'a>, time_elapsed: &'a Signal<f64>) -> Goal<'a, G> {
+    let view = view! { cx,
+        p(class="game-stats-label") { "SURVIVED" }
+        p(class="game-stats-display", style="direction: ltr;") { (util::format_duration(*time_elapsed.get())) }
+    };
+
+    Goal(create_signal(cx, false), view, false, None)
+}
+
+// endless goal which never completes, showing score and lines cleared in this game and over every zen game
+pub fn zen<'a, G: Html>(
+    cx: Scope<'a>,
+    clear_type: &'a Signal<Option<LineClear>>,
+    totals: &'a Signal<RefCell<GameTotals>>,
+    lifetime_lines: &'a ReadSignal<u64>,
+) -> Goal<'a, G> {
+    let score_lines = create_selector(cx, || {
+        clear_type.track();
+        let totals = totals.get();
+        let totals = totals.borrow();
+        (totals.score.score(), totals.stats.n_lines())
+    });
+
+    let view = view! { cx,
+        p(class="game-stats-label") { "SCORE" }
+        p(class="game-stats-display", style="direction: ltr;") { (score_lines.get().0) }
+        p(class="game-stats-label") { "LINES" }
+        p(class="game-stats-display", style="direction: ltr;") { (score_lines.get().1) }
+        p(class="game-stats-label") { "LIFETIME LINES" }
+        p(class="game-stats-display", style="direction: ltr;") { (lifetime_lines.get()) }
+    };
+
+    // zen is untimed
+    Goal(create_signal(cx, false), view, false, None)
+}
+
+// perfect clear practice attempts, kept over the fresh fields dealt for each one
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PcCounts {
+    pub n_attempts: u32,
+    pub n_cleared: u32,
+    pub streak: u32,
+    pub best_streak: u32,
+}
+
+impl PcCounts {
+    pub fn add_attempt(&mut self, cleared: bool) {
+        self.n_attempts += 1;
+        if cleared {
+            self.n_cleared += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+    }
+}
+
+// goal which never completes, counting the attempts at perfect clears that made one
+pub fn perfect_clear<'a, G: Html>(cx: Scope<'a>, pc_counts: &'a ReadSignal<PcCounts>) -> Goal<'a, G> {
+    let cleared = pc_counts.map(cx, |c| format!("{}/{}", c.n_cleared, c.n_attempts));
+
+    let view = view! { cx,
+        p(class="game-stats-label") { "PERFECT CLEARS" }
+        p(class="game-stats-display", style="direction: ltr;") { (cleared.get()) }
+        p(class="game-stats-label") { "STREAK" }
+        p(class="game-stats-display", style="direction: ltr;") { (pc_counts.get().streak) }
+        p(class="game-stats-label") { "BEST STREAK" }
+        p(class="game-stats-display", style="direction: ltr;") { (pc_counts.get().best_streak) }
+    };
+
+    Goal(create_signal(cx, false), view, true, None)
+}
+
+// conditions a combined goal is made of
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Condition {
+    Lines(u32),
+    // seconds
+    Time(u64),
+    Score(u64),
+    TopOut,
+}
+
+// how far the game has gotten, for checking conditions
+struct Progress {
+    n_lines: u32,
+    time: f64,
+    score: u64,
+    topped_out: bool,
+}
+
+impl Condition {
+    fn is_met(&self, progress: &Progress) -> bool {
+        match *self {
+            Condition::Lines(n_lines) => progress.n_lines >= n_lines,
+            Condition::Time(secs) => progress.time >= secs as f64 * 1_000.0,
+            Condition::Score(score) => progress.score >= score,
+            Condition::TopOut => progress.topped_out,
+        }
+    }
+}
+
+// conditions joined with `and` and `or` (with `and` binding tighter), optionally followed by a time limit after which
+// the goal fails (e.g. `lines 40 within 180` or `lines 100 or topout`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoalExpr {
+    // met if every condition in any one group is met
+    groups: Vec<Vec<Condition>>,
+    within_secs: Option<u64>,
+}
+
+impl GoalExpr {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        fn number<T: FromStr>(word: Option<&str>) -> Result<T, String> {
+            let word = word.ok_or("expected a number at the end")?;
+            word.parse().map_err(|_| format!("expected a number but found `{}`", word))
+        }
+
+        let mut words = text.split_whitespace();
+        let mut groups = vec![vec![]];
+        let mut within_secs = None;
+        loop {
+            let condition = match words.next() {
+                Some("lines") => Condition::Lines(number(words.next())?),
+                Some("time") => Condition::Time(number(words.next())?),
+                Some("score") => Condition::Score(number(words.next())?),
+                Some("topout") => Condition::TopOut,
+                Some(word) => return Err(format!("expected a condition but found `{}`", word)),
+                None => return Err("expected a condition at the end".to_string()),
+            };
+            groups.last_mut().unwrap().push(condition);
+
+            match words.next() {
+                Some("and") => {}
+                Some("or") => groups.push(vec![]),
+                Some("within") => {
+                    within_secs = Some(number(words.next())?);
+                    match words.next() {
+                        Some(word) => return Err(format!("expected nothing after the time limit but found `{}`", word)),
+                        None => break,
+                    }
+                }
+                Some(word) => return Err(format!("expected `and`, `or`, or `within` but found `{}`", word)),
+                None => break,
+            }
+        }
+        Ok(GoalExpr { groups, within_secs })
+    }
+
+    fn is_met(&self, progress: &Progress) -> bool {
+        self.groups.iter().any(|group| group.iter().all(|c| c.is_met(progress)))
+    }
+
+    fn conditions(&self) -> impl Iterator<Item = &Condition> { self.groups.iter().flatten() }
+}
+
+// goal made of other goals' conditions, showing a counter for each of them
+pub fn combined<'a, G: Html>(
+    cx: Scope<'a>,
+    config: &'a Signal<RefCell<Config>>,
+    clear_type: &'a Signal<Option<LineClear>>,
+    time_elapsed: &'a Signal<f64>,
+    totals: &'a Signal<RefCell<GameTotals>>,
+) -> Goal<'a, G> {
+    let field = use_context::<Signal<RefCell<DefaultField>>>(cx);
+    let expr = util::create_config_selector(cx, config, |c| GoalExpr::parse(&c.goal_expression));
+
+    // soft drops score points without notifying, so score is also checked whenever the timer ticks
+    let progress = create_memo(cx, || {
+        clear_type.track();
+        let totals = totals.get();
+        let totals = totals.borrow();
+        Progress {
+            n_lines: totals.stats.n_lines(),
+            time: *time_elapsed.get(),
+            score: totals.score.score(),
+            topped_out: field.get().borrow().topped_out(),
+        }
+    });
+
+    let completed = create_selector(cx, || (*expr.get()).as_ref().is_ok_and(|e| e.is_met(&progress.get())));
+    let failed = create_selector(cx, || {
+        let time_limit = (*expr.get()).as_ref().ok().and_then(|e| e.within_secs);
+        time_limit.is_some_and(|secs| *time_elapsed.get() >= secs as f64 * 1_000.0) && !*completed.get()
+    });
+
+    let counters = create_memo(cx, move || {
+        let progress = progress.get();
+        let time_left = |secs: u64| util::format_duration((secs as f64 * 1_000.0 - progress.time).max(0.0));
+        let expr = match &*expr.get() {
+            Ok(expr) => expr.clone(),
+            Err(error) => return vec![("INVALID GOAL", error.clone())],
+        };
+
+        let mut counters = expr
+            .conditions()
+            .filter_map(|condition| match *condition {
+                Condition::Lines(n_lines) => Some(("LINES", format!("{}/{}", progress.n_lines, n_lines))),
+                Condition::Time(secs) => Some(("TIME LEFT", time_left(secs))),
+                Condition::Score(score) => Some(("SCORE", format!("{}/{}", progress.score, score))),
+                Condition::TopOut => None,
+            })
+            .collect::<Vec<_>>();
+        if let Some(secs) = expr.within_secs {
+            counters.push(("TIME LIMIT", time_left(secs)));
+        }
+        counters
+    });
+
+    let view = view! { cx,
+        Indexed {
+            iterable: counters,
+            view: |cx, (label, value)| view! { cx,
+                p(class="game-stats-label") { (label) }
+                p(class="game-stats-display", style="direction: ltr;") { (value) }
+            },
+        }
+    };
+
+    Goal(completed, view, true, Some(failed))
 }
@@ -0,0 +1,88 @@
+use sycamore::{
+    component,
+    generic_node::{DomNode, Html},
+    prelude::{create_effect, create_node_ref, Scope},
+    view,
+    view::View,
+    Prop,
+};
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+use crate::replay::RecordedGame;
+
+// seconds covered by each bar
+const INTERVAL_SECS: f64 = 5.0;
+
+const GRAPH_WIDTH: usize = 240;
+const GRAPH_HEIGHT: usize = 40;
+
+#[derive(Prop)]
+pub struct GameGraphsProps {
+    recorded: RecordedGame,
+}
+
+// how a finished game went over time, worked out by playing back its replay
+#[component]
+pub fn GameGraphs<'a, G: Html>(cx: Scope<'a>, props: GameGraphsProps) -> View<G> {
+    let replay = &props.recorded.replay;
+    let interval = replay.tick_rate.ticks(INTERVAL_SECS * 1_000.0);
+    let intervals = replay.intervals(interval, props.recorded.rules());
+
+    let pps = intervals.iter().map(|i| i.n_pieces as f64 / INTERVAL_SECS).collect();
+    let attack = intervals.iter().map(|i| i.attack as f64).collect();
+    // every recorded input, including repeats from das and gravity
+    let inputs = intervals.iter().map(|i| i.n_events as f64).collect();
+
+    view! { cx,
+        div(class="game-graphs") {
+            Graph { label: "pps", values: pps }
+            Graph { label: "attack", values: attack }
+            Graph { label: "inputs", values: inputs }
+        }
+    }
+}
+
+#[derive(Prop)]
+struct GraphProps {
+    label: &'static str,
+    values: Vec<f64>,
+}
+
+// bar chart of `values` in order, scaled so the largest one fills the height
+#[component]
+fn Graph<'a, G: Html>(cx: Scope<'a>, props: GraphProps) -> View<G> {
+    let GraphProps { label, values } = props;
+    let max = values.iter().copied().fold(0.0, f64::max);
+
+    let canvas_ref = create_node_ref(cx);
+    let view = view! { cx,
+        p(class="game-graph-label") { (format!("{} (max {:.1})", label, max)) }
+        canvas(ref=canvas_ref, class="game-graph", width=GRAPH_WIDTH, height=GRAPH_HEIGHT)
+    };
+
+    create_effect(cx, move || {
+        if let Some(node) = canvas_ref.try_get::<DomNode>() {
+            let canvas = node.unchecked_into::<HtmlCanvasElement>();
+            let context = canvas.get_context("2d").unwrap().unwrap();
+            let context = context.dyn_into::<CanvasRenderingContext2d>().unwrap();
+            draw_bars(&context, &values, max);
+        }
+    });
+
+    view
+}
+
+fn draw_bars(ctx: &CanvasRenderingContext2d, values: &[f64], max: f64) {
+    let (width, height) = (GRAPH_WIDTH as f64, GRAPH_HEIGHT as f64);
+    ctx.clear_rect(0.0, 0.0, width, height);
+    ctx.set_fill_style(&"#ccc".into());
+
+    let bar_width = width / values.len().max(1) as f64;
+    for (i, value) in values.iter().enumerate() {
+        let bar_height = if max > 0.0 { value / max * height } else { 0.0 };
+        // leave a gap between bars when there's room for one
+        let gap = if bar_width > 3.0 { 1.0 } else { 0.0 };
+        ctx.fill_rect(i as f64 * bar_width, height - bar_height, bar_width - gap, bar_height);
+    }
+}
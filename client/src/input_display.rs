@@ -0,0 +1,79 @@
+use std::{cell::RefCell, collections::VecDeque};
+
+use sycamore::{
+    component,
+    generic_node::Html,
+    prelude::{create_memo, create_signal, Indexed, Scope, Signal},
+    view,
+    view::View,
+    Prop,
+};
+
+use crate::{
+    board::InputStates,
+    config::Input,
+    timer::{self, Timer},
+};
+
+// keys per second are counted over this many milliseconds
+const KPS_WINDOW: f64 = 2_000.0;
+
+const SHOWN_INPUTS: [(&str, Input); 8] = [
+    ("L", Input::Left),
+    ("R", Input::Right),
+    ("SD", Input::SoftDrop),
+    ("HD", Input::HardDrop),
+    ("CW", Input::RotateCw),
+    ("CCW", Input::RotateCcw),
+    ("180", Input::Rotate180),
+    ("HOLD", Input::SwapHold),
+];
+
+#[derive(Prop)]
+pub struct InputDisplayProps<'a> {
+    inputs: &'a Signal<RefCell<InputStates>>,
+    // times of recent key presses, from the clock
+    press_times: &'a Signal<RefCell<VecDeque<f64>>>,
+}
+
+// which inputs are held, and a rolling count of keys pressed per second
+#[component]
+pub fn InputDisplay<'a, G: Html>(cx: Scope<'a>, props: InputDisplayProps<'a>) -> View<G> {
+    let InputDisplayProps { inputs, press_times } = props;
+
+    let keys = create_memo(cx, || {
+        let inputs = inputs.get();
+        let inputs = inputs.borrow();
+        SHOWN_INPUTS.map(|(label, input)| (label, inputs.get_state(&input).is_held())).to_vec()
+    });
+
+    // presses drop out of the window as time passes, so this is updated on a timer as well as on presses
+    let kps = create_signal(cx, 0.0);
+    let kps_timer = create_signal(cx, Timer::new(cx, 100));
+    let clock = timer::use_clock(cx);
+    timer::create_timer_finish_effect(cx, kps_timer, move || {
+        let press_times = press_times.get_untracked();
+        let mut press_times = press_times.borrow_mut();
+        while press_times.front().is_some_and(|&t| clock.now() - t > KPS_WINDOW) {
+            press_times.pop_front();
+        }
+        kps.set(press_times.len() as f64 / (KPS_WINDOW / 1_000.0));
+        true
+    });
+    kps_timer.get().start();
+
+    view! { cx,
+        div(class="input-display") {
+            div(class="input-keys") {
+                Indexed {
+                    iterable: keys,
+                    view: |cx, (label, held)| {
+                        let class = if held { "input-key input-key-held" } else { "input-key" };
+                        view! { cx, div(class=class) { (label) } }
+                    },
+                }
+            }
+            p(class="input-kps") { (format!("{:.1} kps", kps.get())) }
+        }
+    }
+}
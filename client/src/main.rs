@@ -1,43 +1,91 @@
 #![feature(type_alias_impl_trait)]
 
-use crate::config::ConfigPanel;
-use sycamore::{component, generic_node::Html, prelude::Scope, reactive, view, view::View};
-use tetrox::pieces::{tetromino::TetrominoSrs, PieceKindTrait};
+use crate::{
+    config::ConfigPanel,
+    skins::{AssetCache, CustomSkin, SkinInfo},
+    timer::{Clock, PerformanceClock},
+};
+use sycamore::{
+    component,
+    futures::spawn_local_scoped,
+    generic_node::Html,
+    prelude::{Indexed, Scope},
+    reactive, view,
+    view::View,
+};
 
 mod board;
 mod canvas;
+mod challenge;
 mod config;
+mod effects;
+mod graphs;
+mod input_display;
 mod menu;
+mod net;
+mod profiles;
+mod replay;
+mod scores;
+mod setups;
+mod skins;
+mod sound;
 mod stats;
 mod util;
 mod timer;
 mod goal;
 
-pub const SKIN_NAMES: &[&str] = &["tetrox", "gradient", "inset", "rounded", "tetrio", "solid"];
-
 #[component]
 fn AssetPreloader<'a, G: Html>(cx: Scope<'a>) -> View<G> {
     let n_loaded = reactive::create_signal(cx, 0);
+    reactive::provide_context(cx, Box::new(PerformanceClock) as Box<dyn Clock>);
 
-    let assets = TetrominoSrs::iter()
-        .map(|k| k.asset_name().to_string())
-        .chain(["grey".to_string()])
-        .flat_map(|kind| {
-            SKIN_NAMES.iter().map(move |skin| {
-                let src = format!("assets/skins/{}/{}.png", skin, kind);
-                view! { cx, img(class="loading-asset", src=src, on:load=|_| { n_loaded.set(*n_loaded.get() + 1) }) }
-            })
-        })
-        // collect to use iterator and start image loading
-        .collect::<Vec<View<G>>>();
+    // every board draws from the same cache, so skins uploaded while playing can be used right away
+    let asset_cache = reactive::provide_context(cx, AssetCache::default());
+    let skin_manifest = reactive::create_signal(cx, Vec::<SkinInfo>::new());
+    reactive::provide_context_ref(cx, skin_manifest);
+    let custom_skins = reactive::create_signal(cx, Vec::<CustomSkin>::new());
+    reactive::provide_context_ref(cx, custom_skins);
+
+    let skins_loaded = reactive::create_signal(cx, false);
+    spawn_local_scoped(cx, async move {
+        match skins::load_skin_manifest().await {
+            Ok(manifest) => {
+                manifest.iter().for_each(|skin| skin.add_to_cache(asset_cache));
+                skin_manifest.set(manifest);
+            }
+            Err(e) => web_sys::console::error_1(&e),
+        }
+        match skins::load_custom_skins().await {
+            Ok(loaded) => {
+                loaded.iter().for_each(|skin| skin.add_to_cache(asset_cache));
+                custom_skins.set(loaded);
+            }
+            Err(e) => web_sys::console::error_1(&e),
+        }
+        skins_loaded.set(true);
+    });
+
+    let assets = reactive::create_memo(cx, || {
+        let manifest = skin_manifest.get();
+        manifest.iter().flat_map(|skin| skin.assets.iter().map(|a| skins::asset_src(&skin.name, a))).collect::<Vec<_>>()
+    });
 
-    let n_total = assets.len();
     view! { cx,
         div(class="bg-gradient")
-        (if *n_loaded.get() == n_total { // show the game once all assets have loaded
+        div {
+            Indexed {
+                iterable: assets,
+                view: move |cx, src: String| view! { cx,
+                    img(class="loading-asset", src=src, on:load=move |_| n_loaded.set(*n_loaded.get() + 1))
+                },
+            }
+        }
+        (if *skins_loaded.get() && *n_loaded.get() == assets.get().len() { // show the game once all assets have loaded
             view! { cx, ConfigPanel {} }
         } else {
-            view! { cx, p(class="loading-text") { "Loading assets... (" (n_loaded.get()) "/" (n_total) ")" } }
+            view! { cx,
+                p(class="loading-text") { "Loading assets... (" (n_loaded.get()) "/" (assets.get().len()) ")" }
+            }
         })
     }
 }
@@ -2,7 +2,12 @@ use std::cell::RefCell;
 
 use crate::{
     board::Board,
-    config::{Config, GoalTypes},
+    challenge::{Challenge, SeedPanel, SeedState},
+    config::{Config, GoalTypes, SpeedCurves},
+    net::{Versus, VersusPanel},
+    replay::{RecordedGame, ReplayViewer},
+    scores::Scores,
+    setups::{FumenInput, SetupLibrary},
     util::{self, Padding, SectionHeading},
 };
 
@@ -10,7 +15,7 @@ use sycamore::{
     component,
     generic_node::Html,
     motion::Tweened,
-    prelude::{create_memo, use_context, ReadSignal, Scope, Signal},
+    prelude::{create_memo, create_signal, provide_context_ref, use_context, ReadSignal, Scope, Signal},
     view,
     view::View,
     Prop,
@@ -21,6 +26,14 @@ use sycamore_router::{HistoryIntegration, Route, Router};
 pub enum Routes {
     #[to("/")]
     Home,
+    #[to("/scores")]
+    Scores,
+    #[to("/replay")]
+    Replay,
+    #[to("/challenge/<seed>/<rules>")]
+    Challenge { seed: u64, rules: String },
+    #[to("/versus/<room>")]
+    Versus { room: String },
     #[not_found]
     NotFound,
 }
@@ -32,14 +45,20 @@ pub struct MenuProps<'a> {
 
 #[component]
 pub fn Menu<'a, G: Html>(cx: Scope<'a>, props: MenuProps<'a>) -> View<G> {
-    let lines_cleared_preset = move |label, n_lines| view! { cx, GoalPresetButton { label, goal_type: GoalTypes::LinesCleared, n_lines, time_limit_secs: 0 } };
-    let time_limit_preset = move |label, time_limit_secs| view! { cx, GoalPresetButton { label, goal_type: GoalTypes::TimeLimit, n_lines: 0, time_limit_secs } };
+    let lines_cleared_preset = move |label, n_lines| view! { cx, GoalPresetButton { label, goal_type: GoalTypes::LinesCleared, n_lines, time_limit_secs: 0, score: 0, speed_curve: SpeedCurves::None } };
+    let time_limit_preset = move |label, time_limit_secs| view! { cx, GoalPresetButton { label, goal_type: GoalTypes::ScoreAttack, n_lines: 0, time_limit_secs, score: 0, speed_curve: SpeedCurves::None } };
+    let score_target_preset = move |label, score| view! { cx, GoalPresetButton { label, goal_type: GoalTypes::ScoreTarget, n_lines: 0, time_limit_secs: 0, score, speed_curve: SpeedCurves::None } };
+    let speed_curve_preset = move |label, speed_curve, goal_type, n_lines| view! { cx, GoalPresetButton { label, goal_type, n_lines, time_limit_secs: 0, score: 0, speed_curve } };
+
+    // provided before the menu is made, since the seed panel uses it
+    provide_context_ref(cx, create_signal(cx, SeedState::default()));
 
     let menu = view! { cx,
         p(class="logo") { "Tetrox" }
 
-        ModeButton("Singleplayer")
-        ModeButton("Scores (not implemented)")
+        ModeButton { label: "Singleplayer", href: "/" }
+        ModeButton { label: "Scores", href: "/scores" }
+        ModeButton { label: "Replay", href: "/replay" }
         Padding(1)
 
         SectionHeading("Sprint")
@@ -57,8 +76,41 @@ pub fn Menu<'a, G: Html>(cx: Scope<'a>, props: MenuProps<'a>) -> View<G> {
             (time_limit_preset("5 minutes", 300))
             (time_limit_preset("1 hour", 3_600))
         }
+
+        SectionHeading("Score target")
+        div(class="menu-button-box menu-button-box-l") {
+            (score_target_preset("10k points", 10_000))
+            (score_target_preset("50k points", 50_000))
+            (score_target_preset("100k points", 100_000))
+        }
+
+        SectionHeading("Marathon")
+        div(class="menu-button-box menu-button-box-l") {
+            (speed_curve_preset("Marathon", SpeedCurves::Marathon, GoalTypes::LinesCleared, 150))
+            (speed_curve_preset("NES", SpeedCurves::Nes, GoalTypes::None, 0))
+            (speed_curve_preset("Master", SpeedCurves::Master, GoalTypes::None, 0))
+            (speed_curve_preset("Zen", SpeedCurves::Marathon, GoalTypes::Zen, 0))
+        }
+
+        SectionHeading("Practice")
+        div(class="menu-button-box menu-button-box-l") {
+            (speed_curve_preset("PC practice", SpeedCurves::None, GoalTypes::PerfectClear, 0))
+        }
+
+        SectionHeading("Setups")
+        SetupLibrary {}
+        FumenInput {}
+
+        SectionHeading("Seed")
+        SeedPanel {}
+
+        SectionHeading("Versus")
+        VersusPanel {}
     };
 
+    // the most recently finished game, shared between the board and the replay viewer
+    provide_context_ref(cx, create_signal(cx, None::<RecordedGame>));
+
     let ui_offset = props.ui_offset;
     let menu_style = create_memo(cx, || format!("margin-left: calc(-{}rem + 20px);", ui_offset.get()));
 
@@ -71,6 +123,12 @@ pub fn Menu<'a, G: Html>(cx: Scope<'a>, props: MenuProps<'a>) -> View<G> {
                         div(class="menu", style=menu_style.get()) { (menu) }
                         (match route.get().as_ref() {
                             Routes::Home => view! { cx, Board {} },
+                            Routes::Scores => view! { cx, Scores {} },
+                            Routes::Replay => view! { cx, ReplayViewer {} },
+                            Routes::Challenge { seed, rules } => view! { cx,
+                                Challenge { seed: *seed, rules: rules.clone() }
+                            },
+                            Routes::Versus { room } => view! { cx, Versus { room: room.clone() } },
                             Routes::NotFound => view! { cx, p(class="loading-text") { "not found" } }
                         })
                     }
@@ -80,11 +138,17 @@ pub fn Menu<'a, G: Html>(cx: Scope<'a>, props: MenuProps<'a>) -> View<G> {
     }
 }
 
+#[derive(Prop)]
+struct ModeButtonProps {
+    label: &'static str,
+    href: &'static str,
+}
+
 #[component]
-fn ModeButton<'a, G: Html>(cx: Scope<'a>, label: &'static str) -> View<G> {
+fn ModeButton<'a, G: Html>(cx: Scope<'a>, props: ModeButtonProps) -> View<G> {
     view! { cx,
-        div(class="mode-button") {
-            p(class="mode-button-text") { (label) }
+        a(class="mode-button", href=props.href) {
+            p(class="mode-button-text") { (props.label) }
         }
     }
 }
@@ -96,6 +160,8 @@ struct GoalPresetProps {
     goal_type: GoalTypes,
     n_lines: u32,
     time_limit_secs: u64,
+    score: u64,
+    speed_curve: SpeedCurves,
 }
 
 #[component]
@@ -111,6 +177,8 @@ fn GoalPresetButton<'a, G: Html>(cx: Scope<'a>, props: GoalPresetProps) -> View<
                     c.goal_type = props.goal_type;
                     c.goal_n_lines = props.n_lines;
                     c.goal_time_limit_secs = props.time_limit_secs;
+                    c.goal_score = props.score;
+                    c.speed_curve = props.speed_curve;
                 }),
             )
         }
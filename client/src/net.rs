@@ -0,0 +1,353 @@
+// versus matches against another player over a websocket, through a server relaying `tetrox::versus` messages
+// each player plays on their own board, with a copy of the opponent's game beside it that is played along from the
+// inputs and garbage they send
+
+use std::cell::{Cell, RefCell};
+
+use sycamore::{
+    component,
+    generic_node::Html,
+    prelude::{
+        create_effect, create_memo, create_rc_signal, create_signal, on_cleanup, provide_context, provide_context_ref,
+        untrack, use_context, RcSignal, ReadSignal, Scope, Signal,
+    },
+    view,
+    view::View,
+    Prop,
+};
+use tetrox::{
+    game::Game,
+    garbage::{CleanGarbage, GarbageGenerator, GarbageQueue, GarbageRules, ScriptedGarbage},
+    sim,
+    ticks::Tick,
+    versus::{self, Message},
+    SingleBag,
+};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{Event, MessageEvent, WebSocket};
+
+use crate::{
+    board::Board,
+    canvas::{ClearAnimation, Field, StackHidden},
+    challenge::{ChallengeRules, SeedState},
+    config::{Config, FieldValues},
+    replay, util,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MatchState {
+    Connecting,
+    // in the room, waiting for a second player to join
+    Waiting,
+    Playing,
+    Won,
+    Lost,
+    OpponentLeft,
+    // the connection to the server closed or couldn't be made before the match ended
+    Disconnected,
+}
+
+impl MatchState {
+    fn text(self) -> &'static str {
+        match self {
+            MatchState::Connecting => "connecting...",
+            MatchState::Waiting => "waiting for an opponent...",
+            MatchState::Playing => "opponent",
+            MatchState::Won => "you won",
+            MatchState::Lost => "you lost",
+            MatchState::OpponentLeft => "opponent left",
+            MatchState::Disconnected => "disconnected",
+        }
+    }
+}
+
+// the connection and garbage for the match being played, which the board sends its inputs and attacks through
+pub struct VersusLink {
+    socket: WebSocket,
+    state: RcSignal<MatchState>,
+    // attacks from the opponent that haven't entered the field yet, entering by the guideline rules
+    pending: RefCell<GarbageQueue>,
+    n_pending: RcSignal<u32>,
+    // holes of garbage entering the field, seeded by the match
+    garbage: RefCell<CleanGarbage>,
+    // the field's height, which caps attacks and garbage entering at once since anything more tops out anyway
+    max_rows: Cell<u32>,
+    // the opponent's game, made once the match starts
+    opponent: RcSignal<RefCell<Option<Game<SingleBag>>>>,
+}
+
+impl VersusLink {
+    fn new(socket: WebSocket) -> Self {
+        VersusLink {
+            socket,
+            state: create_rc_signal(MatchState::Connecting),
+            pending: RefCell::new(GarbageQueue::new()),
+            n_pending: create_rc_signal(0),
+            garbage: RefCell::new(CleanGarbage::new(0)),
+            max_rows: Cell::new(0),
+            opponent: create_rc_signal(RefCell::new(None)),
+        }
+    }
+
+    fn is_playing(&self) -> bool { *self.state.get_untracked() == MatchState::Playing }
+
+    fn garbage_rules(&self) -> GarbageRules {
+        GarbageRules {
+            cap: Some(self.max_rows.get()),
+            ..GarbageRules::default()
+        }
+    }
+
+    // messages sent once the match is over or the connection has closed are dropped
+    fn send(&self, message: &Message) {
+        if self.is_playing() {
+            let _ = self.socket.send_with_str(&message.to_text());
+        }
+    }
+
+    pub fn send_input(&self, tick: Tick, input: sim::Input) { self.send(&Message::Input { tick, input }); }
+
+    // send what's left of an attack after cancelling pending garbage
+    pub fn send_attack(&self, attack: u32) {
+        let attack = self.pending.borrow_mut().cancel(attack, &self.garbage_rules());
+        self.n_pending.set(self.pending.borrow().n_pending());
+        if attack > 0 {
+            self.send(&Message::Attack(attack));
+        }
+    }
+
+    // holes of the garbage rows entering a `width` wide field after a piece locks at `tick` having cleared `n_lines`
+    // lines, from top to bottom, which are also sent for the opponent's copy of the field
+    pub fn take_garbage(&self, n_lines: usize, tick: Tick, width: usize) -> Vec<usize> {
+        let entering = self.pending.borrow_mut().take_entering(n_lines, tick, &self.garbage_rules());
+        self.n_pending.set(self.pending.borrow().n_pending());
+
+        let mut garbage = self.garbage.borrow_mut();
+        let holes = entering.into_iter().flat_map(|n_rows| garbage.holes(n_rows as usize, width)).collect::<Vec<_>>();
+        if !holes.is_empty() {
+            self.send(&Message::Garbage(holes.clone()));
+        }
+        holes
+    }
+
+    // end the match once the game ends, which is won by reaching the goal first
+    pub fn finish(&self, completed: bool) {
+        self.send(if completed { &Message::Finished } else { &Message::ToppedOut });
+        if self.is_playing() {
+            self.state.set(if completed { MatchState::Won } else { MatchState::Lost });
+        }
+    }
+
+    // both players are given the same seed, so the opponent's game starts out like the board's
+    fn start(&self, seed: u64, c: &Config) {
+        let kinds = c.piece_type.kinds();
+        let bag = SingleBag::seeded(kinds.clone(), seed);
+        let game = Game::new(c.field_width, c.field_height, c.field_hidden, &kinds, bag, c.rules());
+        self.opponent.set(RefCell::new(Some(game)));
+
+        *self.garbage.borrow_mut() = CleanGarbage::new(seed);
+        self.max_rows.set(c.field_height as u32);
+        self.pending.borrow_mut().clear();
+        self.n_pending.set(0);
+        self.state.set(MatchState::Playing);
+    }
+
+    fn receive(&self, message: Message) {
+        let playing = self.is_playing();
+        match message {
+            Message::Input { tick, input } => self.with_opponent(|game| {
+                game.advance_to(tick);
+                sim::run(game, &[input]);
+            }),
+            Message::Garbage(mut holes) if !holes.is_empty() => {
+                holes.truncate(self.max_rows.get() as usize);
+                let n_rows = holes.len();
+                let mut holes = ScriptedGarbage::new(holes);
+                self.with_opponent(|game| game.add_garbage(n_rows, &mut holes));
+            }
+            // guideline garbage enters on the next placement that doesn't clear lines, whenever it was received
+            Message::Attack(n_rows) if playing => {
+                self.pending.borrow_mut().push(n_rows.min(self.max_rows.get()), 0);
+                self.n_pending.set(self.pending.borrow().n_pending());
+            }
+            Message::ToppedOut if playing => self.state.set(MatchState::Won),
+            Message::Finished if playing => self.state.set(MatchState::Lost),
+            Message::Left if playing || *self.state.get_untracked() == MatchState::Waiting => {
+                self.state.set(MatchState::OpponentLeft)
+            }
+            _ => {}
+        }
+    }
+
+    fn with_opponent(&self, mut op: impl FnMut(&mut Game<SingleBag>)) {
+        util::with_signal_mut_untracked(&self.opponent, |game| {
+            if let Some(game) = game {
+                op(game);
+            }
+        });
+    }
+}
+
+// an empty server is the one hosting the page
+fn server_url(server: &str) -> String {
+    if !server.trim().is_empty() {
+        return server.trim().to_string();
+    }
+    let location = web_sys::window().unwrap().location();
+    let scheme = if location.protocol().unwrap() == "https:" { "wss" } else { "ws" };
+    format!("{}://{}/versus", scheme, location.host().unwrap())
+}
+
+#[derive(Prop)]
+pub struct VersusProps {
+    room: String,
+}
+
+// a board in a versus room, once a second player joins
+// the player who made the room picks the rules, with their goal deciding the match if anyone reaches it
+#[component]
+pub fn Versus<'a, G: Html>(cx: Scope<'a>, props: VersusProps) -> View<G> {
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let seed_state = use_context::<Signal<SeedState>>(cx);
+
+    let (server, rules) = {
+        let c = config.get_untracked();
+        let c = c.borrow();
+        (server_url(&c.versus_server), ChallengeRules::from_config(&c).to_param())
+    };
+    let Ok(socket) = WebSocket::new(&server) else {
+        return view! { cx, p(class="loading-text") { "invalid versus server" } };
+    };
+    let link = provide_context(cx, VersusLink::new(socket.clone()));
+
+    // the socket's handlers have to be static, so messages are passed through a signal like window events are (see
+    // `util::on_window_event`)
+    let last_message = create_rc_signal(None::<Message>);
+    let join = Message::Join { room: props.room, rules };
+    let on_open = Closure::<dyn Fn(Event)>::new({
+        let (socket, state) = (socket.clone(), link.state.clone());
+        move |_: Event| {
+            let _ = socket.send_with_str(&join.to_text());
+            state.set(MatchState::Waiting);
+        }
+    });
+    let on_message = Closure::<dyn Fn(MessageEvent)>::new({
+        let last_message = last_message.clone();
+        move |e: MessageEvent| {
+            if let Some(message) = e.data().as_string().and_then(|text| Message::from_text(&text)) {
+                last_message.set(Some(message));
+            }
+        }
+    });
+    let on_close = Closure::<dyn Fn(Event)>::new({
+        let state = link.state.clone();
+        move |_: Event| {
+            if matches!(*state.get_untracked(), MatchState::Connecting | MatchState::Waiting | MatchState::Playing) {
+                state.set(MatchState::Disconnected);
+            }
+        }
+    });
+    socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+    // leaving the page leaves the room
+    on_cleanup(cx, move || {
+        socket.set_onopen(None);
+        socket.set_onmessage(None);
+        socket.set_onclose(None);
+        let _ = socket.close();
+        drop((on_open, on_message, on_close));
+    });
+
+    // the match is played with the room's rules and seed, like a challenge
+    let started = create_signal(cx, false);
+    create_effect(cx, move || {
+        let message = last_message.get();
+        let Some(message) = &*message else {
+            return;
+        };
+        untrack(|| match message {
+            Message::Start { seed, rules } => {
+                let Some(rules) = ChallengeRules::from_param(rules) else {
+                    return link.state.set(MatchState::Disconnected);
+                };
+                util::with_signal_mut(config, |c| rules.apply(c));
+                seed_state.modify().fixed = Some(*seed);
+                link.start(*seed, &config.get_untracked().borrow());
+                started.set(true);
+            }
+            message => link.receive(message.clone()),
+        });
+    });
+
+    let status = link.state.map(cx, |s| s.text());
+    view! { cx,
+        (if *started.get() {
+            view! { cx, Board {} }
+        } else {
+            view! { cx, p(class="loading-text") { (status.get()) } }
+        })
+    }
+}
+
+// the opponent's field beside the board, with how the match is going
+#[component]
+pub fn OpponentBoard<'a, G: Html>(cx: Scope<'a>) -> View<G> {
+    let link = use_context::<VersusLink>(cx);
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+
+    // see `GhostBoard`
+    let field_values = {
+        let c = config.get_untracked();
+        let c = c.borrow();
+        FieldValues::new(c.field_width, c.field_height, c.field_hidden, 0, c.piece_type)
+    };
+    let field_values: &ReadSignal<FieldValues> = create_signal(cx, field_values);
+    provide_context_ref(cx, field_values);
+
+    // the board is only shown once the match has started, which makes the opponent's game
+    let opponent_field = move || link.opponent.get().borrow().as_ref().unwrap().field().clone();
+    let field = create_signal(cx, RefCell::new(opponent_field()));
+    create_effect(cx, move || field.set(RefCell::new(opponent_field())));
+    provide_context_ref(cx, field);
+    // the player's clear animations and blind placements are for their own field
+    provide_context_ref(cx, create_signal(cx, None::<ClearAnimation>));
+    let stack_hidden: &ReadSignal<StackHidden> = create_signal(cx, StackHidden(false));
+    provide_context_ref(cx, stack_hidden);
+
+    let status = create_memo(cx, move || match (*link.state.get(), *link.n_pending.get()) {
+        (MatchState::Playing, n_pending) if n_pending > 0 => format!("{} incoming", n_pending),
+        (state, _) => state.text().to_string(),
+    });
+
+    view! { cx,
+        div(class="opponent") {
+            div(class="opponent-field") { Field {} }
+            p(class="opponent-status") { (status.get()) }
+        }
+    }
+}
+
+// join a versus room by name, make a new one with a random name to share, or be matched with anyone waiting
+#[component]
+pub fn VersusPanel<'a, G: Html>(cx: Scope<'a>) -> View<G> {
+    let room_text = create_signal(cx, String::new());
+    let join = move |_| {
+        let room = room_text.get().chars().filter(char::is_ascii_alphanumeric).collect::<String>();
+        let room = if room.is_empty() { format!("{:x}", replay::new_seed()) } else { room };
+        sycamore_router::navigate(&format!("/versus/{}", room));
+    };
+    // the server pairs up players waiting in this room who are playing by the same rules
+    let find = |_| sycamore_router::navigate(&format!("/versus/{}", versus::MATCHMAKING_ROOM));
+
+    view! { cx,
+        div(class="menu-option room-input") {
+            input(type="text", placeholder="room name, or empty for a new room", bind:value=room_text)
+        }
+        div(class="menu-button-box menu-button-box-l") {
+            div(class="menu-option menu-option-l") { input(type="button", value="Join room", on:click=join) }
+            div(class="menu-option menu-option-l") { input(type="button", value="Find opponent", on:click=find) }
+        }
+    }
+}
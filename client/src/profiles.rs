@@ -0,0 +1,124 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use sycamore::{
+    component,
+    generic_node::Html,
+    prelude::{create_effect, create_signal, use_context, Indexed, Scope, Signal},
+    view,
+    view::View,
+};
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlSelectElement};
+
+use crate::config::{self, Config};
+
+const PROFILES_LOCAL_STORAGE_KEY: &str = "profiles";
+
+// name of the profile holding the config from before there were profiles
+const DEFAULT_PROFILE_NAME: &str = "default";
+
+// named configs to switch between (e.g. separate handling for sprint and versus), one of which is in use
+#[derive(Clone, Serialize, Deserialize)]
+struct Profiles {
+    active: String,
+    #[serde(deserialize_with = "deserialize_configs")]
+    configs: BTreeMap<String, Config>,
+}
+
+// stored profiles are upgraded like the stored config is
+fn deserialize_configs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, Config>, D::Error> {
+    let stored = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    Ok(stored.into_iter().map(|(name, c)| (name, Config::from_stored(c))).collect())
+}
+
+impl Profiles {
+    fn from_local_storage(config: &Config) -> Self {
+        let json = config::get_local_storage().get_item(PROFILES_LOCAL_STORAGE_KEY).ok().flatten();
+        json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_else(|| Profiles {
+            active: DEFAULT_PROFILE_NAME.to_string(),
+            configs: [(DEFAULT_PROFILE_NAME.to_string(), config.clone())].into(),
+        })
+    }
+
+    fn save(&self) {
+        let json = serde_json::to_string(self).unwrap();
+        config::get_local_storage().set_item(PROFILES_LOCAL_STORAGE_KEY, &json).unwrap();
+    }
+}
+
+#[component]
+pub fn ProfileSwitcher<'a, G: Html>(cx: Scope<'a>) -> View<G> {
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let profiles = create_signal(cx, Profiles::from_local_storage(&config.get_untracked().borrow()));
+
+    // keep the active profile up to date with changes to the config
+    create_effect(cx, move || {
+        let c = config.get().borrow().clone();
+        let mut profiles = profiles.modify();
+        let active = profiles.active.clone();
+        profiles.configs.insert(active, c);
+    });
+    create_effect(cx, move || profiles.get().save());
+
+    // the active profile has to change first, so the effect above doesn't store the new config under the old name
+    let switch_to = move |name: String| {
+        let new_config = profiles.get_untracked().configs[&name].clone();
+        profiles.modify().active = name;
+        config.set(RefCell::new(new_config));
+    };
+
+    let names = profiles.map(cx, |p| p.configs.keys().cloned().collect::<Vec<_>>());
+    let new_name = create_signal(cx, String::new());
+
+    // start a new profile from the current config
+    let save_as = move |_| {
+        let name = new_name.get().trim().to_string();
+        if !name.is_empty() {
+            let c = config.get_untracked().borrow().clone();
+            let mut profiles = profiles.modify();
+            profiles.configs.insert(name.clone(), c);
+            profiles.active = name;
+            new_name.set(String::new());
+        }
+    };
+
+    // the last profile can't be deleted
+    let delete = move |_| {
+        let active = profiles.get_untracked().active.clone();
+        let next = names.get_untracked().iter().find(|&n| *n != active).cloned();
+        if let Some(next) = next {
+            switch_to(next);
+            profiles.modify().configs.remove(&active);
+        }
+    };
+
+    view! { cx,
+        div(class="menu-option") {
+            label(class="menu-option-label") { "Profile:" }
+            select(
+                on:input=move |e: Event| {
+                    switch_to(e.target().unwrap().dyn_into::<HtmlSelectElement>().unwrap().value());
+                },
+            ) {
+                Indexed {
+                    iterable: names,
+                    view: move |cx, name| {
+                        let (value, label) = (name.clone(), name.clone());
+                        view! { cx,
+                            option(value=value.clone(), selected=profiles.get().active == name) { (label.clone()) }
+                        }
+                    },
+                }
+            }
+        }
+        div(class="menu-option profile-name") {
+            input(type="text", placeholder="new profile name", bind:value=new_name)
+        }
+        div(class="menu-button-box") {
+            div(class="menu-option") { input(type="button", value="Save as new", on:click=save_as) }
+            div(class="menu-option") { input(type="button", value="Delete", on:click=delete) }
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use std::cell::RefCell;
+
+use js_sys::{Array, Date, Math};
+use serde::{Deserialize, Serialize};
+use sycamore::{
+    component,
+    futures::spawn_local_scoped,
+    generic_node::Html,
+    motion::create_raf,
+    prelude::{
+        create_effect, create_memo, create_signal, provide_context_ref, use_context, Indexed, ReadSignal, Scope,
+        Signal,
+    },
+    view,
+    view::View,
+    Prop,
+};
+use tetrox::{
+    game::GameRules,
+    replay::{Replay, ReplayPlayer},
+    sim,
+};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, BlobPropertyBag, Event, HtmlInputElement, IdbTransactionMode, Url};
+
+use crate::{
+    canvas::{ClearAnimation, Field, HoldPiece, NextQueue},
+    config::{self, Config, FieldValues, Input, KickTable180s, KickTables, PieceTypes, SpinTypes},
+    timer, util,
+};
+
+// followed by the number of lines in the sprint
+const PB_REPLAY_LOCAL_STORAGE_KEY: &str = "pb-replay-";
+
+const REPLAY_DB_NAME: &str = "tetrox";
+const REPLAY_STORE_NAME: &str = "replays";
+
+// older saved replays are deleted to keep at most this many
+const MAX_SAVED_REPLAYS: u32 = 10;
+
+// a replay along with the settings it has to be played back with
+#[derive(Clone, PartialEq)]
+pub struct RecordedGame {
+    pub replay: Replay,
+    pub piece_type: PieceTypes,
+    pub kick_table: KickTables,
+    pub kick_table_180: KickTable180s,
+    pub spin_types: SpinTypes,
+    // when the game finished, in milliseconds since the unix epoch
+    pub date: f64,
+}
+
+impl RecordedGame {
+    pub fn rules(&self) -> GameRules {
+        GameRules {
+            kick_table: self.kick_table.table(),
+            kick_table_180: self.kick_table_180.table(),
+            spin_detector: self.spin_types.detector(),
+            ..GameRules::default()
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let file = ReplayFile {
+            replay: self.replay.to_text(),
+            piece_type: self.piece_type,
+            kick_table: self.kick_table,
+            kick_table_180: self.kick_table_180,
+            spin_types: self.spin_types,
+            date: self.date,
+        };
+        serde_json::to_string(&file).unwrap()
+    }
+
+    fn from_json(json: &str) -> Option<RecordedGame> {
+        let file = serde_json::from_str::<ReplayFile>(json).ok()?;
+        Some(RecordedGame {
+            replay: Replay::from_text(&file.replay, &file.piece_type.kinds()).ok()?,
+            piece_type: file.piece_type,
+            kick_table: file.kick_table,
+            kick_table_180: file.kick_table_180,
+            spin_types: file.spin_types,
+            date: file.date,
+        })
+    }
+
+    // yyyy-mm-dd hh:mm:ss
+    fn date_text(&self) -> String {
+        let iso: String = Date::new(&JsValue::from_f64(self.date)).to_iso_string().into();
+        iso[..19].replace('T', " ")
+    }
+}
+
+// how recorded games are saved in files and indexeddb, with the replay in the engine's text format
+#[derive(Serialize, Deserialize)]
+struct ReplayFile {
+    replay: String,
+    piece_type: PieceTypes,
+    kick_table: KickTables,
+    kick_table_180: KickTable180s,
+    spin_types: SpinTypes,
+    date: f64,
+}
+
+// download the replay as a file, and keep it with the other saved replays
+pub fn save_replay(cx: Scope<'_>, recorded: &RecordedGame) {
+    let json = recorded.to_json();
+    let name = format!("tetrox-replay-{}.json", recorded.date_text().replace([' ', ':'], "-"));
+    download_file(&name, &json);
+
+    spawn_local_scoped(cx, async move {
+        if let Err(e) = store_replay(&json).await {
+            web_sys::console::error_1(&e);
+        }
+    });
+}
+
+// keep the replay of a new personal best sprint to race against as a ghost
+pub fn save_pb_replay(n_lines: u64, recorded: &RecordedGame) {
+    let key = format!("{}{}", PB_REPLAY_LOCAL_STORAGE_KEY, n_lines);
+    config::get_local_storage().set_item(&key, &recorded.to_json()).unwrap();
+}
+
+pub fn load_pb_replay(n_lines: u64) -> Option<RecordedGame> {
+    let key = format!("{}{}", PB_REPLAY_LOCAL_STORAGE_KEY, n_lines);
+    let json = config::get_local_storage().get_item(&key).ok().flatten()?;
+    RecordedGame::from_json(&json)
+}
+
+fn download_file(name: &str, contents: &str) {
+    let parts = Array::of1(&JsValue::from_str(contents));
+    let blob = Blob::new_with_str_sequence_and_options(&parts, BlobPropertyBag::new().type_("application/json"));
+    let url = Url::create_object_url_with_blob(&blob.unwrap()).unwrap();
+    util::download_url(name, &url);
+    Url::revoke_object_url(&url).unwrap();
+}
+
+async fn store_replay(json: &str) -> Result<(), JsValue> {
+    let db = util::open_db(REPLAY_DB_NAME, REPLAY_STORE_NAME, true).await?;
+    let store = util::db_store(&db, REPLAY_STORE_NAME, IdbTransactionMode::Readwrite)?;
+    util::request_result(&store.add(&JsValue::from_str(json))?).await?;
+
+    // keys count up, so the oldest replays come first
+    let keys = util::request_result(&store.get_all_keys()?).await?.unchecked_into::<Array>();
+    let n_extra = keys.length().saturating_sub(MAX_SAVED_REPLAYS);
+    for key in keys.iter().take(n_extra as usize) {
+        store.delete(&key)?;
+    }
+    Ok(())
+}
+
+// saved replays, newest first
+async fn load_saved_replays() -> Result<Vec<RecordedGame>, JsValue> {
+    let db = util::open_db(REPLAY_DB_NAME, REPLAY_STORE_NAME, true).await?;
+    let store = util::db_store(&db, REPLAY_STORE_NAME, IdbTransactionMode::Readonly)?;
+    let values = util::request_result(&store.get_all()?).await?.unchecked_into::<Array>();
+    Ok(values.iter().rev().filter_map(|v| RecordedGame::from_json(&v.as_string()?)).collect())
+}
+
+// seed for a new game's bag, kept in its replay
+// limited to 53 bits since it comes from a js number
+pub fn new_seed() -> u64 { (Math::random() * (1u64 << 53) as f64) as u64 }
+
+// the input to record for a board input, if it moves pieces
+pub fn replay_input(input: Input) -> Option<sim::Input> {
+    match input {
+        Input::Left => Some(sim::Input::Left),
+        Input::Right => Some(sim::Input::Right),
+        Input::SoftDrop => Some(sim::Input::SoftDrop),
+        Input::SonicDrop => Some(sim::Input::SonicDrop),
+        Input::RotateCw => Some(sim::Input::Cw),
+        Input::RotateCcw => Some(sim::Input::Ccw),
+        Input::Rotate180 => Some(sim::Input::Flip),
+        Input::SwapHold => Some(sim::Input::Hold),
+        Input::HardDrop => Some(sim::Input::HardDrop),
+        _ => None,
+    }
+}
+
+// playback speeds offered by the viewer
+const SPEEDS: &[f64] = &[0.25, 0.5, 1.0, 2.0, 4.0];
+
+#[component]
+pub fn ReplayViewer<'a, G: Html>(cx: Scope<'a>) -> View<G> {
+    let last_replay = use_context::<Signal<Option<RecordedGame>>>(cx);
+
+    let saved_replays = create_signal(cx, vec![]);
+    spawn_local_scoped(cx, async move {
+        match load_saved_replays().await {
+            Ok(replays) => saved_replays.set(replays),
+            Err(e) => web_sys::console::error_1(&e),
+        }
+    });
+
+    // load a replay file into the viewer
+    let import_failed = create_signal(cx, false);
+    let import = move |e: Event| {
+        let input = e.target().unwrap().unchecked_into::<HtmlInputElement>();
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+
+        spawn_local_scoped(cx, async move {
+            let json = JsFuture::from(file.text()).await.ok().and_then(|json| json.as_string());
+            let recorded = json.and_then(|json| RecordedGame::from_json(&json));
+            import_failed.set(recorded.is_none());
+            if recorded.is_some() {
+                last_replay.set(recorded);
+            }
+        });
+    };
+
+    view! { cx,
+        div(class="replay-page") {
+            div(class="replay-sources") {
+                div(class="menu-option") {
+                    label(class="menu-option-label") { "Import replay:" }
+                    input(type="file", accept=".json", on:change=import)
+                }
+                (if *import_failed.get() {
+                    view! { cx, p(class="replay-empty") { "not a replay file" } }
+                } else {
+                    view! { cx, }
+                })
+
+                div(class="menu-button-box menu-button-box-l") {
+                    Indexed {
+                        iterable: saved_replays,
+                        view: move |cx, recorded| {
+                            let date = recorded.date_text();
+                            view! { cx,
+                                div(class="menu-option menu-option-l") {
+                                    input(
+                                        type="button",
+                                        value=date,
+                                        on:click=move |_| last_replay.set(Some(recorded.clone())),
+                                    )
+                                }
+                            }
+                        },
+                    }
+                }
+            }
+
+            (match (*last_replay.get()).clone() {
+                Some(recorded) => view! { cx, ReplayPlayback { recorded } },
+                None => view! { cx, p(class="replay-empty") { "no replay yet, finish a game or import one" } },
+            })
+        }
+    }
+}
+
+#[derive(Prop)]
+struct ReplayPlaybackProps {
+    recorded: RecordedGame,
+}
+
+#[component]
+fn ReplayPlayback<'a, G: Html>(cx: Scope<'a>, props: ReplayPlaybackProps) -> View<G> {
+    let recorded = props.recorded;
+    let replay = &recorded.replay;
+    let tick_rate = replay.tick_rate;
+    let duration = tick_rate.ms(replay.duration());
+
+    // the canvases draw the field and dimensions in context, so give them the replay's instead of the board's
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let queue_len = config.get_untracked().borrow().queue_len;
+    let field_values = FieldValues::new(replay.width, replay.height, replay.hidden, queue_len, recorded.piece_type);
+    let field_values: &ReadSignal<FieldValues> = create_signal(cx, field_values);
+    provide_context_ref(cx, field_values);
+
+    let player = ReplayPlayer::new(replay.clone(), recorded.rules());
+    let field = create_signal(cx, RefCell::new(player.game().field().clone()));
+    let bag = create_signal(cx, RefCell::new(player.game().bag().clone()));
+    let player = create_signal(cx, RefCell::new(player));
+    provide_context_ref(cx, field);
+
+    // milliseconds into the replay
+    let position = create_signal(cx, 0.0);
+    let playing = create_signal(cx, false);
+    let speed = create_signal(cx, 1.0);
+
+    // show the game as it was at the current position
+    create_effect(cx, move || {
+        let tick = tick_rate.ticks(*position.get());
+        util::with_signal_mut_silent_untracked(player, |player| {
+            player.seek(tick);
+            field.set(RefCell::new(player.game().field().clone()));
+            bag.set(RefCell::new(player.game().bag().clone()));
+        });
+    });
+
+    // move the position along with real time while playing
+    let clock = timer::use_clock(cx);
+    let last_frame = create_signal(cx, clock.now());
+    let (_, start, stop) = create_raf(cx, move || {
+        let now = clock.now();
+        let elapsed = (now - *last_frame.get_untracked()) * *speed.get_untracked();
+        last_frame.set(now);
+
+        let next = (*position.get_untracked() + elapsed).min(duration);
+        position.set(next);
+        if next >= duration {
+            playing.set(false);
+        }
+    });
+    create_effect(cx, move || {
+        if *playing.get() {
+            last_frame.set(clock.now());
+            start();
+        } else {
+            stop();
+        }
+    });
+
+    let toggle_playing = move |_| {
+        // play from the start again once the end is reached
+        if !*playing.get() && *position.get() >= duration {
+            position.set(0.0);
+        }
+        playing.set(!*playing.get());
+    };
+    let play_label = playing.map(cx, |p| if *p { "pause" } else { "play" });
+
+    // laid out like boards are
+    let panels =
+        util::create_config_selector(cx, config, |c| (c.panels_swapped, c.hold_panel_enabled, c.next_panel_enabled));
+    let game_class = panels.map(cx, |p| if p.0 { "game game-swapped" } else { "game" });
+
+    let speed_buttons = SPEEDS
+        .iter()
+        .map(|&s| view! { cx, SpeedButton { speed: s, value: speed } })
+        .collect::<Vec<_>>();
+
+    view! { cx,
+        div(class="replay-viewer") {
+            div(class=*game_class.get()) {
+                div(class="field-panel") {
+                    (if panels.get().1 {
+                        view! { cx, div(class="hold-piece") { HoldPiece {} } }
+                    } else {
+                        view! { cx, }
+                    })
+                }
+                div(class="field") { Field {} }
+                (if panels.get().2 {
+                    view! { cx, div(class="next-queue") { NextQueue { bag } } }
+                } else {
+                    view! { cx, }
+                })
+            }
+
+            div(class="replay-controls") {
+                div(class="menu-option") {
+                    input(type="button", value=play_label.get(), on:click=toggle_playing)
+                }
+                input(
+                    class="replay-scrub-bar",
+                    type="range",
+                    min="0",
+                    max=duration.to_string(),
+                    step="1",
+                    value=position.get().to_string(),
+                    on:input=|e: Event| {
+                        let elem = e.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+                        position.set(elem.value_as_number());
+                    },
+                )
+                p(class="replay-time") {
+                    (util::format_duration(*position.get())) " / " (util::format_duration(duration))
+                }
+                div(class="menu-button-box menu-button-box-l") { (View::new_fragment(speed_buttons.clone())) }
+            }
+        }
+    }
+}
+
+#[derive(Prop)]
+struct SpeedButtonProps<'a> {
+    speed: f64,
+    value: &'a Signal<f64>,
+}
+
+#[component]
+fn SpeedButton<'a, G: Html>(cx: Scope<'a>, props: SpeedButtonProps<'a>) -> View<G> {
+    let SpeedButtonProps { speed, value } = props;
+    let class = value.map(cx, move |v| if *v == speed { "replay-speed-selected" } else { "" });
+
+    view! { cx,
+        div(class="menu-option") {
+            input(type="button", class=class.get(), value=format!("{}x", speed), on:click=move |_| value.set(speed))
+        }
+    }
+}
+
+#[derive(Prop)]
+pub struct GhostBoardProps<'a> {
+    recorded: RecordedGame,
+    // of the live game
+    time_elapsed: &'a ReadSignal<f64>,
+    n_lines: &'a ReadSignal<u32>,
+}
+
+// a personal best played back in time with a live game, showing how far ahead or behind the player is
+#[component]
+pub fn GhostBoard<'a, G: Html>(cx: Scope<'a>, props: GhostBoardProps<'a>) -> View<G> {
+    let GhostBoardProps { recorded, time_elapsed, n_lines } = props;
+    let replay = &recorded.replay;
+    let tick_rate = replay.tick_rate;
+
+    // see `ReplayPlayback`
+    let field_values = FieldValues::new(replay.width, replay.height, replay.hidden, 0, recorded.piece_type);
+    let field_values: &ReadSignal<FieldValues> = create_signal(cx, field_values);
+    provide_context_ref(cx, field_values);
+
+    let player = ReplayPlayer::new(replay.clone(), recorded.rules());
+    let field = create_signal(cx, RefCell::new(player.game().field().clone()));
+    let player = create_signal(cx, RefCell::new(player));
+    provide_context_ref(cx, field);
+    // the live game's clear animations are for its own field
+    provide_context_ref(cx, create_signal(cx, None::<ClearAnimation>));
+
+    let ghost_lines = create_signal(cx, 0);
+    create_effect(cx, move || {
+        let tick = tick_rate.ticks(*time_elapsed.get());
+        util::with_signal_mut_silent_untracked(player, |player| {
+            player.seek(tick);
+            field.set(RefCell::new(player.game().field().clone()));
+            ghost_lines.set(player.n_lines());
+        });
+    });
+
+    let lead = create_memo(cx, move || *n_lines.get() as i64 - *ghost_lines.get() as i64);
+    let lead_text = lead.map(cx, |l| match *l {
+        0 => "even with pb".to_string(),
+        l if l > 0 => format!("{} ahead of pb", l),
+        l => format!("{} behind pb", -l),
+    });
+
+    view! { cx,
+        div(class="ghost") {
+            div(class="ghost-field") { Field {} }
+            p(class="ghost-lead") { (lead_text.get()) }
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use std::cmp::Ordering;
+
+use js_sys::Date;
+use serde::{Deserialize, Serialize};
+use sycamore::{
+    component,
+    generic_node::Html,
+    prelude::{create_memo, create_signal, Indexed, ReadSignal, Scope, Signal},
+    view,
+    view::View,
+    Prop,
+};
+use tetrox::{
+    attack::{AttackTable, ComboTable},
+    field::{DefaultField, LineClear, LivePiece},
+    game::GameRules,
+    scoring::ScoreState,
+    sim,
+    stats::StatsTracker,
+};
+use wasm_bindgen::JsValue;
+
+use crate::{
+    config::{self, GoalTypes, Input},
+    util::{self, SectionHeading},
+};
+
+const RESULTS_LOCAL_STORAGE_KEY: &str = "results";
+const LIFETIME_LINES_LOCAL_STORAGE_KEY: &str = "lifetime_lines";
+
+// only the best results for each mode and goal are kept, so local storage doesn't fill up with every game played
+const MAX_RESULTS_PER_GOAL: usize = 10;
+
+// a finished game, stored to find personal bests
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameResult {
+    pub mode: GoalTypes,
+    // lines to clear for sprint, seconds for ultra and time limit, milliseconds between rows for survival, or points
+    // to score for score target
+    pub goal: u64,
+    // in milliseconds
+    pub time: f64,
+    // guideline score for ultra and score target, or lines of attack sent (using guideline attack and combo tables)
+    // otherwise
+    pub score: u64,
+    pub lines: u32,
+    pub pps: f64,
+    // milliseconds since the unix epoch, like `Date::now`
+    pub date: f64,
+}
+
+impl GameResult {
+    // `Less` if this result is better than `other`, which is a faster time for sprint and score target, a longer one
+    // for survival, and a higher score otherwise
+    fn cmp_best(&self, other: &GameResult) -> Ordering {
+        match self.mode {
+            GoalTypes::LinesCleared | GoalTypes::ScoreTarget => self.time.total_cmp(&other.time),
+            GoalTypes::Survival => other.time.total_cmp(&self.time),
+            _ => other.score.cmp(&self.score).then(other.lines.cmp(&self.lines)),
+        }
+    }
+
+    fn goal_text(&self) -> String {
+        match self.mode {
+            GoalTypes::LinesCleared => format!("{} lines", self.goal),
+            GoalTypes::Survival => format!("{:.1}s rows", self.goal as f64 / 1_000.0),
+            GoalTypes::ScoreTarget => format!("{} points", self.goal),
+            _ if self.goal.is_multiple_of(60) => format!("{} min", self.goal / 60),
+            _ => format!("{} sec", self.goal),
+        }
+    }
+
+    // yyyy-mm-dd
+    fn date_text(&self) -> String {
+        let iso: String = Date::new(&JsValue::from_f64(self.date)).to_iso_string().into();
+        iso[..10].to_string()
+    }
+}
+
+// counts kept during a game for its result
+#[derive(Clone, Default)]
+pub struct GameTotals {
+    pub stats: StatsTracker,
+    // guideline score, for score attack
+    pub score: ScoreState,
+    pub finesse_faults: u32,
+    // stretches of blind placements made without topping out, and blind placements that made new holes
+    pub blind_rounds: u32,
+    pub misdrops: u32,
+
+    // the field as the current piece spawned, and the presses moving that piece so far, for judging finesse
+    spawn_field: Option<DefaultField>,
+    n_piece_keys: usize,
+}
+
+impl GameTotals {
+    pub fn new(field: &DefaultField) -> Self {
+        GameTotals {
+            spawn_field: Some(field.clone()),
+            ..GameTotals::default()
+        }
+    }
+
+    pub fn press_key(&mut self, input: Input) {
+        match input {
+            Input::Reset | Input::ShowHideUi | Input::ExportFumen | Input::ExportImage => {}
+            Input::AddGarbage | Input::Undo | Input::Step | Input::Rewind | Input::SwapPiece => {}
+            Input::Left | Input::Right | Input::RotateCw | Input::RotateCcw | Input::Rotate180 => {
+                self.stats.press_key();
+                self.n_piece_keys += 1;
+            }
+            _ => self.stats.press_key(),
+        }
+    }
+
+    // `dropped` is where the piece was hard dropped from, giving whether it was placed with a finesse fault
+    pub fn add(&mut self, line_clear: &LineClear, dropped: &LivePiece, rules: &GameRules, level: u32) -> bool {
+        self.stats.add_piece(line_clear, &AttackTable::guideline(), &ComboTable::guideline());
+        self.score.add_clear(line_clear, level);
+
+        let min_keys = self.spawn_field.as_ref().and_then(|f| sim::finesse(f, dropped, rules));
+        let fault = min_keys.is_some_and(|min_keys| self.n_piece_keys > min_keys);
+        self.finesse_faults += fault as u32;
+        fault
+    }
+
+    pub fn spawn_field(&self) -> Option<&DefaultField> { self.spawn_field.as_ref() }
+
+    // start judging finesse for a new current piece (e.g. after locking or holding)
+    pub fn spawn_piece(&mut self, field: &DefaultField) {
+        self.spawn_field = Some(field.clone());
+        self.n_piece_keys = 0;
+    }
+}
+
+// lines cleared in zen over every game played
+pub fn load_lifetime_lines() -> u64 {
+    let lines = config::get_local_storage().get_item(LIFETIME_LINES_LOCAL_STORAGE_KEY).ok().flatten();
+    lines.and_then(|lines| lines.parse().ok()).unwrap_or(0)
+}
+
+pub fn save_lifetime_lines(n_lines: u64) {
+    let lines = n_lines.to_string();
+    config::get_local_storage().set_item(LIFETIME_LINES_LOCAL_STORAGE_KEY, &lines).unwrap();
+}
+
+pub fn load_results() -> Vec<GameResult> {
+    let json = config::get_local_storage().get_item(RESULTS_LOCAL_STORAGE_KEY).ok().flatten();
+    json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+}
+
+pub fn save_result(result: GameResult) {
+    let mut results = load_results();
+    results.push(result);
+
+    // a result is beaten by better ones for its goal, and by earlier ones which are just as good
+    let n_beating = |i: usize, r: &GameResult| {
+        let same_goal = results.iter().enumerate().filter(|(_, o)| o.mode == r.mode && o.goal == r.goal);
+        same_goal.filter(|&(j, o)| o.cmp_best(r).then(j.cmp(&i)) == Ordering::Less).count()
+    };
+    let results = results
+        .iter()
+        .enumerate()
+        .filter(|&(i, r)| n_beating(i, r) < MAX_RESULTS_PER_GOAL)
+        .map(|(_, r)| r.clone())
+        .collect::<Vec<_>>();
+
+    let json = serde_json::to_string(&results).unwrap();
+    config::get_local_storage().set_item(RESULTS_LOCAL_STORAGE_KEY, &json).unwrap();
+}
+
+// whether `result` beats every stored result for the same mode and goal
+pub fn is_personal_best(result: &GameResult) -> bool {
+    let results = load_results();
+    let mut same_goal = results.iter().filter(|r| r.mode == result.mode && r.goal == result.goal);
+    same_goal.all(|r| result.cmp_best(r) == Ordering::Less)
+}
+
+// the best result for each goal of a mode
+fn personal_bests(results: &[GameResult], mode: GoalTypes) -> Vec<GameResult> {
+    let mut bests: Vec<GameResult> = vec![];
+    for result in results.iter().filter(|r| r.mode == mode) {
+        match bests.iter_mut().find(|b| b.goal == result.goal) {
+            Some(best) if result.cmp_best(best) == Ordering::Less => *best = result.clone(),
+            Some(_) => {}
+            None => bests.push(result.clone()),
+        }
+    }
+    bests
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum SortKey {
+    Goal,
+    Result,
+    Date,
+}
+
+fn sort_results(results: &mut [GameResult], key: SortKey) {
+    match key {
+        SortKey::Goal => results.sort_by_key(|r| r.goal),
+        SortKey::Result => results.sort_by(|a, b| a.cmp_best(b)),
+        // newest first
+        SortKey::Date => results.sort_by(|a, b| b.date.total_cmp(&a.date)),
+    }
+}
+
+#[component]
+pub fn Scores<'a, G: Html>(cx: Scope<'a>) -> View<G> {
+    let results = create_signal(cx, load_results());
+    let sort_key = create_signal(cx, SortKey::Goal);
+
+    let bests = move |mode| {
+        create_memo(cx, move || {
+            let mut bests = personal_bests(&results.get(), mode);
+            sort_results(&mut bests, *sort_key.get());
+            bests
+        })
+    };
+    let sprint_bests = bests(GoalTypes::LinesCleared);
+    let ultra_bests = bests(GoalTypes::ScoreAttack);
+    let time_limit_bests = bests(GoalTypes::TimeLimit);
+    let survival_bests = bests(GoalTypes::Survival);
+    let score_target_bests = bests(GoalTypes::ScoreTarget);
+
+    view! { cx,
+        div(class="scores") {
+            div(class="menu-button-box menu-button-box-l") {
+                SortButton { label: "by goal", key: SortKey::Goal, sort_key }
+                SortButton { label: "by result", key: SortKey::Result, sort_key }
+                SortButton { label: "by date", key: SortKey::Date, sort_key }
+            }
+
+            SectionHeading("Sprint")
+            ScoreTable { bests: sprint_bests }
+
+            SectionHeading("Ultra")
+            ScoreTable { bests: ultra_bests }
+
+            SectionHeading("Time limit")
+            ScoreTable { bests: time_limit_bests }
+
+            SectionHeading("Survival")
+            ScoreTable { bests: survival_bests }
+
+            SectionHeading("Score target")
+            ScoreTable { bests: score_target_bests }
+        }
+    }
+}
+
+#[derive(Prop)]
+struct SortButtonProps<'a> {
+    label: &'static str,
+    key: SortKey,
+    sort_key: &'a Signal<SortKey>,
+}
+
+#[component]
+fn SortButton<'a, G: Html>(cx: Scope<'a>, props: SortButtonProps<'a>) -> View<G> {
+    let SortButtonProps { label, key, sort_key } = props;
+
+    view! { cx,
+        div(class="menu-option menu-option-l") {
+            input(type="button", value=label, on:click=move |_| sort_key.set(key))
+        }
+    }
+}
+
+#[derive(Prop)]
+struct ScoreTableProps<'a> {
+    bests: &'a ReadSignal<Vec<GameResult>>,
+}
+
+#[component]
+fn ScoreTable<'a, G: Html>(cx: Scope<'a>, props: ScoreTableProps<'a>) -> View<G> {
+    let bests = props.bests;
+
+    view! { cx,
+        (if bests.get().is_empty() {
+            view! { cx, p(class="scores-empty") { "no finished games yet" } }
+        } else {
+            view! { cx,
+                table(class="scores-table") {
+                    tr { th { "GOAL" } th { "TIME" } th { "SCORE" } th { "LINES" } th { "PPS" } th { "DATE" } }
+                    Indexed {
+                        iterable: bests,
+                        view: |cx, r| {
+                            let (goal, date) = (r.goal_text(), r.date_text());
+                            view! { cx,
+                                tr {
+                                    td { (goal.clone()) }
+                                    td { (util::format_duration(r.time)) }
+                                    td { (r.score) }
+                                    td { (r.lines) }
+                                    td { (format!("{:.2}", r.pps)) }
+                                    td { (date.clone()) }
+                                }
+                            }
+                        },
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,154 @@
+use std::cell::RefCell;
+
+use sycamore::{
+    component,
+    generic_node::Html,
+    prelude::{create_memo, create_signal, use_context, Indexed, Scope, Signal},
+    view,
+    view::View,
+};
+use tetrox::fumen::{self, FumenError, FUMEN_WIDTH};
+
+use crate::{
+    config::{Config, GoalTypes, PieceTypes},
+    util,
+};
+
+// a board and queue to start practicing from, loaded as the practice board and queue
+#[derive(Debug, PartialEq, Eq)]
+pub struct Setup {
+    pub name: &'static str,
+    // what to do from the setup, shown once it's picked
+    pub description: &'static str,
+    // bottom rows of the field in the ascii format from `tetrox::sim`
+    pub board: &'static str,
+    // tetromino names (see `tetrox::parse_queue`), with the randomizer taking over once they run out
+    pub queue: &'static str,
+}
+
+// setups are for tetrominoes on a standard width field, and are played in free play so undo and the other practice
+// tools can be used
+fn load_setup(c: &mut Config, board: &str, queue: &str) {
+    c.piece_type = PieceTypes::TetrominoSrs;
+    c.field_width = FUMEN_WIDTH;
+    c.goal_type = GoalTypes::None;
+    c.practice_board = board.to_string();
+    c.practice_queue = queue.to_string();
+    c.practice_queue_loop = false;
+}
+
+pub const SETUPS: &[Setup] = &[
+    Setup {
+        name: "TKI",
+        description: "build the TKI with the first bag, keeping the T for a T-spin double to open with",
+        board: "",
+        queue: "IOLJSZT",
+    },
+    Setup {
+        name: "DT cannon",
+        description: "stack the DT cannon over the first two bags, then spin a T-spin double straight into a triple",
+        board: "",
+        queue: "IOLJSZT IOLJSZT",
+    },
+    Setup {
+        name: "TSD",
+        description: "slide the T under the overhang and spin it in for a T-spin double",
+        board: "
+            ####......
+            ###...####
+            ####.#####
+        ",
+        queue: "T",
+    },
+    Setup {
+        name: "TST",
+        description: "drop the T under the roof and rotate it down into the slot for a T-spin triple",
+        board: "
+            #######...
+            ######....
+            ######.###
+            ######..##
+            ######.###
+        ",
+        queue: "T",
+    },
+    Setup {
+        name: "4-wide",
+        description: "keep the combo going down the four wide well, starting from three squares of residue",
+        board: "
+            ###....###
+            ###....###
+            ###....###
+            ###....###
+            ###....###
+            ###....###
+            ###....###
+            ###....###
+            ######.###
+        ",
+        queue: "",
+    },
+];
+
+#[component]
+pub fn SetupLibrary<'a, G: Html>(cx: Scope<'a>) -> View<G> {
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+
+    // the setup being played, if the practice board and queue haven't been changed since it was picked
+    let description = create_memo(cx, || {
+        let c = config.get();
+        let c = c.borrow();
+        let current = SETUPS.iter().find(|s| s.board == c.practice_board && s.queue == c.practice_queue);
+        current.map_or("", |s| s.description)
+    });
+    let setups = create_signal(cx, SETUPS.iter().collect::<Vec<_>>());
+
+    view! { cx,
+        div(class="menu-button-box menu-button-box-l") {
+            Indexed {
+                iterable: setups,
+                view: move |cx, setup: &'static Setup| {
+                    let load = move |_| util::with_signal_mut(config, |c| load_setup(c, setup.board, setup.queue));
+                    view! { cx,
+                        div(class="menu-option menu-option-l") {
+                            input(type="button", value=setup.name, on:click=load)
+                        }
+                    }
+                },
+            }
+        }
+        p(class="setup-description") { (description.get()) }
+    }
+}
+
+// load a board and queue pasted from the fumen editor, with the queue coming from a quiz comment if there is one
+#[component]
+pub fn FumenInput<'a, G: Html>(cx: Scope<'a>) -> View<G> {
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+
+    let fumen_text = create_signal(cx, String::new());
+    let error = create_signal(cx, None);
+    let load = move |_| match fumen::decode(&fumen_text.get()) {
+        Ok(page) => {
+            let queue = page.quiz_queue().unwrap_or_default();
+            util::with_signal_mut(config, |c| load_setup(c, &page.board(), &queue));
+            fumen_text.set(String::new());
+            error.set(None);
+        }
+        Err(e) => error.set(Some(e)),
+    };
+    let load_label = create_memo(cx, || match *error.get() {
+        None => "Load fumen",
+        Some(FumenError::Version) => "Not v115 fumen",
+        Some(_) => "Invalid fumen",
+    });
+
+    view! { cx,
+        div(class="menu-option fumen-input") {
+            input(type="text", placeholder="fumen, e.g. v115@vhAAgH", bind:value=fumen_text)
+        }
+        div(class="menu-button-box menu-button-box-l") {
+            div(class="menu-option menu-option-l") { input(type="button", value=load_label.get(), on:click=load) }
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use js_sys::{Array, Promise};
+use serde::{Deserialize, Serialize};
+use sycamore::{
+    component,
+    futures::spawn_local_scoped,
+    generic_node::Html,
+    prelude::{create_memo, create_signal, use_context, Keyed, Scope, Signal},
+    view,
+    view::View,
+    Prop,
+};
+use tetrox::{
+    field::Connections,
+    pieces::{tetromino::TetrominoSrs, PieceKindTrait},
+};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    CanvasRenderingContext2d, Event, File, HtmlCanvasElement, HtmlImageElement, HtmlInputElement, HtmlSelectElement,
+    IdbTransactionMode, Response, Url,
+};
+
+use crate::{canvas::SQUARE_WIDTH, config::Config, util};
+
+const SKIN_DB_NAME: &str = "tetrox-skins";
+const SKIN_STORE_NAME: &str = "skins";
+
+// lists the built in skins, so adding one doesn't need the client to be rebuilt
+const SKIN_MANIFEST_SRC: &str = "assets/skins/manifest.json";
+
+// squares missing from a skin are drawn with this skin's
+pub const DEFAULT_SKIN: &str = "tetrox";
+
+// images for every skin's squares, keyed by `asset_src`
+pub type AssetCache = RefCell<HashMap<String, HtmlImageElement>>;
+
+// a square for each tetromino and one for garbage, which is also the order they go in a skin image
+pub fn skin_assets() -> impl Iterator<Item = String> {
+    let tetrominoes = <TetrominoSrs as PieceKindTrait>::iter().map(|k| k.asset_name().to_string());
+    tetrominoes.chain(["grey".to_string()])
+}
+
+pub fn asset_src(skin_name: &str, asset_name: &str) -> String {
+    format!("assets/skins/{}/{}.png", skin_name, asset_name)
+}
+
+// connected skins have a square for each way a square can connect to its neighbours, numbered with bits for up,
+// right, down, and left from the lowest (e.g. `t-5` for a t square connected up and down)
+const N_CONNECTIONS: u32 = 16;
+
+pub fn connection_number(connections: Connections) -> u32 {
+    let Connections { up, right, down, left } = connections;
+    up as u32 | (right as u32) << 1 | (down as u32) << 2 | (left as u32) << 3
+}
+
+pub fn connected_asset(asset_name: &str, connection_number: u32) -> String {
+    format!("{}-{}", asset_name, connection_number)
+}
+
+// a built in skin, as listed in the manifest, with the assets it has squares for
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct SkinInfo {
+    pub name: String,
+    pub label: String,
+    pub assets: Vec<String>,
+}
+
+impl SkinInfo {
+    pub fn add_to_cache(&self, asset_cache: &AssetCache) {
+        for asset_name in &self.assets {
+            let asset_src = asset_src(&self.name, asset_name);
+            add_image(asset_cache, asset_src.clone(), &asset_src);
+        }
+    }
+}
+
+pub async fn load_skin_manifest() -> Result<Vec<SkinInfo>, JsValue> {
+    let response = JsFuture::from(web_sys::window().unwrap().fetch_with_str(SKIN_MANIFEST_SRC)).await?;
+    let json = JsFuture::from(response.unchecked_into::<Response>().text()?).await?;
+    let json = json.as_string().unwrap_or_default();
+    serde_json::from_str(&json).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+fn add_image(asset_cache: &AssetCache, key: String, src: &str) {
+    let field_square_mul = SQUARE_WIDTH as u32;
+    let image = HtmlImageElement::new_with_width_and_height(field_square_mul, field_square_mul).unwrap();
+    image.set_src(src);
+    asset_cache.borrow_mut().insert(key, image);
+}
+
+// a skin uploaded by the player, with each square stored as a png data url
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomSkin {
+    pub name: String,
+    squares: HashMap<String, String>,
+}
+
+impl CustomSkin {
+    // a single file is an image with the squares in a row, or a row of squares for each asset with one for each
+    // connection number for connected skins
+    // otherwise each square has its own file named after its asset (e.g. `t.png`, or `t-5.png` for connected skins)
+    async fn from_files(name: String, files: Vec<File>) -> Result<Self, String> {
+        let mut squares = HashMap::new();
+        if let [file] = &files[..] {
+            let image = load_image(file).await.map_err(|_| format!("{} isn't an image", file.name()))?;
+            let (width, height) = (image.natural_width(), image.natural_height());
+            let n_assets = skin_assets().count() as u32;
+            let connected_size = width / N_CONNECTIONS;
+
+            if connected_size > 0 && height == connected_size * n_assets {
+                for (i, asset_name) in skin_assets().enumerate() {
+                    let y = i as u32 * connected_size;
+                    for n in 0..N_CONNECTIONS {
+                        let square = cut_square(&image, n * connected_size, y, connected_size);
+                        squares.insert(connected_asset(&asset_name, n), square);
+                    }
+                    squares.insert(asset_name, cut_square(&image, 0, y, connected_size));
+                }
+            } else if height > 0 && width >= height * n_assets {
+                for (i, asset_name) in skin_assets().enumerate() {
+                    squares.insert(asset_name, cut_square(&image, i as u32 * height, 0, height));
+                }
+            } else {
+                return Err(format!("a skin image needs {} squares in a row", n_assets));
+            }
+        } else {
+            let find_file = |file_name: &str| files.iter().find(|f| f.name().to_lowercase() == file_name);
+            for asset_name in skin_assets() {
+                let connected = (0..N_CONNECTIONS).map(|n| connected_asset(&asset_name, n));
+                for asset_name in connected.chain([asset_name.clone()]) {
+                    let file_name = format!("{}.png", asset_name);
+                    let Some(file) = find_file(&file_name) else {
+                        continue;
+                    };
+                    let image = load_image(file).await.map_err(|_| format!("{} isn't an image", file_name))?;
+                    let size = image.natural_width().min(image.natural_height());
+                    squares.insert(asset_name, cut_square(&image, 0, 0, size));
+                }
+                if !squares.contains_key(&asset_name) {
+                    return Err(format!("missing {}.png", asset_name));
+                }
+            }
+        }
+        Ok(CustomSkin { name, squares })
+    }
+
+    pub fn add_to_cache(&self, asset_cache: &AssetCache) {
+        for (asset_name, url) in &self.squares {
+            add_image(asset_cache, asset_src(&self.name, asset_name), url);
+        }
+    }
+}
+
+async fn load_image(file: &File) -> Result<HtmlImageElement, JsValue> {
+    let url = Url::create_object_url_with_blob(file)?;
+    let image = HtmlImageElement::new()?;
+    let loaded = Promise::new(&mut |resolve, reject| {
+        image.set_onload(Some(&resolve));
+        image.set_onerror(Some(&reject));
+    });
+    image.set_src(&url);
+
+    let loaded = JsFuture::from(loaded).await;
+    Url::revoke_object_url(&url)?;
+    loaded.map(|_| image)
+}
+
+// the `size` pixel square of `image` with its top left corner at `x` and `y`, scaled to a field square
+fn cut_square(image: &HtmlImageElement, x: u32, y: u32, size: u32) -> String {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let canvas = document.create_element("canvas").unwrap().unchecked_into::<HtmlCanvasElement>();
+    canvas.set_width(SQUARE_WIDTH as u32);
+    canvas.set_height(SQUARE_WIDTH as u32);
+
+    let context = canvas.get_context("2d").unwrap().unwrap();
+    let context = context.unchecked_into::<CanvasRenderingContext2d>();
+    let (x, y, size, square_width) = (x as f64, y as f64, size as f64, SQUARE_WIDTH as f64);
+    context
+        .draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+            image,
+            x,
+            y,
+            size,
+            size,
+            0.0,
+            0.0,
+            square_width,
+            square_width,
+        )
+        .unwrap();
+    canvas.to_data_url().unwrap()
+}
+
+pub async fn load_custom_skins() -> Result<Vec<CustomSkin>, JsValue> {
+    let db = util::open_db(SKIN_DB_NAME, SKIN_STORE_NAME, false).await?;
+    let store = util::db_store(&db, SKIN_STORE_NAME, IdbTransactionMode::Readonly)?;
+    let values = util::request_result(&store.get_all()?).await?.unchecked_into::<Array>();
+    Ok(values.iter().filter_map(|v| serde_json::from_str(&v.as_string()?).ok()).collect())
+}
+
+// skins are keyed by name, so this replaces any skin with the same name
+async fn store_custom_skin(skin: &CustomSkin) -> Result<(), JsValue> {
+    let db = util::open_db(SKIN_DB_NAME, SKIN_STORE_NAME, false).await?;
+    let store = util::db_store(&db, SKIN_STORE_NAME, IdbTransactionMode::Readwrite)?;
+    let json = serde_json::to_string(skin).unwrap();
+    util::request_result(&store.put_with_key(&JsValue::from_str(&json), &JsValue::from_str(&skin.name))?).await?;
+    Ok(())
+}
+
+#[derive(Prop)]
+pub struct SkinSelectProps<'a> {
+    value: &'a Signal<String>,
+}
+
+// the built in skins, then custom ones
+#[component]
+pub fn SkinSelect<'a, G: Html>(cx: Scope<'a>, props: SkinSelectProps<'a>) -> View<G> {
+    let value = props.value;
+    let skin_manifest = use_context::<Signal<Vec<SkinInfo>>>(cx);
+    let custom_skins = use_context::<Signal<Vec<CustomSkin>>>(cx);
+
+    // labels and skin names
+    let items = create_memo(cx, || {
+        let built_in = skin_manifest.get().iter().map(|s| (s.label.clone(), s.name.clone())).collect::<Vec<_>>();
+        let custom = custom_skins.get().iter().map(|s| (s.name.clone(), s.name.clone())).collect::<Vec<_>>();
+        built_in.into_iter().chain(custom).collect::<Vec<_>>()
+    });
+    let set_skin = |e: Event| value.set(e.target().unwrap().unchecked_into::<HtmlSelectElement>().value());
+
+    view! { cx,
+        div(class="menu-option") {
+            label(class="menu-option-label") { "Block skin:" }
+            select(on:input=set_skin) {
+                Keyed {
+                    iterable: items,
+                    view: move |cx, (label, name)| {
+                        let option_value = name.clone();
+                        view! { cx,
+                            option(value=option_value.clone(), selected=*value.get() == name) { (label.clone()) }
+                        }
+                    },
+                    key: |item| item.1.clone(),
+                }
+            }
+        }
+    }
+}
+
+// upload a skin as one image or a folder of them, and switch to it
+#[component]
+pub fn SkinUpload<'a, G: Html>(cx: Scope<'a>) -> View<G> {
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let asset_cache = use_context::<AssetCache>(cx);
+    let skin_manifest = use_context::<Signal<Vec<SkinInfo>>>(cx);
+    let custom_skins = use_context::<Signal<Vec<CustomSkin>>>(cx);
+
+    let skin_name = create_signal(cx, String::new());
+    let error = create_signal(cx, None);
+    let upload = move |e: Event| {
+        let input = e.target().unwrap().unchecked_into::<HtmlInputElement>();
+        let files = input.files().map_or(vec![], |f| (0..f.length()).filter_map(|i| f.get(i)).collect());
+        // so picking the same files again still uploads them
+        input.set_value("");
+
+        let name = skin_name.get().trim().to_string();
+        if files.is_empty() {
+            return;
+        } else if name.is_empty() || skin_manifest.get().iter().any(|s| s.name == name) {
+            error.set(Some("give the skin a name that isn't a built in skin's".to_string()));
+            return;
+        }
+
+        spawn_local_scoped(cx, async move {
+            let skin = match CustomSkin::from_files(name, files).await {
+                Ok(skin) => skin,
+                Err(e) => return error.set(Some(e)),
+            };
+            if let Err(e) = store_custom_skin(&skin).await {
+                web_sys::console::error_1(&e);
+            }
+            skin.add_to_cache(asset_cache);
+
+            let name = skin.name.clone();
+            {
+                let mut custom_skins = custom_skins.modify();
+                custom_skins.retain(|s| s.name != skin.name);
+                custom_skins.push(skin);
+            }
+            util::with_signal_mut(config, |c| c.skin_name = name.clone());
+            skin_name.set(String::new());
+            error.set(None);
+        });
+    };
+    let help = create_memo(cx, || match &*error.get() {
+        Some(e) => e.clone(),
+        None => "one image with the s, z, l, j, t, o, i, and garbage squares in a row (or a row of 16 each for a \
+            connected skin), or a folder with s.png to i.png and grey.png"
+            .to_string(),
+    });
+
+    view! { cx,
+        div(class="menu-option skin-name") {
+            input(type="text", placeholder="custom skin name", bind:value=skin_name)
+        }
+        div(class="menu-option") {
+            label(class="menu-option-label") { "Skin image:" }
+            input(type="file", accept="image/png", on:change=upload)
+        }
+        div(class="menu-option") {
+            label(class="menu-option-label") { "Skin folder:" }
+            input(type="file", webkitdirectory=true, on:change=upload)
+        }
+        p(class="skin-help") { (help.get()) }
+    }
+}
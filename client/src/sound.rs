@@ -0,0 +1,20 @@
+use web_sys::{AudioContext, OscillatorType};
+
+// short low buzz, synthesized so there's no sound asset to load
+pub fn play_fault_sound() {
+    // browsers may refuse to make an audio context (e.g. before any user input), in which case there's no sound
+    let _ = (|| {
+        let context = AudioContext::new()?;
+        let oscillator = context.create_oscillator()?;
+        oscillator.set_type(OscillatorType::Square);
+        oscillator.frequency().set_value(160.0);
+
+        let gain = context.create_gain()?;
+        gain.gain().set_value(0.08);
+        oscillator.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&context.destination())?;
+
+        oscillator.start()?;
+        oscillator.stop_with_when(context.current_time() + 0.12)
+    })();
+}
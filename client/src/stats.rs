@@ -1,18 +1,20 @@
-use std::time::Duration;
+use std::{cell::RefCell, time::Duration};
 
 use sycamore::{
     component, easing,
     generic_node::Html,
     motion::create_tweened_signal,
-    prelude::{create_effect, create_memo, create_signal, use_context, Scope, Signal},
+    prelude::{create_effect, create_memo, create_signal, use_context, Indexed, ReadSignal, Scope, Signal},
     view,
     view::View,
     Prop,
 };
-use tetrox::field::LineClear;
+use tetrox::{curves::Speed, field::LineClear};
 
 use crate::{
+    config::{ClearNames, Config},
     goal::Goal,
+    scores::GameTotals,
     util::{self, Padding},
 };
 
@@ -20,6 +22,10 @@ use crate::{
 pub struct StatsProps<'a, G: Html> {
     last_line_clear: &'a Signal<Option<LineClear>>,
     goal: &'a Signal<Goal<'a, G>>,
+    totals: &'a Signal<RefCell<GameTotals>>,
+    n_finesse_faults: &'a Signal<u32>,
+    // level and timings from the speed curve, if there is one
+    speed: &'a ReadSignal<Option<(usize, Speed)>>,
 }
 
 #[component]
@@ -27,31 +33,35 @@ pub fn Stats<'a, G: Html>(cx: Scope<'a>, props: StatsProps<'a, G>) -> View<G> {
     let StatsProps {
         last_line_clear: line_clear,
         goal,
+        totals,
+        n_finesse_faults,
+        speed,
     } = props;
 
-    let (lc_text, lc_view) = styled_text(cx, "clear-text", 2_000, 0.2, 0.3);
-    let (pc_text, pc_view) = styled_text(cx, "clear-text", 2_000, 0.2, 0.3);
-    let (combo_text, combo_view) = styled_text(cx, "combo-text", 3_000, 0.5, 0.15);
-    let (b2b_text, b2b_view) = styled_text(cx, "b2b-text", 3_000, 0.5, 0.15);
+    // not empty so the <p> elements take up vertical space from load
+    let texts = [(); 5].map(|_| create_signal(cx, "<unset>".to_string()));
+    let [lc_text, pc_text, combo_text, b2b_text, finesse_text] = texts;
+    let finesse_view = styled_text(cx, "finesse-text", finesse_text, (1_500, 1.0), (0.2, 0.3));
+
+    // action texts are remade when how long they show for or their size changes
+    let config = use_context::<Signal<RefCell<Config>>>(cx);
+    let text_style = util::create_config_selector(cx, config, |c| (c.action_text_duration as u64, c.action_text_scale));
 
+    // flash on every finesse fault, but not when the count is reset
     create_effect(cx, || {
+        if *n_finesse_faults.get() > 0 {
+            finesse_text.set("finesse fault".to_string());
+        }
+    });
+
+    let clear_names = util::create_config_selector(cx, config, |c| (c.clear_names, c.spin_minis_shown));
+    create_effect(cx, || {
+        let (names, minis_shown) = *clear_names.get_untracked();
         line_clear
             .get()
             .as_ref()
             .as_ref()
-            .and_then(|l| {
-                // update the line clear text if lines were cleared or the last locked piece was a spin
-                (l.n_lines() > 0 || l.spin().is_some()).then(|| {
-                    let mini = if l.is_mini() { "mini " } else { "" };
-                    let spin = l
-                        .spin()
-                        .map(|s| format!("{}-spin ", s.display_name()))
-                        .unwrap_or("".to_string());
-                    let n_text = ["", "single", "double", "triple", "quad", "penta"][l.n_lines()];
-
-                    format!("{}{}{}", mini, spin, n_text).trim().to_string()
-                })
-            })
+            .and_then(|l| clear_text(l, names, minis_shown))
             .map(|t| lc_text.set(t));
     });
 
@@ -106,9 +116,50 @@ pub fn Stats<'a, G: Html>(cx: Scope<'a>, props: StatsProps<'a, G>) -> View<G> {
 
     let time_elapsed = use_context::<Signal<f64>>(cx);
 
+    // live stats, which change as time passes and pieces lock
+    let enabled = util::create_config_selector(cx, config, |c| {
+        [c.pieces_stat_enabled, c.pps_stat_enabled, c.apm_stat_enabled, c.kpp_stat_enabled, c.faults_stat_enabled]
+    });
+    let blind_enabled = util::create_config_selector(cx, config, |c| c.practice_blind_pieces > 0);
+    let live_stats = create_memo(cx, || {
+        line_clear.track();
+        let millis = *time_elapsed.get();
+        let totals = totals.get_untracked();
+        let totals = totals.borrow();
+        let stats = &totals.stats;
+
+        let values = [
+            ("PIECES", stats.n_pieces().to_string()),
+            ("PPS", format!("{:.2}", stats.pieces_per_second(millis))),
+            ("APM", format!("{:.1}", stats.attack_per_minute(millis))),
+            ("KPP", format!("{:.2}", stats.keys_per_piece())),
+            ("FAULTS", n_finesse_faults.get().to_string()),
+        ];
+        let mut values = values
+            .into_iter()
+            .zip(*enabled.get())
+            .filter(|(_, enabled)| *enabled)
+            .map(|(stat, _)| stat)
+            .collect::<Vec<_>>();
+
+        if *blind_enabled.get() {
+            values.push(("ROUNDS", totals.blind_rounds.to_string()));
+            values.push(("MISDROPS", totals.misdrops.to_string()));
+        }
+        values
+    });
+
     view! { cx,
-        (lc_view) (pc_view)
-        (combo_view) (b2b_view)
+        ({
+            let (duration, scale) = *text_style.get();
+            View::new_fragment(vec![
+                styled_text(cx, "clear-text", lc_text, (duration, scale), (0.2, 0.3)),
+                styled_text(cx, "clear-text", pc_text, (duration, scale), (0.2, 0.3)),
+                styled_text(cx, "combo-text", combo_text, (duration * 3 / 2, scale), (0.5, 0.15)),
+                styled_text(cx, "b2b-text", b2b_text, (duration * 3 / 2, scale), (0.5, 0.15)),
+            ])
+        })
+        (finesse_view)
         Padding(36)
 
         (if goal.get().show_elapsed_time() {
@@ -120,21 +171,52 @@ pub fn Stats<'a, G: Html>(cx: Scope<'a>, props: StatsProps<'a, G>) -> View<G> {
             view! { cx, }
         })
         (goal.get().view())
+        (match *speed.get() {
+            Some((level, speed)) => view! { cx,
+                p(class="game-stats-label") { "LEVEL" }
+                p(class="game-stats-display", style="direction: ltr;") { (level) }
+                p(class="game-stats-label") { "SPEED" }
+                p(class="game-stats-display", style="direction: ltr;") { (gravity_text(&speed)) }
+            },
+            None => view! { cx, },
+        })
+        Indexed {
+            iterable: live_stats,
+            view: |cx, (label, value)| view! { cx,
+                p(class="game-stats-label") { (label) }
+                p(class="game-stats-display", style="direction: ltr;") { (value) }
+            },
+        }
+    }
+}
+
+// the line clear text for a clear, if it cleared lines or was a spin
+// minis can be left out, showing them like clears without a spin
+fn clear_text(line_clear: &LineClear, names: ClearNames, minis_shown: bool) -> Option<String> {
+    let spin = line_clear.spin().filter(|_| minis_shown || !line_clear.is_mini());
+    if line_clear.n_lines() == 0 && spin.is_none() {
+        return None;
     }
+    let mini = if line_clear.is_mini() && spin.is_some() { "mini " } else { "" };
+    let spin = spin.map(|s| format!("{}-spin ", s.display_name())).unwrap_or_default();
+    let n_text = match names {
+        ClearNames::Quad => ["", "single", "double", "triple", "quad", "penta"],
+        ClearNames::Tetris => ["", "single", "double", "triple", "tetris", "pentris"],
+    }[line_clear.n_lines()];
+
+    Some(format!("{}{}{}", mini, spin, n_text).trim().to_string())
 }
 
-// returns the signal for accessing the text, the corresponding view with the dynamic styles applied, a signal for
-// whether the text animation should be reset, and the callback to reset the animation
+// the view for `text` with the dynamic styles applied, which fades out over `duration` milliseconds when the text is
+// updated, scaled in size by `scale`
+// the letter spacing animates from `ls_add` to one, multiplied by `ls_mul` in rems
 fn styled_text<'a, G: Html>(
     cx: Scope<'a>,
-    class: &'a str,
-    duration: u64,
-    ls_add: f64,
-    ls_mul: f64,
-) -> (&'a Signal<String>, View<G>) {
-    // not empty so the <p> elements take up vertical space from load
-    let text = create_signal(cx, "<unset>".to_string());
-
+    class: &'static str,
+    text: &'a Signal<String>,
+    (duration, scale): (u64, f64),
+    (ls_add, ls_mul): (f64, f64),
+) -> View<G> {
     // updating the text causes `show_text` to become true, which will be checked by the animation reset effect
     let show_text = create_signal(cx, false);
     create_effect(cx, || {
@@ -149,7 +231,9 @@ fn styled_text<'a, G: Html>(
 
     let opacity_style = create_memo(cx, || format!("opacity: {}%;", *opacity.get() * 100.0));
     let ls_style = create_memo(cx, move || format!("letter-spacing: {}rem;", *spacing.get() * ls_mul));
-    let style = create_memo(cx, || format!("{}{}", opacity_style.get(), ls_style.get()));
+    let style = create_memo(cx, move || {
+        format!("{}{}--action-text-scale: {};", opacity_style.get(), ls_style.get(), scale)
+    });
 
     // resetting (running) the animation turns `show_text` false, preventing the weird loop thing
     let reset_style_animation = move || {
@@ -168,6 +252,14 @@ fn styled_text<'a, G: Html>(
         }
     });
 
-    let view = view! { cx, p(class=class, style=style.get()) { (text.get()) } };
-    (text, view)
+    view! { cx, p(class=class, style=style.get()) { (text.get()) } }
+}
+
+// gravity in rows per frame at 60 fps, like other games show it (e.g. 20g for instant drops)
+fn gravity_text(speed: &Speed) -> String {
+    if speed.gravity_delay == 0.0 {
+        "20G".to_string()
+    } else {
+        format!("{:.2}G", 1_000.0 / 60.0 / speed.gravity_delay)
+    }
 }
@@ -3,9 +3,24 @@ use std::{cell::RefCell, mem};
 use gloo_timers::callback::Timeout;
 use sycamore::{
     motion::create_raf,
-    prelude::{create_effect, create_signal, use_scope_status, ReadSignal, Scope, Signal},
+    prelude::{create_effect, create_signal, use_context, use_scope_status, ReadSignal, Scope, Signal},
 };
 
+// source of the time in milliseconds, for measuring how long things take
+// it's provided as a context, so everything timing the game reads the same clock
+pub trait Clock {
+    fn now(&self) -> f64;
+}
+
+// `performance.now()`, which only moves forward, unlike `Date::now` which jumps when the system clock is changed
+pub struct PerformanceClock;
+
+impl Clock for PerformanceClock {
+    fn now(&self) -> f64 { web_sys::window().unwrap().performance().unwrap().now() }
+}
+
+pub fn use_clock(cx: Scope<'_>) -> &dyn Clock { use_context::<Box<dyn Clock>>(cx).as_ref() }
+
 // effect executed when the given `timer` finishes
 // if `op` returns true, the timer will start again (making a loop)
 pub fn create_timer_finish_effect<'a>(cx: Scope<'a>, timer: &'a ReadSignal<Timer>, mut op: impl FnMut() -> bool + 'a) {
@@ -16,6 +31,32 @@ pub fn create_timer_finish_effect<'a>(cx: Scope<'a>, timer: &'a ReadSignal<Timer
     });
 }
 
+// time that only moves forward when stepped, for going through a game a tick at a time
+#[derive(Copy, Clone)]
+pub struct StepClock<'a> {
+    // stepped timers started while this is set wait for steps instead of running in real time
+    enabled: &'a ReadSignal<bool>,
+    // milliseconds stepped through so far
+    now: &'a Signal<f64>,
+}
+
+impl<'a> StepClock<'a> {
+    pub fn new(cx: Scope<'a>, enabled: &'a ReadSignal<bool>) -> Self {
+        StepClock {
+            enabled,
+            now: create_signal(cx, 0.0),
+        }
+    }
+
+    pub fn now(&self) -> f64 { *self.now.get_untracked() }
+
+    // move time forward, finishing any timers that are due
+    pub fn step(&self, ms: f64) { self.now.set(self.now() + ms); }
+
+    // go back to an earlier time, with running timers still finishing at the time they were due
+    pub fn rewind_to(&self, now: f64) { self.now.set(now); }
+}
+
 // a resettable timer that waits for a timeout and sets a flag upon completion
 pub struct Timer<'a>(RefCell<TimeoutTimerInner<'a>>);
 
@@ -25,6 +66,10 @@ struct TimeoutTimerInner<'a> {
     duration: u32,
     timeout: Option<Timeout>,
     is_finished: &'a Signal<bool>,
+
+    // when the timer finishes on the step clock, if it was started while stepping
+    clock: Option<StepClock<'a>>,
+    deadline: &'a Signal<Option<f64>>,
 }
 
 impl<'a> Timer<'a> {
@@ -35,9 +80,31 @@ impl<'a> Timer<'a> {
             duration,
             timeout: None,
             is_finished: create_signal(cx, false),
+
+            clock: None,
+            deadline: create_signal(cx, None),
         }))
     }
 
+    // a timer which waits for `clock` to be stepped instead of running in real time while the clock is enabled
+    pub fn stepped(cx: Scope<'a>, duration: u32, clock: StepClock<'a>) -> Self {
+        let timer = Timer::new(cx, duration);
+        timer.0.borrow_mut().clock = Some(clock);
+
+        let (is_finished, deadline) = {
+            let inner = timer.0.borrow();
+            (inner.is_finished, inner.deadline)
+        };
+        create_effect(cx, move || {
+            let now = *clock.now.get();
+            if deadline.get_untracked().is_some_and(|deadline| now >= deadline) {
+                deadline.set(None);
+                is_finished.set(true);
+            }
+        });
+        timer
+    }
+
     // this value is reactive and should be used to perform an action on completion of the timeout
     pub fn is_finished(&self) -> bool { *self.0.borrow().is_finished.get() }
 
@@ -47,6 +114,7 @@ impl<'a> Timer<'a> {
 
         let cx = self.0.borrow().cx;
         let is_finished = self.0.borrow().is_finished.clone();
+        let clock = self.0.borrow().clock.filter(|clock| *clock.enabled.get_untracked());
 
         // make zero duration timers complete instantly (js timeouts often have a delay even if the timeout is 0)
         if self.0.borrow().duration == 0 {
@@ -54,6 +122,9 @@ impl<'a> Timer<'a> {
             let (_, start, stop) = create_raf(cx, move || is_finished.set(true));
             create_effect(cx, || drop(is_finished.get().then(|| stop())));
             start();
+        } else if let Some(clock) = clock {
+            let deadline = clock.now() + self.0.borrow().duration as f64;
+            self.0.borrow().deadline.set(Some(deadline));
         } else {
             let scope_alive = use_scope_status(cx);
 
@@ -73,6 +144,7 @@ impl<'a> Timer<'a> {
     // stop any currently running timer and mark it as unfinished, effectively resetting it
     pub fn stop(&self) {
         self.0.borrow_mut().timeout.take().map(|t| t.cancel());
+        self.0.borrow().deadline.set(None);
         self.0.borrow().is_finished.set(false);
     }
 
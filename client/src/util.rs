@@ -1,12 +1,19 @@
 use std::{cell::RefCell, time::Duration};
 
+use js_sys::{Function, Promise, Reflect};
 use sycamore::{
     component,
     generic_node::Html,
-    prelude::{create_selector, ReadSignal, Scope, Signal},
+    prelude::{create_effect, create_rc_signal, create_selector, on_cleanup, untrack, ReadSignal, Scope, Signal},
     view,
     view::View,
 };
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    Event, HtmlAnchorElement, HtmlInputElement, HtmlSelectElement, IdbDatabase, IdbObjectStore,
+    IdbObjectStoreParameters, IdbOpenDbRequest, IdbRequest, IdbTransactionMode,
+};
 
 use crate::config::Config;
 
@@ -62,6 +69,42 @@ where
     create_selector(cx, move || op(&config.get().borrow()))
 }
 
+// handle events of `event_type` on the window for as long as the scope is alive
+// the listener has to be static, so events are passed to `handler` through a signal, and handled untracked so they
+// aren't handled again when signals read while handling them change
+pub fn on_window_event<'a, E>(cx: Scope<'a>, event_type: &'static str, mut handler: impl FnMut(&E) + 'a)
+where
+    E: JsCast + 'static,
+{
+    let last_event = create_rc_signal(None::<E>);
+    let listener = Closure::<dyn Fn(Event)>::new({
+        let last_event = last_event.clone();
+        move |e: Event| last_event.set(Some(e.unchecked_into()))
+    });
+    let window = web_sys::window().unwrap();
+    window.add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref()).unwrap();
+    on_cleanup(cx, move || {
+        let listener = listener.as_ref().unchecked_ref();
+        window.remove_event_listener_with_callback(event_type, listener).unwrap();
+    });
+    create_effect(cx, move || {
+        if let Some(e) = &*last_event.get() {
+            untrack(|| handler(e));
+        }
+    });
+}
+
+// whether keys pressed in the event's target are typed into it (e.g. text boxes), instead of being game inputs
+pub fn is_text_entry(e: &Event) -> bool {
+    let Some(target) = e.target() else {
+        return false;
+    };
+    match target.dyn_ref::<HtmlInputElement>() {
+        Some(input) => !matches!(&*input.type_(), "button" | "checkbox" | "radio" | "range" | "color" | "file"),
+        None => target.has_type::<HtmlSelectElement>(),
+    }
+}
+
 pub fn format_duration(millis: f64) -> String {
     let time = Duration::from_millis(millis as u64);
 
@@ -72,6 +115,61 @@ pub fn format_duration(millis: f64) -> String {
     format!("{}:{:02}.{:03}", mins, secs, millis)
 }
 
+// the clipboard api is only in web-sys behind its unstable apis, so `navigator.clipboard.writeText` is looked up by
+// hand, doing nothing where there's no clipboard
+pub fn copy_to_clipboard(text: &str) {
+    let navigator = Reflect::get(&web_sys::window().unwrap(), &JsValue::from_str("navigator")).unwrap();
+    let Ok(clipboard) = Reflect::get(&navigator, &JsValue::from_str("clipboard")) else {
+        return;
+    };
+    if let Ok(write_text) = Reflect::get(&clipboard, &JsValue::from_str("writeText")) {
+        if let Some(write_text) = write_text.dyn_ref::<Function>() {
+            let _ = write_text.call1(&clipboard, &JsValue::from_str(text));
+        }
+    }
+}
+
+// download what's at `url` (e.g. a blob or data url) as a file called `name`
+// clicking a link is the only way to start a download from a page
+pub fn download_url(name: &str, url: &str) {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let link = document.create_element("a").unwrap().unchecked_into::<HtmlAnchorElement>();
+    link.set_href(url);
+    link.set_download(name);
+    link.click();
+}
+
+// open an indexeddb database holding one object store, which is created the first time the database is opened
+pub async fn open_db(name: &str, store_name: &'static str, auto_increment: bool) -> Result<IdbDatabase, JsValue> {
+    let factory = web_sys::window().unwrap().indexed_db()?.ok_or("indexeddb is not supported")?;
+    let request = factory.open_with_u32(name, 1)?;
+
+    let on_upgrade = Closure::once(move |e: Event| {
+        let request = e.target().unwrap().unchecked_into::<IdbOpenDbRequest>();
+        let db = request.result().unwrap().unchecked_into::<IdbDatabase>();
+        let mut params = IdbObjectStoreParameters::new();
+        params.auto_increment(auto_increment);
+        db.create_object_store_with_optional_parameters(store_name, &params).unwrap();
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+
+    Ok(request_result(&request).await?.unchecked_into())
+}
+
+pub fn db_store(db: &IdbDatabase, store_name: &str, mode: IdbTransactionMode) -> Result<IdbObjectStore, JsValue> {
+    db.transaction_with_str_and_mode(store_name, mode)?.object_store(store_name)
+}
+
+// wait for an indexeddb request to finish, giving its result
+pub async fn request_result(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    JsFuture::from(promise).await?;
+    request.result()
+}
+
 // vertical padding
 #[component]
 pub fn Padding<'a, G: Html>(cx: Scope<'a>, px: usize) -> View<G> {
@@ -0,0 +1,58 @@
+// best sprint times for each line goal, kept in a json file so they last between restarts
+
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+// the client's sprint presets
+pub const SPRINT_GOALS: [u32; 4] = [20, 40, 100, 1_000];
+
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    // in milliseconds
+    pub time: f64,
+    pub pps: f64,
+    // when the run was submitted, in milliseconds since the unix epoch
+    pub date: f64,
+}
+
+// fastest first, keyed by line goal
+#[derive(Default, Serialize, Deserialize)]
+pub struct Leaderboards {
+    boards: BTreeMap<u32, Vec<Entry>>,
+}
+
+impl Leaderboards {
+    // a missing or unreadable file starts the leaderboards over
+    pub fn load(path: &Path) -> Self {
+        let text = fs::read_to_string(path).ok();
+        text.and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> { fs::write(path, serde_json::to_string(self)?) }
+
+    pub fn board(&self, n_lines: u32) -> &[Entry] { self.boards.get(&n_lines).map_or(&[], |b| b) }
+
+    // players only keep their best time, so this gives the entry's rank (from 1) if it's their best and made the board
+    pub fn submit(&mut self, n_lines: u32, entry: Entry) -> Option<usize> {
+        let board = self.boards.entry(n_lines).or_default();
+        if let Some(i) = board.iter().position(|e| e.name == entry.name) {
+            if board[i].time <= entry.time {
+                return None;
+            }
+            board.remove(i);
+        }
+
+        let rank = board.partition_point(|e| e.time <= entry.time);
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+        board.insert(rank, entry);
+        board.truncate(MAX_ENTRIES);
+        Some(rank + 1)
+    }
+}
@@ -0,0 +1,159 @@
+// serves versus matches and sprint leaderboards for the web client
+// usage: tetrox-server [address] [leaderboard file]
+//
+// `/versus` takes websocket connections speaking the `tetrox::versus` protocol, and `/leaderboards/<lines>` gives the
+// best times for a sprint goal as json, taking new runs posted as `{"name": ..., "replay": ...}` with the replay in
+// its text format
+// runs have to be played with a seed posted to `/runs` for beforehand, which gives back `{"seed": ...}`
+
+mod leaderboard;
+mod rooms;
+mod validate;
+
+use std::{
+    env,
+    path::PathBuf,
+    process,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{Path, State, WebSocketUpgrade},
+    http::StatusCode,
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{net::TcpListener, task};
+use tower_http::cors::CorsLayer;
+
+use crate::{
+    leaderboard::{Entry, Leaderboards, SPRINT_GOALS},
+    rooms::Rooms,
+    validate::Seeds,
+};
+
+const DEFAULT_ADDRESS: &str = "0.0.0.0:3000";
+const DEFAULT_LEADERBOARD_PATH: &str = "leaderboards.json";
+
+const MAX_NAME_LEN: usize = 24;
+
+struct ServerState {
+    rooms: Arc<Mutex<Rooms>>,
+    leaderboards: Mutex<Leaderboards>,
+    leaderboard_path: PathBuf,
+    seeds: Mutex<Seeds>,
+}
+
+type AppState = State<Arc<ServerState>>;
+
+// kept within what javascript numbers hold exactly, like the client's seeds
+fn new_seed() -> u64 { rand::random::<u64>() >> 11 }
+
+async fn versus(ws: WebSocketUpgrade, State(state): AppState) -> Response {
+    let rooms = state.rooms.clone();
+    ws.on_upgrade(move |socket| rooms::play(socket, rooms))
+}
+
+async fn leaderboard(Path(n_lines): Path<u32>, State(state): AppState) -> Result<Json<Vec<Entry>>, StatusCode> {
+    if !SPRINT_GOALS.contains(&n_lines) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(state.leaderboards.lock().unwrap().board(n_lines).to_vec()))
+}
+
+#[derive(Serialize)]
+struct NewRun {
+    seed: u64,
+}
+
+async fn start_run(State(state): AppState) -> Json<NewRun> {
+    Json(NewRun {
+        seed: state.seeds.lock().unwrap().issue(),
+    })
+}
+
+#[derive(Deserialize)]
+struct Submission {
+    name: String,
+    replay: String,
+}
+
+// the time is the one from playing back the replay, which is what gets ranked
+#[derive(Serialize)]
+struct Ranking {
+    time: f64,
+    rank: Option<usize>,
+}
+
+async fn submit(
+    Path(n_lines): Path<u32>,
+    State(state): AppState,
+    Json(submission): Json<Submission>,
+) -> Result<Json<Ranking>, (StatusCode, String)> {
+    if !SPRINT_GOALS.contains(&n_lines) {
+        return Err((StatusCode::NOT_FOUND, "not a sprint goal".to_string()));
+    }
+    let name = submission.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        let message = format!("names must be 1 to {} characters", MAX_NAME_LEN);
+        return Err((StatusCode::BAD_REQUEST, message));
+    }
+
+    // playing back a long run takes a while, so it's kept off the threads serving other requests
+    let replay = submission.replay;
+    let checking = state.clone();
+    let run = task::spawn_blocking(move || validate::check_sprint(&replay, n_lines as usize, &checking.seeds))
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "couldn't check the run".to_string()))?
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+    let entry = Entry {
+        name: name.to_string(),
+        time: run.time,
+        pps: run.pps,
+        date: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as f64,
+    };
+
+    let mut leaderboards = state.leaderboards.lock().unwrap();
+    let rank = leaderboards.submit(n_lines, entry);
+    if rank.is_some() {
+        if let Err(e) = leaderboards.save(&state.leaderboard_path) {
+            eprintln!("couldn't save leaderboards: {}", e);
+        }
+    }
+    Ok(Json(Ranking { time: run.time, rank }))
+}
+
+#[tokio::main]
+async fn main() {
+    let mut args = env::args().skip(1);
+    let address = args.next().unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let leaderboard_path = PathBuf::from(args.next().unwrap_or_else(|| DEFAULT_LEADERBOARD_PATH.to_string()));
+
+    let state = Arc::new(ServerState {
+        rooms: Arc::default(),
+        leaderboards: Mutex::new(Leaderboards::load(&leaderboard_path)),
+        leaderboard_path,
+        seeds: Mutex::default(),
+    });
+    let app = Router::new()
+        .route("/versus", get(versus))
+        .route("/leaderboards/:n_lines", get(leaderboard).post(submit))
+        .route("/runs", post(start_run))
+        // the client may be hosted somewhere else
+        .layer(CorsLayer::permissive())
+        .with_state(state);
+
+    let listener = TcpListener::bind(&address).await.unwrap_or_else(|e| {
+        eprintln!("couldn't listen on {}: {}", address, e);
+        eprintln!("usage: tetrox-server [address] [leaderboard file]");
+        process::exit(1);
+    });
+    println!("listening on {}", address);
+    if let Err(e) = axum::serve(listener, app).await {
+        eprintln!("server stopped: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,147 @@
+// rooms of up to two players, who are relayed each other's messages once the room fills and the match starts
+// the match ends when either player leaves, which closes the room
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::extract::ws::{self, WebSocket};
+use futures::{SinkExt, StreamExt};
+use tetrox::versus::{Message, MATCHMAKING_ROOM};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+type PlayerId = u64;
+
+// the client's tallest field, which is more garbage than any player can take at once
+const MAX_ROWS: u32 = 200;
+
+struct Player {
+    id: PlayerId,
+    tx: UnboundedSender<Message>,
+}
+
+struct Room {
+    // from the player who made the room, which both players play by
+    rules: String,
+    players: Vec<Player>,
+    // rooms made by matchmaking are filled by it, so a player named room is never given a stranger
+    matchmade: bool,
+}
+
+#[derive(Default)]
+pub struct Rooms {
+    rooms: HashMap<String, Room>,
+    next_id: PlayerId,
+}
+
+impl Rooms {
+    // gives the name of the room the player is in and their id, or `None` if the room was already full
+    fn join(&mut self, room: &str, rules: String, tx: UnboundedSender<Message>) -> Option<(String, PlayerId)> {
+        self.next_id += 1;
+        let id = self.next_id;
+
+        let matchmade = room == MATCHMAKING_ROOM;
+        let name = if matchmade {
+            let open = self
+                .rooms
+                .iter()
+                .find(|(_, r)| r.matchmade && r.players.len() == 1 && r.rules == rules);
+            open.map_or_else(|| format!("{}-{}", MATCHMAKING_ROOM, id), |(name, _)| name.clone())
+        } else {
+            room.to_string()
+        };
+
+        let room = self.rooms.entry(name.clone()).or_insert_with(|| Room {
+            rules,
+            players: vec![],
+            matchmade,
+        });
+        if room.players.len() == 2 {
+            return None;
+        }
+        room.players.push(Player { id, tx });
+
+        if room.players.len() == 2 {
+            let start = Message::Start {
+                seed: crate::new_seed(),
+                rules: room.rules.clone(),
+            };
+            for player in &room.players {
+                let _ = player.tx.send(start.clone());
+            }
+        }
+        Some((name, id))
+    }
+
+    // players left behind when their room closes are no longer in it, so they can't reach whoever joins it next
+    fn relay(&self, room: &str, from: PlayerId, message: Message) {
+        let Some(room) = self.rooms.get(room).filter(|r| r.players.iter().any(|p| p.id == from)) else {
+            return;
+        };
+        for player in room.players.iter().filter(|p| p.id != from) {
+            let _ = player.tx.send(message.clone());
+        }
+    }
+
+    fn leave(&mut self, room: &str, id: PlayerId) {
+        let Some(players) = self.rooms.get(room).map(|r| &r.players) else {
+            return;
+        };
+        if players.iter().any(|p| p.id == id) {
+            self.relay(room, id, Message::Left);
+            self.rooms.remove(room);
+        }
+    }
+}
+
+// play through a player's connection until they leave
+// a player's messages are sent from their own task, so relaying to them never waits on their connection
+pub async fn play(socket: WebSocket, rooms: Arc<Mutex<Rooms>>) {
+    let (mut sink, mut stream) = socket.split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+    let forward = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            if sink.send(ws::Message::Text(message.to_text())).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // players join once, and only messages about their own game are relayed
+    let mut joined = None;
+    while let Some(Ok(message)) = stream.next().await {
+        let ws::Message::Text(text) = message else {
+            continue;
+        };
+        let Some(message) = Message::from_text(&text) else {
+            continue;
+        };
+
+        let mut rooms = rooms.lock().unwrap();
+        match (&joined, message) {
+            (None, Message::Join { room, rules }) => match rooms.join(&room, rules, tx.clone()) {
+                Some(player) => joined = Some(player),
+                // full rooms turn the player away
+                None => break,
+            },
+            // bigger attacks would only have the other player's client make more garbage rows than it could use
+            (Some((room, id)), Message::Attack(n_rows)) => {
+                rooms.relay(room, *id, Message::Attack(n_rows.min(MAX_ROWS)));
+            }
+            (Some((room, id)), Message::Garbage(mut holes)) => {
+                holes.truncate(MAX_ROWS as usize);
+                rooms.relay(room, *id, Message::Garbage(holes));
+            }
+            (Some((room, id)), message @ (Message::Input { .. } | Message::ToppedOut | Message::Finished)) => {
+                rooms.relay(room, *id, message)
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((room, id)) = joined {
+        rooms.lock().unwrap().leave(&room, id);
+    }
+    forward.abort();
+}
@@ -0,0 +1,134 @@
+// sprint times are worked out again by playing back the run's replay with the core engine, so a time can only come
+// from inputs that actually clear the lines in it
+// replays don't say which kick tables or spins they were played with, so only runs with the default rules are ranked
+// the ticks in a replay are still up to the client, so runs have to be played with a seed the server gave out, in no
+// less time than has passed since, and at a pace a player could keep up
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tetrox::{
+    game::GameRules,
+    pieces::{tetromino::TetrominoSrs, PieceKindTrait},
+    replay::{Replay, ReplayError, ReplayPlayer},
+    sim::Input,
+    ticks::TickRate,
+};
+
+// width, height, and hidden rows of the client's default field
+const FIELD: (usize, usize, usize) = (10, 40, 20);
+
+// long enough for slow runs of the longest sprint
+const SEED_LIFETIME: Duration = Duration::from_secs(2 * 60 * 60);
+
+// anyone can ask for seeds, so the oldest are given up past this many
+const MAX_SEEDS: usize = 10_000;
+
+// well past the fastest human play
+const MAX_PPS: f64 = 15.;
+
+#[derive(Debug)]
+pub enum InvalidRun {
+    Replay(ReplayError),
+    // not played with the default pieces, field, or tick rate
+    NonStandard,
+    // topped out or ran out of inputs before clearing enough lines
+    Unfinished,
+    // not played with a seed from `Seeds`, or with one that was already used or has expired
+    UnknownSeed,
+    // faster than the time since the seed was given out, or than anyone could play
+    Implausible,
+}
+
+impl fmt::Display for InvalidRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidRun::Replay(e) => write!(f, "invalid replay ({:?})", e),
+            InvalidRun::NonStandard => write!(f, "only runs with the default pieces and field are ranked"),
+            InvalidRun::Unfinished => write!(f, "the replay doesn't clear enough lines"),
+            InvalidRun::UnknownSeed => write!(f, "runs have to be started with a new seed from the server"),
+            InvalidRun::Implausible => write!(f, "the replay is faster than it could have been played"),
+        }
+    }
+}
+
+pub struct Run {
+    // in milliseconds, up to the input that cleared the last line
+    pub time: f64,
+    pub pps: f64,
+}
+
+// seeds given out for sprint runs, each of which can be used for one submission
+#[derive(Default)]
+pub struct Seeds {
+    // when each seed was given out
+    issued: HashMap<u64, Instant>,
+    // oldest first, including seeds that have since been used
+    order: VecDeque<u64>,
+}
+
+impl Seeds {
+    pub fn issue(&mut self) -> u64 {
+        while let Some(&oldest) = self.order.front() {
+            let expired = self.issued.get(&oldest).is_none_or(|i| i.elapsed() >= SEED_LIFETIME);
+            if !expired && self.issued.len() < MAX_SEEDS {
+                break;
+            }
+            self.issued.remove(&oldest);
+            self.order.pop_front();
+        }
+
+        let seed = crate::new_seed();
+        self.issued.insert(seed, Instant::now());
+        self.order.push_back(seed);
+        seed
+    }
+
+    // how long ago the seed was given out, using it up
+    fn take(&mut self, seed: u64) -> Option<Duration> {
+        let elapsed = self.issued.remove(&seed)?.elapsed();
+        (elapsed < SEED_LIFETIME).then_some(elapsed)
+    }
+}
+
+pub fn check_sprint(replay_text: &str, n_lines: usize, seeds: &Mutex<Seeds>) -> Result<Run, InvalidRun> {
+    let kinds = TetrominoSrs::iter().collect::<Vec<_>>();
+    let replay = Replay::from_text(replay_text, &kinds).map_err(InvalidRun::Replay)?;
+    // bags can list their kinds in any order
+    let standard_kinds = replay.kinds.len() == kinds.len() && kinds.iter().all(|k| replay.kinds.contains(k));
+    let field = (replay.width, replay.height, replay.hidden);
+    if !standard_kinds || field != FIELD || replay.tick_rate != TickRate::default() {
+        return Err(InvalidRun::NonStandard);
+    }
+    // the seed is used up even if the run turns out to be invalid, so it can't be tried again
+    let since_issued = seeds.lock().unwrap().take(replay.seed).ok_or(InvalidRun::UnknownSeed)?;
+
+    let mut ticks = replay.events.iter().map(|e| e.tick).collect::<Vec<_>>();
+    ticks.dedup();
+    let mut player = ReplayPlayer::new(replay, GameRules::default());
+    for tick in ticks {
+        player.seek(tick);
+        if player.n_lines() >= n_lines {
+            let events = &player.replay().events;
+            let n_pieces = events
+                .iter()
+                .filter(|e| e.tick <= tick && e.input == Input::HardDrop)
+                .count();
+            let time = player.replay().tick_rate.ms(tick);
+            let pps = n_pieces as f64 * 1_000. / time;
+            // a run with every input on the first tick takes no time, which gives infinite pps
+            if time > since_issued.as_secs_f64() * 1_000. || pps > MAX_PPS {
+                return Err(InvalidRun::Implausible);
+            }
+            return Ok(Run { time, pps });
+        }
+        if player.game().field().topped_out() {
+            break;
+        }
+    }
+    Err(InvalidRun::Unfinished)
+}
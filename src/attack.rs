@@ -0,0 +1,132 @@
+// how many garbage lines clears send, for versus modes
+
+use alloc::{vec, vec::Vec};
+
+use crate::field::LineClear;
+
+// extra lines sent for clearing lines with several pieces in a row
+// tables are indexed by combo, which is the number of clears right before this one (so zero for the first clear)
+// and use their last value for longer combos
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComboTable {
+    // lines added to the attack
+    Additive(Vec<u32>),
+    // attack multiplied by `1 + combo * factor` and rounded down, with lines from `fallback` sent instead for clears
+    // that don't send anything on their own (e.g. singles)
+    Multiplier { factor: f64, fallback: Vec<u32> },
+}
+
+impl ComboTable {
+    // modern guideline games
+    pub fn guideline() -> Self { ComboTable::Additive(vec![0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5]) }
+
+    pub fn jstris() -> Self { ComboTable::Additive(vec![0, 0, 1, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5]) }
+
+    // tetr.io, whose fallback is `ln(1 + 1.25 * combo)` rounded down, up to a 16 combo
+    pub fn multiplier() -> Self {
+        ComboTable::Multiplier {
+            factor: 0.25,
+            fallback: vec![0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3],
+        }
+    }
+
+    // lines sent by a clear that would send `attack` lines without a combo
+    pub fn apply(&self, attack: u32, combo: usize) -> u32 {
+        match self {
+            ComboTable::Additive(table) => attack + table_value(table, combo),
+            ComboTable::Multiplier { fallback, .. } if attack == 0 => table_value(fallback, combo),
+            ComboTable::Multiplier { factor, .. } => (attack as f64 * (1. + combo as f64 * factor)) as u32,
+        }
+    }
+}
+
+impl Default for ComboTable {
+    fn default() -> Self { ComboTable::guideline() }
+}
+
+fn table_value(table: &[u32], index: usize) -> u32 { table.get(index).or(table.last()).copied().unwrap_or(0) }
+
+// lines sent for each kind of clear before combos, with tables indexed by lines cleared and using their last value
+// for clears bigger than they cover (e.g. with pentominoes)
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttackTable {
+    pub clears: Vec<u32>,
+    pub spins: Vec<u32>,
+    pub mini_spins: Vec<u32>,
+    // added to clears continuing a back to back chain of quads and spins
+    pub back_to_back: u32,
+    // added to clears that leave the field empty
+    pub perfect_clear: u32,
+}
+
+impl AttackTable {
+    pub fn guideline() -> Self {
+        AttackTable {
+            clears: vec![0, 0, 1, 2, 4],
+            spins: vec![0, 2, 4, 6],
+            mini_spins: vec![0, 0, 1],
+            back_to_back: 1,
+            perfect_clear: 10,
+        }
+    }
+
+    // lines sent by `line_clear` before combos
+    pub fn base_attack(&self, line_clear: &LineClear) -> u32 {
+        let table = match line_clear.spin() {
+            Some(_) if line_clear.is_mini() => &self.mini_spins,
+            Some(_) => &self.spins,
+            None => &self.clears,
+        };
+        table_value(table, line_clear.n_lines())
+    }
+}
+
+impl Default for AttackTable {
+    fn default() -> Self { AttackTable::guideline() }
+}
+
+// combo and back to back chains carried between clears
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AttackState {
+    // clears in the current combo and difficult clears in the current back to back chain, if there are any
+    combo: Option<usize>,
+    back_to_back: Option<usize>,
+}
+
+impl AttackState {
+    pub fn new() -> Self { AttackState::default() }
+
+    // number of clears before the last one in the current combo (so zero for a single clear)
+    pub fn combo(&self) -> Option<usize> { self.combo }
+
+    // number of difficult clears before the last one in the current back to back chain
+    pub fn back_to_back(&self) -> Option<usize> { self.back_to_back }
+
+    // lines sent by `line_clear`, continuing or breaking the combo and back to back chains
+    pub fn update(&mut self, line_clear: &LineClear, table: &AttackTable, combo_table: &ComboTable) -> u32 {
+        if line_clear.n_lines() == 0 {
+            self.combo = None;
+            return 0;
+        }
+
+        let combo = self.combo.map_or(0, |c| c + 1);
+        self.combo = Some(combo);
+
+        let mut attack = table.base_attack(line_clear);
+        if line_clear.is_difficult() {
+            let back_to_back = self.back_to_back.map_or(0, |b| b + 1);
+            if back_to_back > 0 {
+                attack += table.back_to_back;
+            }
+            self.back_to_back = Some(back_to_back);
+        } else {
+            self.back_to_back = None;
+        }
+
+        let mut attack = combo_table.apply(attack, combo);
+        if line_clear.is_perfect_clear() {
+            attack += table.perfect_clear;
+        }
+        attack
+    }
+}
@@ -0,0 +1,216 @@
+// plays lots of placements without any rendering, checking field invariants along the way
+// usage: tetrox-bench [random|greedy] [placements] [seed]
+
+use std::{env, process, time::Instant};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use tetrox::{
+    field::{DefaultField, Square},
+    game::{Game, GameRules},
+    pieces::{tetromino::TetrominoSrs, PieceKindTrait},
+    SingleBag,
+};
+
+const FIELD_WIDTH: usize = 10;
+const FIELD_HEIGHT: usize = 40;
+const FIELD_HIDDEN: usize = 20;
+
+const DEFAULT_PLACEMENTS: usize = 1_000_000;
+
+#[derive(Copy, Clone)]
+enum Strategy {
+    // random rotation and column, which tops out often and so also exercises field creation
+    Random,
+    // picks the best looking placement, which keeps the game going and clears lots of lines
+    Greedy,
+}
+
+// a placement is some number of clockwise rotations followed by a horizontal shift and a hard drop
+#[derive(Copy, Clone)]
+struct Placement {
+    rotations: usize,
+    cols: i32,
+}
+
+#[derive(Default)]
+struct Stats {
+    placements: usize,
+    lines_cleared: usize,
+    top_outs: usize,
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let strategy = match args.next().as_deref() {
+        None | Some("random") => Strategy::Random,
+        Some("greedy") => Strategy::Greedy,
+        Some(other) => exit_with_usage(&format!("unknown strategy `{}`", other)),
+    };
+    let n_placements = args
+        .next()
+        .map(|n| {
+            n.parse()
+                .unwrap_or_else(|_| exit_with_usage(&format!("invalid placement count `{}`", n)))
+        })
+        .unwrap_or(DEFAULT_PLACEMENTS);
+
+    // with a seed, both the pieces and random placements are the same on every run
+    let seed = args
+        .next()
+        .map(|s| {
+            s.parse()
+                .unwrap_or_else(|_| exit_with_usage(&format!("invalid seed `{}`", s)))
+        })
+        .unwrap_or_else(rand::random);
+    println!("seed:          {}", seed);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut game = new_game(&mut rng);
+    let mut stats = Stats::default();
+
+    let start = Instant::now();
+    while stats.placements < n_placements {
+        let placement = match strategy {
+            Strategy::Random => random_placement(&mut rng),
+            Strategy::Greedy => best_placement(&game),
+        };
+
+        // squares on the field once the current piece locks, before any lines are cleared
+        let n_locked = count_filled(game.field());
+        if !apply_placement(&mut game, placement) {
+            continue;
+        }
+        let n_lines = game.hard_drop().n_lines();
+        stats.placements += 1;
+        stats.lines_cleared += n_lines;
+
+        if game.field().topped_out() {
+            stats.top_outs += 1;
+            game = new_game(&mut rng);
+        } else {
+            check_invariants(game.field(), n_locked, n_lines, stats.placements);
+        }
+    }
+    let elapsed = start.elapsed();
+
+    println!("placements:    {}", stats.placements);
+    println!("lines cleared: {}", stats.lines_cleared);
+    println!("top outs:      {}", stats.top_outs);
+    println!("elapsed:       {:.3}s", elapsed.as_secs_f64());
+    println!("placements/s:  {:.0}", stats.placements as f64 / elapsed.as_secs_f64());
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("usage: tetrox-bench [random|greedy] [placements] [seed]");
+    process::exit(1);
+}
+
+// each game gets its own seed taken from the placement rng
+fn new_game(rng: &mut impl Rng) -> Game<SingleBag> {
+    let kinds = TetrominoSrs::iter().collect::<Vec<_>>();
+    let bag = SingleBag::seeded(kinds.clone(), rng.gen());
+    Game::new(
+        FIELD_WIDTH,
+        FIELD_HEIGHT,
+        FIELD_HIDDEN,
+        &kinds,
+        bag,
+        GameRules::default(),
+    )
+}
+
+fn random_placement(rng: &mut impl Rng) -> Placement {
+    let half_width = FIELD_WIDTH as i32 / 2;
+    Placement {
+        rotations: rng.gen_range(0..4),
+        cols: rng.gen_range(-half_width..=half_width),
+    }
+}
+
+// moves the current piece into place without dropping it, failing if it gets blocked
+fn apply_placement(game: &mut Game<SingleBag>, placement: Placement) -> bool {
+    for _ in 0..placement.rotations {
+        if game.rotate_cw().is_err() {
+            return false;
+        }
+    }
+    (0..placement.cols.abs()).all(|_| game.shift(0, placement.cols.signum()).is_ok())
+}
+
+// tries every placement on a copy of the field, picking the one that leaves the best looking stack
+fn best_placement(game: &Game<SingleBag>) -> Placement {
+    let kick_table = game.rules().kick_table;
+    let half_width = FIELD_WIDTH as i32 / 2;
+
+    (0..4)
+        .flat_map(|rotations| (-half_width..=half_width).map(move |cols| Placement { rotations, cols }))
+        .filter_map(|placement| {
+            let mut field = game.field().clone();
+            let rotated = (0..placement.rotations).all(|_| field.try_rotate_cw(kick_table).is_ok());
+            let shifted = (0..placement.cols.abs()).all(|_| field.try_shift(0, placement.cols.signum()).is_ok());
+            (rotated && shifted).then(|| {
+                field.project_down();
+                (placement, evaluate(&field))
+            })
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(placement, _)| placement)
+        .unwrap_or(Placement { rotations: 0, cols: 0 })
+}
+
+// weighted sum of stack height, bumpiness, holes, and cleared lines (the current piece is part of the stack)
+fn evaluate(field: &DefaultField) -> f64 {
+    let heights = field
+        .columns()
+        .map(|mut column| {
+            let top = column.position(|s| s.is_filled());
+            top.map(|row| field.height() - row).unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let holes = field
+        .columns()
+        .map(|column| column.skip_while(|s| s.is_empty()).filter(|s| s.is_empty()).count())
+        .sum::<usize>();
+
+    let total_height = heights.iter().sum::<usize>();
+    let bumpiness = heights.windows(2).map(|w| w[0].abs_diff(w[1])).sum::<usize>();
+    let lines = field.row_fill_counts().filter(|&n| n == field.width()).count();
+
+    -0.51 * total_height as f64 - 0.18 * bumpiness as f64 - 0.36 * holes as f64 + 0.76 * lines as f64
+}
+
+fn count_filled(field: &DefaultField) -> usize { field.occupied_cells().count() }
+
+// panics with the placement number if the field is in a state it should never get into
+fn check_invariants(field: &DefaultField, n_locked: usize, n_lines: usize, placement: usize) {
+    let cur_piece = field.cur_piece();
+
+    // every full line should have been cleared
+    assert!(
+        field.row_fill_counts().all(|n| n < field.width()),
+        "full line left after placement {}",
+        placement
+    );
+
+    // each cleared line removes a line's worth of squares
+    let n_after = count_filled(field) - cur_piece.coords().len();
+    assert_eq!(
+        n_locked - n_lines * field.width(),
+        n_after,
+        "squares not conserved after placement {}",
+        placement
+    );
+
+    // the new piece should be drawn on the field
+    assert!(
+        cur_piece
+            .coords()
+            .iter()
+            .all(|c| matches!(field.get_at(c), Some(Square::Filled(k)) if k == cur_piece.kind())),
+        "current piece not drawn after placement {}",
+        placement
+    );
+}
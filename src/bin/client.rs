@@ -0,0 +1,428 @@
+// terminal client playing a 40 line sprint
+
+use std::{
+    io::{self, Stdout},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
+    },
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame, Terminal,
+};
+use tetrox::{
+    curves::Speed,
+    field::{DefaultField, Square},
+    game::{Game, GameRules},
+    pieces::{tetromino::TetrominoSrs, PieceKind, PieceKindTrait},
+    Coords, Randomizer, SingleBag,
+};
+
+const FIELD_WIDTH: usize = 10;
+const FIELD_HEIGHT: usize = 40;
+const FIELD_HIDDEN: usize = 20;
+
+// the two rows above the visible field are shown so pieces spawning there can be seen
+const SHOWN_HIDDEN_ROWS: usize = 2;
+const QUEUE_LEN: usize = 5;
+const SPRINT_LINES: usize = 40;
+
+// all in milliseconds, with zero meaning instant
+const DELAYED_AUTO_SHIFT: u64 = 120;
+const AUTO_REPEAT_RATE: u64 = 0;
+const SOFT_DROP_RATE: u64 = 0;
+// these can be overridden for specific piece kinds by `GameRules::piece_rules`
+const SPEED: Speed = Speed {
+    gravity_delay: 1_000.,
+    lock_delay: 500.,
+    are: 0.,
+};
+const MOVE_LIMIT: usize = 15;
+
+const FRAME_DURATION: Duration = Duration::from_millis(8);
+
+// each square is two characters wide so the field looks roughly square
+const SQUARE: &str = "██";
+const SHADOW_SQUARE: &str = "▒▒";
+const EMPTY_SQUARE: &str = " .";
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum ShiftDir {
+    Left,
+    Right,
+}
+
+impl ShiftDir {
+    fn cols(self) -> i32 {
+        match self {
+            ShiftDir::Left => -1,
+            ShiftDir::Right => 1,
+        }
+    }
+}
+
+// a held shift key, which starts auto repeating after `DELAYED_AUTO_SHIFT`
+struct HeldShift {
+    dir: ShiftDir,
+    pressed: Instant,
+    last_repeat: Option<Instant>,
+}
+
+struct App {
+    game: Game<SingleBag>,
+    lines_cleared: usize,
+    start: Instant,
+    finished: Option<Duration>,
+
+    held_shift: Option<HeldShift>,
+    soft_dropping: Option<Instant>,
+    last_gravity: Instant,
+    grounded_since: Option<Instant>,
+
+    // without key release events, shifts and soft drops only happen on (os repeated) key presses
+    has_release_events: bool,
+    quit: bool,
+}
+
+impl App {
+    fn new(has_release_events: bool) -> Self {
+        let kinds = TetrominoSrs::iter().collect::<Vec<_>>();
+        let bag = SingleBag::new(kinds.clone());
+        let game = Game::new(
+            FIELD_WIDTH,
+            FIELD_HEIGHT,
+            FIELD_HIDDEN,
+            &kinds,
+            bag,
+            GameRules::default(),
+        );
+        let now = Instant::now();
+
+        App {
+            game,
+            lines_cleared: 0,
+            start: now,
+            finished: None,
+            held_shift: None,
+            soft_dropping: None,
+            last_gravity: now,
+            grounded_since: None,
+            has_release_events,
+            quit: false,
+        }
+    }
+
+    fn restart(&mut self) { *self = App::new(self.has_release_events); }
+
+    fn game_over(&self) -> bool { self.finished.is_some() || self.game.field().topped_out() }
+
+    fn elapsed(&self) -> Duration { self.finished.unwrap_or_else(|| self.start.elapsed()) }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        let now = Instant::now();
+        let pressed = key.kind == KeyEventKind::Press;
+        let repeated = key.kind == KeyEventKind::Repeat && !self.has_release_events;
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc if pressed => self.quit = true,
+            KeyCode::Char('r') if pressed => self.restart(),
+            _ if self.game_over() => {}
+            KeyCode::Left | KeyCode::Right => {
+                let dir = if key.code == KeyCode::Left {
+                    ShiftDir::Left
+                } else {
+                    ShiftDir::Right
+                };
+                if pressed || repeated {
+                    let _ = self.game.shift(0, dir.cols());
+                    if pressed && self.has_release_events {
+                        self.held_shift = Some(HeldShift {
+                            dir,
+                            pressed: now,
+                            last_repeat: None,
+                        });
+                    }
+                } else if key.kind == KeyEventKind::Release {
+                    // releasing the key of the other direction keeps the current one going
+                    if self.held_shift.as_ref().is_some_and(|h| h.dir == dir) {
+                        self.held_shift = None;
+                    }
+                }
+            }
+            KeyCode::Down => {
+                if pressed || repeated {
+                    self.soft_drop();
+                    if pressed && self.has_release_events {
+                        self.soft_dropping = Some(now);
+                    }
+                } else if key.kind == KeyEventKind::Release {
+                    self.soft_dropping = None;
+                }
+            }
+            KeyCode::Up | KeyCode::Char('x') if pressed => {
+                let _ = self.game.rotate_cw();
+            }
+            KeyCode::Char('z') if pressed => {
+                let _ = self.game.rotate_ccw();
+            }
+            KeyCode::Char('a') if pressed => {
+                let _ = self.game.rotate_180();
+            }
+            KeyCode::Char('c') if pressed => {
+                let _ = self.game.swap_hold();
+            }
+            KeyCode::Char(' ') if pressed => self.hard_drop(),
+            _ => {}
+        }
+    }
+
+    fn soft_drop(&mut self) {
+        if SOFT_DROP_RATE == 0 {
+            self.game.sonic_drop();
+        } else {
+            let _ = self.game.shift(1, 0);
+        }
+    }
+
+    fn hard_drop(&mut self) {
+        let line_clear = self.game.hard_drop();
+        self.lines_cleared += line_clear.n_lines();
+        self.grounded_since = None;
+        self.last_gravity = Instant::now();
+
+        if self.lines_cleared >= SPRINT_LINES {
+            self.finished = Some(self.start.elapsed());
+        }
+    }
+
+    // apply auto shift, soft drop, gravity, and lock delay
+    fn tick(&mut self) {
+        if self.game_over() {
+            return;
+        }
+        let now = Instant::now();
+        let elapsed_ms = (now - self.start).as_secs_f64() * 1_000.;
+        let tick = self.game.rules().tick_rate.ticks(elapsed_ms);
+        self.game.advance_to(tick);
+
+        if let Some(ref mut held) = self.held_shift {
+            if now - held.pressed >= Duration::from_millis(DELAYED_AUTO_SHIFT) {
+                let cols = held.dir.cols();
+                if AUTO_REPEAT_RATE == 0 {
+                    while self.game.shift(0, cols).is_ok() {}
+                } else {
+                    let last_repeat = held
+                        .last_repeat
+                        .get_or_insert(now - Duration::from_millis(AUTO_REPEAT_RATE));
+                    while now - *last_repeat >= Duration::from_millis(AUTO_REPEAT_RATE) {
+                        *last_repeat += Duration::from_millis(AUTO_REPEAT_RATE);
+                        let _ = self.game.shift(0, cols);
+                    }
+                }
+            }
+        }
+
+        if let Some(ref mut last_drop) = self.soft_dropping {
+            if SOFT_DROP_RATE == 0 {
+                self.game.sonic_drop();
+            } else {
+                while now - *last_drop >= Duration::from_millis(SOFT_DROP_RATE) {
+                    *last_drop += Duration::from_millis(SOFT_DROP_RATE);
+                    let _ = self.game.shift(1, 0);
+                }
+            }
+        }
+
+        let speed = self.game.cur_speed(SPEED);
+        if now - self.last_gravity >= Duration::from_secs_f64(speed.gravity_delay / 1_000.) {
+            self.last_gravity = now;
+            let _ = self.game.shift(1, 0);
+        }
+
+        // lock the piece after it has been on the ground for long enough or has moved too many times there
+        let field = self.game.field_mut();
+        if field.cur_piece_cannot_move_down() {
+            field.activate_lock_delay();
+            let grounded_since = *self.grounded_since.get_or_insert(now);
+            let n_actions = field.actions_since_lock_delay().unwrap_or(0);
+            let lock_delay = Duration::from_secs_f64(speed.lock_delay / 1_000.);
+            if now - grounded_since >= lock_delay || n_actions >= MOVE_LIMIT {
+                self.hard_drop();
+            }
+        } else {
+            self.grounded_since = None;
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut terminal = setup_terminal()?;
+    let has_release_events = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    if has_release_events {
+        execute!(
+            io::stdout(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        )?;
+    }
+
+    let res = run(&mut terminal, App::new(has_release_events));
+
+    if has_release_events {
+        execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+    }
+    restore_terminal(&mut terminal)?;
+    res
+}
+
+fn setup_terminal() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(io::stdout()))
+}
+
+fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()
+}
+
+fn run(terminal: &mut Terminal<CrosstermBackend<Stdout>>, mut app: App) -> io::Result<()> {
+    while !app.quit {
+        terminal.draw(|frame| draw(frame, &mut app))?;
+
+        if event::poll(FRAME_DURATION)? {
+            if let Event::Key(key) = event::read()? {
+                app.handle_key(key);
+            }
+        }
+        app.tick();
+    }
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let field = app.game.field();
+    let board_width = (field.width() * 2 + 2) as u16;
+    let board_height = (field.height() - field.hidden() + SHOWN_HIDDEN_ROWS + 2) as u16;
+
+    let [left, board, right] = Layout::horizontal([
+        Constraint::Length(14),
+        Constraint::Length(board_width),
+        Constraint::Length(14),
+    ])
+    .areas(centered(frame.size(), 14 + board_width + 14, board_height));
+    let [hold, stats] = Layout::vertical([Constraint::Length(6), Constraint::Min(0)]).areas(left);
+
+    let hold_style = if field.hold_swapped() {
+        Style::default().add_modifier(Modifier::DIM)
+    } else {
+        Style::default()
+    };
+    let hold_lines = field.hold_piece().map(piece_lines).unwrap_or_default();
+    frame.render_widget(Paragraph::new(hold_lines).style(hold_style).block(panel("Hold")), hold);
+
+    let elapsed = app.elapsed();
+    let mut stat_lines = vec![
+        Line::from(format!("{}.{:03}", elapsed.as_secs(), elapsed.subsec_millis())),
+        Line::from(format!(
+            "{}/{} lines",
+            app.lines_cleared.min(SPRINT_LINES),
+            SPRINT_LINES
+        )),
+    ];
+    if app.finished.is_some() {
+        stat_lines.push(Line::from("finished!"));
+    } else if field.topped_out() {
+        stat_lines.push(Line::from("topped out"));
+    }
+    if app.game_over() {
+        stat_lines.push(Line::from("r to restart"));
+    }
+    frame.render_widget(Paragraph::new(stat_lines).block(panel("Sprint")), stats);
+
+    frame.render_widget(
+        Paragraph::new(field_lines(field)).block(Block::default().borders(Borders::ALL)),
+        board,
+    );
+
+    let queue = app.game.bag_mut().peek_n(QUEUE_LEN).collect::<Vec<_>>();
+    let queue_lines = queue
+        .into_iter()
+        .flat_map(|kind| piece_lines(kind).into_iter().chain([Line::default()]));
+    frame.render_widget(
+        Paragraph::new(queue_lines.collect::<Vec<_>>()).block(panel("Next")),
+        right,
+    );
+}
+
+fn panel(title: &str) -> Block<'_> { Block::default().title(title).borders(Borders::ALL) }
+
+// area of the given size in the middle of `area`, shrunk if it doesn't fit
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn kind_color(kind: PieceKind) -> Color {
+    let color = kind.color();
+    Color::Rgb(color.0, color.1, color.2)
+}
+
+fn field_lines(field: &DefaultField) -> Vec<Line<'static>> {
+    let shadow = field.shadow_piece();
+    let cur_color = kind_color(field.cur_piece().kind());
+
+    (field.hidden() - SHOWN_HIDDEN_ROWS..field.height())
+        .map(|row| {
+            let spans = (0..field.width()).map(|col| {
+                let coords = Coords(row as i32, col as i32);
+                match field.get_at(&coords) {
+                    Some(Square::Filled(kind)) => Span::styled(SQUARE, Style::default().fg(kind_color(kind))),
+                    Some(Square::Garbage) => Span::styled(SQUARE, Style::default().fg(Color::Gray)),
+                    _ if shadow.coords().contains(&coords) => {
+                        Span::styled(SHADOW_SQUARE, Style::default().fg(cur_color))
+                    }
+                    _ => Span::styled(EMPTY_SQUARE, Style::default().fg(Color::DarkGray)),
+                }
+            });
+            Line::from(spans.collect::<Vec<_>>())
+        })
+        .collect()
+}
+
+// lines drawing a piece in its spawn orientation
+fn piece_lines(kind: PieceKind) -> Vec<Line<'static>> {
+    let offsets = kind.spawn_offsets();
+    let min_row = offsets.iter().map(|c| c.0).min().unwrap_or(0);
+    let max_row = offsets.iter().map(|c| c.0).max().unwrap_or(0);
+    let min_col = offsets.iter().map(|c| c.1).min().unwrap_or(0);
+    let max_col = offsets.iter().map(|c| c.1).max().unwrap_or(0);
+    let style = Style::default().fg(kind_color(kind));
+
+    (min_row..=max_row)
+        .map(|row| {
+            let spans = (min_col..=max_col).map(|col| {
+                let filled = offsets.contains(&Coords(row, col));
+                Span::styled(if filled { SQUARE } else { "  " }, style)
+            });
+            Line::from(spans.collect::<Vec<_>>())
+        })
+        .collect()
+}
@@ -0,0 +1,149 @@
+// speed curves from other games, mapping progress (lines or levels) to gravity and delays
+
+// timings at some level, all in milliseconds
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Speed {
+    // time for a piece to fall one row, with zero meaning instant (20g)
+    pub gravity_delay: f64,
+    pub lock_delay: f64,
+    // entry delay between a piece locking and the next one spawning
+    pub are: f64,
+}
+
+pub trait SpeedCurve: Sync {
+    // level reached after clearing `lines` lines
+    fn level(&self, lines: usize) -> usize;
+
+    fn speed(&self, level: usize) -> Speed;
+
+    fn speed_after_lines(&self, lines: usize) -> Speed { self.speed(self.level(lines)) }
+}
+
+fn frames_to_ms(frames: f64, fps: f64) -> f64 { frames * 1_000. / fps }
+
+// modern guideline marathon, starting at level 1 and going up every 10 lines
+pub struct GuidelineMarathonCurve;
+
+impl GuidelineMarathonCurve {
+    // gravity stops getting faster past this level
+    const MAX_LEVEL: usize = 20;
+}
+
+impl SpeedCurve for GuidelineMarathonCurve {
+    fn level(&self, lines: usize) -> usize { 1 + lines / 10 }
+
+    fn speed(&self, level: usize) -> Speed {
+        // seconds per row is (0.8 - (level - 1) * 0.007) ^ (level - 1)
+        let n = level.clamp(1, Self::MAX_LEVEL) - 1;
+        let base = 0.8 - n as f64 * 0.007;
+        let seconds = (0..n).fold(1., |acc, _| acc * base);
+
+        Speed {
+            gravity_delay: seconds * 1_000.,
+            lock_delay: 500.,
+            are: 0.,
+        }
+    }
+}
+
+// nes tetris, starting at level 0 and going up every 10 lines
+pub struct NesCurve;
+
+impl NesCurve {
+    const FPS: f64 = 60.0988;
+
+    // ntsc frames per row for levels 0 to 8 (later levels change speed less often, see `speed`)
+    const FRAMES_PER_ROW: [u32; 9] = [48, 43, 38, 33, 28, 23, 18, 13, 8];
+}
+
+impl SpeedCurve for NesCurve {
+    fn level(&self, lines: usize) -> usize { lines / 10 }
+
+    fn speed(&self, level: usize) -> Speed {
+        let frames = match level {
+            0..=8 => Self::FRAMES_PER_ROW[level],
+            9 => 6,
+            10..=12 => 5,
+            13..=15 => 4,
+            16..=18 => 3,
+            19..=28 => 2,
+            _ => 1,
+        };
+
+        // pieces lock as soon as they land, and are varies from 10 to 18 frames based on where the piece locked
+        Speed {
+            gravity_delay: frames_to_ms(frames as f64, Self::FPS),
+            lock_delay: 0.,
+            are: frames_to_ms(10., Self::FPS),
+        }
+    }
+}
+
+// tgm2 master mode, going up to level 999
+// the level is also raised by one per piece in tgm, which is left to the caller (e.g. `level(lines) + n_pieces`)
+pub struct TgmMasterCurve;
+
+impl TgmMasterCurve {
+    const FPS: f64 = 60.;
+    const MAX_LEVEL: usize = 999;
+
+    // (starting level, gravity in 1/256 rows per frame)
+    const GRAVITY: [(usize, u32); 30] = [
+        (0, 4),
+        (30, 6),
+        (35, 8),
+        (40, 10),
+        (50, 12),
+        (60, 16),
+        (70, 32),
+        (80, 48),
+        (90, 64),
+        (100, 80),
+        (120, 96),
+        (140, 112),
+        (160, 128),
+        (170, 144),
+        (200, 4),
+        (220, 32),
+        (230, 64),
+        (233, 96),
+        (236, 128),
+        (239, 160),
+        (243, 192),
+        (247, 224),
+        (251, 256),
+        (300, 512),
+        (330, 768),
+        (360, 1024),
+        (400, 1280),
+        (420, 1024),
+        (450, 768),
+        (500, 5120),
+    ];
+
+    // (starting level, are frames, lock delay frames)
+    const DELAYS: [(usize, u32, u32); 4] = [(0, 25, 30), (700, 16, 30), (800, 12, 30), (900, 12, 17)];
+}
+
+impl SpeedCurve for TgmMasterCurve {
+    fn level(&self, lines: usize) -> usize { lines.min(Self::MAX_LEVEL) }
+
+    fn speed(&self, level: usize) -> Speed {
+        let level = level.min(Self::MAX_LEVEL);
+        let (_, gravity) = Self::GRAVITY.iter().rev().find(|(start, _)| level >= *start).unwrap();
+        let (_, are, lock_delay) = Self::DELAYS.iter().rev().find(|(start, ..)| level >= *start).unwrap();
+
+        // 20g (5120/256) fills the field instantly
+        let gravity_delay = if *gravity >= 5120 {
+            0.
+        } else {
+            frames_to_ms(256. / *gravity as f64, Self::FPS)
+        };
+
+        Speed {
+            gravity_delay,
+            lock_delay: frames_to_ms(*lock_delay as f64, Self::FPS),
+            are: frames_to_ms(*are as f64, Self::FPS),
+        }
+    }
+}
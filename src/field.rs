@@ -1,8 +1,12 @@
-use std::collections::HashSet;
+use alloc::{collections::BTreeSet, vec, vec::Vec};
+use core::{iter::FromIterator, mem, ops::Deref};
 
 use crate::{
+    garbage::GarbageGenerator,
     kicks::{KickTable, KickTable180, RotationState},
     spins::SpinDetector,
+    pieces::MAX_SQUARES,
+    ticks::Tick,
     Coords, CoordsFloat, PieceKind, Randomizer,
 };
 
@@ -10,23 +14,48 @@ use crate::{
 pub enum Square {
     Empty,
     Filled(PieceKind),
+    Garbage,
 }
 
 impl Square {
     pub fn is_empty(&self) -> bool { matches!(self, Square::Empty) }
 
-    pub fn is_filled(&self) -> bool { matches!(self, Square::Filled(_)) }
+    pub fn is_filled(&self) -> bool { matches!(self, Square::Filled(_) | Square::Garbage) }
+}
+
+// which neighbours of a square are part of the same piece, for drawing connected skins
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Connections {
+    pub up: bool,
+    pub right: bool,
+    pub down: bool,
+    pub left: bool,
+}
+
+impl Connections {
+    // connections of the square at `coords` to the other squares of a piece with the squares `piece`
+    pub fn within(piece: &[Coords], Coords(row, col): Coords) -> Self {
+        Connections {
+            up: piece.contains(&Coords(row - 1, col)),
+            right: piece.contains(&Coords(row, col + 1)),
+            down: piece.contains(&Coords(row + 1, col)),
+            left: piece.contains(&Coords(row, col - 1)),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Line {
     squares: Vec<Square>,
+    // set for locked squares as their piece locks, and cut when the squares they connect to are cleared or changed
+    connections: Vec<Connections>,
 }
 
 impl Line {
     fn new(width: usize) -> Self {
         Line {
             squares: (0..width).map(|_| Square::Empty).collect(),
+            connections: vec![Connections::default(); width],
         }
     }
 
@@ -40,6 +69,35 @@ impl Line {
     pub fn get(&self, i: usize) -> Square { self.squares[i] }
 
     fn get_mut(&mut self, i: usize) -> &mut Square { &mut self.squares[i] }
+
+    pub fn connections(&self, i: usize) -> Connections { self.connections[i] }
+
+    fn make_empty(&mut self) {
+        self.squares.fill(Square::Empty);
+        self.connections.fill(Connections::default());
+    }
+
+    fn resize(&mut self, width: usize) {
+        self.squares.resize(width, Square::Empty);
+        self.connections.resize(width, Connections::default());
+        if let Some(last) = self.connections.last_mut() {
+            last.right = false;
+        }
+    }
+
+    // for when the line above or below this one is cleared or pushed out of the field
+    fn disconnect_up(&mut self) { self.connections.iter_mut().for_each(|c| c.up = false); }
+
+    fn disconnect_down(&mut self) { self.connections.iter_mut().for_each(|c| c.down = false); }
+
+    fn new_garbage(width: usize, hole: usize) -> Self {
+        let mut line = Line {
+            squares: vec![Square::Garbage; width],
+            connections: vec![Connections::default(); width],
+        };
+        line.squares[hole] = Square::Empty;
+        line
+    }
 }
 
 #[derive(Clone)]
@@ -48,6 +106,12 @@ pub struct LineClear {
     spin: Option<PieceKind>,
     is_mini: bool,
     is_perfect_clear: bool,
+    // rows of the field which were cleared, counted from the top from before they were cleared
+    cleared_rows: Vec<usize>,
+    // lines sent to opponents, which depends on the game's rules, and when the clear happened
+    // these are only filled in by `Game`
+    attack: u32,
+    tick: Tick,
 }
 
 impl LineClear {
@@ -57,9 +121,18 @@ impl LineClear {
             spin,
             is_mini,
             is_perfect_clear,
+            cleared_rows: vec![],
+            attack: 0,
+            tick: 0,
         }
     }
 
+    pub fn with_attack(self, attack: u32) -> Self { LineClear { attack, ..self } }
+
+    pub fn with_tick(self, tick: Tick) -> Self { LineClear { tick, ..self } }
+
+    pub fn with_cleared_rows(self, cleared_rows: Vec<usize>) -> Self { LineClear { cleared_rows, ..self } }
+
     pub fn n_lines(&self) -> usize { self.n_lines }
 
     pub fn spin(&self) -> Option<PieceKind> { self.spin }
@@ -67,11 +140,81 @@ impl LineClear {
     pub fn is_mini(&self) -> bool { self.is_mini }
 
     pub fn is_perfect_clear(&self) -> bool { self.is_perfect_clear }
+
+    pub fn cleared_rows(&self) -> &[usize] { &self.cleared_rows }
+
+    // clears that keep back to back going
+    pub fn is_difficult(&self) -> bool { self.n_lines >= 4 || self.n_lines > 0 && self.spin.is_some() }
+
+    pub fn attack(&self) -> u32 { self.attack }
+
+    pub fn tick(&self) -> Tick { self.tick }
+}
+
+// a piece hard dropped and locked, for feedback like drop trails
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PieceDrop {
+    pub kind: PieceKind,
+    // where the piece was dropped from and where it locked, before any lines were cleared
+    pub from: Vec<Coords>,
+    pub to: Vec<Coords>,
+}
+
+// what a successful move did, for feedback like sounds
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveOk {
+    Shifted,
+    // rotated and then moved by this kick, which is `Coords(0, 0)` if the piece didn't need kicking
+    Rotated(Coords),
+    Held,
+}
+
+// why a move failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    // the piece would go past the left or right side of the field
+    Wall,
+    // the piece would go below the bottom of the field
+    OutOfBounds,
+    // the piece would overlap the stack (or another player's piece)
+    Stack,
+    HoldUsed,
+    // the game's rules don't allow the move
+    Disallowed,
+}
+
+// inline storage for the coords of a `LivePiece` so moving pieces around never allocates
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PieceCoords {
+    coords: [Coords; MAX_SQUARES],
+    len: usize,
 }
 
+impl Deref for PieceCoords {
+    type Target = [Coords];
+
+    fn deref(&self) -> &Self::Target { &self.coords[..self.len] }
+}
+
+impl FromIterator<Coords> for PieceCoords {
+    // panics if there are more than `MAX_SQUARES` coords
+    fn from_iter<T: IntoIterator<Item = Coords>>(iter: T) -> Self {
+        let mut piece_coords = PieceCoords {
+            coords: [Coords(0, 0); MAX_SQUARES],
+            len: 0,
+        };
+        for coords in iter {
+            piece_coords.coords[piece_coords.len] = coords;
+            piece_coords.len += 1;
+        }
+        piece_coords
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
 pub struct LivePiece {
     kind: PieceKind,
-    coords: Vec<Coords>,
+    coords: PieceCoords,
     rotation_state: RotationState,
 }
 
@@ -79,8 +222,8 @@ impl LivePiece {
     fn new(kind: PieceKind, origin: &Coords) -> Self {
         let coords = kind
             .spawn_offsets()
-            .into_iter()
-            .map(|offset| *origin + offset)
+            .iter()
+            .map(|offset| *origin + *offset)
             .collect();
 
         LivePiece {
@@ -90,7 +233,7 @@ impl LivePiece {
         }
     }
 
-    pub fn coords(&self) -> &Vec<Coords> { &self.coords }
+    pub fn coords(&self) -> &[Coords] { &self.coords }
 
     pub fn kind(&self) -> PieceKind { self.kind }
 
@@ -130,14 +273,46 @@ impl LivePiece {
 
     pub fn rotated_180(&self) -> LivePiece { self.rotated_cw().rotated_cw() }
 
+    // mirror image of this piece in a field `width` squares wide, as the mirror image of its kind
+    pub fn mirrored(&self, width: usize) -> LivePiece {
+        let mirror = |Coords(row, col): Coords| Coords(row, width as i32 - 1 - col);
+        let coords = self.coords.iter().map(|&c| mirror(c)).collect::<PieceCoords>();
+        let rotation_state = match self.rotation_state {
+            RotationState::Cw => RotationState::Ccw,
+            RotationState::Ccw => RotationState::Cw,
+            state => state,
+        };
+
+        // rebuild the piece from its mirrored kind so its squares are in the order its pivot offsets expect
+        let mut piece = LivePiece::new(self.kind.mirrored(), &Coords(0, 0));
+        while piece.rotation_state != rotation_state {
+            piece = piece.rotated_cw();
+        }
+        let top_left = |coords: &[Coords]| {
+            let row = coords.iter().map(|c| c.0).min().unwrap_or(0);
+            let col = coords.iter().map(|c| c.1).min().unwrap_or(0);
+            Coords(row, col)
+        };
+        let Coords(rows, cols) = top_left(&coords) - top_left(&piece.coords);
+        let piece = piece.shifted(rows, cols);
+
+        // fall back to the mirrored squares as they are if the kind's rotation system isn't symmetric
+        if piece.coords.iter().all(|c| coords.contains(c)) {
+            piece
+        } else {
+            LivePiece {
+                kind: self.kind.mirrored(),
+                coords,
+                rotation_state,
+            }
+        }
+    }
+
     // shadow piece, hard drop position, etc.
     pub fn projected_down(&self, field: &DefaultField) -> LivePiece {
         let shifted = self.shifted(1, 0);
         if shifted.is_blocked(Some(field.cur_piece()), field) {
-            LivePiece {
-                coords: self.coords.clone(),
-                ..(*self)
-            }
+            *self
         } else {
             shifted.projected_down(field)
         }
@@ -145,14 +320,47 @@ impl LivePiece {
 
     // if the piece being checked has a previous state, `old_piece` should represent that state
     pub fn is_blocked(&self, old_piece: Option<&LivePiece>, field: &DefaultField) -> bool {
-        // make sure the coords are in bounds and are not filled by other pieces
-        self.coords.iter().any(|c| {
-            !field.coords_in_bounds(&c)
-                || !field.get_at(&c).unwrap().is_empty() && old_piece.map(|p| !p.coords.contains(c)).unwrap_or(true)
-        })
+        self.blocked_by(old_piece, field).is_some()
+    }
+
+    // why the piece is blocked, if it is, with the field's bounds taking precedence over the stack
+    pub fn blocked_by(&self, old_piece: Option<&LivePiece>, field: &DefaultField) -> Option<MoveError> {
+        // the field grows to fit pieces moved above it, so only the columns and bottom matter for bounds
+        if self.coords.iter().any(|c| !(0..field.width as i32).contains(&c.1)) {
+            Some(MoveError::Wall)
+        } else if self.coords.iter().any(|c| c.0 >= field.height as i32) {
+            Some(MoveError::OutOfBounds)
+        } else if self.coords.iter().any(|c| {
+            c.0 >= 0 && !field.get_at(c).unwrap().is_empty() && old_piece.map(|p| !p.coords.contains(c)).unwrap_or(true)
+        }) {
+            Some(MoveError::Stack)
+        } else {
+            None
+        }
     }
 }
 
+// a player's piece and everything that goes with it, for fields shared by several players (see `add_player`)
+// rows of a field changed since they were last taken, which are all changed in a clone of the field, since it may be
+// drawn somewhere else or replace another field (e.g. when undoing)
+struct DirtyRows(Vec<bool>);
+
+impl Clone for DirtyRows {
+    fn clone(&self) -> Self { DirtyRows(vec![true; self.0.len()]) }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct PlayerState {
+    cur_piece: LivePiece,
+    hold_piece: Option<PieceKind>,
+    hold_swapped: bool,
+    lock_delay_actions: Option<usize>,
+    last_cur_piece_kick: Option<Coords>,
+    last_move_rotated: bool,
+    spawn_offset: i32,
+}
+
+#[derive(Clone)]
 pub struct DefaultField {
     width: usize,
     height: usize,
@@ -166,31 +374,36 @@ pub struct DefaultField {
 
     topped_out: bool,
 
-    piece_origin: Coords,
-    spawn_area: HashSet<Coords>,
+    // kinds which may spawn, used to find the spawn area
+    kinds: Vec<PieceKind>,
+    spawn_area: BTreeSet<Coords>,
 
     lock_delay_actions: Option<usize>,
 
     // used for spin detection (e.g. t-spins)
     last_cur_piece_kick: Option<Coords>,
     last_move_rotated: bool,
+
+    // the last piece hard dropped, if any have been
+    last_drop: Option<PieceDrop>,
+
+    // rows changed since the last call to `take_dirty_rows`
+    dirty_rows: DirtyRows,
+
+    // how many rows up blocked pieces may be moved when they spawn before they block out
+    spawn_grace_rows: usize,
+
+    // every player's piece is drawn on the field, but only the active player's is the current piece
+    // the others are kept here by player number, with the active player's state out of date until switching away
+    players: Vec<PlayerState>,
+    active_player: usize,
+    // columns right of the center that the active player's pieces spawn at
+    spawn_offset: i32,
 }
 
 impl DefaultField {
     pub fn new(width: usize, height: usize, hidden: usize, kinds: &[PieceKind], bag: &mut impl Randomizer) -> Self {
-        // coordinates of the center (left-aligned) of the bottom-most line of pieces spawned on this field
-        // i.e. the coordinates of the @ sign in the following 10-wide field:
-        // |    #     |
-        // |   #@#    |
-        // note how the center is left-aligned for even field widths
-        let piece_origin = Coords(hidden as i32 - 2, width as i32 / 2 - 1);
-
-        // set of coords where pieces may spawn (used to detect topping out)
-        let spawn_area = kinds
-            .iter()
-            .flat_map(|k| k.spawn_offsets().into_iter().map(|c| c + piece_origin))
-            .collect();
-
+        let kind = bag.next();
         let mut field = DefaultField {
             width,
             height,
@@ -198,24 +411,353 @@ impl DefaultField {
 
             lines: (0..height).map(|_| Line::new(width)).collect(),
 
-            cur_piece: LivePiece::new(bag.next(), &piece_origin),
+            cur_piece: LivePiece::new(kind, &Self::spawn_origin(width, hidden, kind)),
             hold_piece: None,
             hold_swapped: false,
 
             topped_out: false,
 
-            piece_origin,
-            spawn_area,
+            kinds: kinds.to_vec(),
+            spawn_area: Self::spawn_area(width, hidden, kinds, &[0]),
 
             lock_delay_actions: None,
 
             last_cur_piece_kick: None,
             last_move_rotated: false,
+
+            last_drop: None,
+
+            // the whole field needs to be drawn initially
+            dirty_rows: DirtyRows(vec![true; height]),
+
+            spawn_grace_rows: 0,
+
+            players: vec![],
+            active_player: 0,
+            spawn_offset: 0,
         };
+        field.cur_piece = field.grow_to_fit(field.cur_piece);
         field.draw_cur_piece();
+        field.players.push(field.player_state());
+        field
+    }
+
+    // change the dimensions of the field, keeping the stack aligned to the bottom left
+    // rows and columns which no longer fit are cropped from the top and right
+    pub fn resize(&mut self, width: usize, height: usize, hidden: usize) {
+        let rows_added = height as i32 - self.height as i32;
+        self.without_other_pieces(|field| {
+            field.resize_with_cur_piece(width, height, hidden);
+            for player in &mut field.players {
+                player.cur_piece = player.cur_piece.shifted(rows_added, 0);
+            }
+        });
+    }
+
+    fn resize_with_cur_piece(&mut self, width: usize, height: usize, hidden: usize) {
+        let cur_piece = self.cur_piece;
+        self.erase_cur_piece();
+
+        let rows_added = height as i32 - self.height as i32;
+        if rows_added < 0 {
+            self.lines.drain(..-rows_added as usize);
+            if let Some(top) = self.lines.first_mut() {
+                top.disconnect_up();
+            }
+        } else {
+            self.lines.splice(0..0, (0..rows_added).map(|_| Line::new(width)));
+        }
+        for line in &mut self.lines {
+            line.resize(width);
+        }
+
+        self.spawn_area = Self::spawn_area(width, hidden, &self.kinds, &self.spawn_offsets());
+        self.width = width;
+        self.height = height;
+        self.hidden = hidden;
+        self.dirty_rows = DirtyRows(vec![true; height]);
+
+        // keep the current piece where it was relative to the stack if possible, and respawn it otherwise
+        let shifted = cur_piece.shifted(rows_added, 0);
+        let new_piece = if shifted.is_blocked(None, self) {
+            self.spawned_piece(cur_piece.kind())
+        } else {
+            shifted
+        };
+        self.topped_out |= new_piece.is_blocked(None, self);
+        self.cur_piece = self.grow_to_fit(new_piece);
+        if !self.topped_out {
+            self.draw_cur_piece();
+        }
+    }
+
+    // adds hidden rows to the top of the field if part of `piece` is above it
+    // everything moves down along with the field, so this returns `piece` moved as well
+    fn grow_to_fit(&mut self, piece: LivePiece) -> LivePiece {
+        let top = piece.coords().iter().map(|c| c.0).min().unwrap_or(0);
+        if top >= 0 {
+            return piece;
+        }
+
+        let n_rows = -top;
+        self.lines.splice(0..0, (0..n_rows).map(|_| Line::new(self.width)));
+        self.height += n_rows as usize;
+        self.hidden += n_rows as usize;
+
+        let delta = Coords(n_rows, 0);
+        self.spawn_area = self.spawn_area.iter().map(|c| *c + delta).collect();
+        self.cur_piece = self.cur_piece.shifted(n_rows, 0);
+        for player in &mut self.players {
+            player.cur_piece = player.cur_piece.shifted(n_rows, 0);
+        }
+        self.dirty_rows = DirtyRows(vec![true; self.height]);
+
+        piece.shifted(n_rows, 0)
+    }
+
+    // horizontally flipped copy of the field where every piece is replaced by its mirror image
+    // used to practice the other side of the board, or to check that kicks behave symmetrically
+    pub fn mirrored(&self) -> DefaultField {
+        let mut field = self.clone();
+        for line in &mut field.lines {
+            line.squares.reverse();
+            line.connections.reverse();
+            for connections in &mut line.connections {
+                mem::swap(&mut connections.left, &mut connections.right);
+            }
+            for square in &mut line.squares {
+                if let Square::Filled(kind) = square {
+                    *kind = kind.mirrored();
+                }
+            }
+        }
+
+        field.cur_piece = self.cur_piece.mirrored(self.width);
+        field.hold_piece = self.hold_piece.map(|k| k.mirrored());
+        field.last_cur_piece_kick = self.last_cur_piece_kick.map(|Coords(row, col)| Coords(row, -col));
+        field.spawn_offset = -self.spawn_offset;
+        for player in &mut field.players {
+            player.cur_piece = player.cur_piece.mirrored(self.width);
+            player.hold_piece = player.hold_piece.map(|k| k.mirrored());
+            player.last_cur_piece_kick = player.last_cur_piece_kick.map(|Coords(row, col)| Coords(row, -col));
+            player.spawn_offset = -player.spawn_offset;
+        }
+        field.spawn_area = Self::spawn_area(self.width, self.hidden, &self.kinds, &field.spawn_offsets());
         field
     }
 
+    // switch to a different piece set without clearing the stack
+    // `bag` should already be using the new set, as the current piece is replaced with one from it
+    pub fn set_kinds(&mut self, kinds: &[PieceKind], bag: &mut impl Randomizer) {
+        if kinds == self.kinds {
+            return;
+        }
+        self.kinds = kinds.to_vec();
+        self.spawn_area = Self::spawn_area(self.width, self.hidden, kinds, &self.spawn_offsets());
+
+        self.hold_piece = self.hold_piece.filter(|k| kinds.contains(k));
+        self.hold_swapped = false;
+        self.lock_delay_actions = None;
+        self.last_cur_piece_kick = None;
+
+        if !self.try_spawn(bag) {
+            self.topped_out = true;
+        }
+    }
+
+    // push the stack up by `n_rows` rows of garbage, with holes chosen by `generator`
+    // the current piece is moved up if the garbage would overlap it, and stack pushed out of the field tops out
+    pub fn add_garbage(&mut self, n_rows: usize, generator: &mut dyn GarbageGenerator) {
+        self.without_other_pieces(|field| field.add_garbage_under_cur_piece(n_rows, generator));
+    }
+
+    fn add_garbage_under_cur_piece(&mut self, n_rows: usize, generator: &mut dyn GarbageGenerator) {
+        let n_rows = n_rows.min(self.height);
+        let holes = generator.holes(n_rows, self.width);
+        let cur_piece = self.cur_piece;
+        self.erase_cur_piece();
+
+        self.topped_out |= self.lines[..n_rows].iter().any(|l| !l.is_empty());
+        self.lines.drain(..n_rows);
+        if let Some(top) = self.lines.first_mut() {
+            top.disconnect_up();
+        }
+        self.lines.extend(holes.into_iter().map(|hole| Line::new_garbage(self.width, hole)));
+        self.dirty_rows.0.fill(true);
+
+        let mut new_piece = cur_piece;
+        while new_piece.is_blocked(None, self) {
+            new_piece = new_piece.shifted(-1, 0);
+        }
+        self.cur_piece = self.grow_to_fit(new_piece);
+        self.draw_cur_piece();
+    }
+
+    // coordinates which the spawn offsets of `kind` are relative to
+    // this centers the piece's bounding box (rounding left) and puts its bottom row two rows above the visible area
+    // e.g. the @ signs in the following 10-wide field, for a t tetromino and an i pentomino:
+    // |    #     |  |          |
+    // |   #@#    |  |  ##@##   |
+    fn spawn_origin(width: usize, hidden: usize, kind: PieceKind) -> Coords {
+        let offsets = kind.spawn_offsets();
+        let min_col = offsets.iter().map(|c| c.1).min().unwrap_or(0);
+        let max_col = offsets.iter().map(|c| c.1).max().unwrap_or(0);
+        let max_row = offsets.iter().map(|c| c.0).max().unwrap_or(0);
+
+        let box_width = max_col - min_col + 1;
+        let left_col = (width as i32 - box_width).div_euclid(2);
+        Coords(hidden as i32 - 2 - max_row, left_col - min_col)
+    }
+
+    // set of coords where pieces may spawn for players with the given spawn offsets (used to detect topping out)
+    fn spawn_area(width: usize, hidden: usize, kinds: &[PieceKind], spawn_offsets: &[i32]) -> BTreeSet<Coords> {
+        kinds
+            .iter()
+            .flat_map(|&k| {
+                spawn_offsets.iter().flat_map(move |&offset| {
+                    let origin = Self::spawn_origin(width, hidden, k) + Coords(0, offset);
+                    k.spawn_offsets().iter().map(move |c| *c + origin)
+                })
+            })
+            .collect()
+    }
+
+    // the first spot from `piece` up to the spawn grace rows above it which isn't blocked, or `piece` if they all are
+    fn graced(&self, piece: LivePiece, old_piece: Option<&LivePiece>) -> LivePiece {
+        (0..=self.spawn_grace_rows as i32)
+            .map(|rows| piece.shifted(-rows, 0))
+            .find(|p| !p.is_blocked(old_piece, self))
+            .unwrap_or(piece)
+    }
+
+    // let pieces spawning blocked move up by up to `n_rows` rows instead of blocking out, like some guideline games
+    pub fn set_spawn_grace_rows(&mut self, n_rows: usize) { self.spawn_grace_rows = n_rows; }
+
+    fn spawned_piece(&self, kind: PieceKind) -> LivePiece { self.spawned_piece_at(kind, self.spawn_offset) }
+
+    fn spawned_piece_at(&self, kind: PieceKind, spawn_offset: i32) -> LivePiece {
+        LivePiece::new(kind, &(Self::spawn_origin(self.width, self.hidden, kind) + Coords(0, spawn_offset)))
+    }
+
+    fn spawn_offsets(&self) -> Vec<i32> {
+        let others = self.players.iter().enumerate().filter(|(p, _)| *p != self.active_player);
+        others.map(|(_, s)| s.spawn_offset).chain([self.spawn_offset]).collect()
+    }
+
+    // add another player with their own piece, for co-op on a (usually wider) shared field
+    // their pieces spawn `spawn_offset` columns right of the center, and this fails if their first piece is blocked
+    pub fn add_player(&mut self, spawn_offset: i32, bag: &mut impl Randomizer) -> Option<usize> {
+        let piece = self.spawned_piece_at(bag.next(), spawn_offset);
+        if piece.is_blocked(None, self) {
+            return None;
+        }
+
+        let piece = self.grow_to_fit(piece);
+        self.draw_piece(&piece);
+        self.players.push(PlayerState {
+            cur_piece: piece,
+            hold_piece: None,
+            hold_swapped: false,
+            lock_delay_actions: None,
+            last_cur_piece_kick: None,
+            last_move_rotated: false,
+            spawn_offset,
+        });
+        self.spawn_area = Self::spawn_area(self.width, self.hidden, &self.kinds, &self.spawn_offsets());
+        Some(self.players.len() - 1)
+    }
+
+    pub fn n_players(&self) -> usize { self.players.len() }
+
+    pub fn active_player(&self) -> usize { self.active_player }
+
+    // make `player`'s piece the current piece, which every other method then acts on
+    // panics if there is no such player
+    pub fn set_active_player(&mut self, player: usize) {
+        assert!(player < self.players.len(), "no player {}", player);
+        self.players[self.active_player] = self.player_state();
+
+        let state = self.players[player];
+        self.cur_piece = state.cur_piece;
+        self.hold_piece = state.hold_piece;
+        self.hold_swapped = state.hold_swapped;
+        self.lock_delay_actions = state.lock_delay_actions;
+        self.last_cur_piece_kick = state.last_cur_piece_kick;
+        self.last_move_rotated = state.last_move_rotated;
+        self.spawn_offset = state.spawn_offset;
+        self.active_player = player;
+    }
+
+    pub fn set_spawn_offset(&mut self, spawn_offset: i32) {
+        self.spawn_offset = spawn_offset;
+        self.spawn_area = Self::spawn_area(self.width, self.hidden, &self.kinds, &self.spawn_offsets());
+    }
+
+    // every player's piece, by player number
+    pub fn live_pieces(&self) -> impl Iterator<Item = &LivePiece> + '_ {
+        let pieces = self.players.iter().map(|s| &s.cur_piece).enumerate();
+        pieces.map(|(p, piece)| if p == self.active_player { &self.cur_piece } else { piece })
+    }
+
+    fn player_state(&self) -> PlayerState {
+        PlayerState {
+            cur_piece: self.cur_piece,
+            hold_piece: self.hold_piece,
+            hold_swapped: self.hold_swapped,
+            lock_delay_actions: self.lock_delay_actions,
+            last_cur_piece_kick: self.last_cur_piece_kick,
+            last_move_rotated: self.last_move_rotated,
+            spawn_offset: self.spawn_offset,
+        }
+    }
+
+    // takes the other players' pieces off the field while `f` changes the stack under them, then puts them back
+    // pieces the stack has moved into are pushed up, and pieces which no longer fit at all are respawned
+    fn without_other_pieces<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let others = (0..self.players.len()).filter(|&p| p != self.active_player).collect::<Vec<_>>();
+        for &player in &others {
+            for coords in self.players[player].cur_piece.coords().to_vec() {
+                if self.coords_in_bounds(&coords) {
+                    self.set_at(&coords, Square::Empty);
+                }
+            }
+        }
+
+        let result = f(self);
+
+        for &player in &others {
+            let state = self.players[player];
+            let in_bounds = |p: &LivePiece| p.coords().iter().all(|c| (0..self.width as i32).contains(&c.1));
+            let piece = if in_bounds(&state.cur_piece) {
+                state.cur_piece
+            } else {
+                self.shifted_into_columns(self.spawned_piece_at(state.cur_piece.kind(), state.spawn_offset))
+            };
+
+            // a piece within the columns is clear of the stack once it's pushed above the field, so this only fails
+            // when the field has become narrower than the piece
+            let mut pushed = (0..=self.height as i32).map(|rows| piece.shifted(-rows, 0));
+            let Some(piece) = pushed.find(|p| !p.is_blocked(None, self)) else {
+                self.players[player].cur_piece = piece;
+                self.topped_out = true;
+                continue;
+            };
+
+            let piece = self.grow_to_fit(piece);
+            self.players[player].cur_piece = piece;
+            self.draw_piece(&piece);
+        }
+        result
+    }
+
+    // `piece` moved sideways just far enough to be within the field's columns, if it's narrow enough to fit
+    fn shifted_into_columns(&self, piece: LivePiece) -> LivePiece {
+        let cols = piece.coords().iter().map(|c| c.1);
+        let (min_col, max_col) = (cols.clone().min().unwrap_or(0), cols.max().unwrap_or(0));
+        let shift = if min_col < 0 { -min_col } else { (self.width as i32 - 1 - max_col).min(0) };
+        piece.shifted(0, shift)
+    }
+
     pub fn width(&self) -> usize { self.width }
 
     pub fn height(&self) -> usize { self.height }
@@ -228,6 +770,55 @@ impl DefaultField {
 
     pub fn lines(&self) -> &[Line] { &self.lines }
 
+    // these iterators include the current piece, and should be used instead of indexing into `lines`
+    // rows of squares from top to bottom
+    pub fn rows(&self) -> impl Iterator<Item = &[Square]> + '_ { self.lines.iter().map(|l| l.squares()) }
+
+    // columns of squares from left to right, each from top to bottom
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = Square> + '_> + '_ {
+        (0..self.width).map(move |col| self.lines.iter().map(move |l| l.get(col)))
+    }
+
+    // coords of every non-empty square along with what's there, in row-major order
+    pub fn occupied_cells(&self) -> impl Iterator<Item = (Coords, Square)> + '_ {
+        self.rows().enumerate().flat_map(|(row, squares)| {
+            let squares = squares.iter().enumerate().filter(|(_, s)| s.is_filled());
+            squares.map(move |(col, s)| (Coords(row as i32, col as i32), *s))
+        })
+    }
+
+    // number of non-empty squares in each row, from top to bottom
+    pub fn row_fill_counts(&self) -> impl Iterator<Item = usize> + '_ {
+        self.rows().map(|squares| squares.iter().filter(|s| s.is_filled()).count())
+    }
+
+    // empty squares under a filled square in the same column, not counting the squares of any live piece
+    pub fn n_holes(&self) -> usize {
+        let live = self.live_pieces().flat_map(|p| p.coords().iter().copied()).collect::<Vec<_>>();
+        let mut n_holes = 0;
+        for (col, squares) in self.columns().enumerate() {
+            let mut covered = false;
+            for (row, square) in squares.enumerate() {
+                if live.contains(&Coords(row as i32, col as i32)) {
+                    continue;
+                }
+                if square.is_filled() {
+                    covered = true;
+                } else if covered {
+                    n_holes += 1;
+                }
+            }
+        }
+        n_holes
+    }
+
+    // rows from the bottom of the field up to the highest filled square, not counting the squares of any live piece
+    pub fn stack_height(&self) -> usize {
+        let live = self.live_pieces().flat_map(|p| p.coords().iter().copied()).collect::<Vec<_>>();
+        let stack = self.occupied_cells().filter(|(coords, _)| !live.contains(coords));
+        stack.map(|(Coords(row, _), _)| self.lines.len() - row as usize).max().unwrap_or(0)
+    }
+
     // if after clearing lines the board is empty (used to check perfect clears)
     pub fn is_clear(&mut self) -> bool { self.lines.iter().all(|l| l.is_empty() || l.is_clear()) }
 
@@ -239,8 +830,63 @@ impl DefaultField {
         }
     }
 
-    fn set_at(&mut self, Coords(row, col): &Coords, square: Square) {
+    // change a square outside of the current piece, for setting up boards (e.g. for puzzles or `sim` scripts)
+    pub fn set_square(&mut self, coords: &Coords, square: Square) -> bool {
+        let settable = self.coords_in_bounds(coords) && !self.cur_piece.coords().contains(coords);
+        if settable {
+            self.set_at(coords, square);
+        }
+        settable
+    }
+
+    // which neighbours of the square at `coords` are part of the same piece, whether it's locked or live
+    pub fn connections_at(&self, coords @ Coords(row, col): &Coords) -> Connections {
+        match self.live_pieces().find(|p| p.coords().contains(coords)) {
+            Some(piece) => Connections::within(piece.coords(), *coords),
+            None if self.coords_in_bounds(coords) => self.lines[*row as usize].connections(*col as usize),
+            None => Connections::default(),
+        }
+    }
+
+    fn set_at(&mut self, coords @ Coords(row, col): &Coords, square: Square) {
         *self.lines[*row as usize].get_mut(*col as usize) = square;
+        self.disconnect(coords);
+        self.dirty_rows.0[*row as usize] = true;
+    }
+
+    // a changed square is no longer part of the piece it locked with
+    fn disconnect(&mut self, Coords(row, col): &Coords) {
+        let (row, col) = (*row as usize, *col as usize);
+        let connections = mem::take(&mut self.lines[row].connections[col]);
+        if connections.up && row > 0 {
+            self.lines[row - 1].connections[col].down = false;
+        }
+        if connections.down && row + 1 < self.height {
+            self.lines[row + 1].connections[col].up = false;
+        }
+        if connections.left && col > 0 {
+            self.lines[row].connections[col - 1].right = false;
+        }
+        if connections.right && col + 1 < self.width {
+            self.lines[row].connections[col + 1].left = false;
+        }
+    }
+
+    // the current piece's squares stay connected to each other once it locks
+    fn connect_cur_piece(&mut self) {
+        let piece = self.cur_piece;
+        for coords @ Coords(row, col) in piece.coords() {
+            if self.coords_in_bounds(coords) {
+                self.lines[*row as usize].connections[*col as usize] = Connections::within(piece.coords(), *coords);
+            }
+        }
+    }
+
+    // indices of rows that changed since this was last called, so renderers can redraw only those rows
+    pub fn take_dirty_rows(&mut self) -> Vec<usize> {
+        let dirty = (0..self.height).filter(|&row| self.dirty_rows.0[row]).collect();
+        self.dirty_rows.0.fill(false);
+        dirty
     }
 
     pub fn topped_out(&self) -> bool { self.topped_out }
@@ -253,7 +899,7 @@ impl DefaultField {
 
     pub fn hold_swapped(&self) -> bool { self.hold_swapped }
 
-    pub fn shadow_piece(&self) -> LivePiece { self.cur_piece.projected_down(&self) }
+    pub fn shadow_piece(&self) -> LivePiece { self.cur_piece.projected_down(self) }
 
     pub fn actions_since_lock_delay(&self) -> Option<usize> { self.lock_delay_actions }
 
@@ -261,125 +907,154 @@ impl DefaultField {
 
     pub fn last_move_rotated(&self) -> bool { self.last_move_rotated }
 
+    pub fn last_drop(&self) -> Option<&PieceDrop> { self.last_drop.as_ref() }
+
     // used to check whether to activate lock delay
     pub fn cur_piece_cannot_move_down(&self) -> bool {
-        self.cur_piece.shifted(1, 0).is_blocked(Some(&self.cur_piece), &self)
+        self.cur_piece.shifted(1, 0).is_blocked(Some(&self.cur_piece), self)
     }
 
     pub fn activate_lock_delay(&mut self) { self.lock_delay_actions.get_or_insert(0); }
 
-    fn update_lock_delay(&mut self, action: bool) -> bool {
-        if action {
+    fn update_lock_delay(&mut self, result: Result<MoveOk, MoveError>) -> Result<MoveOk, MoveError> {
+        if result.is_ok() {
             if let Some(ref mut n_actions) = self.lock_delay_actions {
                 *n_actions += 1;
             }
         }
-        action
+        result
     }
 
     // move the current piece to a different position (fails if blocked)
-    pub fn try_shift(&mut self, rows: i32, cols: i32) -> bool {
-        let action = self.try_update_cur_piece(self.cur_piece.shifted(rows, cols));
-        self.last_move_rotated &= !action;
-        self.update_lock_delay(action)
+    pub fn try_shift(&mut self, rows: i32, cols: i32) -> Result<MoveOk, MoveError> {
+        let result = self.try_update_cur_piece(self.cur_piece.shifted(rows, cols)).map(|_| MoveOk::Shifted);
+        self.last_move_rotated &= result.is_err();
+        self.update_lock_delay(result)
     }
 
-    pub fn try_rotate_cw(&mut self, kick_table: &dyn KickTable) -> bool {
+    pub fn try_rotate_cw(&mut self, kick_table: &dyn KickTable) -> Result<MoveOk, MoveError> {
         let kicks = kick_table.rotate_cw(self.cur_piece.kind(), self.cur_piece.rotation_state());
         let rotated = self.cur_piece.rotated_cw();
-        self.last_move_rotated = self.try_rotate_with_kicks(kicks, rotated);
-        self.update_lock_delay(self.last_move_rotated)
+        let result = self.try_rotate_with_kicks(kicks, rotated);
+        self.last_move_rotated = result.is_ok();
+        self.update_lock_delay(result)
     }
 
-    pub fn try_rotate_ccw(&mut self, kick_table: &dyn KickTable) -> bool {
+    pub fn try_rotate_ccw(&mut self, kick_table: &dyn KickTable) -> Result<MoveOk, MoveError> {
         let kicks = kick_table.rotate_ccw(self.cur_piece.kind(), self.cur_piece.rotation_state());
         let rotated = self.cur_piece.rotated_ccw();
-        self.last_move_rotated = self.try_rotate_with_kicks(kicks, rotated);
-        self.update_lock_delay(self.last_move_rotated)
+        let result = self.try_rotate_with_kicks(kicks, rotated);
+        self.last_move_rotated = result.is_ok();
+        self.update_lock_delay(result)
     }
 
-    pub fn try_rotate_180(&mut self, kick_table: &dyn KickTable180) -> bool {
+    pub fn try_rotate_180(&mut self, kick_table: &dyn KickTable180) -> Result<MoveOk, MoveError> {
         let kicks = kick_table.rotate_180(self.cur_piece.kind(), self.cur_piece.rotation_state());
         let rotated = self.cur_piece.rotated_180();
-        self.last_move_rotated = self.try_rotate_with_kicks(kicks, rotated);
-        self.update_lock_delay(self.last_move_rotated)
+        let result = self.try_rotate_with_kicks(kicks, rotated);
+        self.last_move_rotated = result.is_ok();
+        self.update_lock_delay(result)
     }
 
     // tries kicks on a rotated piece, swapping with the current piece if one fits
-    fn try_rotate_with_kicks(&mut self, kicks: Vec<Coords>, rotated: LivePiece) -> bool {
+    // if none fit, the error is why the unkicked rotation was blocked
+    fn try_rotate_with_kicks(&mut self, kicks: &[Coords], rotated: LivePiece) -> Result<MoveOk, MoveError> {
         kicks
-            .into_iter()
-            .map(|kick| (rotated.shifted(kick.0, kick.1), kick)) // apply kick to rotated piece
-            .find(|(piece, _)| !piece.is_blocked(Some(&self.cur_piece), &self)) // first kick that isn't blcoked
+            .iter()
+            .map(|&kick| (rotated.shifted(kick.0, kick.1), kick)) // apply kick to rotated piece
+            .find(|(piece, _)| !piece.is_blocked(Some(&self.cur_piece), self)) // first kick that isn't blcoked
             .map(|(piece, kick)| {
                 if kick != Coords(0, 0) {
                     // used for checking spins (e.g t-spins)
                     self.last_cur_piece_kick = Some(kick);
                 }
                 // update if a fitting kicked rotation exists
-                self.try_update_cur_piece(piece)
+                self.try_update_cur_piece(piece).map(|_| MoveOk::Rotated(kick))
             })
-            .unwrap_or(false)
+            .unwrap_or_else(|| Err(rotated.blocked_by(Some(&self.cur_piece), self).unwrap_or(MoveError::Stack)))
     }
 
     // tries to spawn a new piece using the provided bag, without erasing the current piece
     // behaves like locking the current piece and spawning a new one
+    // other players' pieces in the way are pushed up out of it, so only the stack can block the new piece
     pub fn try_spawn_no_erase(&mut self, bag: &mut impl Randomizer) -> bool {
         let kind = bag.next();
-        let new_piece = LivePiece::new(kind, &self.piece_origin);
-
-        let blocked = new_piece.is_blocked(None, &self);
-        if !blocked {
-            self.cur_piece = new_piece;
-            self.draw_cur_piece();
-        }
-        !blocked
+        self.without_other_pieces(|field| {
+            let new_piece = field.graced(field.spawned_piece(kind), None);
+            let blocked = new_piece.is_blocked(None, field);
+            if !blocked {
+                field.cur_piece = field.grow_to_fit(new_piece);
+                field.draw_cur_piece();
+            }
+            !blocked
+        })
     }
 
     // same as `try_spawn_no_erase` but erases the current piece
     // behaves like swapping out a hold piece
     pub fn try_spawn(&mut self, bag: &mut impl Randomizer) -> bool {
         let kind = bag.next();
-        self.try_update_cur_piece(LivePiece::new(kind, &self.piece_origin))
+        self.try_respawn_cur_piece(kind).is_ok()
     }
 
-    pub fn swap_hold_piece(&mut self, bag: &mut impl Randomizer) {
-        if !self.hold_swapped {
-            self.last_cur_piece_kick = None;
-            self.hold_swapped = true;
-            self.lock_delay_actions = None;
-
-            let hold_kind = self.hold_piece;
-            self.hold_piece = Some(self.cur_piece.kind());
+    // replace the current piece with a newly spawned one of `kind`, pushing other players' pieces up out of its way
+    fn try_respawn_cur_piece(&mut self, kind: PieceKind) -> Result<(), MoveError> {
+        self.without_other_pieces(|field| {
+            let new_piece = field.graced(field.spawned_piece(kind), Some(&field.cur_piece));
+            field.try_update_cur_piece(new_piece)
+        })
+    }
 
-            if let Some(kind) = hold_kind {
-                self.try_update_cur_piece(LivePiece::new(kind, &self.piece_origin));
-            } else {
-                self.try_spawn(bag);
-            }
+    // fails if hold was already used for this piece, or if the piece swapped in is blocked (which still uses hold)
+    pub fn swap_hold_piece(&mut self, bag: &mut impl Randomizer) -> Result<MoveOk, MoveError> {
+        if self.hold_swapped {
+            return Err(MoveError::HoldUsed);
         }
+        self.last_cur_piece_kick = None;
+        self.hold_swapped = true;
+        self.lock_delay_actions = None;
+
+        let hold_kind = self.hold_piece;
+        self.hold_piece = Some(self.cur_piece.kind());
+
+        let kind = hold_kind.unwrap_or_else(|| bag.next());
+        self.try_respawn_cur_piece(kind).map(|_| MoveOk::Held)
+    }
+
+    // respawn the current piece as one of `kind`, without drawing from the bag or using hold (e.g. for practice)
+    pub fn try_replace_cur_piece(&mut self, kind: PieceKind) -> Result<(), MoveError> {
+        self.last_cur_piece_kick = None;
+        self.lock_delay_actions = None;
+        self.try_respawn_cur_piece(kind)
     }
 
     // swap the current piece with the shadow piece
     pub fn project_down(&mut self) -> bool {
-        let projected = self.cur_piece.projected_down(&self);
+        let projected = self.cur_piece.projected_down(self);
 
         // make soft drop reset the last move rotation flag but not hard drop or soft drop without movement
         self.last_move_rotated &= self.cur_piece.coords() == projected.coords();
-        self.try_update_cur_piece(projected)
+        self.try_update_cur_piece(projected).is_ok()
     }
 
     pub fn hard_drop(&mut self, bag: &mut impl Randomizer, spin_detector: &dyn SpinDetector) -> LineClear {
         self.hold_swapped = false;
         self.lock_delay_actions = None;
 
+        let from = self.cur_piece.coords().to_vec();
         self.project_down();
+        self.last_drop = Some(PieceDrop {
+            kind: self.cur_piece.kind(),
+            from,
+            to: self.cur_piece.coords().to_vec(),
+        });
+        self.connect_cur_piece();
         let clear_type = self.clear_lines(spin_detector);
         self.last_cur_piece_kick = None;
         self.topped_out = self.cur_piece_tops_out();
 
         if !self.topped_out {
-            self.try_spawn_no_erase(bag);
+            self.topped_out = !self.try_spawn_no_erase(bag);
         }
         clear_type
     }
@@ -392,42 +1067,71 @@ impl DefaultField {
     }
 
     pub fn clear_lines(&mut self, spin_detector: &dyn SpinDetector) -> LineClear {
-        let uncleared_lines = self
-            .lines
-            .iter()
-            .filter(|l| !l.is_clear())
-            .map(|l| l.clone())
-            .collect::<Vec<_>>();
+        self.without_other_pieces(|field| field.clear_lines_under_other_pieces(spin_detector))
+    }
 
-        let n_cleared = self.height - uncleared_lines.len();
-        let clear_type = self.line_clear_type(n_cleared, spin_detector);
+    fn clear_lines_under_other_pieces(&mut self, spin_detector: &dyn SpinDetector) -> LineClear {
+        let cleared_rows = (0..self.height).filter(|&row| self.lines[row].is_clear()).collect::<Vec<_>>();
+        let clear_type = self.line_clear_type(cleared_rows.len(), spin_detector).with_cleared_rows(cleared_rows);
 
-        // pad board with empty lines
-        self.lines = (0..n_cleared).map(|_| Line::new(self.width)).collect();
-        self.lines.extend(uncleared_lines);
+        // pieces split by the clear aren't connected across it
+        for row in 0..self.height {
+            if self.lines[row].is_clear() {
+                if row > 0 {
+                    self.lines[row - 1].disconnect_down();
+                }
+                if row + 1 < self.height {
+                    self.lines[row + 1].disconnect_up();
+                }
+            }
+        }
+
+        // move uncleared lines down over the cleared ones, bottom to top, which leaves the cleared lines at the top
+        let mut dest = self.height;
+        let mut lowest_cleared = None;
+        for row in (0..self.height).rev() {
+            if self.lines[row].is_clear() {
+                lowest_cleared.get_or_insert(row);
+            } else {
+                dest -= 1;
+                self.lines.swap(row, dest);
+            }
+        }
+        for line in &mut self.lines[..dest] {
+            line.make_empty();
+        }
+
+        // every line above the lowest cleared line has moved
+        if let Some(lowest_cleared) = lowest_cleared {
+            self.dirty_rows.0[..=lowest_cleared].fill(true);
+        }
 
         clear_type
     }
 
     pub fn line_clear_type(&mut self, n_cleared: usize, spin_detector: &dyn SpinDetector) -> LineClear {
-        let (spin, is_mini) = spin_detector.detect(&self);
+        let (spin, is_mini) = spin_detector.detect(self);
         LineClear::new(n_cleared, spin, is_mini, self.is_clear())
     }
 
     // changes and redraws the current piece if the new piece isn't blocked
-    fn try_update_cur_piece(&mut self, new_piece: LivePiece) -> bool {
-        let blocked = new_piece.is_blocked(Some(&self.cur_piece), &self);
-        if !blocked {
-            self.erase_cur_piece();
-            self.draw_piece(&new_piece);
-            self.cur_piece = new_piece;
+    fn try_update_cur_piece(&mut self, new_piece: LivePiece) -> Result<(), MoveError> {
+        match new_piece.blocked_by(Some(&self.cur_piece), self) {
+            Some(error) => Err(error),
+            None => {
+                let new_piece = self.grow_to_fit(new_piece);
+                self.erase_cur_piece();
+                self.draw_piece(&new_piece);
+                self.cur_piece = new_piece;
+                Ok(())
+            }
         }
-        !blocked
     }
 
     fn erase_cur_piece(&mut self) {
-        for coords in self.cur_piece.coords().clone() {
-            self.set_at(&coords, Square::Empty);
+        let piece = self.cur_piece;
+        for coords in piece.coords() {
+            self.set_at(coords, Square::Empty);
         }
     }
 
@@ -438,8 +1142,7 @@ impl DefaultField {
     }
 
     fn draw_cur_piece(&mut self) {
-        for coords in self.cur_piece.coords().clone() {
-            self.set_at(&coords, Square::Filled(self.cur_piece.kind()));
-        }
+        let piece = self.cur_piece;
+        self.draw_piece(&piece);
     }
 }
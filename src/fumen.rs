@@ -0,0 +1,278 @@
+// boards shared as fumen strings (e.g. `v115@vhAAgH`), the format of the fumen editor used to pass setups around
+// only the first page is read or written, since that's where a setup's board and queue are
+
+use alloc::{format, string::String, vec, vec::Vec};
+
+use crate::{
+    field::{DefaultField, Square},
+    pieces::PieceKind,
+    Coords,
+};
+
+pub const FUMEN_WIDTH: usize = 10;
+
+// 23 rows of field, then a row of garbage under it waiting to rise
+const FIELD_ROWS: usize = 24;
+const FIELD_LEN: usize = FIELD_ROWS * FUMEN_WIDTH;
+
+const ENCODING_TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// comments are stored escaped, four printable ascii characters at a time
+const COMMENT_CHAR_VALUES: u32 = 96;
+
+// a page's action is its piece (kind, rotation, and position), with flags above that
+const PIECE_VALUES: u32 = 8 * 4 * FIELD_LEN as u32;
+const COLOR_FLAG: u32 = 4;
+const COMMENT_FLAG: u32 = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FumenBlock {
+    Empty,
+    I,
+    L,
+    O,
+    Z,
+    T,
+    J,
+    S,
+    Gray,
+}
+
+impl FumenBlock {
+    fn from_number(n: u32) -> Option<Self> {
+        let block = match n {
+            0 => FumenBlock::Empty,
+            1 => FumenBlock::I,
+            2 => FumenBlock::L,
+            3 => FumenBlock::O,
+            4 => FumenBlock::Z,
+            5 => FumenBlock::T,
+            6 => FumenBlock::J,
+            7 => FumenBlock::S,
+            8 => FumenBlock::Gray,
+            _ => return None,
+        };
+        Some(block)
+    }
+
+    // tetrominoes by name, with squares from any other piece and garbage being gray
+    fn from_square(square: &Square) -> Self {
+        match square {
+            Square::Empty => FumenBlock::Empty,
+            Square::Filled(kind) => match kind.display_name() {
+                "i" => FumenBlock::I,
+                "l" => FumenBlock::L,
+                "o" => FumenBlock::O,
+                "z" => FumenBlock::Z,
+                "t" => FumenBlock::T,
+                "j" => FumenBlock::J,
+                "s" => FumenBlock::S,
+                _ => FumenBlock::Gray,
+            },
+            Square::Garbage => FumenBlock::Gray,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FumenError {
+    // data from before `v115@`, or not fumen data at all
+    Version,
+    // a character outside the encoding's alphabet
+    Character(char),
+    // the data ended partway through the first page
+    Truncated,
+    // a run of blocks going past the end of the field, or a block number that doesn't exist
+    Field,
+}
+
+// the first page of a fumen
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FumenPage {
+    // top row first, with the garbage row last
+    pub blocks: Vec<FumenBlock>,
+    pub comment: String,
+}
+
+impl FumenPage {
+    // rows of the field from the highest one with a block in it, in the ascii format used by `sim`
+    pub fn board(&self) -> String {
+        let rows = self.blocks[..FIELD_LEN - FUMEN_WIDTH].chunks(FUMEN_WIDTH);
+        let rows = rows.skip_while(|row| row.iter().all(|b| *b == FumenBlock::Empty));
+
+        let mut board = String::new();
+        for row in rows {
+            board.extend(row.iter().map(|b| if *b == FumenBlock::Empty { '.' } else { '#' }));
+            board.push('\n');
+        }
+        board
+    }
+
+    // pieces from a quiz comment (e.g. `#Q=[S](T)IOZ`), as hold then the current piece then the next pieces
+    // starting with the held piece plays the same as the quiz, since holding it brings the current piece out
+    pub fn quiz_queue(&self) -> Option<String> {
+        let quiz = self.comment.strip_prefix("#Q=[")?;
+        let (hold, rest) = quiz.split_once("](")?;
+        let (cur, next) = rest.split_once(')')?;
+        Some([hold, cur, next].concat())
+    }
+}
+
+// reads the first page from fumen data, which can be given on its own or as part of a link to the editor
+pub fn decode(text: &str) -> Result<FumenPage, FumenError> {
+    let (_, data) = text.trim().split_once("115@").ok_or(FumenError::Version)?;
+    let mut values = data
+        .chars()
+        .filter(|&c| c != '?')
+        .map(|c| match ENCODING_TABLE.iter().position(|&e| e as char == c) {
+            Some(value) => Ok(value as u32),
+            None => Err(FumenError::Character(c)),
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter();
+    let mut poll = |n_chars: u32| {
+        (0..n_chars).try_fold(0, |value, i| {
+            Ok(value + values.next().ok_or(FumenError::Truncated)? * 64u32.pow(i))
+        })
+    };
+
+    // runs of blocks, each as a block number and a length
+    let mut blocks = vec![];
+    while blocks.len() < FIELD_LEN {
+        let run = poll(2)?;
+        let (block, len) = (run / FIELD_LEN as u32, run as usize % FIELD_LEN + 1);
+        // the first page is drawn over an empty field, so anything below zero isn't a block
+        let block = block
+            .checked_sub(8)
+            .and_then(FumenBlock::from_number)
+            .ok_or(FumenError::Field)?;
+        if blocks.len() + len > FIELD_LEN {
+            return Err(FumenError::Field);
+        }
+        blocks.extend((0..len).map(|_| block));
+    }
+    // an empty field is followed by how many pages after it are also empty
+    if blocks.iter().all(|b| *b == FumenBlock::Empty) {
+        poll(1)?;
+    }
+
+    // the piece on the page and its flags, of which only whether there's a comment matters here
+    let action = poll(3)?;
+    let has_comment = (action / PIECE_VALUES) & COMMENT_FLAG != 0;
+
+    let mut comment = String::new();
+    if has_comment {
+        let len = poll(2)? as usize;
+        let mut escaped = String::new();
+        for _ in 0..len.div_ceil(4) {
+            let mut value = poll(5)?;
+            for _ in 0..4 {
+                escaped.push(char::from_u32(value % COMMENT_CHAR_VALUES + ' ' as u32).unwrap_or(' '));
+                value /= COMMENT_CHAR_VALUES;
+            }
+        }
+        escaped.truncate(len);
+        comment = unescape(&escaped);
+    }
+
+    Ok(FumenPage { blocks, comment })
+}
+
+// the stack of `field` (without its live pieces) as fumen data with one page, keeping the bottom rows and left
+// columns that fit in the editor's field
+pub fn encode(field: &DefaultField, comment: &str) -> String {
+    let live = field
+        .live_pieces()
+        .flat_map(|p| p.coords().iter().copied())
+        .collect::<Vec<_>>();
+
+    // the bottom row of the field goes just above the garbage row
+    let mut blocks = vec![FumenBlock::Empty; FIELD_LEN];
+    let first_row = field.height().saturating_sub(FIELD_ROWS - 1);
+    for (row, squares) in field.rows().enumerate().skip(first_row) {
+        let start = FIELD_LEN - FUMEN_WIDTH * (field.height() - row + 1);
+        for (col, square) in squares.iter().enumerate().take(FUMEN_WIDTH) {
+            if !live.contains(&Coords(row as i32, col as i32)) {
+                blocks[start + col] = FumenBlock::from_square(square);
+            }
+        }
+    }
+
+    let mut data = String::from("v115@");
+    let mut push = |value: u32, n_chars: u32| {
+        let digits = (0..n_chars).map(|i| ENCODING_TABLE[(value / 64u32.pow(i) % 64) as usize] as char);
+        data.extend(digits);
+    };
+
+    let mut runs = blocks.as_slice();
+    while let Some(&block) = runs.first() {
+        let len = runs.iter().take_while(|&&b| b == block).count();
+        push((block as u32 + 8) * FIELD_LEN as u32 + len as u32 - 1, 2);
+        runs = &runs[len..];
+    }
+    if blocks.iter().all(|b| *b == FumenBlock::Empty) {
+        push(0, 1);
+    }
+
+    // no piece, with the field in color like the editor draws it by default
+    let escaped = escape(comment);
+    let flags = COLOR_FLAG | if escaped.is_empty() { 0 } else { COMMENT_FLAG };
+    push(flags * PIECE_VALUES, 3);
+
+    if !escaped.is_empty() {
+        push(escaped.len() as u32, 2);
+        for chars in escaped.as_bytes().chunks(4) {
+            let value = chars
+                .iter()
+                .rev()
+                .fold(0, |value, &c| value * COMMENT_CHAR_VALUES + (c - b' ') as u32);
+            push(value, 5);
+        }
+    }
+    data
+}
+
+// a quiz comment giving the queue, like the editor writes them (e.g. `#Q=[S](T)IOZ`)
+pub fn quiz(hold: Option<PieceKind>, cur: PieceKind, next: impl IntoIterator<Item = PieceKind>) -> String {
+    let name = |kind: PieceKind| kind.display_name().to_ascii_uppercase();
+    let next = next.into_iter().map(name).collect::<String>();
+    format!("#Q=[{}]({}){}", hold.map(name).unwrap_or_default(), name(cur), next)
+}
+
+// javascript's `escape`, which comments are stored with
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '@' | '*' | '_' | '+' | '-' | '.' | '/' => escaped.push(c),
+            _ if (c as u32) < 0x100 => escaped.push_str(&format!("%{:02X}", c as u32)),
+            _ => escaped.push_str(&format!("%u{:04X}", c as u32)),
+        }
+    }
+    escaped
+}
+
+// undoes javascript's `escape`, which writes characters as `%xx` or `%uxxxx`
+fn unescape(escaped: &str) -> String {
+    let mut text = String::new();
+    let mut rest = escaped;
+    while let Some(i) = rest.find('%') {
+        text.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+
+        let (start, end) = if rest.starts_with('u') { (1, 5) } else { (0, 2) };
+        match rest
+            .get(start..end)
+            .and_then(|d| u32::from_str_radix(d, 16).ok())
+            .and_then(char::from_u32)
+        {
+            Some(c) => {
+                text.push(c);
+                rest = &rest[end..];
+            }
+            None => text.push('%'),
+        }
+    }
+    text.push_str(rest);
+    text
+}
@@ -0,0 +1,487 @@
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+};
+
+use crate::{
+    attack::{AttackState, AttackTable, ComboTable},
+    curves::{Speed, SpeedCurve},
+    field::{DefaultField, LineClear, MoveError, MoveOk, PieceCoords},
+    garbage::{GarbageGenerator, GarbageQueue, GarbageRules},
+    kicks::{BasicKickTable, KickTable, KickTable180, RotationState, SrsKickTable, TetrIo180KickTable},
+    spins::{SpinDetector, TSpinDetector},
+    ticks::{Tick, TickRate},
+    Coords, PieceKind, QueuePolicy, Randomizer,
+};
+
+// which rotations a piece is allowed to make
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rotations {
+    pub cw: bool,
+    pub ccw: bool,
+    pub half: bool,
+}
+
+impl Rotations {
+    pub const ALL: Rotations = Rotations {
+        cw: true,
+        ccw: true,
+        half: true,
+    };
+    pub const NONE: Rotations = Rotations {
+        cw: false,
+        ccw: false,
+        half: false,
+    };
+    pub const CW: Rotations = Rotations {
+        cw: true,
+        ccw: false,
+        half: false,
+    };
+}
+
+// inputs a game allows, for modes that leave some out
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Controls {
+    // rotations for pieces without their own in `GameRules::piece_rules`
+    pub rotations: Rotations,
+    pub hold: bool,
+    pub hard_drop: bool,
+}
+
+impl Controls {
+    pub const ALL: Controls = Controls {
+        rotations: Rotations::ALL,
+        hold: true,
+        hard_drop: true,
+    };
+
+    // rotation in one direction only, and no hold or hard drop
+    pub const LIMITED: Controls = Controls {
+        rotations: Rotations::CW,
+        hold: false,
+        hard_drop: false,
+    };
+}
+
+impl Default for Controls {
+    fn default() -> Self { Controls::ALL }
+}
+
+// rules for one piece kind that replace the game's usual ones, with `None` leaving them as they are
+// delays are in milliseconds, like in `Speed`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PieceRules {
+    pub gravity_delay: Option<f64>,
+    pub lock_delay: Option<f64>,
+    pub rotations: Option<Rotations>,
+}
+
+// rules a game is played with
+#[derive(Clone)]
+pub struct GameRules {
+    pub kick_table: &'static dyn KickTable,
+    pub kick_table_180: &'static dyn KickTable180,
+    pub spin_detector: &'static dyn SpinDetector,
+    // gravity and delays for games that speed up, like marathon
+    pub speed_curve: Option<&'static dyn SpeedCurve>,
+    pub attack_table: AttackTable,
+    pub combo_table: ComboTable,
+    pub garbage_rules: GarbageRules,
+    // how finely the game's clock measures time (see `Game::advance`)
+    pub tick_rate: TickRate,
+    // overrides for specific piece kinds (e.g. an o piece that never rotates), for custom modes
+    pub piece_rules: BTreeMap<PieceKind, PieceRules>,
+    pub controls: Controls,
+    // rows a piece spawning blocked may be moved up before it blocks out
+    pub spawn_grace_rows: usize,
+}
+
+impl GameRules {
+    // limited rotation without kicks, and no hold or hard drop
+    // this isn't a faithful classic game, which would also need nes randomizer and rotation system
+    pub fn limited() -> Self {
+        GameRules {
+            kick_table: &BasicKickTable,
+            kick_table_180: &BasicKickTable,
+            controls: Controls::LIMITED,
+            ..GameRules::default()
+        }
+    }
+
+    pub fn piece_rules(&self, kind: PieceKind) -> PieceRules {
+        self.piece_rules.get(&kind).copied().unwrap_or_default()
+    }
+
+    pub fn set_piece_rules(&mut self, kind: PieceKind, rules: PieceRules) { self.piece_rules.insert(kind, rules); }
+
+    pub fn rotations(&self, kind: PieceKind) -> Rotations {
+        self.piece_rules(kind).rotations.unwrap_or(self.controls.rotations)
+    }
+
+    // `speed` with any gravity or lock delay overrides for `kind` applied
+    pub fn piece_speed(&self, kind: PieceKind, speed: Speed) -> Speed {
+        let rules = self.piece_rules(kind);
+        Speed {
+            gravity_delay: rules.gravity_delay.unwrap_or(speed.gravity_delay),
+            lock_delay: rules.lock_delay.unwrap_or(speed.lock_delay),
+            ..speed
+        }
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            kick_table: &SrsKickTable,
+            kick_table_180: &TetrIo180KickTable,
+            spin_detector: &TSpinDetector,
+            speed_curve: None,
+            attack_table: AttackTable::default(),
+            combo_table: ComboTable::default(),
+            garbage_rules: GarbageRules::default(),
+            tick_rate: TickRate::default(),
+            piece_rules: BTreeMap::new(),
+            controls: Controls::default(),
+            spawn_grace_rows: 0,
+        }
+    }
+}
+
+// a piece as it locked
+#[derive(Copy, Clone, Debug)]
+pub struct Placement {
+    pub kind: PieceKind,
+    pub coords: PieceCoords,
+    pub rotation_state: RotationState,
+    pub tick: Tick,
+}
+
+// the most recent placements in a game, oldest first, for things like heatmaps and finesse review
+#[derive(Clone, Debug)]
+pub struct PlacementHistory {
+    placements: VecDeque<Placement>,
+    capacity: usize,
+}
+
+impl PlacementHistory {
+    // keeps at most `capacity` placements, dropping the oldest ones
+    pub fn new(capacity: usize) -> Self {
+        PlacementHistory {
+            placements: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, placement: Placement) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.placements.len() == self.capacity {
+            self.placements.pop_front();
+        }
+        self.placements.push_back(placement);
+    }
+
+    pub fn placements(&self) -> impl DoubleEndedIterator<Item = &Placement> + '_ { self.placements.iter() }
+
+    pub fn last(&self) -> Option<&Placement> { self.placements.back() }
+
+    pub fn len(&self) -> usize { self.placements.len() }
+
+    pub fn is_empty(&self) -> bool { self.placements.is_empty() }
+
+    pub fn clear(&mut self) { self.placements.clear(); }
+}
+
+// a move of the current piece, by the rows and columns it moved (or was kicked, for rotations)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PieceMotion {
+    pub delta: Coords,
+    pub tick: Tick,
+}
+
+// a field along with the randomizer and rules used to play on it
+// this saves callers from passing the bag, kick tables, and spin detector into every field method
+pub struct Game<R: Randomizer> {
+    field: DefaultField,
+    bag: R,
+    rules: GameRules,
+    // where received garbage puts its holes, kept apart from the bag (see `receive_garbage`)
+    garbage: Option<Box<dyn GarbageGenerator>>,
+    pending_garbage: GarbageQueue,
+    attack_state: AttackState,
+    // only recorded if enabled, since most games don't need it
+    history: Option<PlacementHistory>,
+    // the game's clock, which only moves when told to so that events happen at the same ticks in replays
+    tick: Tick,
+    // when the current piece last moved down and how it last moved, for renderers to animate it between squares
+    last_fall: Tick,
+    last_motion: Option<PieceMotion>,
+}
+
+impl<R: Randomizer> Game<R> {
+    pub fn new(width: usize, height: usize, hidden: usize, kinds: &[PieceKind], mut bag: R, rules: GameRules) -> Self {
+        let mut field = DefaultField::new(width, height, hidden, kinds, &mut bag);
+        field.set_spawn_grace_rows(rules.spawn_grace_rows);
+        Game {
+            field,
+            bag,
+            rules,
+            garbage: None,
+            pending_garbage: GarbageQueue::new(),
+            attack_state: AttackState::new(),
+            history: None,
+            tick: 0,
+            last_fall: 0,
+            last_motion: None,
+        }
+    }
+
+    pub fn field(&self) -> &DefaultField { &self.field }
+
+    pub fn field_mut(&mut self) -> &mut DefaultField { &mut self.field }
+
+    pub fn bag(&self) -> &R { &self.bag }
+
+    pub fn bag_mut(&mut self) -> &mut R { &mut self.bag }
+
+    pub fn rules(&self) -> &GameRules { &self.rules }
+
+    pub fn set_rules(&mut self, rules: GameRules) {
+        self.field.set_spawn_grace_rows(rules.spawn_grace_rows);
+        self.rules = rules;
+    }
+
+    pub fn tick(&self) -> Tick { self.tick }
+
+    pub fn advance(&mut self, n_ticks: Tick) { self.tick += n_ticks; }
+
+    // move the clock forward to `tick`, doing nothing if it's already past it
+    pub fn advance_to(&mut self, tick: Tick) { self.tick = self.tick.max(tick); }
+
+    pub fn shift(&mut self, rows: i32, cols: i32) -> Result<MoveOk, MoveError> {
+        let result = self.field.try_shift(rows, cols);
+        if result.is_ok() {
+            self.record_motion(Coords(rows, cols));
+        }
+        result
+    }
+
+    // rotations fail without moving the piece if its kind isn't allowed to make them
+    pub fn rotate_cw(&mut self) -> Result<MoveOk, MoveError> {
+        allowed(self.cur_rotations().cw)?;
+        let result = self.field.try_rotate_cw(self.rules.kick_table);
+        self.record_rotation(result)
+    }
+
+    pub fn rotate_ccw(&mut self) -> Result<MoveOk, MoveError> {
+        allowed(self.cur_rotations().ccw)?;
+        let result = self.field.try_rotate_ccw(self.rules.kick_table);
+        self.record_rotation(result)
+    }
+
+    pub fn rotate_180(&mut self) -> Result<MoveOk, MoveError> {
+        allowed(self.cur_rotations().half)?;
+        let result = self.field.try_rotate_180(self.rules.kick_table_180);
+        self.record_rotation(result)
+    }
+
+    pub fn swap_hold(&mut self) -> Result<MoveOk, MoveError> {
+        allowed(self.rules.controls.hold)?;
+        let result = self.field.swap_hold_piece(&mut self.bag);
+        if result.is_ok() {
+            self.reset_motion();
+        }
+        result
+    }
+
+    // move the current piece as far down as possible without locking it
+    pub fn sonic_drop(&mut self) -> bool {
+        let row = self.field.cur_piece().coords()[0].0;
+        let dropped = self.field.project_down();
+        let rows = self.field.cur_piece().coords()[0].0 - row;
+        if rows > 0 {
+            self.record_motion(Coords(rows, 0));
+        }
+        dropped
+    }
+
+    // how far the current piece is towards falling another row under the gravity of `speed`, from 0 to 1
+    // this is zero while the piece is on the ground, so renderers can draw it this far below its row
+    pub fn gravity_progress(&self, speed: Speed) -> f64 {
+        let gravity_delay = self.cur_speed(speed).gravity_delay;
+        if gravity_delay <= 0. || self.field.cur_piece_cannot_move_down() {
+            return 0.;
+        }
+        let elapsed = self.rules.tick_rate.ms(self.tick.saturating_sub(self.last_fall));
+        (elapsed / gravity_delay).min(1.)
+    }
+
+    // the current piece's most recent movement, which is `None` for a piece that hasn't moved since spawning
+    pub fn last_motion(&self) -> Option<PieceMotion> { self.last_motion }
+
+    // co-op players share the bag, and the active player is the one the other methods move
+    pub fn add_player(&mut self, spawn_offset: i32) -> Option<usize> {
+        self.field.add_player(spawn_offset, &mut self.bag)
+    }
+
+    pub fn set_active_player(&mut self, player: usize) {
+        self.field.set_active_player(player);
+        self.reset_motion();
+    }
+
+    pub fn set_kinds(&mut self, kinds: &[PieceKind], policy: QueuePolicy) {
+        self.bag.set_kinds(kinds.to_vec(), policy);
+        self.field.set_kinds(kinds, &mut self.bag);
+    }
+
+    pub fn add_garbage(&mut self, n_rows: usize, generator: &mut dyn GarbageGenerator) {
+        self.field.add_garbage(n_rows, generator);
+    }
+
+    pub fn set_garbage_generator(&mut self, generator: Box<dyn GarbageGenerator>) { self.garbage = Some(generator); }
+
+    // add garbage rows with holes from the game's own generator, failing if it doesn't have one
+    // the generator is only used here, so replays get the same holes even if pieces are held or placed differently
+    pub fn receive_garbage(&mut self, n_rows: usize) -> bool {
+        match self.garbage {
+            Some(ref mut generator) => {
+                self.field.add_garbage(n_rows, generator.as_mut());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn attack_state(&self) -> &AttackState { &self.attack_state }
+
+    // start recording up to `capacity` placements, replacing any history recorded so far
+    pub fn enable_history(&mut self, capacity: usize) { self.history = Some(PlacementHistory::new(capacity)); }
+
+    pub fn history(&self) -> Option<&PlacementHistory> { self.history.as_ref() }
+
+    // hard drop from a player's input, which fails if the rules don't allow it
+    pub fn try_hard_drop(&mut self) -> Option<LineClear> { self.rules.controls.hard_drop.then(|| self.hard_drop()) }
+
+    // lock the current piece whatever the rules' controls (e.g. when its lock delay runs out), then let in any
+    // pending garbage the rules allow using the game's garbage generator
+    // the returned line clear includes its attack, which isn't sent anywhere until passed to `send_attack`
+    pub fn hard_drop(&mut self) -> LineClear {
+        if let Some(ref mut history) = self.history {
+            let piece = self.field.shadow_piece();
+            history.push(Placement {
+                kind: piece.kind(),
+                coords: piece.coords().iter().copied().collect(),
+                rotation_state: piece.rotation_state(),
+                tick: self.tick,
+            });
+        }
+
+        let line_clear = self.field.hard_drop(&mut self.bag, self.rules.spin_detector);
+        let attack = self
+            .attack_state
+            .update(&line_clear, &self.rules.attack_table, &self.rules.combo_table);
+        let line_clear = line_clear.with_attack(attack).with_tick(self.tick);
+
+        let (n_lines, rules) = (line_clear.n_lines(), &self.rules.garbage_rules);
+        let entering = self.pending_garbage.take_entering(n_lines, self.tick, rules);
+        for n_rows in entering {
+            self.receive_garbage(n_rows as usize);
+        }
+        self.reset_motion();
+        line_clear
+    }
+
+    pub fn pending_garbage(&self) -> &GarbageQueue { &self.pending_garbage }
+
+    // queue an attack of `n_rows` received now, to enter the field when the rules allow it
+    pub fn queue_garbage(&mut self, n_rows: u32) { self.pending_garbage.push(n_rows, self.tick); }
+
+    // lines of an attack left to send after cancelling pending garbage
+    pub fn send_attack(&mut self, attack: u32) -> u32 { self.pending_garbage.cancel(attack, &self.rules.garbage_rules) }
+
+    // gravity and delays for the current piece, starting from `speed`
+    pub fn cur_speed(&self, speed: Speed) -> Speed { self.rules.piece_speed(self.field.cur_piece().kind(), speed) }
+
+    fn cur_rotations(&self) -> Rotations { self.rules.rotations(self.field.cur_piece().kind()) }
+
+    fn record_rotation(&mut self, result: Result<MoveOk, MoveError>) -> Result<MoveOk, MoveError> {
+        if let Ok(MoveOk::Rotated(kick)) = result {
+            self.record_motion(kick);
+        }
+        result
+    }
+
+    fn record_motion(&mut self, delta: Coords) {
+        self.last_motion = Some(PieceMotion { delta, tick: self.tick });
+        if delta.0 > 0 {
+            self.last_fall = self.tick;
+        }
+    }
+
+    // a new piece (or a different player's piece) starts out still
+    fn reset_motion(&mut self) {
+        self.last_motion = None;
+        self.last_fall = self.tick;
+    }
+}
+
+impl<R: Randomizer + Clone> Game<R> {
+    // the game's state as of now, to go back to later with `restore`
+    pub fn snapshot(&self) -> GameSnapshot<R> {
+        GameSnapshot(Arc::new(SnapshotState {
+            field: self.field.clone(),
+            bag: self.bag.clone(),
+            garbage: self.garbage.clone(),
+            pending_garbage: self.pending_garbage.clone(),
+            attack_state: self.attack_state,
+            history: self.history.clone(),
+            tick: self.tick,
+        }))
+    }
+
+    // go back to the state of a snapshot, keeping the current rules
+    pub fn restore(&mut self, snapshot: &GameSnapshot<R>) {
+        let state = &*snapshot.0;
+        self.field = state.field.clone();
+        self.bag = state.bag.clone();
+        self.garbage = state.garbage.clone();
+        self.pending_garbage = state.pending_garbage.clone();
+        self.attack_state = state.attack_state;
+        self.history = state.history.clone();
+        self.tick = state.tick;
+        self.field.set_spawn_grace_rows(self.rules.spawn_grace_rows);
+        self.reset_motion();
+    }
+}
+
+// everything about a game that changes during play, for rewinding, speculative search, and rollback netcode
+// snapshots are shared rather than copied when cloned, so keeping many of them around is cheap
+#[derive(Clone)]
+pub struct GameSnapshot<R: Randomizer>(Arc<SnapshotState<R>>);
+
+impl<R: Randomizer> GameSnapshot<R> {
+    pub fn field(&self) -> &DefaultField { &self.0.field }
+
+    pub fn tick(&self) -> Tick { self.0.tick }
+}
+
+struct SnapshotState<R: Randomizer> {
+    field: DefaultField,
+    bag: R,
+    garbage: Option<Box<dyn GarbageGenerator>>,
+    pending_garbage: GarbageQueue,
+    attack_state: AttackState,
+    history: Option<PlacementHistory>,
+    tick: Tick,
+}
+
+fn allowed(allowed: bool) -> Result<(), MoveError> {
+    if allowed {
+        Ok(())
+    } else {
+        Err(MoveError::Disallowed)
+    }
+}
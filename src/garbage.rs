@@ -0,0 +1,270 @@
+// where the holes go in garbage rows sent to a field
+// generators are seeded so both players in a match can be given the same garbage
+// each one has its own rng, so hole columns don't depend on the pieces drawn or how they were placed
+
+use alloc::{boxed::Box, collections::VecDeque, vec, vec::Vec};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    ticks::{Tick, TickRate},
+    RandomSource,
+};
+
+pub trait GarbageGenerator: CloneGarbageGenerator {
+    // hole column of each of `n_rows` new garbage rows, from top to bottom, for a field `width` squares wide
+    fn holes(&mut self, n_rows: usize, width: usize) -> Vec<usize>;
+}
+
+// lets boxed generators be cloned (e.g. for game snapshots), implemented for every generator that is `Clone`
+pub trait CloneGarbageGenerator {
+    fn boxed_clone(&self) -> Box<dyn GarbageGenerator>;
+}
+
+impl<G: GarbageGenerator + Clone + 'static> CloneGarbageGenerator for G {
+    fn boxed_clone(&self) -> Box<dyn GarbageGenerator> { Box::new(self.clone()) }
+}
+
+impl Clone for Box<dyn GarbageGenerator> {
+    fn clone(&self) -> Self { self.boxed_clone() }
+}
+
+// seeded bags use the default stream, so a match can give its bags and garbage the same seed without the holes
+// following the pieces
+const GARBAGE_STREAM: u64 = 1;
+
+fn seeded_rng(seed: u64) -> Box<dyn RandomSource + Send + Sync> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(GARBAGE_STREAM);
+    Box::new(rng)
+}
+
+// every row in an attack has its hole in the same column, which changes between attacks
+#[derive(Clone)]
+pub struct CleanGarbage {
+    rng: Box<dyn RandomSource + Send + Sync>,
+}
+
+impl CleanGarbage {
+    pub fn new(seed: u64) -> Self { CleanGarbage { rng: seeded_rng(seed) } }
+}
+
+impl GarbageGenerator for CleanGarbage {
+    fn holes(&mut self, n_rows: usize, width: usize) -> Vec<usize> { vec![self.rng.gen_index(width); n_rows] }
+}
+
+// each row has a chance to move its hole to a different column from the row below
+// a messiness of 0 gives one clean column for the whole game, and 1 moves the hole every row
+#[derive(Clone)]
+pub struct MessyGarbage {
+    rng: Box<dyn RandomSource + Send + Sync>,
+    messiness: f64,
+    last_hole: Option<usize>,
+}
+
+impl MessyGarbage {
+    pub fn new(seed: u64, messiness: f64) -> Self {
+        MessyGarbage {
+            rng: seeded_rng(seed),
+            messiness: messiness.clamp(0., 1.),
+            last_hole: None,
+        }
+    }
+
+    // uniformly random number in `0..1`
+    fn gen_chance(&mut self) -> f64 {
+        const STEPS: usize = 1 << 16;
+        self.rng.gen_index(STEPS) as f64 / STEPS as f64
+    }
+}
+
+impl GarbageGenerator for MessyGarbage {
+    fn holes(&mut self, n_rows: usize, width: usize) -> Vec<usize> {
+        let mut holes = (0..n_rows)
+            .map(|_| {
+                let hole = match self.last_hole.filter(|&hole| hole < width) {
+                    Some(hole) if self.gen_chance() >= self.messiness => hole,
+                    // always pick a different column when the hole moves, if there is one
+                    Some(hole) if width > 1 => (hole + 1 + self.rng.gen_index(width - 1)) % width,
+                    _ => self.rng.gen_index(width),
+                };
+                self.last_hole = Some(hole);
+                hole
+            })
+            .collect::<Vec<_>>();
+
+        // rows are generated from the bottom up so each one follows the row below it
+        holes.reverse();
+        holes
+    }
+}
+
+// every row has its hole in a uniformly random column
+#[derive(Clone)]
+pub struct RandomGarbage {
+    rng: Box<dyn RandomSource + Send + Sync>,
+}
+
+impl RandomGarbage {
+    pub fn new(seed: u64) -> Self { RandomGarbage { rng: seeded_rng(seed) } }
+}
+
+impl GarbageGenerator for RandomGarbage {
+    fn holes(&mut self, n_rows: usize, width: usize) -> Vec<usize> {
+        (0..n_rows).map(|_| self.rng.gen_index(width)).collect()
+    }
+}
+
+// holes taken from a fixed pattern, repeating it once it runs out (e.g. for drills or cheese races)
+// columns are wrapped to fit the field
+#[derive(Clone)]
+pub struct ScriptedGarbage {
+    pattern: Vec<usize>,
+    next: usize,
+}
+
+impl ScriptedGarbage {
+    // panics if `pattern` is empty
+    pub fn new(pattern: Vec<usize>) -> Self {
+        assert!(!pattern.is_empty(), "garbage pattern must not be empty");
+        ScriptedGarbage { pattern, next: 0 }
+    }
+}
+
+impl GarbageGenerator for ScriptedGarbage {
+    fn holes(&mut self, n_rows: usize, width: usize) -> Vec<usize> {
+        (0..n_rows)
+            .map(|_| {
+                let hole = self.pattern[self.next] % width;
+                self.next = (self.next + 1) % self.pattern.len();
+                hole
+            })
+            .collect()
+    }
+}
+
+// when pending garbage rises into the field
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GarbageEntry {
+    // all pending garbage enters when a piece locks without clearing lines, so clears hold it back
+    Immediate,
+    // like `Immediate`, but only for garbage that has been pending for at least this many ticks
+    Delayed(Tick),
+    // all pending garbage enters whenever a piece locks, whether or not it cleared lines
+    BetweenPieces,
+}
+
+// what attacks do while garbage is pending
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GarbageCancelling {
+    // attacks cancel pending garbage first, and only what's left is sent
+    Cancel,
+    // attacks are sent in full, and pending garbage still comes in
+    PassThrough,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GarbageRules {
+    pub entry: GarbageEntry,
+    pub cancelling: GarbageCancelling,
+    // most rows that can enter after a single piece, with the rest staying pending
+    pub cap: Option<u32>,
+}
+
+impl GarbageRules {
+    pub fn guideline() -> Self {
+        GarbageRules {
+            entry: GarbageEntry::Immediate,
+            cancelling: GarbageCancelling::Cancel,
+            cap: None,
+        }
+    }
+
+    // tetr.io's defaults, with a 20 frame garbage delay and at most 8 rows at once
+    pub fn tetrio(tick_rate: TickRate) -> Self {
+        GarbageRules {
+            entry: GarbageEntry::Delayed(tick_rate.ticks(20. * 1_000. / 60.)),
+            cancelling: GarbageCancelling::Cancel,
+            cap: Some(8),
+        }
+    }
+}
+
+impl Default for GarbageRules {
+    fn default() -> Self { GarbageRules::guideline() }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct PendingAttack {
+    n_rows: u32,
+    received: Tick,
+}
+
+// garbage received from opponents that hasn't entered the field yet, oldest first
+#[derive(Clone, Debug, Default)]
+pub struct GarbageQueue {
+    pending: VecDeque<PendingAttack>,
+}
+
+impl GarbageQueue {
+    pub fn new() -> Self { GarbageQueue::default() }
+
+    pub fn push(&mut self, n_rows: u32, received: Tick) {
+        if n_rows > 0 {
+            self.pending.push_back(PendingAttack { n_rows, received });
+        }
+    }
+
+    pub fn n_pending(&self) -> u32 { self.pending.iter().fold(0, |n, a| n.saturating_add(a.n_rows)) }
+
+    pub fn is_empty(&self) -> bool { self.pending.is_empty() }
+
+    pub fn clear(&mut self) { self.pending.clear(); }
+
+    // lines of `attack` left to send to the opponent after cancelling pending garbage (oldest first)
+    pub fn cancel(&mut self, mut attack: u32, rules: &GarbageRules) -> u32 {
+        if rules.cancelling == GarbageCancelling::PassThrough {
+            return attack;
+        }
+
+        while let Some(oldest) = self.pending.front_mut() {
+            if attack < oldest.n_rows {
+                oldest.n_rows -= attack;
+                return 0;
+            }
+            attack -= oldest.n_rows;
+            self.pending.pop_front();
+        }
+        attack
+    }
+
+    // sizes of the attacks entering the field after a piece locks at `tick` having cleared `n_lines` lines
+    // an attack cut off by the cap is split, with the rest of it staying pending
+    pub fn take_entering(&mut self, n_lines: usize, tick: Tick, rules: &GarbageRules) -> Vec<u32> {
+        if n_lines > 0 && rules.entry != GarbageEntry::BetweenPieces {
+            return vec![];
+        }
+        let min_pending_ticks = match rules.entry {
+            GarbageEntry::Delayed(delay) => delay,
+            _ => 0,
+        };
+
+        let mut n_left = rules.cap.unwrap_or(u32::MAX);
+        let mut entering = vec![];
+        while let Some(oldest) = self.pending.front_mut() {
+            if n_left == 0 || tick.saturating_sub(oldest.received) < min_pending_ticks {
+                break;
+            }
+
+            let n_rows = oldest.n_rows.min(n_left);
+            entering.push(n_rows);
+            n_left -= n_rows;
+            oldest.n_rows -= n_rows;
+            if oldest.n_rows == 0 {
+                self.pending.pop_front();
+            }
+        }
+        entering
+    }
+}
@@ -1,5 +1,3 @@
-use std::ops;
-
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 
@@ -8,7 +6,7 @@ use crate::{
     Coords,
 };
 
-#[derive(Clone, Copy, FromPrimitive, ToPrimitive)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromPrimitive, ToPrimitive)]
 pub enum RotationState {
     Initial,
     Cw,
@@ -23,68 +21,90 @@ impl RotationState {
 }
 
 // cw/ccw kick table
-pub trait KickTable {
-    fn rotate_cw(&self, piece: PieceKind, rotation_state: RotationState) -> Vec<Coords>;
+pub trait KickTable: Sync {
+    fn rotate_cw(&self, piece: PieceKind, rotation_state: RotationState) -> &'static [Coords];
 
-    fn rotate_ccw(&self, piece: PieceKind, rotation_state: RotationState) -> Vec<Coords>;
+    fn rotate_ccw(&self, piece: PieceKind, rotation_state: RotationState) -> &'static [Coords];
 }
 
-pub trait KickTable180 {
-    fn rotate_180(&self, piece: PieceKind, rotation_state: RotationState) -> Vec<Coords>;
+pub trait KickTable180: Sync {
+    fn rotate_180(&self, piece: PieceKind, rotation_state: RotationState) -> &'static [Coords];
 }
 
 // kicks left, right, or up by one square
 pub struct BasicKickTable;
 
 impl KickTable for BasicKickTable {
-    fn rotate_cw(&self, _: PieceKind, _: RotationState) -> Vec<Coords> {
-        vec![Coords(0, 0), Coords(0, -1), Coords(0, 1), Coords(-1, 0)]
+    fn rotate_cw(&self, _: PieceKind, _: RotationState) -> &'static [Coords] {
+        coords![(0, 0), (0, -1), (0, 1), (-1, 0)]
     }
 
-    fn rotate_ccw(&self, piece: PieceKind, rotation_state: RotationState) -> Vec<Coords> {
+    fn rotate_ccw(&self, piece: PieceKind, rotation_state: RotationState) -> &'static [Coords] {
         self.rotate_cw(piece, rotation_state)
     }
 }
 
 impl KickTable180 for BasicKickTable {
-    fn rotate_180(&self, piece: PieceKind, rotation_state: RotationState) -> Vec<Coords> {
+    fn rotate_180(&self, piece: PieceKind, rotation_state: RotationState) -> &'static [Coords] {
         self.rotate_cw(piece, rotation_state)
     }
 }
 
+// srs kicks indexed by the rotation state being rotated from
+// the ccw kicks from a rotation state are the negated cw kicks from the state ccw of it
+const SRS_I_CW: [&[Coords]; 4] = [
+    coords![(0, 0), (0, -2), (0, 1), (1, -2), (-2, 1)],
+    coords![(0, 0), (0, -1), (0, 2), (-2, -1), (1, 2)],
+    coords![(0, 0), (0, 2), (0, -1), (-1, 2), (2, -1)],
+    coords![(0, 0), (0, 1), (0, -2), (2, 1), (-1, -2)],
+];
+const SRS_I_CCW: [&[Coords]; 4] = [
+    coords![(0, 0), (0, -1), (0, 2), (-2, -1), (1, 2)],
+    coords![(0, 0), (0, 2), (0, -1), (-1, 2), (2, -1)],
+    coords![(0, 0), (0, 1), (0, -2), (2, 1), (-1, -2)],
+    coords![(0, 0), (0, -2), (0, 1), (1, -2), (-2, 1)],
+];
+const SRS_JLSTZ_CW: [&[Coords]; 4] = [
+    coords![(0, 0), (0, -1), (-1, -1), (2, 0), (2, -1)],
+    coords![(0, 0), (0, 1), (1, 1), (-2, 0), (-2, 1)],
+    coords![(0, 0), (0, 1), (-1, 1), (2, 0), (2, 1)],
+    coords![(0, 0), (0, -1), (1, -1), (-2, 0), (-2, -1)],
+];
+const SRS_JLSTZ_CCW: [&[Coords]; 4] = [
+    coords![(0, 0), (0, 1), (-1, 1), (2, 0), (2, 1)],
+    coords![(0, 0), (0, 1), (1, 1), (-2, 0), (-2, 1)],
+    coords![(0, 0), (0, -1), (-1, -1), (2, 0), (2, -1)],
+    coords![(0, 0), (0, -1), (1, -1), (-2, 0), (-2, -1)],
+];
+
 // standard asymmetrical srs kick table
 pub struct SrsKickTable;
 
-impl KickTable for SrsKickTable {
-    fn rotate_cw(&self, piece: PieceKind, rotation_state: RotationState) -> Vec<Coords> {
+impl SrsKickTable {
+    fn kicks(
+        piece: PieceKind,
+        rotation_state: RotationState,
+        i_kicks: [&'static [Coords]; 4],
+        jlstz_kicks: [&'static [Coords]; 4],
+    ) -> &'static [Coords] {
         match piece {
             PieceKind::TetrominoSrs(kind) => match kind {
-                TetrominoSrs::I => match rotation_state {
-                    RotationState::Initial => vec![(0, 0), (0, -2), (0, 1), (1, -2), (-2, 1)],
-                    RotationState::Cw => vec![(0, 0), (0, -1), (0, 2), (-2, -1), (1, 2)],
-                    RotationState::Flipped => vec![(0, 0), (0, 2), (0, -1), (-1, 2), (2, -1)],
-                    RotationState::Ccw => vec![(0, 0), (0, 1), (0, -2), (2, 1), (-1, -2)],
-                },
-                TetrominoSrs::O => vec![], // don't let o rotate at all
-                _ => match rotation_state {
-                    RotationState::Initial => vec![(0, 0), (0, -1), (-1, -1), (2, 0), (2, -1)],
-                    RotationState::Cw => vec![(0, 0), (0, 1), (1, 1), (-2, 0), (-2, 1)],
-                    RotationState::Flipped => vec![(0, 0), (0, 1), (-1, 1), (2, 0), (2, 1)],
-                    RotationState::Ccw => vec![(0, 0), (0, -1), (1, -1), (-2, 0), (-2, -1)],
-                },
+                TetrominoSrs::I => i_kicks[rotation_state as usize],
+                TetrominoSrs::O => &[], // don't let o rotate at all
+                _ => jlstz_kicks[rotation_state as usize],
             },
-            _ => vec![(0, 0)],
+            _ => coords![(0, 0)],
         }
-        .into_iter()
-        .map(|(row_shift, col_shift)| Coords(row_shift, col_shift))
-        .collect::<Vec<_>>()
+    }
+}
+
+impl KickTable for SrsKickTable {
+    fn rotate_cw(&self, piece: PieceKind, rotation_state: RotationState) -> &'static [Coords] {
+        Self::kicks(piece, rotation_state, SRS_I_CW, SRS_JLSTZ_CW)
     }
 
-    fn rotate_ccw(&self, piece: PieceKind, rotation_state: RotationState) -> Vec<Coords> {
-        self.rotate_cw(piece, rotation_state.next_ccw())
-            .into_iter()
-            .map(ops::Neg::neg)
-            .collect()
+    fn rotate_ccw(&self, piece: PieceKind, rotation_state: RotationState) -> &'static [Coords] {
+        Self::kicks(piece, rotation_state, SRS_I_CCW, SRS_JLSTZ_CCW)
     }
 }
 
@@ -92,37 +112,35 @@ impl KickTable for SrsKickTable {
 pub struct TetrIo180KickTable;
 
 impl KickTable180 for TetrIo180KickTable {
-    fn rotate_180(&self, _: PieceKind, rotation_state: RotationState) -> Vec<Coords> {
+    fn rotate_180(&self, _: PieceKind, rotation_state: RotationState) -> &'static [Coords] {
         match rotation_state {
-            RotationState::Initial => vec![(0, 0), (-1, 0), (-1, 1), (-1, -1), (0, 1), (0, -1)],
-            RotationState::Cw => vec![(0, 0), (0, 1), (-2, 1), (-1, 1), (-2, 0), (-1, 0)],
-            RotationState::Flipped => vec![(0, 0), (1, 0), (1, -1), (1, 1), (0, -1), (0, 1)],
-            RotationState::Ccw => vec![(0, 0), (0, -1), (-2, -1), (-1, -1), (-2, 0), (-1, 0)],
+            RotationState::Initial => coords![(0, 0), (-1, 0), (-1, 1), (-1, -1), (0, 1), (0, -1)],
+            RotationState::Cw => coords![(0, 0), (0, 1), (-2, 1), (-1, 1), (-2, 0), (-1, 0)],
+            RotationState::Flipped => coords![(0, 0), (1, 0), (1, -1), (1, 1), (0, -1), (0, 1)],
+            RotationState::Ccw => coords![(0, 0), (0, -1), (-2, -1), (-1, -1), (-2, 0), (-1, 0)],
         }
-        .into_iter()
-        .map(|(row_shift, col_shift)| Coords(row_shift, col_shift))
-        .collect::<Vec<_>>()
     }
 }
 
 // ascension kick table
+// kicks to the right (then left, then other directions) are tried first when rotating ccw, and the mirror image of
+// that when rotating cw
 pub struct AscKickTable;
 
 impl KickTable for AscKickTable {
-    fn rotate_cw(&self, piece: PieceKind, rotation_state: RotationState) -> Vec<Coords> {
-        self.rotate_ccw(piece, rotation_state)
-            .into_iter()
-            .map(|k| Coords(k.0, -k.1))
-            .collect()
+    fn rotate_cw(&self, _: PieceKind, _: RotationState) -> &'static [Coords] {
+        coords![
+            (0, 0), (0, -1), (1, 0), (1, -1), (2, 0), (2, -1), (0, -2), (1, -2), (2, -2), // left
+            (0, 1), (1, 1), (-1, 0), (-1, -1), (-1, -2), (2, 1), (0, 2), (-2, 0), // right
+            (-2, -1), (-2, -2), (1, 2), (2, 2), (-1, 1), // other
+        ]
     }
 
-    fn rotate_ccw(&self, _: PieceKind, _: RotationState) -> Vec<Coords> {
-        let right = [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
-        let left = [(0, -1), (1, -1), (-1, 0), (-1, 1), (-1, 2), (2, -1), (0, -2), (-2, 0)];
-        let other = [(-2, 1), (-2, 2), (1, -2), (2, -2), (-1, -1)];
-        let kicks = right.into_iter().chain(left).chain(other);
-        kicks
-            .map(|(row_shift, col_shift)| Coords(row_shift, col_shift))
-            .collect()
+    fn rotate_ccw(&self, _: PieceKind, _: RotationState) -> &'static [Coords] {
+        coords![
+            (0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1), (0, 2), (1, 2), (2, 2), // right
+            (0, -1), (1, -1), (-1, 0), (-1, 1), (-1, 2), (2, -1), (0, -2), (-2, 0), // left
+            (-2, 1), (-2, 2), (1, -2), (2, -2), (-1, -1), // other
+        ]
     }
 }
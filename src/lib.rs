@@ -1,18 +1,41 @@
-#![feature(array_chunks)]
-#![feature(min_specialization)]
-#![feature(type_alias_impl_trait)]
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+// static slice of `Coords` from `(row, col)` pairs, used for tables of offsets and kicks
+macro_rules! coords {
+    ($(($row:expr, $col:expr)),* $(,)?) => { &[$($crate::Coords($row, $col)),*] };
+}
+
+pub mod attack;
+pub mod curves;
 pub mod field;
+pub mod fumen;
+pub mod game;
+pub mod garbage;
 pub mod pieces;
 pub mod kicks;
+pub mod puzzle;
+pub mod replay;
+pub mod scoring;
+pub mod search;
+pub mod sim;
 pub mod spins;
+pub mod stats;
+pub mod ticks;
+pub mod versus;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-use std::{mem, ops};
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{mem, ops};
 
 use pieces::PieceKind;
-use rand::prelude::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 pub struct Coords(pub i32, pub i32);
 
 impl Coords {
@@ -62,28 +85,123 @@ pub trait Randomizer {
     fn next(&mut self) -> PieceKind;
     fn peek(&mut self) -> Box<dyn Iterator<Item = PieceKind> + '_>;
 
+    // like `peek`, but always gives exactly `n` pieces even if they haven't been generated yet
+    fn peek_n(&mut self, n: usize) -> Box<dyn Iterator<Item = PieceKind> + '_>;
+
     fn lookahead(&self) -> usize;
+
+    // indices in `peek_n(n)` of the pieces starting a new bag (not including the first piece), for randomizers that
+    // give pieces in bags
+    fn bag_starts(&mut self, _n: usize) -> Vec<usize> { vec![] }
+
+    // switch to a different piece set mid-game
+    fn set_kinds(&mut self, kinds: Vec<PieceKind>, policy: QueuePolicy);
+}
+
+// what to do with pieces already in the queue when switching piece sets
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QueuePolicy {
+    // replace the queue with pieces from the new set
+    Flush,
+    // give out the queued pieces first
+    Keep,
+}
+
+// source of randomness for randomizers, so servers can use cryptographic rngs and replays can use seeded ones
+// implemented for every `RngCore` that can be cloned and shared between threads (for parallel searches)
+pub trait RandomSource {
+    // uniformly random index in `0..n`
+    fn gen_index(&mut self, n: usize) -> usize;
+
+    // copy of the rng, used to clone randomizers (e.g. for game snapshots)
+    fn boxed_clone(&self) -> Box<dyn RandomSource + Send + Sync>;
 }
 
+impl<R: RngCore + Clone + Send + Sync + 'static> RandomSource for R {
+    // sampled as a `u32` so seeded rngs give the same results on 32 and 64 bit targets
+    fn gen_index(&mut self, n: usize) -> usize { self.gen_range(0..n as u32) as usize }
+
+    fn boxed_clone(&self) -> Box<dyn RandomSource + Send + Sync> { Box::new(self.clone()) }
+}
+
+impl Clone for Box<dyn RandomSource + Send + Sync> {
+    fn clone(&self) -> Self { self.boxed_clone() }
+}
+
+// fisher-yates shuffle, since `SliceRandom` can't be used with a `dyn RandomSource`
+fn shuffle<T>(items: &mut [T], rng: &mut dyn RandomSource) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, rng.gen_index(i + 1));
+    }
+}
+
+// clones give the same pieces as the original, as long as its rng clones exactly (unlike `rand::thread_rng`)
+#[derive(Clone)]
 pub struct SingleBag {
     kinds: Vec<PieceKind>,
     bag: Vec<PieceKind>,
+    // number of pieces left in each bag in `bag`, in the same order
+    bag_lens: Vec<usize>,
+    rng: Box<dyn RandomSource + Send + Sync>,
+
+    // used to recreate seeded bags in the same state (see `fork`)
+    seed: Option<u64>,
+    n_drawn: usize,
 }
 
 impl SingleBag {
-    pub fn new(kinds: Vec<PieceKind>) -> Self {
-        let mut bag = SingleBag { kinds, bag: vec![] };
-        bag.update_bag();
+    // seeded from the thread local rng rather than using it, which clones would share
+    #[cfg(feature = "std")]
+    pub fn new(kinds: Vec<PieceKind>) -> Self { SingleBag::seeded(kinds, rand::random()) }
+
+    // shuffle bags with the given rng instead of the thread local one (required without the `std` feature)
+    pub fn with_rng(kinds: Vec<PieceKind>, rng: impl RandomSource + Send + Sync + 'static) -> Self {
+        let mut bag = SingleBag {
+            kinds,
+            bag: vec![],
+            bag_lens: vec![],
+            rng: Box::new(rng),
+            seed: None,
+            n_drawn: 0,
+        };
         bag.update_bag();
         bag
     }
 
-    fn update_bag(&mut self) {
-        if self.bag.len() <= self.kinds.len() {
+    // bags with the same kinds and seed always produce the same pieces, on every platform
+    pub fn seeded(kinds: Vec<PieceKind>, seed: u64) -> Self {
+        let mut bag = SingleBag::with_rng(kinds, ChaCha8Rng::seed_from_u64(seed));
+        bag.seed = Some(seed);
+        bag
+    }
+
+    pub fn seed(&self) -> Option<u64> { self.seed }
+
+    // a bag which will produce the same pieces as this one from now on (e.g. for the other player in a match)
+    // only seeded bags which haven't switched piece sets can be forked, since other rngs can't be recreated
+    pub fn fork(&self) -> Option<SingleBag> {
+        let mut bag = SingleBag::seeded(self.kinds.clone(), self.seed?);
+        for _ in 0..self.n_drawn {
+            bag.next();
+        }
+        Some(bag)
+    }
+
+    // bag of every kind in the given piece set (which may be a `CompositeSet` of multiple sets)
+    #[cfg(feature = "std")]
+    pub fn from_set<P: pieces::PieceKindTrait>() -> Self { SingleBag::new(P::iter().collect()) }
+
+    // keep more than one bag's worth of pieces queued so `peek` always shows the next full bag
+    fn update_bag(&mut self) { self.fill_bag(self.kinds.len() + 1); }
+
+    // generate bags until at least `len` pieces are queued
+    fn fill_bag(&mut self, len: usize) {
+        while self.bag.len() < len && !self.kinds.is_empty() {
             let mut next_bag = self.kinds.clone();
-            next_bag.shuffle(&mut rand::thread_rng());
+            shuffle(&mut next_bag, self.rng.as_mut());
 
             // prepend to preserve peek order
+            self.bag_lens.insert(0, next_bag.len());
             mem::swap(&mut self.bag, &mut next_bag);
             self.bag.extend(next_bag);
         }
@@ -93,6 +211,13 @@ impl SingleBag {
 impl Randomizer for SingleBag {
     fn next(&mut self) -> PieceKind {
         self.update_bag();
+        self.n_drawn += 1;
+
+        let bag_len = self.bag_lens.last_mut().unwrap();
+        *bag_len -= 1;
+        if *bag_len == 0 {
+            self.bag_lens.pop();
+        }
         self.bag.pop().unwrap()
     }
 
@@ -101,5 +226,253 @@ impl Randomizer for SingleBag {
         Box::new(self.bag.iter().rev().cloned())
     }
 
+    fn peek_n(&mut self, n: usize) -> Box<dyn Iterator<Item = PieceKind> + '_> {
+        self.fill_bag(n);
+        Box::new(self.bag.iter().rev().take(n).cloned())
+    }
+
     fn lookahead(&self) -> usize { self.kinds.len() }
+
+    fn bag_starts(&mut self, n: usize) -> Vec<usize> {
+        self.fill_bag(n);
+        let starts = self.bag_lens.iter().rev().scan(0, |start, len| {
+            *start += len;
+            Some(*start)
+        });
+        starts.take_while(|start| *start < n).collect()
+    }
+
+    fn set_kinds(&mut self, kinds: Vec<PieceKind>, policy: QueuePolicy) {
+        if kinds == self.kinds {
+            return;
+        }
+
+        // the seed alone no longer determines the pieces
+        self.seed = None;
+        self.kinds = kinds;
+        if policy == QueuePolicy::Flush {
+            self.bag.clear();
+            self.bag_lens.clear();
+        }
+        self.update_bag();
+    }
+}
+
+// randomizer which remembers every piece it gives out, used to check that players were given the same pieces
+#[derive(Clone)]
+pub struct RecordingRandomizer<R: Randomizer> {
+    inner: R,
+    history: Vec<PieceKind>,
+}
+
+impl<R: Randomizer> RecordingRandomizer<R> {
+    pub fn new(inner: R) -> Self { RecordingRandomizer { inner, history: vec![] } }
+
+    pub fn history(&self) -> &[PieceKind] { &self.history }
+
+    pub fn into_inner(self) -> R { self.inner }
+}
+
+impl<R: Randomizer> Randomizer for RecordingRandomizer<R> {
+    fn next(&mut self) -> PieceKind {
+        let kind = self.inner.next();
+        self.history.push(kind);
+        kind
+    }
+
+    fn peek(&mut self) -> Box<dyn Iterator<Item = PieceKind> + '_> { self.inner.peek() }
+
+    fn peek_n(&mut self, n: usize) -> Box<dyn Iterator<Item = PieceKind> + '_> { self.inner.peek_n(n) }
+
+    fn lookahead(&self) -> usize { self.inner.lookahead() }
+
+    fn bag_starts(&mut self, n: usize) -> Vec<usize> { self.inner.bag_starts(n) }
+
+    fn set_kinds(&mut self, kinds: Vec<PieceKind>, policy: QueuePolicy) { self.inner.set_kinds(kinds, policy) }
+}
+
+// randomizer giving the mirror image of every piece from another one, for use with `DefaultField::mirrored`
+#[derive(Clone)]
+pub struct MirroredRandomizer<R: Randomizer> {
+    inner: R,
+}
+
+impl<R: Randomizer> MirroredRandomizer<R> {
+    pub fn new(inner: R) -> Self { MirroredRandomizer { inner } }
+
+    pub fn into_inner(self) -> R { self.inner }
+}
+
+impl<R: Randomizer> Randomizer for MirroredRandomizer<R> {
+    fn next(&mut self) -> PieceKind { self.inner.next().mirrored() }
+
+    fn peek(&mut self) -> Box<dyn Iterator<Item = PieceKind> + '_> {
+        Box::new(self.inner.peek().map(|k| k.mirrored()))
+    }
+
+    fn peek_n(&mut self, n: usize) -> Box<dyn Iterator<Item = PieceKind> + '_> {
+        Box::new(self.inner.peek_n(n).map(|k| k.mirrored()))
+    }
+
+    fn lookahead(&self) -> usize { self.inner.lookahead() }
+
+    fn bag_starts(&mut self, n: usize) -> Vec<usize> { self.inner.bag_starts(n) }
+
+    fn set_kinds(&mut self, kinds: Vec<PieceKind>, policy: QueuePolicy) { self.inner.set_kinds(kinds, policy) }
+}
+
+// randomizer giving pieces from a fixed sequence, starting over once it runs out (e.g. for puzzles or `sim` scripts)
+#[derive(Clone, Debug)]
+pub struct FixedQueue {
+    pieces: Vec<PieceKind>,
+    next: usize,
+}
+
+impl FixedQueue {
+    // panics if `pieces` is empty
+    pub fn new(pieces: Vec<PieceKind>) -> Self {
+        assert!(!pieces.is_empty(), "fixed queue must not be empty");
+        FixedQueue { pieces, next: 0 }
+    }
+}
+
+impl Randomizer for FixedQueue {
+    fn next(&mut self) -> PieceKind {
+        let kind = self.pieces[self.next];
+        self.next = (self.next + 1) % self.pieces.len();
+        kind
+    }
+
+    // the rest of the sequence before it starts over
+    fn peek(&mut self) -> Box<dyn Iterator<Item = PieceKind> + '_> {
+        Box::new(self.pieces[self.next..].iter().copied())
+    }
+
+    fn peek_n(&mut self, n: usize) -> Box<dyn Iterator<Item = PieceKind> + '_> {
+        Box::new(self.pieces.iter().copied().cycle().skip(self.next).take(n))
+    }
+
+    fn lookahead(&self) -> usize { self.pieces.len() }
+
+    // flushing replaces the sequence with the new kinds in order, and keeping leaves it as it is
+    fn set_kinds(&mut self, kinds: Vec<PieceKind>, policy: QueuePolicy) {
+        if policy == QueuePolicy::Flush && !kinds.is_empty() {
+            *self = FixedQueue::new(kinds);
+        }
+    }
+}
+
+// randomizer giving a written sequence of pieces before another randomizer takes over, for drilling openers and
+// setups, with `None` in the sequence drawing a piece from the other randomizer instead (`*` in `parse_queue`)
+// a looping sequence starts over once it runs out, so the other randomizer only fills in the `None`s
+#[derive(Clone)]
+pub struct CustomQueue<R: Randomizer> {
+    sequence: Vec<Option<PieceKind>>,
+    looping: bool,
+    next: usize,
+    fallback: R,
+}
+
+impl<R: Randomizer> CustomQueue<R> {
+    // an empty sequence gives the same pieces as `fallback`
+    pub fn new(sequence: Vec<Option<PieceKind>>, looping: bool, fallback: R) -> Self {
+        CustomQueue {
+            sequence,
+            looping,
+            next: 0,
+            fallback,
+        }
+    }
+
+    pub fn fallback(&self) -> &R { &self.fallback }
+
+    // the next `n` slots, with `None` for pieces from the fallback
+    fn slots(&self, n: usize) -> Vec<Option<PieceKind>> {
+        if self.looping && !self.sequence.is_empty() {
+            self.sequence.iter().copied().cycle().skip(self.next).take(n).collect()
+        } else {
+            let rest = self.sequence.get(self.next..).unwrap_or_default();
+            rest.iter().copied().chain(core::iter::repeat(None)).take(n).collect()
+        }
+    }
+}
+
+impl<R: Randomizer> Randomizer for CustomQueue<R> {
+    fn next(&mut self) -> PieceKind {
+        let slot = self.sequence.get(self.next).copied().flatten();
+        if self.next < self.sequence.len() {
+            self.next += 1;
+            if self.looping && self.next == self.sequence.len() {
+                self.next = 0;
+            }
+        }
+        slot.unwrap_or_else(|| self.fallback.next())
+    }
+
+    fn peek(&mut self) -> Box<dyn Iterator<Item = PieceKind> + '_> { self.peek_n(self.lookahead()) }
+
+    fn peek_n(&mut self, n: usize) -> Box<dyn Iterator<Item = PieceKind> + '_> {
+        let slots = self.slots(n);
+        let n_fallback = slots.iter().filter(|s| s.is_none()).count();
+        let mut fallback = self.fallback.peek_n(n_fallback).collect::<Vec<_>>().into_iter();
+        Box::new(slots.into_iter().map(move |s| s.or_else(|| fallback.next()).unwrap()))
+    }
+
+    fn lookahead(&self) -> usize { self.fallback.lookahead() }
+
+    // only the pieces from the fallback come in bags
+    fn bag_starts(&mut self, n: usize) -> Vec<usize> {
+        let slots = self.slots(n);
+        let fallback_indices = slots.iter().enumerate().filter(|(_, s)| s.is_none()).map(|(i, _)| i);
+        let fallback_indices = fallback_indices.collect::<Vec<_>>();
+        let starts = self.fallback.bag_starts(fallback_indices.len());
+        starts.into_iter().map(|start| fallback_indices[start]).collect()
+    }
+
+    // flushing also drops the written sequence if it has pieces that aren't in the new set
+    fn set_kinds(&mut self, kinds: Vec<PieceKind>, policy: QueuePolicy) {
+        let in_set = self.sequence.iter().flatten().all(|k| kinds.contains(k));
+        if policy == QueuePolicy::Flush && !in_set {
+            self.sequence.clear();
+            self.next = 0;
+        }
+        self.fallback.set_kinds(kinds, policy)
+    }
+}
+
+// pieces named in `text` (e.g. `"TIOSZLJ*"`), with `*` for a piece from the randomizer (see `CustomQueue`)
+// names are matched longest first, falling back to ignoring case, and whitespace between them is skipped
+// gives back the rest of the text from the first thing that isn't a piece name
+pub fn parse_queue<'a>(text: &'a str, set: &[PieceKind]) -> Result<Vec<Option<PieceKind>>, &'a str> {
+    let mut queue = vec![];
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('*') {
+            queue.push(None);
+            rest = after;
+        } else {
+            let matches = |exact: bool| {
+                set.iter()
+                    .map(|k| (k, k.display_name()))
+                    .filter(|(_, name)| match rest.get(..name.len()) {
+                        Some(prefix) if exact => prefix == *name,
+                        Some(prefix) => prefix.eq_ignore_ascii_case(name),
+                        None => false,
+                    })
+                    .max_by_key(|(_, name)| name.len())
+            };
+            let (kind, name) = matches(true).or_else(|| matches(false)).ok_or(rest)?;
+            queue.push(Some(*kind));
+            rest = &rest[name.len()..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(queue)
+}
+
+// whether two piece histories come from the same sequence
+// games rarely last the same number of pieces, so one only has to be a prefix of the other
+pub fn same_sequence(a: &[PieceKind], b: &[PieceKind]) -> bool {
+    let len = a.len().min(b.len());
+    a[..len] == b[..len]
 }
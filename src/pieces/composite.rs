@@ -0,0 +1,63 @@
+use alloc::boxed::Box;
+use crate::{Coords, CoordsFloat, PieceKind};
+
+use super::{PieceKindTrait, Rgb, RotationState};
+
+// a piece set made of two other piece sets (e.g. tetrominoes and pentominoes)
+// more than two sets can be merged by nesting (e.g. `CompositeSet<A, CompositeSet<B, C>>`)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CompositeSet<A, B> {
+    First(A),
+    Second(B),
+}
+
+impl<A: PieceKindTrait, B: PieceKindTrait> PieceKindTrait for CompositeSet<A, B> {
+    const MAX_SQUARES: usize = super::max_squares(A::MAX_SQUARES, B::MAX_SQUARES);
+
+    fn spawn_offsets(&self) -> &'static [Coords] {
+        match self {
+            CompositeSet::First(p) => p.spawn_offsets(),
+            CompositeSet::Second(p) => p.spawn_offsets(),
+        }
+    }
+
+    fn pivot_offset(&self, rotation_state: RotationState) -> (usize, CoordsFloat) {
+        match self {
+            CompositeSet::First(p) => p.pivot_offset(rotation_state),
+            CompositeSet::Second(p) => p.pivot_offset(rotation_state),
+        }
+    }
+
+    fn display_name(&self) -> &str {
+        match self {
+            CompositeSet::First(p) => p.display_name(),
+            CompositeSet::Second(p) => p.display_name(),
+        }
+    }
+
+    fn asset_name(&self) -> &str {
+        match self {
+            CompositeSet::First(p) => p.asset_name(),
+            CompositeSet::Second(p) => p.asset_name(),
+        }
+    }
+
+    fn color(&self) -> Rgb {
+        match self {
+            CompositeSet::First(p) => p.color(),
+            CompositeSet::Second(p) => p.color(),
+        }
+    }
+
+    fn mirrored(&self) -> Self {
+        match self {
+            CompositeSet::First(p) => CompositeSet::First(p.mirrored()),
+            CompositeSet::Second(p) => CompositeSet::Second(p.mirrored()),
+        }
+    }
+
+    // the kinds of each set are yielded as themselves, so a tetromino from a composite set is still a tetromino
+    fn iter() -> Box<dyn Iterator<Item = PieceKind>> { Box::new(A::iter().chain(B::iter())) }
+
+    fn n_kinds() -> usize { A::n_kinds() + B::n_kinds() }
+}
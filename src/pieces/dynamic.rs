@@ -0,0 +1,76 @@
+use std::sync::RwLock;
+
+use crate::{kicks::RotationState, Coords, CoordsFloat, PieceKind};
+
+use super::{PieceKindTrait, Rgb};
+
+// all piece kinds registered at runtime, indexed by `PieceKindId`
+// definitions are leaked so they can be borrowed for the rest of the program like the built in kinds
+static REGISTRY: RwLock<Vec<&'static PieceKindDef>> = RwLock::new(vec![]);
+
+// definition of a piece kind registered at runtime
+#[derive(Clone, Debug)]
+pub struct PieceKindDef {
+    pub display_name: String,
+    pub asset_name: String,
+    pub color: Rgb,
+
+    // same as `PieceKindTrait::spawn_offsets`
+    pub spawn_offsets: Vec<Coords>,
+
+    // index into `spawn_offsets` of the pivot and its offset in the initial rotation state
+    // see `PieceKindTrait::pivot_offset`
+    pub pivot_index: usize,
+    pub pivot_offset: CoordsFloat,
+}
+
+// handle to a piece kind registered at runtime (used instead of adding a variant to `PieceKind`)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PieceKindId(usize);
+
+impl PieceKindId {
+    fn def(&self) -> &'static PieceKindDef { REGISTRY.read().unwrap()[self.0] }
+}
+
+// register a new piece set, returning its kinds (e.g. for use with `SingleBag::new`)
+// panics if a kind has more squares than `PieceKindId::MAX_SQUARES`
+pub fn register_set(defs: impl IntoIterator<Item = PieceKindDef>) -> Vec<PieceKind> {
+    let mut registry = REGISTRY.write().unwrap();
+    defs.into_iter()
+        .map(|def| {
+            assert!(def.spawn_offsets.len() <= PieceKindId::MAX_SQUARES, "too many squares in piece kind");
+            registry.push(Box::leak(Box::new(def)));
+            PieceKind::Dynamic(PieceKindId(registry.len() - 1))
+        })
+        .collect()
+}
+
+impl PieceKindTrait for PieceKindId {
+    // enough for hexominoes
+    const MAX_SQUARES: usize = 6;
+
+    fn spawn_offsets(&self) -> &'static [Coords] { &self.def().spawn_offsets }
+
+    fn pivot_offset(&self, rotation_state: RotationState) -> (usize, CoordsFloat) {
+        let def = self.def();
+        let CoordsFloat(rows, cols) = def.pivot_offset;
+        (def.pivot_index, super::make_pivot_offset(rotation_state, rows, cols))
+    }
+
+    fn display_name(&self) -> &str { &self.def().display_name }
+
+    fn asset_name(&self) -> &str { &self.def().asset_name }
+
+    fn color(&self) -> Rgb { self.def().color }
+
+    // definitions don't say which kind is the mirror image of which, so kinds are treated as symmetric
+    fn mirrored(&self) -> Self { *self }
+
+    // iterates through every registered kind, regardless of which set it was registered with
+    fn iter() -> Box<dyn Iterator<Item = PieceKind>> {
+        let n_kinds = Self::n_kinds();
+        Box::new((0..n_kinds).map(|id| PieceKind::Dynamic(PieceKindId(id))))
+    }
+
+    fn n_kinds() -> usize { REGISTRY.read().unwrap().len() }
+}
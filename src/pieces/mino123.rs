@@ -1,11 +1,12 @@
+use alloc::boxed::Box;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use crate::{Coords, CoordsFloat, PieceKind};
 
-use super::{PieceKindTrait, RotationState};
+use super::{PieceKindTrait, Rgb, RotationState};
 
-#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Mino123 {
     Monomino,
     Domino,
@@ -14,16 +15,15 @@ pub enum Mino123 {
 }
 
 impl PieceKindTrait for Mino123 {
-    fn spawn_offsets(&self) -> Vec<Coords> {
+    const MAX_SQUARES: usize = 3;
+
+    fn spawn_offsets(&self) -> &'static [Coords] {
         match self {
-            Mino123::Monomino => vec![(0, 0)],
-            Mino123::Domino => vec![(0, 0), (0, 1)],
-            Mino123::LTromino => vec![(0, 0), (0, 1), (-1, 1)],
-            Mino123::ITromino => vec![(0, -1), (0, 0), (0, 1)],
+            Mino123::Monomino => coords![(0, 0)],
+            Mino123::Domino => coords![(0, 0), (0, 1)],
+            Mino123::LTromino => coords![(0, 0), (0, 1), (-1, 1)],
+            Mino123::ITromino => coords![(0, -1), (0, 0), (0, 1)],
         }
-        .into_iter()
-        .map(|(row, col)| Coords(row, col))
-        .collect()
     }
 
     fn pivot_offset(&self, rotation_state: RotationState) -> (usize, CoordsFloat) {
@@ -52,8 +52,20 @@ impl PieceKindTrait for Mino123 {
         }
     }
 
+    fn color(&self) -> Rgb {
+        match self {
+            Mino123::Monomino => Rgb(0x9a, 0x9a, 0x9a),
+            Mino123::Domino => Rgb(0x2e, 0xb8, 0x8a),
+            Mino123::LTromino => Rgb(0xe3, 0x7b, 0x3c),
+            Mino123::ITromino => Rgb(0x4a, 0x7e, 0xd9),
+        }
+    }
+
+    // every piece up to three squares is symmetric
+    fn mirrored(&self) -> Self { *self }
+
     fn iter() -> Box<dyn Iterator<Item = PieceKind>> {
-        Box::new(<Self as IntoEnumIterator>::iter().map(|p| PieceKind::Mino123(p)))
+        Box::new(<Self as IntoEnumIterator>::iter().map(PieceKind::Mino123))
     }
 
     fn n_kinds() -> usize { 4 }
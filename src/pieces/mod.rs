@@ -1,3 +1,5 @@
+use alloc::{boxed::Box, format, string::String};
+
 use crate::{kicks::RotationState, Coords, CoordsFloat};
 
 use self::{
@@ -7,14 +9,20 @@ use self::{
     tetromino::{TetrominoAsc, TetrominoSrs},
 };
 
+pub mod composite;
+#[cfg(feature = "dynamic-kinds")]
+pub mod dynamic;
 pub mod mino123;
 pub mod mino1234;
 pub mod pentomino;
 pub mod tetromino;
 
 pub trait PieceKindTrait {
+    // maximum number of squares in a piece of this kind's set, used to size inline piece storage
+    const MAX_SQUARES: usize;
+
     // coords of the squares composing the piece relative to the spawn coords
-    fn spawn_offsets(&self) -> Vec<Coords>;
+    fn spawn_offsets(&self) -> &'static [Coords];
 
     // index of the rotation pivot of the piece with a possibly zero offset
     // pieces like the i tetromino have apparent pivots that intersect
@@ -24,21 +32,49 @@ pub trait PieceKindTrait {
 
     fn asset_name(&self) -> &str;
 
+    // solid color of the piece's squares, for renderers that don't use image assets
+    fn color(&self) -> Rgb;
+
+    // mirror image of this kind (e.g. s and z tetrominoes), which is itself for symmetric pieces
+    fn mirrored(&self) -> Self;
+
     // iterator through all piece kinds
     fn iter() -> Box<dyn Iterator<Item = PieceKind>>;
 
     fn n_kinds() -> usize;
 }
 
+// maximum number of squares in any piece kind, which bounds the size of a `LivePiece`
+pub const MAX_SQUARES: usize = {
+    let max = max_squares(
+        max_squares(TetrominoSrs::MAX_SQUARES, TetrominoAsc::MAX_SQUARES),
+        max_squares(Mino1234::MAX_SQUARES, Pentomino::MAX_SQUARES),
+    );
+    #[cfg(feature = "dynamic-kinds")]
+    let max = max_squares(max, dynamic::PieceKindId::MAX_SQUARES);
+    max
+};
+
+const fn max_squares(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
 // a piece kind (e.g. t tetromino (srs), domino, l tromino)
 // not a trait to avoid trait objects as this type is used in relatively large numbers
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum PieceKind {
     TetrominoSrs(TetrominoSrs),
     TetrominoAsc(TetrominoAsc),
     Mino123(Mino123),
     Mino1234(Mino1234),
     Pentomino(Pentomino),
+    // piece kinds registered at runtime (see `dynamic::register_set`)
+    #[cfg(feature = "dynamic-kinds")]
+    Dynamic(dynamic::PieceKindId),
 }
 
 // generate match statement over all `PieceKind`s that calls a method, optionally with arguments
@@ -50,6 +86,8 @@ macro_rules! gen_piece_kind_match {
             PieceKind::Mino123(p) => p.$method($($arg,)*),
             PieceKind::Mino1234(p) => p.$method($($arg,)*),
             PieceKind::Pentomino(p) => p.$method($($arg,)*),
+            #[cfg(feature = "dynamic-kinds")]
+            PieceKind::Dynamic(p) => p.$method($($arg,)*),
         }
     }
 }
@@ -63,12 +101,14 @@ macro_rules! gen_piece_kind_match_associated {
             PieceKind::Mino123(_) => Mino123::$method(),
             PieceKind::Mino1234(_) => Mino1234::$method(),
             PieceKind::Pentomino(_) => Pentomino::$method(),
+            #[cfg(feature = "dynamic-kinds")]
+            PieceKind::Dynamic(_) => dynamic::PieceKindId::$method(),
         }
     };
 }
 
 impl PieceKind {
-    pub fn spawn_offsets(&self) -> Vec<Coords> { gen_piece_kind_match!(self, spawn_offsets) }
+    pub fn spawn_offsets(&self) -> &'static [Coords] { gen_piece_kind_match!(self, spawn_offsets) }
 
     pub fn pivot_offset(&self, rotation_state: RotationState) -> (usize, CoordsFloat) {
         gen_piece_kind_match!(self, pivot_offset, rotation_state)
@@ -78,9 +118,31 @@ impl PieceKind {
 
     pub fn asset_name(&self) -> &str { gen_piece_kind_match!(self, asset_name) }
 
+    pub fn color(&self) -> Rgb { gen_piece_kind_match!(self, color) }
+
+    pub fn mirrored(&self) -> PieceKind {
+        match self {
+            PieceKind::TetrominoSrs(p) => PieceKind::TetrominoSrs(p.mirrored()),
+            PieceKind::TetrominoAsc(p) => PieceKind::TetrominoAsc(p.mirrored()),
+            PieceKind::Mino123(p) => PieceKind::Mino123(p.mirrored()),
+            PieceKind::Mino1234(p) => PieceKind::Mino1234(p.mirrored()),
+            PieceKind::Pentomino(p) => PieceKind::Pentomino(p.mirrored()),
+            #[cfg(feature = "dynamic-kinds")]
+            PieceKind::Dynamic(p) => PieceKind::Dynamic(p.mirrored()),
+        }
+    }
+
     pub fn iter(&self) -> Box<dyn Iterator<Item = PieceKind>> { gen_piece_kind_match_associated!(self, iter) }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    // css-style hex color (e.g. `#0f9bd7`)
+    pub fn to_hex(&self) -> String { format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2) }
+}
+
 // calculate the correct pivot offset based on the current rotation state and an initial offset
 fn make_pivot_offset(rotation_state: RotationState, rows: f64, cols: f64) -> CoordsFloat {
     match rotation_state {
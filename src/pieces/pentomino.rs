@@ -1,11 +1,12 @@
+use alloc::boxed::Box;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use crate::{kicks::RotationState, Coords, CoordsFloat, PieceKind};
 
-use super::PieceKindTrait;
+use super::{PieceKindTrait, Rgb};
 
-#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Pentomino {
     I,
     F,
@@ -28,30 +29,29 @@ pub enum Pentomino {
 }
 
 impl PieceKindTrait for Pentomino {
-    fn spawn_offsets(&self) -> Vec<Coords> {
+    const MAX_SQUARES: usize = 5;
+
+    fn spawn_offsets(&self) -> &'static [Coords] {
         match self {
-            Pentomino::I => [(0, -2), (0, -1), (0, 0), (0, 1), (0, 2)],
-            Pentomino::F => [(0, 0), (-1, -1), (-1, 0), (-1, 1), (-2, -1)],
-            Pentomino::FPrime => [(0, 0), (-1, -1), (-1, 0), (-1, 1), (-2, 1)],
-            Pentomino::J => [(0, -1), (0, 0), (0, 1), (0, 2), (-1, -1)],
-            Pentomino::L => [(0, -1), (0, 0), (0, 1), (0, 2), (-1, 2)],
-            Pentomino::Q => [(0, -1), (0, 0), (0, 1), (-1, 0), (-1, 1)],
-            Pentomino::P => [(0, -1), (0, 0), (0, 1), (-1, -1), (-1, 0)],
-            Pentomino::N => [(0, 0), (0, 1), (0, 2), (-1, -1), (-1, 0)],
-            Pentomino::NPrime => [(0, -1), (0, 0), (0, 1), (-1, 1), (-1, 2)],
-            Pentomino::T => [(0, -1), (0, 0), (0, 1), (-1, 0), (-2, 0)],
-            Pentomino::U => [(0, -1), (0, 0), (0, 1), (-1, -1), (-1, 1)],
-            Pentomino::V => [(0, -1), (0, 0), (0, 1), (-1, 1), (-2, 1)],
-            Pentomino::W => [(0, -1), (0, 0), (-1, 0), (-1, 1), (-2, 1)],
-            Pentomino::X => [(0, 0), (-1, -1), (-1, 0), (-1, 1), (-2, 0)],
-            Pentomino::Y => [(0, -1), (0, 0), (0, 1), (0, 2), (-1, 1)],
-            Pentomino::YPrime => [(0, -1), (0, 0), (0, 1), (0, 2), (-1, 0)],
-            Pentomino::S => [(0, 1), (-1, -1), (-1, 0), (-1, 1), (-2, -1)],
-            Pentomino::Z => [(0, -1), (-1, -1), (-1, 0), (-1, 1), (-2, 1)],
+            Pentomino::I => coords![(0, -2), (0, -1), (0, 0), (0, 1), (0, 2)],
+            Pentomino::F => coords![(0, 0), (-1, -1), (-1, 0), (-1, 1), (-2, -1)],
+            Pentomino::FPrime => coords![(0, 0), (-1, -1), (-1, 0), (-1, 1), (-2, 1)],
+            Pentomino::J => coords![(0, -1), (0, 0), (0, 1), (0, 2), (-1, -1)],
+            Pentomino::L => coords![(0, -1), (0, 0), (0, 1), (0, 2), (-1, 2)],
+            Pentomino::Q => coords![(0, -1), (0, 0), (0, 1), (-1, 0), (-1, 1)],
+            Pentomino::P => coords![(0, -1), (0, 0), (0, 1), (-1, -1), (-1, 0)],
+            Pentomino::N => coords![(0, 0), (0, 1), (0, 2), (-1, -1), (-1, 0)],
+            Pentomino::NPrime => coords![(0, -1), (0, 0), (0, 1), (-1, 1), (-1, 2)],
+            Pentomino::T => coords![(0, -1), (0, 0), (0, 1), (-1, 0), (-2, 0)],
+            Pentomino::U => coords![(0, -1), (0, 0), (0, 1), (-1, -1), (-1, 1)],
+            Pentomino::V => coords![(0, -1), (0, 0), (0, 1), (-1, 1), (-2, 1)],
+            Pentomino::W => coords![(0, -1), (0, 0), (-1, 0), (-1, 1), (-2, 1)],
+            Pentomino::X => coords![(0, 0), (-1, -1), (-1, 0), (-1, 1), (-2, 0)],
+            Pentomino::Y => coords![(0, -1), (0, 0), (0, 1), (0, 2), (-1, 1)],
+            Pentomino::YPrime => coords![(0, -1), (0, 0), (0, 1), (0, 2), (-1, 0)],
+            Pentomino::S => coords![(0, 1), (-1, -1), (-1, 0), (-1, 1), (-2, -1)],
+            Pentomino::Z => coords![(0, -1), (-1, -1), (-1, 0), (-1, 1), (-2, 1)],
         }
-        .into_iter()
-        .map(|(row, col)| Coords(row, col))
-        .collect()
     }
 
     // these should feel fairly natural
@@ -108,8 +108,51 @@ impl PieceKindTrait for Pentomino {
         }
     }
 
+    // unlike the asset names, every pentomino gets its own color
+    fn color(&self) -> Rgb {
+        match self {
+            Pentomino::I => Rgb(0x0f, 0x9b, 0xd7),
+            Pentomino::F => Rgb(0x7c, 0xb3, 0x42),
+            Pentomino::FPrime => Rgb(0xc6, 0x28, 0x28),
+            Pentomino::J => Rgb(0x21, 0x41, 0xc6),
+            Pentomino::L => Rgb(0xe3, 0x5b, 0x02),
+            Pentomino::Q => Rgb(0x00, 0x89, 0x7b),
+            Pentomino::P => Rgb(0xd8, 0x1b, 0x60),
+            Pentomino::N => Rgb(0x39, 0x49, 0xab),
+            Pentomino::NPrime => Rgb(0xf4, 0x8f, 0x1f),
+            Pentomino::T => Rgb(0xaf, 0x29, 0x8a),
+            Pentomino::U => Rgb(0x00, 0xac, 0xc1),
+            Pentomino::V => Rgb(0x5e, 0x35, 0xb1),
+            Pentomino::W => Rgb(0x8e, 0x24, 0xaa),
+            Pentomino::X => Rgb(0xe3, 0x9f, 0x02),
+            Pentomino::Y => Rgb(0xff, 0x70, 0x43),
+            Pentomino::YPrime => Rgb(0x1e, 0x88, 0xe5),
+            Pentomino::S => Rgb(0x59, 0xb1, 0x01),
+            Pentomino::Z => Rgb(0xd7, 0x0f, 0x37),
+        }
+    }
+
+    // v and w are their own mirror images rotated
+    fn mirrored(&self) -> Self {
+        match self {
+            Pentomino::F => Pentomino::FPrime,
+            Pentomino::FPrime => Pentomino::F,
+            Pentomino::J => Pentomino::L,
+            Pentomino::L => Pentomino::J,
+            Pentomino::Q => Pentomino::P,
+            Pentomino::P => Pentomino::Q,
+            Pentomino::N => Pentomino::NPrime,
+            Pentomino::NPrime => Pentomino::N,
+            Pentomino::Y => Pentomino::YPrime,
+            Pentomino::YPrime => Pentomino::Y,
+            Pentomino::S => Pentomino::Z,
+            Pentomino::Z => Pentomino::S,
+            _ => *self,
+        }
+    }
+
     fn iter() -> Box<dyn Iterator<Item = PieceKind>> {
-        Box::new(<Self as IntoEnumIterator>::iter().map(|p| PieceKind::Pentomino(p)))
+        Box::new(<Self as IntoEnumIterator>::iter().map(PieceKind::Pentomino))
     }
 
     fn n_kinds() -> usize { 18 }
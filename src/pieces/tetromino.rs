@@ -1,11 +1,12 @@
+use alloc::boxed::Box;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use crate::{kicks::RotationState, Coords, CoordsFloat, PieceKind};
 
-use super::PieceKindTrait;
+use super::{PieceKindTrait, Rgb};
 
-#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TetrominoSrs {
     S,
     Z,
@@ -17,19 +18,18 @@ pub enum TetrominoSrs {
 }
 
 impl PieceKindTrait for TetrominoSrs {
-    fn spawn_offsets(&self) -> Vec<Coords> {
+    const MAX_SQUARES: usize = 4;
+
+    fn spawn_offsets(&self) -> &'static [Coords] {
         match self {
-            TetrominoSrs::S => [(0, -1), (0, 0), (-1, 0), (-1, 1)],
-            TetrominoSrs::Z => [(0, 0), (0, 1), (-1, -1), (-1, 0)],
-            TetrominoSrs::L => [(0, -1), (0, 0), (0, 1), (-1, 1)],
-            TetrominoSrs::J => [(0, -1), (0, 0), (0, 1), (-1, -1)],
-            TetrominoSrs::T => [(0, -1), (0, 0), (0, 1), (-1, 0)],
-            TetrominoSrs::O => [(0, 0), (0, 1), (-1, 0), (-1, 1)],
-            TetrominoSrs::I => [(0, -1), (0, 0), (0, 1), (0, 2)],
+            TetrominoSrs::S => coords![(0, -1), (0, 0), (-1, 0), (-1, 1)],
+            TetrominoSrs::Z => coords![(0, 0), (0, 1), (-1, -1), (-1, 0)],
+            TetrominoSrs::L => coords![(0, -1), (0, 0), (0, 1), (-1, 1)],
+            TetrominoSrs::J => coords![(0, -1), (0, 0), (0, 1), (-1, -1)],
+            TetrominoSrs::T => coords![(0, -1), (0, 0), (0, 1), (-1, 0)],
+            TetrominoSrs::O => coords![(0, 0), (0, 1), (-1, 0), (-1, 1)],
+            TetrominoSrs::I => coords![(0, -1), (0, 0), (0, 1), (0, 2)],
         }
-        .into_iter()
-        .map(|(row, col)| Coords(row, col))
-        .collect()
     }
 
     fn pivot_offset(&self, rotation_state: RotationState) -> (usize, CoordsFloat) {
@@ -55,14 +55,36 @@ impl PieceKindTrait for TetrominoSrs {
 
     fn asset_name(&self) -> &str { self.display_name() }
 
+    fn color(&self) -> Rgb {
+        match self {
+            TetrominoSrs::S => Rgb(0x59, 0xb1, 0x01),
+            TetrominoSrs::Z => Rgb(0xd7, 0x0f, 0x37),
+            TetrominoSrs::L => Rgb(0xe3, 0x5b, 0x02),
+            TetrominoSrs::J => Rgb(0x21, 0x41, 0xc6),
+            TetrominoSrs::T => Rgb(0xaf, 0x29, 0x8a),
+            TetrominoSrs::O => Rgb(0xe3, 0x9f, 0x02),
+            TetrominoSrs::I => Rgb(0x0f, 0x9b, 0xd7),
+        }
+    }
+
+    fn mirrored(&self) -> Self {
+        match self {
+            TetrominoSrs::S => TetrominoSrs::Z,
+            TetrominoSrs::Z => TetrominoSrs::S,
+            TetrominoSrs::L => TetrominoSrs::J,
+            TetrominoSrs::J => TetrominoSrs::L,
+            _ => *self,
+        }
+    }
+
     fn iter() -> Box<dyn Iterator<Item = PieceKind>> {
-        Box::new(<Self as IntoEnumIterator>::iter().map(|p| PieceKind::TetrominoSrs(p)))
+        Box::new(<Self as IntoEnumIterator>::iter().map(PieceKind::TetrominoSrs))
     }
 
     fn n_kinds() -> usize { 7 }
 }
 
-#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TetrominoAsc {
     S,
     Z,
@@ -74,7 +96,7 @@ pub enum TetrominoAsc {
 }
 
 impl TetrominoAsc {
-    fn to_srs(&self) -> TetrominoSrs {
+    fn to_srs(self) -> TetrominoSrs {
         match self {
             TetrominoAsc::S => TetrominoSrs::S,
             TetrominoAsc::Z => TetrominoSrs::Z,
@@ -88,7 +110,9 @@ impl TetrominoAsc {
 }
 
 impl PieceKindTrait for TetrominoAsc {
-    fn spawn_offsets(&self) -> Vec<Coords> { self.to_srs().spawn_offsets() }
+    const MAX_SQUARES: usize = 4;
+
+    fn spawn_offsets(&self) -> &'static [Coords] { self.to_srs().spawn_offsets() }
 
     fn pivot_offset(&self, rotation_state: RotationState) -> (usize, CoordsFloat) {
         match self {
@@ -112,8 +136,20 @@ impl PieceKindTrait for TetrominoAsc {
 
     fn asset_name(&self) -> &str { self.display_name() }
 
+    fn color(&self) -> Rgb { self.to_srs().color() }
+
+    fn mirrored(&self) -> Self {
+        match self {
+            TetrominoAsc::S => TetrominoAsc::Z,
+            TetrominoAsc::Z => TetrominoAsc::S,
+            TetrominoAsc::L => TetrominoAsc::J,
+            TetrominoAsc::J => TetrominoAsc::L,
+            _ => *self,
+        }
+    }
+
     fn iter() -> Box<dyn Iterator<Item = PieceKind>> {
-        Box::new(<Self as IntoEnumIterator>::iter().map(|p| PieceKind::TetrominoAsc(p)))
+        Box::new(<Self as IntoEnumIterator>::iter().map(PieceKind::TetrominoAsc))
     }
 
     fn n_kinds() -> usize { 7 }
@@ -0,0 +1,112 @@
+// puzzles with a fixed starting board and queue, which are solved by a sequence of inputs (e.g. for puzzle packs)
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    game::{Game, GameRules},
+    sim::{self, Input},
+    FixedQueue, PieceKind,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PuzzleGoal {
+    PerfectClear,
+    ClearLines(usize),
+    Attack(u32),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PuzzleError {
+    // a piece from past the end of the queue was placed
+    OutOfPieces,
+    // more than the puzzle's maximum number of pieces were placed before the goal was reached
+    TooManyPieces,
+    ToppedOut,
+    // the inputs ran out before the goal was reached
+    Unsolved,
+}
+
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    pub width: usize,
+    pub height: usize,
+    pub hidden: usize,
+    // bottom rows of the starting board in the ascii format used by `sim`
+    pub board: String,
+    pub queue: Vec<PieceKind>,
+    pub goal: PuzzleGoal,
+    pub max_pieces: usize,
+}
+
+impl Puzzle {
+    // panics if the queue is empty
+    pub fn new_game(&self, rules: GameRules) -> Game<FixedQueue> {
+        let bag = FixedQueue::new(self.queue.clone());
+        let mut game = Game::new(self.width, self.height, self.hidden, &self.kinds(), bag, rules);
+        sim::set_board(game.field_mut(), &self.board);
+        game
+    }
+
+    // number of pieces placed before the goal was reached by playing `inputs`, ignoring any inputs after that
+    pub fn check(&self, inputs: &[Input], rules: GameRules) -> Result<usize, PuzzleError> {
+        let mut game = self.new_game(rules);
+
+        // positions in the queue of the current and hold pieces, to tell when pieces from past the end get placed
+        let (mut cur_index, mut hold_index, mut n_drawn) = (0, None, 1);
+
+        let (mut n_pieces, mut n_lines, mut attack) = (0, 0, 0);
+        for input in inputs {
+            if *input != Input::HardDrop {
+                let did_something = sim::run(&mut game, &[*input])[0];
+                if *input == Input::Hold && did_something {
+                    match hold_index.replace(cur_index) {
+                        Some(index) => cur_index = index,
+                        None => {
+                            cur_index = n_drawn;
+                            n_drawn += 1;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            if cur_index >= self.queue.len() {
+                return Err(PuzzleError::OutOfPieces);
+            }
+            let Some(line_clear) = game.try_hard_drop() else {
+                continue;
+            };
+            cur_index = n_drawn;
+            n_drawn += 1;
+
+            n_pieces += 1;
+            n_lines += line_clear.n_lines();
+            attack += line_clear.attack();
+
+            let solved = match self.goal {
+                PuzzleGoal::PerfectClear => line_clear.is_perfect_clear(),
+                PuzzleGoal::ClearLines(goal) => n_lines >= goal,
+                PuzzleGoal::Attack(goal) => attack >= goal,
+            };
+            if n_pieces > self.max_pieces {
+                return Err(PuzzleError::TooManyPieces);
+            } else if solved {
+                return Ok(n_pieces);
+            } else if game.field().topped_out() {
+                return Err(PuzzleError::ToppedOut);
+            }
+        }
+        Err(PuzzleError::Unsolved)
+    }
+
+    // distinct kinds in the queue, in order of first appearance
+    fn kinds(&self) -> Vec<PieceKind> {
+        let mut kinds = Vec::new();
+        for kind in &self.queue {
+            if !kinds.contains(kind) {
+                kinds.push(*kind);
+            }
+        }
+        kinds
+    }
+}
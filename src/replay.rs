@@ -0,0 +1,316 @@
+// recorded games, played back by giving the same inputs to a new game with the same seeded bag
+// inputs are recorded as they took effect (e.g. every repeat of a held shift), so playback doesn't depend on handling
+// settings like das and arr, only on the rules
+// replays are saved as text, with a header followed by one `<tick> <input>` line per event, e.g.
+//
+//     tetrox-replay 1
+//     field 10 40 20
+//     seed 1234
+//     tick-rate 60
+//     kinds z l o s i j t
+//     0 left
+//     15 cw
+//     40 hard
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::Write;
+
+use crate::{
+    game::{Game, GameRules, GameSnapshot},
+    sim::{self, Input},
+    ticks::{Tick, TickRate},
+    PieceKind, SingleBag,
+};
+
+const FORMAT_HEADER: &str = "tetrox-replay 1";
+
+// inputs between snapshots kept by `ReplayPlayer`, trading memory for how far back seeking has to replay from
+const KEYFRAME_INTERVAL: usize = 64;
+
+// largest fields read from replays, which are those the client allows, so a replay can't ask for a huge game
+const MAX_WIDTH: u64 = 100;
+const MAX_HEIGHT: u64 = 200;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    // the text doesn't start with the format's header line, so it isn't a replay or is from a newer version
+    NotAReplay,
+    // a header line is missing or malformed
+    BadHeader(&'static str),
+    // a piece kind's name isn't in the set the replay is being read with
+    UnknownKind(String),
+    // an event line (numbered from 1, counting the whole text) isn't a tick and an input, or goes back in time
+    BadEvent(usize),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ReplayEvent {
+    pub tick: Tick,
+    pub input: Input,
+}
+
+// what happened during one stretch of a replay, for charting how a game went over time
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplayInterval {
+    pub n_pieces: u32,
+    pub attack: u32,
+    pub n_events: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub width: usize,
+    pub height: usize,
+    pub hidden: usize,
+    pub kinds: Vec<PieceKind>,
+    pub seed: u64,
+    pub tick_rate: TickRate,
+    // in the order they were made, so ticks never decrease
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    pub fn new(
+        width: usize,
+        height: usize,
+        hidden: usize,
+        kinds: &[PieceKind],
+        seed: u64,
+        tick_rate: TickRate,
+    ) -> Self {
+        Replay {
+            width,
+            height,
+            hidden,
+            kinds: kinds.to_vec(),
+            seed,
+            tick_rate,
+            events: Vec::new(),
+        }
+    }
+
+    // inputs recorded with an earlier tick than the last one are moved up to it, so events stay in order
+    pub fn push(&mut self, tick: Tick, input: Input) {
+        let tick = tick.max(self.duration());
+        self.events.push(ReplayEvent { tick, input });
+    }
+
+    // tick of the last input
+    pub fn duration(&self) -> Tick { self.events.last().map_or(0, |e| e.tick) }
+
+    // the replay in its text format
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let kinds = self.kinds.iter().map(|k| k.display_name()).collect::<Vec<_>>();
+        // writing to a string can't fail
+        let _ = writeln!(text, "{}", FORMAT_HEADER);
+        let _ = writeln!(text, "field {} {} {}", self.width, self.height, self.hidden);
+        let _ = writeln!(text, "seed {}", self.seed);
+        let _ = writeln!(text, "tick-rate {}", self.tick_rate.0);
+        let _ = writeln!(text, "kinds {}", kinds.join(" "));
+        for event in &self.events {
+            let _ = writeln!(text, "{} {}", event.tick, event.input.word());
+        }
+        text
+    }
+
+    // read a replay from its text format
+    // piece sets reuse names (e.g. the srs and asc tetrominoes), so kinds are looked up by name in `set`, which
+    // should be the set the replay was recorded with
+    pub fn from_text(text: &str, set: &[PieceKind]) -> Result<Replay, ReplayError> {
+        let mut lines = text.lines().map(str::trim).enumerate().filter(|(_, l)| !l.is_empty());
+        if lines.next().map(|(_, l)| l) != Some(FORMAT_HEADER) {
+            return Err(ReplayError::NotAReplay);
+        }
+
+        // values after the key of the next line, which must be `key`
+        let mut header = |key| {
+            let (_, line) = lines.next().ok_or(ReplayError::BadHeader(key))?;
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some(word) if word == key => Ok(words.collect::<Vec<_>>()),
+                _ => Err(ReplayError::BadHeader(key)),
+            }
+        };
+        let numbers = |values: Vec<&str>, key| {
+            let numbers = values.iter().map(|v| v.parse::<u64>()).collect::<Result<Vec<_>, _>>();
+            numbers.map_err(|_| ReplayError::BadHeader(key))
+        };
+
+        let field = numbers(header("field")?, "field")?;
+        let [width, height, hidden] = field[..] else {
+            return Err(ReplayError::BadHeader("field"));
+        };
+        let [seed] = numbers(header("seed")?, "seed")?[..] else {
+            return Err(ReplayError::BadHeader("seed"));
+        };
+        let [tick_rate] = numbers(header("tick-rate")?, "tick-rate")?[..] else {
+            return Err(ReplayError::BadHeader("tick-rate"));
+        };
+        let kinds = header("kinds")?
+            .into_iter()
+            .map(|name| {
+                let kind = set.iter().find(|k| k.display_name() == name);
+                kind.copied().ok_or_else(|| ReplayError::UnknownKind(name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // the field has to fit the widest piece, and its hidden rows are part of its height
+        let box_width = |kind: &PieceKind| {
+            let cols = kind.spawn_offsets().iter().map(|c| c.1);
+            (cols.clone().max().unwrap_or(0) - cols.min().unwrap_or(0) + 1) as u64
+        };
+        let Some(min_width) = kinds.iter().map(box_width).max() else {
+            return Err(ReplayError::BadHeader("kinds"));
+        };
+        let fits = (min_width..=MAX_WIDTH).contains(&width) && (1..=MAX_HEIGHT).contains(&height);
+        if !fits || !(1..=height).contains(&hidden) {
+            return Err(ReplayError::BadHeader("field"));
+        }
+        let tick_rate = u32::try_from(tick_rate).ok().filter(|&r| r > 0).ok_or(ReplayError::BadHeader("tick-rate"))?;
+
+        let tick_rate = TickRate(tick_rate);
+        let mut replay = Replay::new(
+            width as usize,
+            height as usize,
+            hidden as usize,
+            &kinds,
+            seed,
+            tick_rate,
+        );
+        for (index, line) in lines {
+            let mut words = line.split_whitespace();
+            let tick = words.next().and_then(|w| w.parse::<Tick>().ok());
+            let input = words
+                .next()
+                .and_then(|w| Input::parse(w).ok())
+                .and_then(|i| i.first().copied());
+            match (tick, input, words.next()) {
+                (Some(tick), Some(input), None) if tick >= replay.duration() => replay.push(tick, input),
+                _ => return Err(ReplayError::BadEvent(index + 1)),
+            }
+        }
+        Ok(replay)
+    }
+
+    // the game as it was before the first input
+    pub fn new_game(&self, rules: GameRules) -> Game<SingleBag> {
+        let bag = SingleBag::seeded(self.kinds.clone(), self.seed);
+        Game::new(self.width, self.height, self.hidden, &self.kinds, bag, rules)
+    }
+
+    // the replay split into stretches of `interval` ticks each, up to its last event
+    pub fn intervals(&self, interval: Tick, rules: GameRules) -> Vec<ReplayInterval> {
+        let interval = interval.max(1);
+        let mut intervals = vec![ReplayInterval::default(); (self.duration() / interval) as usize + 1];
+
+        let mut game = self.new_game(rules);
+        for event in &self.events {
+            game.advance_to(event.tick);
+            let stretch = &mut intervals[(event.tick / interval) as usize];
+            stretch.n_events += 1;
+
+            if event.input == Input::HardDrop {
+                if let Some(line_clear) = game.try_hard_drop() {
+                    stretch.n_pieces += 1;
+                    stretch.attack += line_clear.attack();
+                }
+            } else {
+                sim::run(&mut game, &[event.input]);
+            }
+        }
+        intervals
+    }
+
+    // the game after every input up to and including `tick`
+    pub fn game_at(&self, tick: Tick, rules: GameRules) -> Game<SingleBag> {
+        let mut player = ReplayPlayer::new(self.clone(), rules);
+        player.seek(tick);
+        player.into_game()
+    }
+}
+
+// steps a game through a replay for viewers, which can seek both ways
+// snapshots are kept every so often while playing forwards, so seeking backwards only replays from the nearest one
+pub struct ReplayPlayer {
+    replay: Replay,
+    game: Game<SingleBag>,
+    // index of the first event not yet played
+    next_event: usize,
+    n_lines: usize,
+    keyframes: Vec<Keyframe>,
+}
+
+struct Keyframe {
+    next_event: usize,
+    n_lines: usize,
+    snapshot: GameSnapshot<SingleBag>,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay, rules: GameRules) -> Self {
+        let game = replay.new_game(rules);
+        let keyframes = vec![Keyframe {
+            next_event: 0,
+            n_lines: 0,
+            snapshot: game.snapshot(),
+        }];
+        ReplayPlayer {
+            replay,
+            game,
+            next_event: 0,
+            n_lines: 0,
+            keyframes,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay { &self.replay }
+
+    pub fn game(&self) -> &Game<SingleBag> { &self.game }
+
+    pub fn into_game(self) -> Game<SingleBag> { self.game }
+
+    pub fn tick(&self) -> Tick { self.game.tick() }
+
+    // lines cleared so far, for showing progress (e.g. when racing a replay)
+    pub fn n_lines(&self) -> usize { self.n_lines }
+
+    pub fn is_finished(&self) -> bool { self.next_event == self.replay.events.len() }
+
+    // play or rewind to `tick`, with every input up to and including it applied
+    pub fn seek(&mut self, tick: Tick) {
+        if tick < self.game.tick() {
+            // keyframes are taken right after an event, so their tick is that of the last event they include
+            let keyframe = self.keyframes.iter().rev().find(|k| k.snapshot.tick() <= tick).unwrap();
+            self.next_event = keyframe.next_event;
+            self.n_lines = keyframe.n_lines;
+            self.game.restore(&keyframe.snapshot);
+        }
+
+        while let Some(event) = self.replay.events.get(self.next_event).filter(|e| e.tick <= tick) {
+            self.game.advance_to(event.tick);
+            if event.input == Input::HardDrop {
+                self.n_lines += self.game.try_hard_drop().map_or(0, |c| c.n_lines());
+            } else {
+                sim::run(&mut self.game, &[event.input]);
+            }
+            self.next_event += 1;
+
+            if self.next_event >= self.keyframes.last().unwrap().next_event + KEYFRAME_INTERVAL {
+                self.keyframes.push(Keyframe {
+                    next_event: self.next_event,
+                    n_lines: self.n_lines,
+                    snapshot: self.game.snapshot(),
+                });
+            }
+        }
+        self.game.advance_to(tick);
+    }
+
+    // move forward by `n_ticks`, like `Game::advance`
+    pub fn advance(&mut self, n_ticks: Tick) { self.seek(self.game.tick() + n_ticks); }
+}
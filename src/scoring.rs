@@ -0,0 +1,72 @@
+// guideline points for clears and drops, for score attack modes like ultra
+// points for clears are multiplied by the level (starting at 1), but drops are always worth the same
+
+use crate::field::LineClear;
+
+// points at level 1 indexed by lines cleared, with bigger clears (e.g. with pentominoes) scored as quads
+const CLEAR_POINTS: [u64; 5] = [0, 100, 300, 500, 800];
+const SPIN_POINTS: [u64; 5] = [400, 800, 1_200, 1_600, 1_600];
+const MINI_SPIN_POINTS: [u64; 5] = [100, 200, 400, 400, 400];
+const PERFECT_CLEAR_POINTS: [u64; 5] = [0, 800, 1_200, 1_800, 2_000];
+const BACK_TO_BACK_QUAD_PERFECT_CLEAR_POINTS: u64 = 3_200;
+
+// for each clear in a combo after the first
+const COMBO_POINTS: u64 = 50;
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ScoreState {
+    score: u64,
+    // clears before the last one in the current combo, like `AttackState`
+    combo: Option<usize>,
+    // whether the last clear was difficult, so the next difficult one continues back to back
+    back_to_back: bool,
+}
+
+impl ScoreState {
+    pub fn new() -> Self { ScoreState::default() }
+
+    pub fn score(&self) -> u64 { self.score }
+
+    // points for a piece locking with `line_clear` at `level`, which are added to the score
+    // difficult clears (see `LineClear::is_difficult`) continuing back to back are worth half again as much
+    pub fn add_clear(&mut self, line_clear: &LineClear, level: u32) -> u64 {
+        let n_lines = line_clear.n_lines().min(4);
+        let mut points = match line_clear.spin() {
+            Some(_) if line_clear.is_mini() => MINI_SPIN_POINTS[n_lines],
+            Some(_) => SPIN_POINTS[n_lines],
+            None => CLEAR_POINTS[n_lines],
+        };
+
+        if n_lines == 0 {
+            self.combo = None;
+        } else {
+            let back_to_back = self.back_to_back && line_clear.is_difficult();
+            if back_to_back {
+                points += points / 2;
+            }
+            self.back_to_back = line_clear.is_difficult();
+
+            let combo = self.combo.map_or(0, |c| c + 1);
+            self.combo = Some(combo);
+            points += COMBO_POINTS * combo as u64;
+
+            if line_clear.is_perfect_clear() {
+                points += match n_lines {
+                    4 if back_to_back => BACK_TO_BACK_QUAD_PERFECT_CLEAR_POINTS,
+                    _ => PERFECT_CLEAR_POINTS[n_lines],
+                };
+            }
+        }
+
+        let points = points * level as u64;
+        self.score += points;
+        points
+    }
+
+    // one point per row soft dropped and two per row hard dropped
+    pub fn add_drop(&mut self, n_rows: u32, hard: bool) -> u64 {
+        let points = n_rows as u64 * if hard { 2 } else { 1 };
+        self.score += points;
+        points
+    }
+}
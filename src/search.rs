@@ -0,0 +1,124 @@
+// lookahead search for bots, trying every placement of the current piece, the hold piece, and pieces in the queue
+// with the `parallel` feature, placements of the current piece are searched on separate threads
+
+use alloc::{vec, vec::Vec};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::{
+    field::{DefaultField, LivePiece},
+    game::GameRules,
+    sim, Randomizer,
+};
+
+// scores a field after a placement, with higher being better
+// the next piece has already spawned and is part of the field
+pub type Evaluator = dyn Fn(&DefaultField) -> f64 + Sync;
+
+#[derive(Copy, Clone, Debug)]
+pub struct SearchResult {
+    // final position of the piece to place, which is the hold piece if `hold` is set
+    pub placement: LivePiece,
+    pub hold: bool,
+    // best score found at the end of the lookahead
+    pub score: f64,
+}
+
+// randomizers a search can copy, which also have to be shared between threads with the `parallel` feature
+#[cfg(feature = "parallel")]
+pub trait SearchBag: Randomizer + Clone + Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<R: Randomizer + Clone + Send + Sync> SearchBag for R {}
+
+#[cfg(not(feature = "parallel"))]
+pub trait SearchBag: Randomizer + Clone {}
+
+#[cfg(not(feature = "parallel"))]
+impl<R: Randomizer + Clone> SearchBag for R {}
+
+// best placement looking `depth` pieces ahead including the current one, so a depth of 1 only tries the current and
+// hold pieces, or `None` if every line of play tops out
+pub fn search<R: SearchBag>(
+    field: &DefaultField,
+    bag: &R,
+    rules: &GameRules,
+    depth: usize,
+    evaluate: &Evaluator,
+) -> Option<SearchResult> {
+    let depth = depth.max(1);
+    let branches = branches(field, bag, rules);
+
+    #[cfg(feature = "parallel")]
+    let branches = branches.into_par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let branches = branches.into_iter();
+
+    branches
+        .filter_map(|branch| {
+            let score = best_score(&branch.field, &branch.bag, rules, depth - 1, evaluate)?;
+            Some(SearchResult {
+                placement: branch.placement,
+                hold: branch.hold,
+                score,
+            })
+        })
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+}
+
+// a field after locking a piece, with the bag that spawned the next piece
+struct Branch<R> {
+    field: DefaultField,
+    bag: R,
+    placement: LivePiece,
+    hold: bool,
+}
+
+// every placement of the current piece and of the hold piece, if the rules allow holding
+fn branches<R: Randomizer + Clone>(field: &DefaultField, bag: &R, rules: &GameRules) -> Vec<Branch<R>> {
+    let mut starts = vec![(field.clone(), bag.clone(), false)];
+
+    // holding a piece of the same kind gives the same placements
+    let can_hold = rules.controls.hold && !field.hold_swapped();
+    if can_hold && field.hold_piece() != Some(field.cur_piece().kind()) {
+        let (mut held, mut held_bag) = (field.clone(), bag.clone());
+        if held.swap_hold_piece(&mut held_bag).is_ok() {
+            starts.push((held, held_bag, true));
+        }
+    }
+
+    starts
+        .into_iter()
+        .flat_map(|(field, bag, hold)| {
+            sim::reachable(&field, rules).into_iter().map(move |mut field| {
+                let mut bag = bag.clone();
+                let placement = field.shadow_piece();
+                field.hard_drop(&mut bag, rules.spin_detector);
+                Branch {
+                    field,
+                    bag,
+                    placement,
+                    hold,
+                }
+            })
+        })
+        .collect()
+}
+
+// best score with `depth` more pieces to place, or `None` if every line of play tops out
+fn best_score<R>(field: &DefaultField, bag: &R, rules: &GameRules, depth: usize, evaluate: &Evaluator) -> Option<f64>
+where
+    R: Randomizer + Clone,
+{
+    if field.topped_out() {
+        return None;
+    } else if depth == 0 {
+        return Some(evaluate(field));
+    }
+
+    branches(field, bag, rules)
+        .iter()
+        .filter_map(|branch| best_score(&branch.field, &branch.bag, rules, depth - 1, evaluate))
+        .max_by(|a, b| a.total_cmp(b))
+}
@@ -0,0 +1,256 @@
+// scripted play for pinning down rotation and kick behaviour, e.g. when adding kick tables
+// boards are written in ascii with one line per row, using `.` for empty squares, `#` for locked and garbage squares,
+// and `@` for the current piece
+
+use alloc::{
+    collections::{BTreeSet, VecDeque},
+    string::String,
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    field::{DefaultField, LivePiece, MoveError, Square},
+    game::{Game, GameRules, Rotations},
+    Coords, Randomizer,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    Left,
+    Right,
+    // one row down
+    SoftDrop,
+    // all the way down without locking
+    SonicDrop,
+    Cw,
+    Ccw,
+    Flip,
+    Hold,
+    HardDrop,
+}
+
+impl Input {
+    // whitespace separated inputs (e.g. `"cw left left sonic hard"`), giving back the first word that isn't one
+    pub fn parse(script: &str) -> Result<Vec<Input>, &str> {
+        script
+            .split_whitespace()
+            .map(|word| match word {
+                "left" => Ok(Input::Left),
+                "right" => Ok(Input::Right),
+                "soft" => Ok(Input::SoftDrop),
+                "sonic" => Ok(Input::SonicDrop),
+                "cw" => Ok(Input::Cw),
+                "ccw" => Ok(Input::Ccw),
+                "flip" => Ok(Input::Flip),
+                "hold" => Ok(Input::Hold),
+                "hard" => Ok(Input::HardDrop),
+                _ => Err(word),
+            })
+            .collect()
+    }
+
+    // the word `parse` reads as this input
+    pub fn word(self) -> &'static str {
+        match self {
+            Input::Left => "left",
+            Input::Right => "right",
+            Input::SoftDrop => "soft",
+            Input::SonicDrop => "sonic",
+            Input::Cw => "cw",
+            Input::Ccw => "ccw",
+            Input::Flip => "flip",
+            Input::Hold => "hold",
+            Input::HardDrop => "hard",
+        }
+    }
+}
+
+// apply `inputs` in order, giving whether each one did anything
+pub fn run<R: Randomizer>(game: &mut Game<R>, inputs: &[Input]) -> Vec<bool> {
+    inputs
+        .iter()
+        .map(|input| match input {
+            Input::Left => game.shift(0, -1).is_ok(),
+            Input::Right => game.shift(0, 1).is_ok(),
+            Input::SoftDrop => game.shift(1, 0).is_ok(),
+            Input::SonicDrop => game.sonic_drop(),
+            Input::Cw => game.rotate_cw().is_ok(),
+            Input::Ccw => game.rotate_ccw().is_ok(),
+            Input::Flip => game.rotate_180().is_ok(),
+            // hold is used up even if the piece swapped in is blocked
+            Input::Hold => !matches!(game.swap_hold(), Err(MoveError::HoldUsed | MoveError::Disallowed)),
+            Input::HardDrop => game.try_hard_drop().is_some(),
+        })
+        .collect()
+}
+
+// the bottom `n_rows` rows of the field in ascii
+pub fn board_string(field: &DefaultField, n_rows: usize) -> String {
+    let cur_piece = field.cur_piece().coords();
+    let first_row = field.height().saturating_sub(n_rows);
+
+    let mut board = String::new();
+    for (row, squares) in field.rows().enumerate().skip(first_row) {
+        for (col, square) in squares.iter().enumerate() {
+            board.push(match square {
+                _ if cur_piece.contains(&Coords(row as i32, col as i32)) => '@',
+                Square::Empty => '.',
+                Square::Filled(_) | Square::Garbage => '#',
+            });
+        }
+        board.push('\n');
+    }
+    board
+}
+
+// fill the bottom rows of the field from ascii, with `#` as garbage and anything else as empty
+// squares under the current piece are left alone
+pub fn set_board(field: &mut DefaultField, board: &str) {
+    let rows = board
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>();
+    let first_row = field.height().saturating_sub(rows.len());
+
+    for (row, line) in rows.iter().rev().take(field.height()).rev().enumerate() {
+        for (col, c) in line.chars().enumerate() {
+            let square = if c == '#' { Square::Garbage } else { Square::Empty };
+            field.set_square(&Coords((first_row + row) as i32, col as i32), square);
+        }
+    }
+}
+
+// panics showing both boards if the bottom rows of the field don't match `expected`
+// leading and trailing whitespace on each line is ignored, so boards can be indented in source
+pub fn assert_board(field: &DefaultField, expected: &str) {
+    let expected = expected
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>();
+    let actual = board_string(field, expected.len());
+
+    if !actual.lines().eq(expected.iter().copied()) {
+        panic!(
+            "board mismatch\nexpected:\n{}\nactual:\n{}",
+            expected.join("\n"),
+            actual
+        );
+    }
+}
+
+// every distinct position the current piece can be locked in, found by trying all shifts, rotations the rules allow,
+// and soft drops (without hold), with each given as the piece in its final position
+pub fn placements(field: &DefaultField, rules: &GameRules) -> Vec<LivePiece> {
+    reachable(field, rules).iter().map(|f| f.shadow_piece()).collect()
+}
+
+// number of ways to place the current piece and the next `depth - 1` pieces from `bag`, like perft in chess engines
+// games ending in a top out are counted but not followed
+pub fn perft<R: Randomizer + Clone>(field: &DefaultField, bag: &R, rules: &GameRules, depth: usize) -> usize {
+    if depth == 0 {
+        return 1;
+    }
+
+    reachable(field, rules)
+        .into_iter()
+        .map(|mut field| {
+            let mut bag = bag.clone();
+            field.hard_drop(&mut bag, rules.spin_detector);
+            if field.topped_out() {
+                1
+            } else {
+                perft(&field, &bag, rules, depth - 1)
+            }
+        })
+        .sum()
+}
+
+// one copy of the field for each distinct position the current piece can be locked in, with the piece moved to
+// somewhere it can be hard dropped into that position from (so spins are detected the same way as in play)
+pub fn reachable(field: &DefaultField, rules: &GameRules) -> Vec<DefaultField> {
+    let moves: [fn(&mut DefaultField, &GameRules) -> bool; 6] = [
+        |f, _| f.try_shift(0, -1).is_ok(),
+        |f, _| f.try_shift(0, 1).is_ok(),
+        |f, _| f.try_shift(1, 0).is_ok(),
+        |f, r| rotations(f, r).cw && f.try_rotate_cw(r.kick_table).is_ok(),
+        |f, r| rotations(f, r).ccw && f.try_rotate_ccw(r.kick_table).is_ok(),
+        |f, r| rotations(f, r).half && f.try_rotate_180(r.kick_table_180).is_ok(),
+    ];
+
+    let mut seen = BTreeSet::new();
+    let mut landed = BTreeSet::new();
+    let mut reachable = Vec::new();
+
+    // depth-first search over piece positions, each of which is also checked for where it lands
+    let mut stack = vec![field.clone()];
+    seen.insert(piece_state(field.cur_piece()));
+    while let Some(field) = stack.pop() {
+        for make_move in moves {
+            let mut next = field.clone();
+            if make_move(&mut next, rules) && seen.insert(piece_state(next.cur_piece())) {
+                stack.push(next);
+            }
+        }
+        if landed.insert(sorted_coords(&field.shadow_piece())) {
+            reachable.push(field);
+        }
+    }
+    reachable
+}
+
+// fewest key presses that get the current piece to land where `target` is, for judging finesse
+// shifts, rotations, and shifting all the way to a wall (das) are one press each, and the hard drop isn't counted
+// gives `None` if the piece can't get there without soft dropping, since finesse doesn't apply to those placements
+pub fn finesse(field: &DefaultField, target: &LivePiece, rules: &GameRules) -> Option<usize> {
+    let moves: [fn(&mut DefaultField, &GameRules) -> bool; 7] = [
+        |f, _| f.try_shift(0, -1).is_ok(),
+        |f, _| f.try_shift(0, 1).is_ok(),
+        |f, _| shift_to_wall(f, -1),
+        |f, _| shift_to_wall(f, 1),
+        |f, r| rotations(f, r).cw && f.try_rotate_cw(r.kick_table).is_ok(),
+        |f, r| rotations(f, r).ccw && f.try_rotate_ccw(r.kick_table).is_ok(),
+        |f, r| rotations(f, r).half && f.try_rotate_180(r.kick_table_180).is_ok(),
+    ];
+    let target = sorted_coords(target);
+
+    // breadth-first search, so the first position landing on the target took the fewest presses
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::from([(field.clone(), 0)]);
+    seen.insert(piece_state(field.cur_piece()));
+    while let Some((field, n_presses)) = queue.pop_front() {
+        if sorted_coords(&field.shadow_piece()) == target {
+            return Some(n_presses);
+        }
+        for make_move in moves {
+            let mut next = field.clone();
+            if make_move(&mut next, rules) && seen.insert(piece_state(next.cur_piece())) {
+                queue.push_back((next, n_presses + 1));
+            }
+        }
+    }
+    None
+}
+
+// rotations the current piece is allowed to make
+fn rotations(field: &DefaultField, rules: &GameRules) -> Rotations { rules.rotations(field.cur_piece().kind()) }
+
+// shift the current piece as far as it goes, giving whether it moved at all
+fn shift_to_wall(field: &mut DefaultField, cols: i32) -> bool {
+    let mut moved = false;
+    while field.try_shift(0, cols).is_ok() {
+        moved = true;
+    }
+    moved
+}
+
+// pieces in the same squares can still kick differently if their rotation states differ
+fn piece_state(piece: &LivePiece) -> (Vec<Coords>, u8) { (sorted_coords(piece), piece.rotation_state() as u8) }
+
+fn sorted_coords(piece: &LivePiece) -> Vec<Coords> {
+    let mut coords = piece.coords().to_vec();
+    coords.sort();
+    coords
+}
@@ -1,7 +1,9 @@
+use alloc::vec::Vec;
+
 use crate::{
     field::DefaultField,
     pieces::{
-        mino1234::Mino1234,
+        composite::CompositeSet,
         tetromino::{TetrominoAsc, TetrominoSrs},
         PieceKind,
     },
@@ -10,7 +12,7 @@ use crate::{
 
 use num_traits::ToPrimitive;
 
-pub trait SpinDetector {
+pub trait SpinDetector: Sync {
     // returns the type of spin after a hard drop (if any) and whether it is mini
     // `field.cur_piece()` is the piece that was just dropped
     fn detect(&self, field: &DefaultField) -> (Option<PieceKind>, bool);
@@ -24,7 +26,7 @@ impl TSpinDetector {
             kind,
             PieceKind::TetrominoSrs(TetrominoSrs::T)
                 | PieceKind::TetrominoAsc(TetrominoAsc::T)
-                | PieceKind::Mino1234(Mino1234::TetrominoSrs(TetrominoSrs::T))
+                | PieceKind::Mino1234(CompositeSet::Second(TetrominoSrs::T))
         )
     }
 }
@@ -0,0 +1,57 @@
+// running totals for a game, for speed and efficiency stats like pieces per second, attack per minute, and keys per
+// piece
+
+use crate::{
+    attack::{AttackState, AttackTable, ComboTable},
+    field::LineClear,
+};
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StatsTracker {
+    n_pieces: u32,
+    n_lines: u32,
+    attack: u32,
+    n_keys: u32,
+    max_combo: usize,
+    max_back_to_back: usize,
+    attack_state: AttackState,
+}
+
+impl StatsTracker {
+    pub fn new() -> Self { StatsTracker::default() }
+
+    // a key press for a game input, whether or not it moved the piece
+    pub fn press_key(&mut self) { self.n_keys += 1; }
+
+    // a piece locking with `line_clear`, giving the lines it sent
+    pub fn add_piece(&mut self, line_clear: &LineClear, table: &AttackTable, combo_table: &ComboTable) -> u32 {
+        let attack = self.attack_state.update(line_clear, table, combo_table);
+        self.n_pieces += 1;
+        self.n_lines += line_clear.n_lines() as u32;
+        self.attack += attack;
+
+        self.max_combo = self.max_combo.max(self.attack_state.combo().unwrap_or(0));
+        self.max_back_to_back = self.max_back_to_back.max(self.attack_state.back_to_back().unwrap_or(0));
+        attack
+    }
+
+    pub fn n_pieces(&self) -> u32 { self.n_pieces }
+
+    pub fn n_lines(&self) -> u32 { self.n_lines }
+
+    pub fn attack(&self) -> u32 { self.attack }
+
+    pub fn n_keys(&self) -> u32 { self.n_keys }
+
+    // longest combo and back to back chain so far, counted like `AttackState`
+    pub fn max_combo(&self) -> usize { self.max_combo }
+
+    pub fn max_back_to_back(&self) -> usize { self.max_back_to_back }
+
+    // rates over `millis` milliseconds of play
+    pub fn pieces_per_second(&self, millis: f64) -> f64 { self.n_pieces as f64 / (millis / 1_000.).max(0.001) }
+
+    pub fn attack_per_minute(&self, millis: f64) -> f64 { self.attack as f64 / (millis / 60_000.).max(0.001) }
+
+    pub fn keys_per_piece(&self) -> f64 { self.n_keys as f64 / self.n_pieces.max(1) as f64 }
+}
@@ -0,0 +1,18 @@
+// engine time is counted in whole ticks, so replays play back exactly the same on every machine
+
+pub type Tick = u64;
+
+// number of ticks in a second
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TickRate(pub u32);
+
+impl TickRate {
+    // ticks in `ms` milliseconds, rounded to the nearest tick (for converting timings like those in `Speed`)
+    pub fn ticks(&self, ms: f64) -> Tick { (ms * self.0 as f64 / 1_000. + 0.5) as Tick }
+
+    pub fn ms(&self, ticks: Tick) -> f64 { ticks as f64 * 1_000. / self.0 as f64 }
+}
+
+impl Default for TickRate {
+    fn default() -> Self { TickRate(60) }
+}
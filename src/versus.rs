@@ -0,0 +1,80 @@
+// messages for playing versus over a network, sent as one line of text each (e.g. `input 120 left`)
+// players join a room, and once it has two the server starts a match by giving both the same seed for their bags
+// each player's inputs, attacks, and the garbage entering their field are then relayed to the other, who plays the
+// inputs and garbage on their own copy of the opponent's game to show it
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{sim::Input, ticks::Tick};
+
+// joining this room puts the player in a room with someone else waiting for an opponent with the same rules instead
+pub const MATCHMAKING_ROOM: &str = "any";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    // join a room, with the rules to play by if it's new (in a form the server doesn't need to read)
+    Join { room: String, rules: String },
+    // the room has two players, who play with the same seed and the rules of the player who made it
+    Start { seed: u64, rules: String },
+    // an input that moved the sender's piece, as recorded in replays
+    Input { tick: Tick, input: Input },
+    // lines sent to the opponent, after cancelling the sender's pending garbage
+    Attack(u32),
+    // garbage rows entering the sender's field, by hole column from top to bottom
+    Garbage(Vec<usize>),
+    // the sender topped out, losing the match
+    ToppedOut,
+    // the sender reached the goal, winning the match
+    Finished,
+    // the opponent disconnected, sent by the server
+    Left,
+}
+
+impl Message {
+    pub fn to_text(&self) -> String {
+        match self {
+            Message::Join { room, rules } => format!("join {} {}", room, rules),
+            Message::Start { seed, rules } => format!("start {} {}", seed, rules),
+            Message::Input { tick, input } => format!("input {} {}", tick, input.word()),
+            Message::Attack(n_lines) => format!("attack {}", n_lines),
+            Message::Garbage(holes) => {
+                let holes = holes.iter().map(ToString::to_string).collect::<Vec<_>>();
+                format!("garbage {}", holes.join(" "))
+            }
+            Message::ToppedOut => "topped-out".to_string(),
+            Message::Finished => "finished".to_string(),
+            Message::Left => "left".to_string(),
+        }
+    }
+
+    // rooms and rules are single words, so anything after them makes the message invalid
+    pub fn from_text(text: &str) -> Option<Message> {
+        let mut words = text.split_whitespace();
+        let message = match words.next()? {
+            "join" => Message::Join {
+                room: words.next()?.to_string(),
+                rules: words.next()?.to_string(),
+            },
+            "start" => Message::Start {
+                seed: words.next()?.parse().ok()?,
+                rules: words.next()?.to_string(),
+            },
+            "input" => {
+                let tick = words.next()?.parse().ok()?;
+                let input = *Input::parse(words.next()?).ok()?.first()?;
+                Message::Input { tick, input }
+            }
+            "attack" => Message::Attack(words.next()?.parse().ok()?),
+            "garbage" => return Some(Message::Garbage(words.map(|w| w.parse().ok()).collect::<Option<_>>()?)),
+            "topped-out" => Message::ToppedOut,
+            "finished" => Message::Finished,
+            "left" => Message::Left,
+            _ => return None,
+        };
+        words.next().is_none().then_some(message)
+    }
+}
@@ -0,0 +1,437 @@
+// javascript bindings for the engine
+// pieces are referred to by their index in `Game.kindNames()`, with -1 standing for an empty square or no piece
+// and -2 for a garbage square
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    attack::ComboTable,
+    curves::Speed,
+    field::{DefaultField, LineClear, MoveError, MoveOk, Square},
+    game::{self, Game},
+    garbage::{self, CleanGarbage},
+    kicks::{AscKickTable, BasicKickTable, SrsKickTable, TetrIo180KickTable},
+    pieces::{
+        mino123::Mino123, mino1234::Mino1234, pentomino::Pentomino, tetromino::TetrominoAsc, tetromino::TetrominoSrs,
+        PieceKind, PieceKindTrait,
+    },
+    spins::{ImmobileSpinDetector, NoSpinDetector, TSpinDetector},
+    ticks::TickRate,
+    Coords, Randomizer, SingleBag,
+};
+
+#[wasm_bindgen]
+#[derive(Copy, Clone)]
+pub enum PieceSet {
+    TetrominoSrs,
+    TetrominoAsc,
+    Mino123,
+    Mino1234,
+    Pentomino,
+}
+
+impl PieceSet {
+    fn kinds(self) -> Vec<PieceKind> {
+        match self {
+            PieceSet::TetrominoSrs => TetrominoSrs::iter(),
+            PieceSet::TetrominoAsc => TetrominoAsc::iter(),
+            PieceSet::Mino123 => Mino123::iter(),
+            PieceSet::Mino1234 => Mino1234::iter(),
+            PieceSet::Pentomino => Pentomino::iter(),
+        }
+        .collect()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone)]
+pub enum KickTableKind {
+    Srs,
+    Asc,
+    Basic,
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone)]
+pub enum KickTable180Kind {
+    TetrIo,
+    Basic,
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone)]
+pub enum SpinDetectorKind {
+    TSpins,
+    Immobile,
+    None,
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone)]
+pub enum ComboTableKind {
+    Guideline,
+    Jstris,
+    Multiplier,
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone)]
+pub enum GarbageRulesKind {
+    Guideline,
+    TetrIo,
+}
+
+// what a move did, or why it failed
+#[wasm_bindgen]
+#[derive(Copy, Clone)]
+pub enum MoveResult {
+    Shifted,
+    Rotated,
+    Kicked,
+    Held,
+    Wall,
+    OutOfBounds,
+    Stack,
+    HoldUsed,
+    Disallowed,
+}
+
+impl From<Result<MoveOk, MoveError>> for MoveResult {
+    fn from(result: Result<MoveOk, MoveError>) -> Self {
+        match result {
+            Ok(MoveOk::Shifted) => MoveResult::Shifted,
+            Ok(MoveOk::Rotated(Coords(0, 0))) => MoveResult::Rotated,
+            Ok(MoveOk::Rotated(_)) => MoveResult::Kicked,
+            Ok(MoveOk::Held) => MoveResult::Held,
+            Err(MoveError::Wall) => MoveResult::Wall,
+            Err(MoveError::OutOfBounds) => MoveResult::OutOfBounds,
+            Err(MoveError::Stack) => MoveResult::Stack,
+            Err(MoveError::HoldUsed) => MoveResult::HoldUsed,
+            Err(MoveError::Disallowed) => MoveResult::Disallowed,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct GameRules(game::GameRules);
+
+#[wasm_bindgen]
+impl GameRules {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self { GameRules::default() }
+
+    pub fn limited() -> Self { GameRules(game::GameRules::limited()) }
+
+    #[wasm_bindgen(js_name = setKickTable)]
+    pub fn set_kick_table(&mut self, kind: KickTableKind) {
+        self.0.kick_table = match kind {
+            KickTableKind::Srs => &SrsKickTable,
+            KickTableKind::Asc => &AscKickTable,
+            KickTableKind::Basic => &BasicKickTable,
+        };
+    }
+
+    #[wasm_bindgen(js_name = setKickTable180)]
+    pub fn set_kick_table_180(&mut self, kind: KickTable180Kind) {
+        self.0.kick_table_180 = match kind {
+            KickTable180Kind::TetrIo => &TetrIo180KickTable,
+            KickTable180Kind::Basic => &BasicKickTable,
+        };
+    }
+
+    #[wasm_bindgen(js_name = setSpinDetector)]
+    pub fn set_spin_detector(&mut self, kind: SpinDetectorKind) {
+        self.0.spin_detector = match kind {
+            SpinDetectorKind::TSpins => &TSpinDetector,
+            SpinDetectorKind::Immobile => &ImmobileSpinDetector,
+            SpinDetectorKind::None => &NoSpinDetector,
+        };
+    }
+
+    #[wasm_bindgen(js_name = setComboTable)]
+    pub fn set_combo_table(&mut self, kind: ComboTableKind) {
+        self.0.combo_table = match kind {
+            ComboTableKind::Guideline => ComboTable::guideline(),
+            ComboTableKind::Jstris => ComboTable::jstris(),
+            ComboTableKind::Multiplier => ComboTable::multiplier(),
+        };
+    }
+
+    #[wasm_bindgen(js_name = setSpawnGraceRows)]
+    pub fn set_spawn_grace_rows(&mut self, n_rows: usize) { self.0.spawn_grace_rows = n_rows; }
+
+    // should be set before `setGarbageRules`, whose delays are converted to ticks
+    #[wasm_bindgen(js_name = setTickRate)]
+    pub fn set_tick_rate(&mut self, ticks_per_second: u32) { self.0.tick_rate = TickRate(ticks_per_second); }
+
+    #[wasm_bindgen(js_name = setGarbageRules)]
+    pub fn set_garbage_rules(&mut self, kind: GarbageRulesKind) {
+        self.0.garbage_rules = match kind {
+            GarbageRulesKind::Guideline => garbage::GarbageRules::guideline(),
+            GarbageRulesKind::TetrIo => garbage::GarbageRules::tetrio(self.0.tick_rate),
+        };
+    }
+
+    // additive combo table with custom values (see `ComboTable::Additive`)
+    #[wasm_bindgen(js_name = setCustomComboTable)]
+    pub fn set_custom_combo_table(&mut self, table: Vec<u32>) { self.0.combo_table = ComboTable::Additive(table); }
+}
+
+// state of a game to go back to with `Game.restore`
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct GameSnapshot(game::GameSnapshot<SingleBag>);
+
+#[wasm_bindgen(js_name = Game)]
+pub struct JsGame {
+    game: Game<SingleBag>,
+    kinds: Vec<PieceKind>,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl JsGame {
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: usize, height: usize, hidden: usize, piece_set: PieceSet, rules: &GameRules) -> Self {
+        let kinds = piece_set.kinds();
+        let bag = SingleBag::new(kinds.clone());
+        let garbage = CleanGarbage::new(rand::random());
+        JsGame::with_bag(width, height, hidden, bag, garbage, kinds, rules)
+    }
+
+    // game whose pieces and garbage holes are determined by `seed`
+    #[wasm_bindgen(js_name = withSeed)]
+    pub fn with_seed(
+        width: usize,
+        height: usize,
+        hidden: usize,
+        piece_set: PieceSet,
+        rules: &GameRules,
+        seed: u64,
+    ) -> JsGame {
+        let kinds = piece_set.kinds();
+        JsGame::with_bag(
+            width,
+            height,
+            hidden,
+            SingleBag::seeded(kinds.clone(), seed),
+            CleanGarbage::new(seed),
+            kinds,
+            rules,
+        )
+    }
+
+    #[wasm_bindgen(js_name = setRules)]
+    pub fn set_rules(&mut self, rules: &GameRules) { self.game.set_rules(rules.0.clone()); }
+
+    pub fn field(&self) -> Field { Field::new(self.game.field(), &self.kinds) }
+
+    pub fn snapshot(&self) -> GameSnapshot { GameSnapshot(self.game.snapshot()) }
+
+    pub fn restore(&mut self, snapshot: &GameSnapshot) { self.game.restore(&snapshot.0); }
+
+    // names of the piece kinds, indexed by the numbers used for pieces everywhere else
+    #[wasm_bindgen(js_name = kindNames)]
+    pub fn kind_names(&self) -> Vec<String> { self.kinds.iter().map(|k| k.display_name().into()).collect() }
+
+    // css colors of the piece kinds
+    #[wasm_bindgen(js_name = kindColors)]
+    pub fn kind_colors(&self) -> Vec<String> { self.kinds.iter().map(|k| k.color().to_hex()).collect() }
+
+    // the next `n` pieces
+    pub fn queue(&mut self, n: usize) -> Vec<i32> {
+        let next = self.game.bag_mut().peek_n(n).collect::<Vec<_>>();
+        next.into_iter().map(|k| kind_index(&self.kinds, Some(k))).collect()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tick(&self) -> u64 { self.game.tick() }
+
+    pub fn advance(&mut self, n_ticks: u64) { self.game.advance(n_ticks); }
+
+    #[wasm_bindgen(js_name = advanceTo)]
+    pub fn advance_to(&mut self, tick: u64) { self.game.advance_to(tick); }
+
+    pub fn shift(&mut self, rows: i32, cols: i32) -> MoveResult { self.game.shift(rows, cols).into() }
+
+    #[wasm_bindgen(js_name = rotateCw)]
+    pub fn rotate_cw(&mut self) -> MoveResult { self.game.rotate_cw().into() }
+
+    #[wasm_bindgen(js_name = rotateCcw)]
+    pub fn rotate_ccw(&mut self) -> MoveResult { self.game.rotate_ccw().into() }
+
+    #[wasm_bindgen(js_name = rotate180)]
+    pub fn rotate_180(&mut self) -> MoveResult { self.game.rotate_180().into() }
+
+    #[wasm_bindgen(js_name = swapHold)]
+    pub fn swap_hold(&mut self) -> MoveResult { self.game.swap_hold().into() }
+
+    // how far the current piece is towards falling another row, from 0 to 1, for drawing it between rows
+    #[wasm_bindgen(js_name = gravityProgress)]
+    pub fn gravity_progress(&self, gravity_delay: f64) -> f64 {
+        let speed = Speed {
+            gravity_delay,
+            lock_delay: 0.,
+            are: 0.,
+        };
+        self.game.gravity_progress(speed)
+    }
+
+    // `[rows, cols]` the current piece last moved by, or undefined if it hasn't moved since spawning
+    #[wasm_bindgen(js_name = lastMotion)]
+    pub fn last_motion(&self) -> Option<Vec<i32>> { self.game.last_motion().map(|m| [m.delta.0, m.delta.1].to_vec()) }
+
+    #[wasm_bindgen(js_name = sonicDrop)]
+    pub fn sonic_drop(&mut self) -> bool { self.game.sonic_drop() }
+
+    // queue an attack received now, which enters the field in `hardDrop` when the rules allow it
+    #[wasm_bindgen(js_name = queueGarbage)]
+    pub fn queue_garbage(&mut self, n_rows: u32) { self.game.queue_garbage(n_rows); }
+
+    #[wasm_bindgen(js_name = pendingGarbage)]
+    pub fn pending_garbage(&self) -> u32 { self.game.pending_garbage().n_pending() }
+
+    // lines of an attack left to send after cancelling pending garbage
+    #[wasm_bindgen(js_name = sendAttack)]
+    pub fn send_attack(&mut self, attack: u32) -> u32 { self.game.send_attack(attack) }
+
+    // add `n_rows` rows of garbage, with holes from the game's own rng
+    #[wasm_bindgen(js_name = receiveGarbage)]
+    pub fn receive_garbage(&mut self, n_rows: usize) { self.game.receive_garbage(n_rows); }
+
+    #[wasm_bindgen(js_name = hardDrop)]
+    pub fn hard_drop(&mut self) -> JsLineClear { JsLineClear::new(&self.game.hard_drop(), &self.kinds) }
+
+    // like `hardDrop`, but giving undefined if the rules don't allow hard drops (e.g. limited)
+    #[wasm_bindgen(js_name = tryHardDrop)]
+    pub fn try_hard_drop(&mut self) -> Option<JsLineClear> {
+        self.game
+            .try_hard_drop()
+            .map(|line_clear| JsLineClear::new(&line_clear, &self.kinds))
+    }
+}
+
+impl JsGame {
+    fn with_bag(
+        width: usize,
+        height: usize,
+        hidden: usize,
+        bag: SingleBag,
+        garbage: CleanGarbage,
+        kinds: Vec<PieceKind>,
+        rules: &GameRules,
+    ) -> JsGame {
+        let mut game = Game::new(width, height, hidden, &kinds, bag, rules.0.clone());
+        game.set_garbage_generator(Box::new(garbage));
+        JsGame { game, kinds }
+    }
+}
+
+// copy of the state of a `DefaultField` for rendering
+#[wasm_bindgen(js_name = DefaultField)]
+pub struct Field {
+    width: usize,
+    height: usize,
+    hidden: usize,
+    squares: Vec<i32>,
+    cur_piece: Vec<i32>,
+    shadow_piece: Vec<i32>,
+    cur_piece_kind: i32,
+    hold_piece: i32,
+    hold_swapped: bool,
+    topped_out: bool,
+}
+
+impl Field {
+    fn new(field: &DefaultField, kinds: &[PieceKind]) -> Self {
+        let squares = field
+            .rows()
+            .flatten()
+            .map(|s| match s {
+                Square::Empty => -1,
+                Square::Garbage => -2,
+                Square::Filled(kind) => kind_index(kinds, Some(*kind)),
+            })
+            .collect();
+
+        Field {
+            width: field.width(),
+            height: field.height(),
+            hidden: field.hidden(),
+            squares,
+            cur_piece: flatten_coords(field.cur_piece().coords()),
+            shadow_piece: flatten_coords(field.shadow_piece().coords()),
+            cur_piece_kind: kind_index(kinds, Some(field.cur_piece().kind())),
+            hold_piece: kind_index(kinds, field.hold_piece()),
+            hold_swapped: field.hold_swapped(),
+            topped_out: field.topped_out(),
+        }
+    }
+}
+
+#[wasm_bindgen(js_class = DefaultField)]
+impl Field {
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize { self.width }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize { self.height }
+
+    #[wasm_bindgen(getter)]
+    pub fn hidden(&self) -> usize { self.hidden }
+
+    // row-major squares, including the current piece
+    pub fn squares(&self) -> Vec<i32> { self.squares.clone() }
+
+    // flattened (row, col) pairs
+    #[wasm_bindgen(js_name = curPiece)]
+    pub fn cur_piece(&self) -> Vec<i32> { self.cur_piece.clone() }
+
+    #[wasm_bindgen(js_name = shadowPiece)]
+    pub fn shadow_piece(&self) -> Vec<i32> { self.shadow_piece.clone() }
+
+    #[wasm_bindgen(getter, js_name = curPieceKind)]
+    pub fn cur_piece_kind(&self) -> i32 { self.cur_piece_kind }
+
+    #[wasm_bindgen(getter, js_name = holdPiece)]
+    pub fn hold_piece(&self) -> i32 { self.hold_piece }
+
+    #[wasm_bindgen(getter, js_name = holdSwapped)]
+    pub fn hold_swapped(&self) -> bool { self.hold_swapped }
+
+    #[wasm_bindgen(getter, js_name = toppedOut)]
+    pub fn topped_out(&self) -> bool { self.topped_out }
+}
+
+#[wasm_bindgen(js_name = LineClear)]
+pub struct JsLineClear {
+    pub n_lines: usize,
+    pub spin: i32,
+    pub is_mini: bool,
+    pub is_perfect_clear: bool,
+    pub attack: u32,
+    pub tick: u64,
+}
+
+impl JsLineClear {
+    fn new(line_clear: &LineClear, kinds: &[PieceKind]) -> Self {
+        JsLineClear {
+            n_lines: line_clear.n_lines(),
+            spin: kind_index(kinds, line_clear.spin()),
+            is_mini: line_clear.is_mini(),
+            is_perfect_clear: line_clear.is_perfect_clear(),
+            attack: line_clear.attack(),
+            tick: line_clear.tick(),
+        }
+    }
+}
+
+fn kind_index(kinds: &[PieceKind], kind: Option<PieceKind>) -> i32 {
+    kind.and_then(|kind| kinds.iter().position(|k| *k == kind))
+        .map(|i| i as i32)
+        .unwrap_or(-1)
+}
+
+fn flatten_coords(coords: &[Coords]) -> Vec<i32> { coords.iter().flat_map(|c| [c.0, c.1]).collect() }
@@ -0,0 +1,47 @@
+// lines sent by clears, from the combo tables through to clears in a game
+
+use tetrox::{
+    attack::ComboTable,
+    game::{Game, GameRules},
+    pieces::{tetromino::TetrominoSrs, PieceKind},
+    sim::{self, Input},
+    SingleBag,
+};
+
+#[test]
+fn combo_tables() {
+    let guideline = ComboTable::guideline();
+    assert_eq!(guideline.apply(0, 0), 0);
+    assert_eq!(guideline.apply(1, 2), 2);
+    // long combos keep the table's last value
+    assert_eq!(guideline.apply(4, 50), 9);
+
+    // singles in a combo send the fallback instead of nothing
+    let multiplier = ComboTable::multiplier();
+    assert_eq!(multiplier.apply(4, 4), 8);
+    assert_eq!(multiplier.apply(0, 2), 1);
+}
+
+#[test]
+fn back_to_back_quads() {
+    let kinds = [PieceKind::TetrominoSrs(TetrominoSrs::I)];
+    let mut game = Game::new(
+        10,
+        40,
+        20,
+        &kinds,
+        SingleBag::seeded(kinds.to_vec(), 0),
+        GameRules::default(),
+    );
+    // an extra row is left after both quads, so neither is a perfect clear
+    sim::set_board(game.field_mut(), &"#########.\n".repeat(9));
+
+    // a quad, then another continuing both the back to back chain and the combo
+    let into_well = Input::parse("cw right right right right").unwrap();
+    for attack in [4, 6] {
+        assert!(sim::run(&mut game, &into_well).iter().all(|&ok| ok));
+        let line_clear = game.try_hard_drop().unwrap();
+        assert_eq!((line_clear.n_lines(), line_clear.attack()), (4, attack));
+    }
+    assert_eq!(game.attack_state().back_to_back(), Some(1));
+}
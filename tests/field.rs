@@ -0,0 +1,112 @@
+// spawning on fields with tall stacks and shared by several players
+
+use tetrox::{
+    field::{DefaultField, Square},
+    pieces::{tetromino::TetrominoSrs, PieceKind},
+    spins::NoSpinDetector,
+    Coords, SingleBag,
+};
+
+fn t_field() -> (DefaultField, SingleBag) {
+    let kinds = [PieceKind::TetrominoSrs(TetrominoSrs::T)];
+    let mut bag = SingleBag::seeded(kinds.to_vec(), 0);
+    let field = DefaultField::new(10, 40, 20, &kinds, &mut bag);
+    (field, bag)
+}
+
+// a field whose current piece has been moved to the left wall, leaving a tower under where pieces spawn which reaches
+// `n_rows` rows into them, along with where pieces spawn without the tower
+fn towered_field(n_rows: i32, grace_rows: usize) -> (DefaultField, SingleBag, Vec<Coords>) {
+    let (mut field, bag) = t_field();
+    field.set_spawn_grace_rows(grace_rows);
+    let spawn = field.cur_piece().coords().to_vec();
+    assert!(field.try_shift(0, -3).is_ok());
+
+    let spawn_bottom = spawn.iter().map(|c| c.0).max().unwrap();
+    let spawn_cols = spawn.iter().map(|c| c.1).collect::<Vec<_>>();
+    for row in spawn_bottom + 1 - n_rows..field.height() as i32 {
+        for &col in &spawn_cols {
+            field.set_square(&Coords(row, col), Square::Garbage);
+        }
+    }
+    (field, bag, spawn)
+}
+
+// spawns a piece on towers of different heights with different numbers of grace rows, checking that it's moved up
+// just clear of the tower if there are enough grace rows, and blocked otherwise
+fn check_spawn_grace_rows(spawn: impl Fn(&mut DefaultField, &mut SingleBag) -> bool) {
+    let cases = [
+        (1, 0, None),
+        (1, 1, Some(1)),
+        (1, 2, Some(1)),
+        (2, 2, Some(2)),
+        (3, 2, None),
+    ];
+    for (n_rows, grace_rows, expected) in cases {
+        let (mut field, mut bag, spawn_coords) = towered_field(n_rows, grace_rows);
+        let spawned = spawn(&mut field, &mut bag);
+        let case = format!("{} row tower, {} grace rows", n_rows, grace_rows);
+        match expected {
+            Some(up) => {
+                assert!(spawned, "{}", case);
+                let raised = spawn_coords.iter().map(|&c| c + Coords(-up, 0)).collect::<Vec<_>>();
+                assert_eq!(field.cur_piece().coords(), &raised[..], "{}", case);
+            }
+            None => assert!(!spawned, "{}", case),
+        }
+    }
+}
+
+#[test]
+fn hard_drop_spawn_grace_rows() {
+    check_spawn_grace_rows(|field, bag| {
+        field.hard_drop(bag, &NoSpinDetector);
+        !field.topped_out()
+    });
+}
+
+#[test]
+fn hold_spawn_grace_rows() { check_spawn_grace_rows(|field, bag| field.swap_hold_piece(bag).is_ok()); }
+
+#[test]
+fn try_spawn_grace_rows() { check_spawn_grace_rows(|field, bag| field.try_spawn(bag)); }
+
+#[test]
+fn spawn_pushes_other_players_up() {
+    let (mut field, mut bag) = t_field();
+    let player = field.add_player(4, &mut bag).unwrap();
+
+    // the first player moves out of the way, and the second moves right over where the first's pieces spawn
+    let spawn = field.cur_piece().coords().to_vec();
+    assert!(field.try_shift(0, -3).is_ok() && field.try_shift(4, 0).is_ok());
+    field.set_active_player(player);
+    assert!(field.try_shift(0, -4).is_ok());
+    assert!(field.cur_piece().coords().iter().all(|c| spawn.contains(c)));
+
+    field.set_active_player(0);
+    field.hard_drop(&mut bag, &NoSpinDetector);
+    assert!(!field.topped_out());
+    assert_eq!(field.cur_piece().coords(), &spawn[..]);
+
+    let pushed = field.live_pieces().nth(player).unwrap();
+    assert!(pushed.coords().iter().all(|c| !spawn.contains(c)));
+    assert!(pushed
+        .coords()
+        .iter()
+        .all(|c| c.0 < spawn.iter().map(|c| c.0).min().unwrap()));
+}
+
+#[test]
+fn narrowing_respawns_other_players_in_bounds() {
+    let kinds = [PieceKind::TetrominoSrs(TetrominoSrs::T)];
+    let mut bag = SingleBag::seeded(kinds.to_vec(), 0);
+    let mut field = DefaultField::new(20, 40, 20, &kinds, &mut bag);
+    let player = field.add_player(6, &mut bag).unwrap();
+
+    // the second player's pieces spawn past the right wall of the narrower field
+    field.resize(8, 40, 20);
+    assert!(!field.topped_out());
+    let piece = field.live_pieces().nth(player).unwrap();
+    assert!(piece.coords().iter().all(|c| field.coords_in_bounds(c)));
+    assert!(piece.coords().iter().all(|c| !field.cur_piece().coords().contains(c)));
+}
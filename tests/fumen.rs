@@ -0,0 +1,41 @@
+// fumen data written by the editor read back, and boards written as fumens read back the same
+
+use tetrox::{
+    field::DefaultField,
+    fumen::{self, FumenBlock},
+    pieces::{tetromino::TetrominoSrs, PieceKind},
+    sim, SingleBag,
+};
+
+fn field() -> DefaultField {
+    let kinds = [PieceKind::TetrominoSrs(TetrominoSrs::T)];
+    DefaultField::new(10, 40, 20, &kinds, &mut SingleBag::seeded(kinds.to_vec(), 0))
+}
+
+#[test]
+fn empty_field() {
+    let page = fumen::decode("https://fumen.zui.jp/?v115@vhAAgH").unwrap();
+    assert!(page.blocks.iter().all(|b| *b == FumenBlock::Empty));
+    assert_eq!(page.comment, "");
+
+    // the live piece isn't part of the stack
+    assert_eq!(fumen::encode(&field(), ""), "v115@vhAAgH");
+}
+
+#[test]
+fn round_trip() {
+    let board = "
+        #.........
+        ##...#####
+        ###.######
+        ";
+    let mut field = field();
+    sim::set_board(&mut field, board);
+    let comment = "#Q=[S](T)IOZ";
+
+    let page = fumen::decode(&fumen::encode(&field, comment)).unwrap();
+    let expected = board.lines().map(str::trim).filter(|l| !l.is_empty());
+    assert!(page.board().lines().eq(expected));
+    assert_eq!(page.comment, comment);
+    assert_eq!(page.quiz_queue().as_deref(), Some("STIOZ"));
+}
@@ -0,0 +1,71 @@
+// pending garbage being cancelled by attacks and let into the field under each of the rules
+
+use tetrox::garbage::{GarbageCancelling, GarbageEntry, GarbageQueue, GarbageRules};
+
+fn queue(attacks: &[(u32, u64)]) -> GarbageQueue {
+    let mut queue = GarbageQueue::new();
+    for &(n_rows, received) in attacks {
+        queue.push(n_rows, received);
+    }
+    queue
+}
+
+#[test]
+fn cancels_oldest_first() {
+    let rules = GarbageRules::guideline();
+    let mut pending = queue(&[(3, 0), (4, 10)]);
+
+    // the first attack is cancelled whole, and the second in part
+    assert_eq!(pending.cancel(5, &rules), 0);
+    assert_eq!(pending.n_pending(), 2);
+    assert_eq!(pending.cancel(5, &rules), 3);
+    assert!(pending.is_empty());
+
+    let rules = GarbageRules {
+        cancelling: GarbageCancelling::PassThrough,
+        ..rules
+    };
+    let mut pending = queue(&[(3, 0)]);
+    assert_eq!(pending.cancel(5, &rules), 5);
+    assert_eq!(pending.n_pending(), 3);
+}
+
+#[test]
+fn cap_splits_attacks() {
+    let rules = GarbageRules {
+        cap: Some(8),
+        ..GarbageRules::guideline()
+    };
+    let mut pending = queue(&[(5, 0), (6, 0)]);
+    assert_eq!(pending.take_entering(0, 0, &rules), [5, 3]);
+    assert_eq!(pending.n_pending(), 3);
+    assert_eq!(pending.take_entering(0, 0, &rules), [3]);
+    assert!(pending.is_empty());
+}
+
+#[test]
+fn entry_rules() {
+    // clearing lines holds garbage back unless it enters between every piece
+    let guideline = GarbageRules::guideline();
+    assert!(queue(&[(2, 0)]).take_entering(1, 0, &guideline).is_empty());
+    let between_pieces = GarbageRules {
+        entry: GarbageEntry::BetweenPieces,
+        ..guideline
+    };
+    assert_eq!(queue(&[(2, 0)]).take_entering(1, 0, &between_pieces), [2]);
+
+    // delayed garbage waits until it's been pending long enough, and holds back what came after it
+    let delayed = GarbageRules {
+        entry: GarbageEntry::Delayed(20),
+        ..guideline
+    };
+    let mut pending = queue(&[(2, 0), (3, 10)]);
+    assert!(pending.take_entering(0, 19, &delayed).is_empty());
+    assert_eq!(pending.take_entering(0, 20, &delayed), [2]);
+    assert_eq!(pending.take_entering(0, 30, &delayed), [3]);
+}
+
+#[test]
+fn pending_rows_saturate() {
+    assert_eq!(queue(&[(u32::MAX, 0), (u32::MAX, 0)]).n_pending(), u32::MAX);
+}
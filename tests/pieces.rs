@@ -0,0 +1,31 @@
+// piece sets made by combining other sets
+
+use tetrox::pieces::{
+    composite::CompositeSet, mino123::Mino123, mino1234::Mino1234, pentomino::Pentomino, tetromino::TetrominoSrs,
+    PieceKind, PieceKindTrait,
+};
+
+#[test]
+fn composite_kinds() {
+    // kinds keep their own sets, with the first set's kinds first
+    let kinds = CompositeSet::<TetrominoSrs, Pentomino>::iter().collect::<Vec<_>>();
+    assert_eq!(kinds.len(), CompositeSet::<TetrominoSrs, Pentomino>::n_kinds());
+    assert!(kinds[..TetrominoSrs::n_kinds()]
+        .iter()
+        .all(|k| matches!(k, PieceKind::TetrominoSrs(_))));
+    assert!(kinds[TetrominoSrs::n_kinds()..]
+        .iter()
+        .all(|k| matches!(k, PieceKind::Pentomino(_))));
+}
+
+#[test]
+fn nested_composite_kinds() {
+    // 1234mino is itself a composite, so it can be nested in another
+    let kinds = CompositeSet::<Mino1234, Pentomino>::iter().collect::<Vec<_>>();
+    let expected = Mino123::iter().chain(TetrominoSrs::iter()).chain(Pentomino::iter());
+    assert!(kinds.iter().copied().eq(expected));
+    assert_eq!(
+        kinds.len(),
+        Mino123::n_kinds() + TetrominoSrs::n_kinds() + Pentomino::n_kinds()
+    );
+}
@@ -0,0 +1,78 @@
+// checking puzzle solutions, with an i piece fitting into a gap in the middle of the bottom row
+
+use tetrox::{
+    game::GameRules,
+    pieces::{tetromino::TetrominoSrs, PieceKind},
+    puzzle::{Puzzle, PuzzleError, PuzzleGoal},
+    sim::Input,
+};
+
+fn puzzle(board: &str, queue: &[TetrominoSrs], goal: PuzzleGoal) -> Puzzle {
+    Puzzle {
+        width: 10,
+        height: 40,
+        hidden: 20,
+        board: board.to_string(),
+        queue: queue.iter().map(|&k| PieceKind::TetrominoSrs(k)).collect(),
+        goal,
+        max_pieces: 2,
+    }
+}
+
+fn check(puzzle: &Puzzle, script: &str) -> Result<usize, PuzzleError> {
+    puzzle.check(&Input::parse(script).unwrap(), GameRules::default())
+}
+
+#[test]
+fn accepts_solution() {
+    let puzzle = puzzle(
+        "###....###",
+        &[TetrominoSrs::O, TetrominoSrs::I],
+        PuzzleGoal::PerfectClear,
+    );
+    assert_eq!(check(&puzzle, "hold hard"), Ok(1));
+}
+
+#[test]
+fn rejects_wrong_piece() {
+    // the o piece can't fill the gap, and the i piece can't clear what it leaves
+    let puzzle = puzzle(
+        "###....###",
+        &[TetrominoSrs::O, TetrominoSrs::I],
+        PuzzleGoal::PerfectClear,
+    );
+    assert_eq!(check(&puzzle, "hard hard"), Err(PuzzleError::Unsolved));
+
+    let puzzle = Puzzle {
+        max_pieces: 1,
+        ..puzzle
+    };
+    assert_eq!(check(&puzzle, "hard hard"), Err(PuzzleError::TooManyPieces));
+}
+
+#[test]
+fn rejects_leftover_garbage() {
+    let board = "
+        ###....###
+        #########.
+        ";
+    let puzzle = puzzle(board, &[TetrominoSrs::I], PuzzleGoal::PerfectClear);
+    assert_eq!(check(&puzzle, "hard"), Err(PuzzleError::Unsolved));
+
+    // clearing the line is still enough when that's all the goal asks for
+    let puzzle = Puzzle {
+        goal: PuzzleGoal::ClearLines(1),
+        ..puzzle
+    };
+    assert_eq!(check(&puzzle, "hard"), Ok(1));
+}
+
+#[test]
+fn rejects_pieces_past_queue() {
+    let board = "
+        ###....###
+        ###....###
+        ";
+    let puzzle = puzzle(board, &[TetrominoSrs::I], PuzzleGoal::ClearLines(2));
+    assert_eq!(check(&puzzle, "hard hard"), Err(PuzzleError::OutOfPieces));
+}
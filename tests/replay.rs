@@ -0,0 +1,74 @@
+// reading replays back from their text format
+
+use tetrox::{
+    pieces::{tetromino::TetrominoSrs, PieceKind, PieceKindTrait},
+    replay::{Replay, ReplayError},
+    sim::Input,
+    ticks::TickRate,
+};
+
+fn srs() -> Vec<PieceKind> { TetrominoSrs::iter().collect() }
+
+// a replay's text with the header values swapped for `field`, `tick_rate`, and `kinds`
+fn text(field: &str, tick_rate: &str, kinds: &str) -> String {
+    format!(
+        "tetrox-replay 1\nfield {}\nseed 1234\ntick-rate {}\nkinds {}\n0 left\n",
+        field, tick_rate, kinds
+    )
+}
+
+#[test]
+fn round_trip() {
+    let mut replay = Replay::new(10, 40, 20, &srs(), 1234, TickRate::default());
+    replay.push(0, Input::Left);
+    replay.push(15, Input::Cw);
+    replay.push(40, Input::HardDrop);
+    assert_eq!(Replay::from_text(&replay.to_text(), &srs()), Ok(replay));
+}
+
+#[test]
+fn accepts_smallest_field() {
+    let replay = Replay::from_text(&text("4 1 1", "60", "i o"), &srs()).unwrap();
+    assert_eq!((replay.width, replay.height, replay.hidden), (4, 1, 1));
+}
+
+#[test]
+fn rejects_bad_field() {
+    for field in [
+        "0 40 20",
+        "10 0 0",
+        "10 40 0",
+        "10 20 40",
+        "101 40 20",
+        "10 201 20",
+        "3 40 20",
+    ] {
+        let result = Replay::from_text(&text(field, "60", "i o"), &srs());
+        assert_eq!(result, Err(ReplayError::BadHeader("field")), "field {}", field);
+    }
+}
+
+#[test]
+fn rejects_empty_kinds() {
+    let result = Replay::from_text(&text("10 40 20", "60", ""), &srs());
+    assert_eq!(result, Err(ReplayError::BadHeader("kinds")));
+}
+
+#[test]
+fn rejects_bad_tick_rate() {
+    for tick_rate in ["0", "4294967296"] {
+        let result = Replay::from_text(&text("10 40 20", tick_rate, "i o"), &srs());
+        assert_eq!(
+            result,
+            Err(ReplayError::BadHeader("tick-rate")),
+            "tick rate {}",
+            tick_rate
+        );
+    }
+}
+
+#[test]
+fn rejects_events_going_back() {
+    let text = text("10 40 20", "60", "t") + "10 cw\n5 hard\n";
+    assert_eq!(Replay::from_text(&text, &srs()), Err(ReplayError::BadEvent(8)));
+}
@@ -0,0 +1,32 @@
+// searches with the crate's own bags, which have to work with and without the `parallel` feature
+
+use tetrox::{
+    field::DefaultField,
+    game::GameRules,
+    pieces::{tetromino::TetrominoSrs, PieceKindTrait},
+    search, SingleBag,
+};
+
+#[test]
+fn search_single_bag() {
+    let kinds = TetrominoSrs::iter().collect::<Vec<_>>();
+    let mut bag = SingleBag::seeded(kinds.clone(), 0);
+    let field = DefaultField::new(10, 40, 20, &kinds, &mut bag);
+
+    let result = search::search(&field, &bag, &GameRules::default(), 1, &|_| 0.);
+    assert!(result.is_some());
+}
+
+#[test]
+fn search_follows_hold_rules() {
+    let kinds = TetrominoSrs::iter().collect::<Vec<_>>();
+    let mut bag = SingleBag::seeded(kinds.clone(), 0);
+    let field = DefaultField::new(10, 40, 20, &kinds, &mut bag);
+
+    // placements after holding are the only ones leaving a piece in hold
+    let held = |field: &DefaultField| field.hold_piece().map_or(0., |_| 1.);
+    let result = search::search(&field, &bag, &GameRules::default(), 1, &held);
+    assert!(result.unwrap().hold);
+    let result = search::search(&field, &bag, &GameRules::limited(), 1, &held);
+    assert!(!result.unwrap().hold);
+}
@@ -0,0 +1,188 @@
+// rotation, kick, and placement behaviour pinned down with `sim` scripts
+
+use tetrox::{
+    game::{Game, GameRules, PieceRules, Rotations},
+    kicks::AscKickTable,
+    pieces::{tetromino::TetrominoSrs, PieceKind},
+    sim::{self, Input},
+    SingleBag,
+};
+
+// a standard field where every piece is `kind`
+fn game(kind: TetrominoSrs, rules: GameRules) -> Game<SingleBag> {
+    let kinds = [PieceKind::TetrominoSrs(kind)];
+    Game::new(10, 40, 20, &kinds, SingleBag::seeded(kinds.to_vec(), 0), rules)
+}
+
+fn run(game: &mut Game<SingleBag>, script: &str) -> Vec<bool> { sim::run(game, &Input::parse(script).unwrap()) }
+
+fn asc_rules() -> GameRules {
+    GameRules {
+        kick_table: &AscKickTable,
+        ..GameRules::default()
+    }
+}
+
+#[test]
+fn srs_t_spin_triple() {
+    let mut game = game(TetrominoSrs::T, GameRules::default());
+    sim::set_board(
+        game.field_mut(),
+        "
+        ##........
+        #.........
+        #.########
+        #..#######
+        #.########
+        ",
+    );
+
+    // slid under the overhang, then kicked down two rows by the last test
+    assert!(run(&mut game, "left sonic left").iter().all(|&ok| ok));
+    sim::assert_board(
+        game.field(),
+        "
+        ##@.......
+        #@@@......
+        #.########
+        #..#######
+        #.########
+        ",
+    );
+    assert_eq!(run(&mut game, "cw"), [true]);
+    sim::assert_board(
+        game.field(),
+        "
+        ##........
+        #.........
+        #@########
+        #@@#######
+        #@########
+        ",
+    );
+
+    let clear = game.hard_drop();
+    assert_eq!(clear.n_lines(), 3);
+    assert_eq!(clear.spin(), Some(PieceKind::TetrominoSrs(TetrominoSrs::T)));
+    sim::assert_board(
+        game.field(),
+        "
+        ..........
+        ##........
+        #.........
+        ",
+    );
+}
+
+#[test]
+fn srs_i_wall_kick() {
+    // rotating back to flat against the left wall kicks two columns right
+    let mut game = game(TetrominoSrs::I, GameRules::default());
+    assert_eq!(
+        run(&mut game, "left left left cw left left ccw hard"),
+        [true, true, true, true, true, true, true, true]
+    );
+    sim::assert_board(
+        game.field(),
+        "
+        ..........
+        ####......
+        ",
+    );
+}
+
+#[test]
+fn asc_kicks_i_out_of_well() {
+    let well = "
+        .#########
+        .#########
+        .#########
+    ";
+    let script = "left left left ccw left sonic cw hard";
+
+    // srs kicks don't reach high enough, so the i stays upright and clears the well
+    let mut srs = game(TetrominoSrs::I, GameRules::default());
+    sim::set_board(srs.field_mut(), well);
+    assert_eq!(run(&mut srs, script), [true, true, true, true, true, true, false, true]);
+    sim::assert_board(
+        srs.field(),
+        "
+        ..........
+        #.........
+        ",
+    );
+
+    let mut asc = game(TetrominoSrs::I, asc_rules());
+    sim::set_board(asc.field_mut(), well);
+    assert_eq!(run(&mut asc, script), [true, true, true, true, true, true, true, true]);
+    sim::assert_board(
+        asc.field(),
+        "
+        ..........
+        ####......
+        .#########
+        .#########
+        .#########
+        ",
+    );
+}
+
+#[test]
+fn perft_empty_field() {
+    let counts = [
+        (TetrominoSrs::I, 17),
+        (TetrominoSrs::O, 9),
+        (TetrominoSrs::T, 34),
+        (TetrominoSrs::S, 17),
+        (TetrominoSrs::Z, 17),
+        (TetrominoSrs::J, 34),
+        (TetrominoSrs::L, 34),
+    ];
+    for (kind, count) in counts {
+        let game = game(kind, GameRules::default());
+        assert_eq!(
+            sim::perft(game.field(), game.bag(), game.rules(), 1),
+            count,
+            "{:?}",
+            kind
+        );
+    }
+
+    // without line clears, every placement of the first piece leaves every column open to the second
+    for (kind, count) in [(TetrominoSrs::I, 17 * 17), (TetrominoSrs::O, 9 * 9)] {
+        let game = game(kind, GameRules::default());
+        assert_eq!(
+            sim::perft(game.field(), game.bag(), game.rules(), 2),
+            count,
+            "{:?}",
+            kind
+        );
+    }
+}
+
+#[test]
+fn perft_follows_rotation_rules() {
+    // a t piece that never rotates can only land flat side down
+    let mut rules = GameRules::default();
+    let t = PieceKind::TetrominoSrs(TetrominoSrs::T);
+    let no_rotation = PieceRules {
+        rotations: Some(Rotations::NONE),
+        ..PieceRules::default()
+    };
+    rules.set_piece_rules(t, no_rotation);
+    let game = game(TetrominoSrs::T, rules);
+    assert_eq!(sim::perft(game.field(), game.bag(), game.rules(), 1), 8);
+}
+
+#[test]
+fn finesse_follows_rotation_rules() {
+    let free = game(TetrominoSrs::T, GameRules::default());
+    let mut target = free.field().clone();
+    assert!(target.try_rotate_ccw(free.rules().kick_table).is_ok());
+    let target = target.shadow_piece();
+    assert_eq!(sim::finesse(free.field(), &target, free.rules()), Some(1));
+
+    // turning the other way takes three presses when only clockwise rotation is allowed
+    let limited = game(TetrominoSrs::T, GameRules::limited());
+    assert_eq!(sim::finesse(limited.field(), &target, limited.rules()), Some(3));
+}
@@ -0,0 +1,55 @@
+// games restored from a snapshot carry on exactly as they would have from when it was taken
+
+use tetrox::{
+    game::{Game, GameRules},
+    garbage::RandomGarbage,
+    pieces::{tetromino::TetrominoSrs, PieceKind, PieceKindTrait},
+    sim::{self, Input},
+    SingleBag,
+};
+
+// the whole board along with the pieces in play
+fn state(game: &Game<SingleBag>) -> (String, PieceKind, Option<PieceKind>, u32) {
+    let field = game.field();
+    let board = sim::board_string(field, field.height());
+    (
+        board,
+        field.cur_piece().kind(),
+        field.hold_piece(),
+        game.pending_garbage().n_pending(),
+    )
+}
+
+// the state after playing `script`, including any garbage its pieces let in
+fn play(game: &mut Game<SingleBag>, script: &str) -> (String, PieceKind, Option<PieceKind>, u32) {
+    sim::run(game, &Input::parse(script).unwrap());
+    state(game)
+}
+
+#[test]
+fn restore_carries_on_the_same() {
+    let kinds = TetrominoSrs::iter().collect::<Vec<_>>();
+    let mut game = Game::new(
+        10,
+        40,
+        20,
+        &kinds,
+        SingleBag::seeded(kinds.clone(), 1),
+        GameRules::default(),
+    );
+    game.set_garbage_generator(Box::new(RandomGarbage::new(1)));
+    play(&mut game, "left left hard cw right hard");
+    game.queue_garbage(3);
+    game.advance(30);
+
+    let snapshot = game.snapshot();
+    let before = state(&game);
+    let script = "hold hard ccw sonic hard flip right right hard right hard";
+    let after = play(&mut game, script);
+    assert_ne!(after, before);
+
+    game.restore(&snapshot);
+    assert_eq!(state(&game), before);
+    assert_eq!(game.tick(), snapshot.tick());
+    assert_eq!(play(&mut game, script), after);
+}